@@ -1,4 +1,4 @@
-use crate::{file_watcher::FileWatcher, FileFingerprint, FilePosition};
+use crate::{file_watcher::FileWatcher, FileFingerprint, FilePosition, FileSourceInternalEvents};
 use bytes::Bytes;
 use futures::{
     executor::block_on,
@@ -9,7 +9,7 @@ use glob::glob;
 use indexmap::IndexMap;
 use std::collections::{HashMap, HashSet};
 use std::fs::{self, File};
-use std::io::{self, Read, Seek, Write};
+use std::io::{self, BufRead, Read, Seek, Write};
 use std::path::{Path, PathBuf};
 use std::time;
 use tokio::time::delay_for;
@@ -27,9 +27,10 @@ use crate::paths_provider::PathsProvider;
 /// `FileServer` is configured on a path to watch. The files do _not_ need to
 /// exist at startup. `FileServer` will discover new files which match
 /// its path in at most 60 seconds.
-pub struct FileServer<PP>
+pub struct FileServer<PP, E>
 where
     PP: PathsProvider,
+    E: FileSourceInternalEvents,
 {
     pub paths_provider: PP,
     pub max_read_bytes: usize,
@@ -40,6 +41,7 @@ where
     pub glob_minimum_cooldown: time::Duration,
     pub fingerprinter: Fingerprinter,
     pub oldest_first: bool,
+    pub emitter: E,
 }
 
 /// `FileServer` as Source
@@ -55,9 +57,10 @@ where
 ///
 /// Specific operating systems support evented interfaces that correct this
 /// problem but your intrepid authors know of no generic solution.
-impl<PP> FileServer<PP>
+impl<PP, E> FileServer<PP, E>
 where
     PP: PathsProvider,
+    E: FileSourceInternalEvents,
 {
     pub fn run<C>(
         self,
@@ -132,7 +135,13 @@ where
                     .ok();
 
                 // Search (glob) for files to detect major file changes.
-                for (_file_id, watcher) in &mut fp_map {
+                for (&file_id, watcher) in &mut fp_map {
+                    self.emitter.emit_file_watch_state(
+                        &watcher.path,
+                        file_id,
+                        watcher.get_file_position(),
+                        watcher.last_read_success(),
+                    );
                     watcher.set_file_findable(false); // assume not findable until found
                 }
                 for path in self.paths_provider.paths().into_iter() {
@@ -226,6 +235,12 @@ where
                 if bytes_read > 0 {
                     global_bytes_read = global_bytes_read.saturating_add(bytes_read);
                     checkpointer.set_checkpoint(file_id, watcher.get_file_position());
+                } else if let Ok(Some(old_position)) = watcher.reset_if_truncated() {
+                    // The file was truncated in place (e.g. copy-truncate
+                    // rotation); start over from the beginning of it.
+                    self.emitter
+                        .emit_file_truncated(&watcher.path, file_id, old_position);
+                    checkpointer.set_checkpoint(file_id, 0);
                 }
                 // Do not move on to newer files if we are behind on an older file
                 if self.oldest_first && maxed_out_reading_single_file {
@@ -378,6 +393,14 @@ pub enum Fingerprinter {
         fingerprint_bytes: usize,
         ignored_header_bytes: usize,
     },
+    /// Checksum of the first line of the file, or of its first
+    /// `max_line_length` bytes if the first line is longer than that. Unlike
+    /// `Checksum` this works for files shorter than a fixed byte count, and
+    /// the fingerprint does not change as the file is appended to.
+    FirstLineChecksum {
+        max_line_length: usize,
+        ignored_header_bytes: usize,
+    },
     DevInode,
 }
 
@@ -407,6 +430,24 @@ impl Fingerprinter {
                 fp.seek(io::SeekFrom::Start(i))?;
                 fp.read_exact(&mut buffer[..b])?;
             }
+            Fingerprinter::FirstLineChecksum {
+                max_line_length,
+                ignored_header_bytes,
+            } => {
+                buffer.clear();
+                let mut fp = fs::File::open(path)?;
+                fp.seek(io::SeekFrom::Start(ignored_header_bytes as u64))?;
+                let mut reader = io::BufReader::new(fp).take(max_line_length as u64);
+                reader.read_until(b'\n', buffer)?;
+                // Until the first line is complete the fingerprint would keep
+                // changing, so treat the file as too small to fingerprint.
+                if buffer.last() != Some(&b'\n') && buffer.len() < max_line_length {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "first line is not yet complete",
+                    ));
+                }
+            }
         }
         let fingerprint = crc::crc64::checksum_ecma(&buffer[..]);
         Ok(fingerprint)
@@ -422,7 +463,7 @@ impl Fingerprinter {
             .map_err(|err| {
                 if err.kind() == io::ErrorKind::UnexpectedEof {
                     if !known_small_files.contains(path) {
-                        warn!(message = "Ignoring file smaller than fingerprint_bytes", file = ?path);
+                        warn!(message = "Ignoring file too small to fingerprint", file = ?path);
                         known_small_files.insert(path.clone());
                     }
                 } else {
@@ -478,6 +519,55 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_first_line_checksum_fingerprinting() {
+        let fingerprinter = Fingerprinter::FirstLineChecksum {
+            max_line_length: 64,
+            ignored_header_bytes: 0,
+        };
+
+        let target_dir = tempdir().unwrap();
+        let empty_path = target_dir.path().join("empty.log");
+        let incomplete_path = target_dir.path().join("incomplete.log");
+        let small_path = target_dir.path().join("small.log");
+        let appended_path = target_dir.path().join("appended.log");
+        let other_path = target_dir.path().join("other.log");
+        let long_path = target_dir.path().join("long.log");
+        fs::write(&empty_path, &[]).unwrap();
+        fs::write(&incomplete_path, b"first line").unwrap();
+        fs::write(&small_path, b"first line\n").unwrap();
+        fs::write(&appended_path, b"first line\nsecond line\n").unwrap();
+        fs::write(&other_path, b"another first line\n").unwrap();
+        fs::write(&long_path, &vec![b'x'; 100]).unwrap();
+
+        let mut buf = Vec::new();
+        assert!(fingerprinter
+            .get_fingerprint_of_file(&empty_path, &mut buf)
+            .is_err());
+        assert!(fingerprinter
+            .get_fingerprint_of_file(&incomplete_path, &mut buf)
+            .is_err());
+        assert!(fingerprinter
+            .get_fingerprint_of_file(&long_path, &mut buf)
+            .is_ok());
+        assert_eq!(
+            fingerprinter
+                .get_fingerprint_of_file(&small_path, &mut buf)
+                .unwrap(),
+            fingerprinter
+                .get_fingerprint_of_file(&appended_path, &mut buf)
+                .unwrap(),
+        );
+        assert_ne!(
+            fingerprinter
+                .get_fingerprint_of_file(&small_path, &mut buf)
+                .unwrap(),
+            fingerprinter
+                .get_fingerprint_of_file(&other_path, &mut buf)
+                .unwrap(),
+        );
+    }
+
     #[test]
     fn test_inode_fingerprinting() {
         let fingerprinter = Fingerprinter::DevInode;
@@ -24,6 +24,7 @@ pub struct FileWatcher {
     file_position: FilePosition,
     devno: u64,
     inode: u64,
+    gzipped: bool,
     is_dead: bool,
     last_read_attempt: Instant,
     last_read_success: Instant,
@@ -53,8 +54,8 @@ impl FileWatcher {
             false
         };
 
-        let (reader, file_position): (Box<dyn BufRead>, FilePosition) = if is_gzipped(&mut reader)?
-        {
+        let gzipped = is_gzipped(&mut reader)?;
+        let (reader, file_position): (Box<dyn BufRead>, FilePosition) = if gzipped {
             if file_position != 0 || too_old {
                 // We can't accurately seek into gzipped files without manually scanning through
                 // the entire thing, so for now we simply refuse to read gzipped files for which we
@@ -90,6 +91,7 @@ impl FileWatcher {
             file_position,
             devno: devno,
             inode: ino,
+            gzipped,
             is_dead: false,
             last_read_attempt: ts.clone(),
             last_read_success: ts,
@@ -112,6 +114,7 @@ impl FileWatcher {
                 Box::new(reader)
             };
             self.reader = new_reader;
+            self.gzipped = gzipped;
             self.devno = file_handle.portable_dev()?;
             self.inode = file_handle.portable_ino()?;
         }
@@ -139,6 +142,39 @@ impl FileWatcher {
         self.file_position
     }
 
+    pub fn last_read_success(&self) -> Instant {
+        self.last_read_success
+    }
+
+    /// Detect whether the underlying file has been truncated
+    ///
+    /// A file whose length is now smaller than our read position has been
+    /// truncated in place, as happens with copy-truncate rotation. In that
+    /// case the file is reopened and reading resumes from the start. Returns
+    /// the position we were at before the reset, if one happened. Gzipped
+    /// files are never checked since our position is in decompressed bytes.
+    pub fn reset_if_truncated(&mut self) -> io::Result<Option<FilePosition>> {
+        if self.gzipped {
+            return Ok(None);
+        }
+
+        // A different file living at our path means the file was rotated by
+        // renaming, which is handled through fingerprinting instead.
+        let file_handle = File::open(&self.path)?;
+        if (file_handle.portable_dev()?, file_handle.portable_ino()?) != (self.devno, self.inode) {
+            return Ok(None);
+        }
+        if file_handle.metadata()?.len() >= self.file_position {
+            return Ok(None);
+        }
+
+        self.reader = Box::new(io::BufReader::new(file_handle));
+
+        let old_position = self.file_position;
+        self.file_position = 0;
+        Ok(Some(old_position))
+    }
+
     /// Read a single line from the underlying file
     ///
     /// This function will attempt to read a new line from its file, blocking,
@@ -261,8 +297,10 @@ fn read_until_with_max_size<R: BufRead + ?Sized>(
 
 #[cfg(test)]
 mod test {
-    use super::read_until_with_max_size;
+    use super::{read_until_with_max_size, FileWatcher};
+    use std::fs;
     use std::io::Cursor;
+    use tempfile::tempdir;
 
     #[test]
     fn test_read_until_with_max_size() {
@@ -310,4 +348,25 @@ mod test {
         assert_eq!(p, 12);
         assert_eq!(v, []);
     }
+
+    #[test]
+    fn test_reset_if_truncated() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("truncated.log");
+        fs::write(&path, b"first\nsecond\n").unwrap();
+
+        let mut watcher = FileWatcher::new(path.clone(), 0, None).unwrap();
+        let mut buf = Vec::new();
+        watcher.read_line(&mut buf, 100).unwrap();
+        watcher.read_line(&mut buf, 100).unwrap();
+        assert_eq!(watcher.get_file_position(), 13);
+        assert_eq!(watcher.reset_if_truncated().unwrap(), None);
+
+        fs::write(&path, b"third\n").unwrap();
+        assert_eq!(watcher.reset_if_truncated().unwrap(), Some(13));
+        assert_eq!(watcher.get_file_position(), 0);
+
+        watcher.read_line(&mut buf, 100).unwrap();
+        assert_eq!(buf, b"third");
+    }
 }
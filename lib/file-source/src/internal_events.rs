@@ -0,0 +1,26 @@
+use crate::{FileFingerprint, FilePosition};
+use std::{path::Path, time::Instant};
+
+/// Hooks through which `FileServer` reports what it is doing with each file
+/// it watches. The file server itself only logs; the embedding application
+/// decides how these are surfaced, e.g. as internal metrics.
+pub trait FileSourceInternalEvents: Send + Sync + Clone + 'static {
+    /// Called for every watched file each time the file server globs, with
+    /// the state that determines whether and where the file is being read.
+    fn emit_file_watch_state(
+        &self,
+        path: &Path,
+        fingerprint: FileFingerprint,
+        file_position: FilePosition,
+        last_read_success: Instant,
+    );
+
+    /// Called when a watched file was found to be shorter than our read
+    /// position and reading has been restarted from the beginning.
+    fn emit_file_truncated(
+        &self,
+        path: &Path,
+        fingerprint: FileFingerprint,
+        old_position: FilePosition,
+    );
+}
@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    conditions::{AnyCondition, Condition, ConditionConfig, ConditionDescription},
+    Event,
+};
+
+//------------------------------------------------------------------------------
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct AllConfig {
+    conditions: Vec<AnyCondition>,
+}
+
+inventory::submit! {
+    ConditionDescription::new::<AllConfig>("all")
+}
+
+#[typetag::serde(name = "all")]
+impl ConditionConfig for AllConfig {
+    fn build(&self) -> crate::Result<Box<dyn Condition>> {
+        if self.conditions.is_empty() {
+            return Err("all condition requires at least one condition".into());
+        }
+        let conditions = self
+            .conditions
+            .iter()
+            .map(AnyCondition::build)
+            .collect::<crate::Result<Vec<_>>>()?;
+        Ok(Box::new(All { conditions }))
+    }
+}
+
+//------------------------------------------------------------------------------
+
+pub struct All {
+    conditions: Vec<Box<dyn Condition>>,
+}
+
+impl Condition for All {
+    fn check(&self, e: &Event) -> bool {
+        self.conditions.iter().all(|c| c.check(e))
+    }
+
+    fn check_with_context(&self, e: &Event) -> Result<(), String> {
+        let errors = self
+            .conditions
+            .iter()
+            .filter_map(|c| c.check_with_context(e).err())
+            .collect::<Vec<_>>();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("conditions failed: [ {} ]", errors.join(", ")))
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Event;
+
+    #[test]
+    fn all_matches_when_every_condition_matches() {
+        let cond = toml::from_str::<AnyCondition>(
+            r#"
+            type = "all"
+            [[conditions]]
+              type = "is_log"
+            [[conditions]]
+              type = "any"
+              [[conditions.conditions]]
+                "message.eq" = "foo"
+              [[conditions.conditions]]
+                "message.eq" = "bar"
+            "#,
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+
+        assert_eq!(cond.check(&Event::from("foo")), true);
+        assert_eq!(cond.check(&Event::from("bar")), true);
+        assert_eq!(cond.check_with_context(&Event::from("bar")), Ok(()));
+        assert_eq!(cond.check(&Event::from("baz")), false);
+        assert_eq!(
+            cond.check_with_context(&Event::from("baz")),
+            Err(
+                "conditions failed: [ no conditions matched: [ predicates failed: [ message.eq: \"foo\" ], predicates failed: [ message.eq: \"bar\" ] ] ]"
+                    .to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn all_requires_conditions() {
+        let config = toml::from_str::<AnyCondition>("type = \"all\"\nconditions = []").unwrap();
+        assert_eq!(
+            config.build().err().unwrap().to_string(),
+            "all condition requires at least one condition"
+        );
+    }
+}
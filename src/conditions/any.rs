@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    conditions::{AnyCondition, Condition, ConditionConfig, ConditionDescription},
+    Event,
+};
+
+//------------------------------------------------------------------------------
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct AnyConfig {
+    conditions: Vec<AnyCondition>,
+}
+
+inventory::submit! {
+    ConditionDescription::new::<AnyConfig>("any")
+}
+
+#[typetag::serde(name = "any")]
+impl ConditionConfig for AnyConfig {
+    fn build(&self) -> crate::Result<Box<dyn Condition>> {
+        if self.conditions.is_empty() {
+            return Err("any condition requires at least one condition".into());
+        }
+        let conditions = self
+            .conditions
+            .iter()
+            .map(AnyCondition::build)
+            .collect::<crate::Result<Vec<_>>>()?;
+        Ok(Box::new(Any { conditions }))
+    }
+}
+
+//------------------------------------------------------------------------------
+
+pub struct Any {
+    conditions: Vec<Box<dyn Condition>>,
+}
+
+impl Condition for Any {
+    fn check(&self, e: &Event) -> bool {
+        self.conditions.iter().any(|c| c.check(e))
+    }
+
+    fn check_with_context(&self, e: &Event) -> Result<(), String> {
+        let mut errors = Vec::new();
+        for condition in &self.conditions {
+            match condition.check_with_context(e) {
+                Ok(()) => return Ok(()),
+                Err(error) => errors.push(error),
+            }
+        }
+        Err(format!("no conditions matched: [ {} ]", errors.join(", ")))
+    }
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Event;
+
+    fn parse(s: &str) -> Box<dyn Condition> {
+        toml::from_str::<AnyCondition>(s).unwrap().build().unwrap()
+    }
+
+    #[test]
+    fn any_matches_when_one_condition_matches() {
+        let cond = parse(
+            r#"
+            type = "any"
+            [[conditions]]
+              type = "is_metric"
+            [[conditions]]
+              "message.eq" = "foo"
+            "#,
+        );
+
+        assert_eq!(cond.check(&Event::from("foo")), true);
+        assert_eq!(cond.check_with_context(&Event::from("foo")), Ok(()));
+        assert_eq!(cond.check(&Event::from("bar")), false);
+        assert_eq!(
+            cond.check_with_context(&Event::from("bar")),
+            Err(
+                "no conditions matched: [ event is not a metric type, predicates failed: [ message.eq: \"foo\" ] ]"
+                    .to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn any_requires_conditions() {
+        let config = toml::from_str::<AnyCondition>("type = \"any\"\nconditions = []").unwrap();
+        assert_eq!(
+            config.build().err().unwrap().to_string(),
+            "any condition requires at least one condition"
+        );
+    }
+}
@@ -6,6 +6,8 @@ use crate::{
 use indexmap::IndexMap;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::str::FromStr;
 use string_cache::DefaultAtom as Atom;
 
 #[derive(Deserialize, Serialize, Clone, Derivative)]
@@ -20,6 +22,8 @@ pub enum CheckFieldsPredicateArg {
     Float(f64),
     #[derivative(Debug = "transparent")]
     Boolean(bool),
+    #[derivative(Debug = "transparent")]
+    Array(Vec<CheckFieldsPredicateArg>),
 }
 
 pub trait CheckFieldsPredicate: std::fmt::Debug + Send + Sync {
@@ -39,41 +43,57 @@ impl EqualsPredicate {
         target: String,
         arg: &CheckFieldsPredicateArg,
     ) -> Result<Box<dyn CheckFieldsPredicate>, String> {
-        Ok(Box::new(Self {
-            target: target.into(),
-            arg: arg.clone(),
-        }))
+        match arg {
+            CheckFieldsPredicateArg::Array(_) => {
+                Err("equals predicate requires a scalar argument, use in for arrays".to_owned())
+            }
+            _ => Ok(Box::new(Self {
+                target: target.into(),
+                arg: arg.clone(),
+            })),
+        }
+    }
+}
+
+fn value_equals(arg: &CheckFieldsPredicateArg, v: &Value) -> bool {
+    match arg {
+        CheckFieldsPredicateArg::String(s) => s.as_bytes() == v.as_bytes(),
+        CheckFieldsPredicateArg::Integer(i) => match v {
+            Value::Integer(vi) => *i == *vi,
+            Value::Float(vf) => *i == *vf as i64,
+            _ => false,
+        },
+        CheckFieldsPredicateArg::Float(f) => match v {
+            Value::Float(vf) => *f == *vf,
+            Value::Integer(vi) => *f == *vi as f64,
+            _ => false,
+        },
+        CheckFieldsPredicateArg::Boolean(b) => match v {
+            Value::Boolean(vb) => *b == *vb,
+            _ => false,
+        },
+        CheckFieldsPredicateArg::Array(_) => false,
+    }
+}
+
+fn tag_equals(arg: &CheckFieldsPredicateArg, v: &str) -> bool {
+    match arg {
+        CheckFieldsPredicateArg::String(s) => s.as_bytes() == v.as_bytes(),
+        _ => false,
     }
 }
 
 impl CheckFieldsPredicate for EqualsPredicate {
     fn check(&self, event: &Event) -> bool {
         match event {
-            Event::Log(l) => l.get(&self.target).map_or(false, |v| match &self.arg {
-                CheckFieldsPredicateArg::String(s) => s.as_bytes() == v.as_bytes(),
-                CheckFieldsPredicateArg::Integer(i) => match v {
-                    Value::Integer(vi) => *i == *vi,
-                    Value::Float(vf) => *i == *vf as i64,
-                    _ => false,
-                },
-                CheckFieldsPredicateArg::Float(f) => match v {
-                    Value::Float(vf) => *f == *vf,
-                    Value::Integer(vi) => *f == *vi as f64,
-                    _ => false,
-                },
-                CheckFieldsPredicateArg::Boolean(b) => match v {
-                    Value::Boolean(vb) => *b == *vb,
-                    _ => false,
-                },
-            }),
+            Event::Log(l) => l
+                .get(&self.target)
+                .map_or(false, |v| value_equals(&self.arg, v)),
             Event::Metric(m) => m
                 .tags
                 .as_ref()
                 .and_then(|t| t.get(self.target.as_ref()))
-                .map_or(false, |v| match &self.arg {
-                    CheckFieldsPredicateArg::String(s) => s.as_bytes() == v.as_bytes(),
-                    _ => false,
-                }),
+                .map_or(false, |v| tag_equals(&self.arg, v)),
         }
     }
 }
@@ -200,6 +220,12 @@ impl NotEqualsPredicate {
                 CheckFieldsPredicateArg::Integer(a) => format!("{}", a),
                 CheckFieldsPredicateArg::Float(a) => format!("{}", a),
                 CheckFieldsPredicateArg::Boolean(a) => format!("{}", a),
+                CheckFieldsPredicateArg::Array(_) => {
+                    return Err(
+                        "not_equals predicate requires a scalar argument, use not_in for arrays"
+                            .to_owned(),
+                    )
+                }
             },
         }))
     }
@@ -298,6 +324,247 @@ impl CheckFieldsPredicate for ExistsPredicate {
 
 //------------------------------------------------------------------------------
 
+#[derive(Debug, Clone, Copy)]
+enum Comparison {
+    GreaterThan,
+    GreaterThanOrEqual,
+    LessThan,
+    LessThanOrEqual,
+}
+
+impl Comparison {
+    fn name(self) -> &'static str {
+        match self {
+            Comparison::GreaterThan => "gt",
+            Comparison::GreaterThanOrEqual => "gte",
+            Comparison::LessThan => "lt",
+            Comparison::LessThanOrEqual => "lte",
+        }
+    }
+
+    fn compare(self, value: f64, arg: f64) -> bool {
+        match self {
+            Comparison::GreaterThan => value > arg,
+            Comparison::GreaterThanOrEqual => value >= arg,
+            Comparison::LessThan => value < arg,
+            Comparison::LessThanOrEqual => value <= arg,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ComparisonPredicate {
+    target: Atom,
+    comparison: Comparison,
+    arg: f64,
+}
+
+impl ComparisonPredicate {
+    pub fn new(
+        target: String,
+        arg: &CheckFieldsPredicateArg,
+        comparison: Comparison,
+    ) -> Result<Box<dyn CheckFieldsPredicate>, String> {
+        let arg = match arg {
+            CheckFieldsPredicateArg::Integer(i) => *i as f64,
+            CheckFieldsPredicateArg::Float(f) => *f,
+            _ => {
+                return Err(format!(
+                    "{} predicate requires a numeric argument",
+                    comparison.name()
+                ))
+            }
+        };
+        Ok(Box::new(Self {
+            target: target.into(),
+            comparison,
+            arg,
+        }))
+    }
+}
+
+/// Numeric fields are compared as they are, strings are parsed as numbers so
+/// that values extracted by parsers without type coercion can be compared.
+fn value_as_f64(v: &Value) -> Option<f64> {
+    match v {
+        Value::Integer(i) => Some(*i as f64),
+        Value::Float(f) => Some(*f),
+        Value::Bytes(_) => v.to_string_lossy().trim().parse().ok(),
+        _ => None,
+    }
+}
+
+impl CheckFieldsPredicate for ComparisonPredicate {
+    fn check(&self, event: &Event) -> bool {
+        let value = match event {
+            Event::Log(l) => l.get(&self.target).and_then(value_as_f64),
+            Event::Metric(m) => m
+                .tags
+                .as_ref()
+                .and_then(|t| t.get(self.target.as_ref()))
+                .and_then(|v| v.trim().parse().ok()),
+        };
+        value.map_or(false, |v| self.comparison.compare(v, self.arg))
+    }
+}
+
+//------------------------------------------------------------------------------
+
+#[derive(Debug, Clone)]
+struct InPredicate {
+    target: Atom,
+    args: Vec<CheckFieldsPredicateArg>,
+}
+
+impl InPredicate {
+    pub fn new(
+        target: String,
+        arg: &CheckFieldsPredicateArg,
+    ) -> Result<Box<dyn CheckFieldsPredicate>, String> {
+        match arg {
+            CheckFieldsPredicateArg::Array(args)
+                if args.iter().all(|a| match a {
+                    CheckFieldsPredicateArg::Array(_) => false,
+                    _ => true,
+                }) =>
+            {
+                Ok(Box::new(Self {
+                    target: target.into(),
+                    args: args.clone(),
+                }))
+            }
+            _ => Err("in predicate requires an array of scalar arguments".to_owned()),
+        }
+    }
+}
+
+impl CheckFieldsPredicate for InPredicate {
+    fn check(&self, event: &Event) -> bool {
+        match event {
+            Event::Log(l) => l
+                .get(&self.target)
+                .map_or(false, |v| self.args.iter().any(|a| value_equals(a, v))),
+            Event::Metric(m) => m
+                .tags
+                .as_ref()
+                .and_then(|t| t.get(self.target.as_ref()))
+                .map_or(false, |v| self.args.iter().any(|a| tag_equals(a, v))),
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq)]
+struct IpNetwork {
+    address: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNetwork {
+    fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.address, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::max_value()
+                    .checked_shl(32 - u32::from(self.prefix_len))
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::max_value()
+                    .checked_shl(128 - u32::from(self.prefix_len))
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(*ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| format!("Invalid CIDR \"{}\": {}", s, reason);
+        let mut parts = s.splitn(2, '/');
+        let address = parts
+            .next()
+            .unwrap_or_default()
+            .parse::<IpAddr>()
+            .map_err(|error| invalid(&error.to_string()))?;
+        let max_len = match address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix_len = match parts.next() {
+            Some(len) => len
+                .parse::<u8>()
+                .ok()
+                .filter(|len| *len <= max_len)
+                .ok_or_else(|| invalid("invalid prefix length"))?,
+            None => max_len,
+        };
+        Ok(Self {
+            address,
+            prefix_len,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct IpCidrPredicate {
+    target: Atom,
+    networks: Vec<IpNetwork>,
+}
+
+impl IpCidrPredicate {
+    pub fn new(
+        target: String,
+        arg: &CheckFieldsPredicateArg,
+    ) -> Result<Box<dyn CheckFieldsPredicate>, String> {
+        let requires = || "ip_cidr predicate requires a string or array of strings argument";
+        let networks = match arg {
+            CheckFieldsPredicateArg::String(s) => vec![s.parse()?],
+            CheckFieldsPredicateArg::Array(args) => args
+                .iter()
+                .map(|a| match a {
+                    CheckFieldsPredicateArg::String(s) => s.parse(),
+                    _ => Err(requires().to_owned()),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => return Err(requires().to_owned()),
+        };
+        Ok(Box::new(Self {
+            target: target.into(),
+            networks,
+        }))
+    }
+
+    fn matches(&self, ip: &str) -> bool {
+        ip.trim()
+            .parse::<IpAddr>()
+            .map(|ip| self.networks.iter().any(|n| n.contains(&ip)))
+            .unwrap_or(false)
+    }
+}
+
+impl CheckFieldsPredicate for IpCidrPredicate {
+    fn check(&self, event: &Event) -> bool {
+        match event {
+            Event::Log(l) => l
+                .get(&self.target)
+                .map_or(false, |v| self.matches(&v.to_string_lossy())),
+            Event::Metric(m) => m
+                .tags
+                .as_ref()
+                .and_then(|t| t.get(self.target.as_ref()))
+                .map_or(false, |v| self.matches(v)),
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+
 #[derive(Debug)]
 struct NegatePredicate {
     subpred: Box<dyn CheckFieldsPredicate>,
@@ -342,6 +609,12 @@ fn build_predicate(
         "ends_with" => EndsWithPredicate::new(target, arg),
         "exists" => ExistsPredicate::new(target, arg),
         "regex" => RegexPredicate::new(target, arg),
+        "gt" => ComparisonPredicate::new(target, arg, Comparison::GreaterThan),
+        "gte" => ComparisonPredicate::new(target, arg, Comparison::GreaterThanOrEqual),
+        "lt" => ComparisonPredicate::new(target, arg, Comparison::LessThan),
+        "lte" => ComparisonPredicate::new(target, arg, Comparison::LessThanOrEqual),
+        "in" => InPredicate::new(target, arg),
+        "ip_cidr" => IpCidrPredicate::new(target, arg),
         _ if predicate.starts_with("not_") => NegatePredicate::new(&predicate[4..], target, arg),
         _ => Err(format!("predicate type '{}' not recognized", predicate)),
     }
@@ -846,4 +1119,152 @@ mod test {
             Err("predicates failed: [ foo.not_exists: true ]".into())
         );
     }
+
+    #[test]
+    fn check_field_numeric_comparisons() {
+        let mut preds: IndexMap<String, CheckFieldsPredicateArg> = IndexMap::new();
+        preds.insert("status.gte".into(), CheckFieldsPredicateArg::Integer(500));
+        preds.insert("status.lt".into(), CheckFieldsPredicateArg::Integer(600));
+        preds.insert("duration.gt".into(), CheckFieldsPredicateArg::Float(0.5));
+        preds.insert("duration.lte".into(), CheckFieldsPredicateArg::Integer(2));
+
+        let cond = CheckFieldsConfig { predicates: preds }.build().unwrap();
+
+        let mut event = Event::from("ignored field");
+        event.as_mut_log().insert("status", 503);
+        event.as_mut_log().insert("duration", 1.5);
+        assert_eq!(cond.check(&event), true);
+        assert_eq!(cond.check_with_context(&event), Ok(()));
+
+        event.as_mut_log().insert("status", "404");
+        event.as_mut_log().insert("duration", 2.5);
+        assert_eq!(cond.check(&event), false);
+        assert_eq!(
+            cond.check_with_context(&event),
+            Err("predicates failed: [ status.gte: 500, duration.lte: 2 ]".to_owned())
+        );
+
+        event.as_mut_log().insert("status", "600");
+        event.as_mut_log().insert("duration", "not a number");
+        assert_eq!(
+            cond.check_with_context(&event),
+            Err(
+                "predicates failed: [ status.lt: 600, duration.gt: 0.5, duration.lte: 2 ]"
+                    .to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn check_field_in() {
+        let mut preds: IndexMap<String, CheckFieldsPredicateArg> = IndexMap::new();
+        preds.insert(
+            "level.in".into(),
+            CheckFieldsPredicateArg::Array(vec![
+                CheckFieldsPredicateArg::String("error".into()),
+                CheckFieldsPredicateArg::String("warn".into()),
+            ]),
+        );
+        preds.insert(
+            "status.not_in".into(),
+            CheckFieldsPredicateArg::Array(vec![
+                CheckFieldsPredicateArg::Integer(200),
+                CheckFieldsPredicateArg::Integer(204),
+            ]),
+        );
+
+        let cond = CheckFieldsConfig { predicates: preds }.build().unwrap();
+
+        let mut event = Event::from("ignored field");
+        event.as_mut_log().insert("level", "warn");
+        event.as_mut_log().insert("status", 500);
+        assert_eq!(cond.check(&event), true);
+        assert_eq!(cond.check_with_context(&event), Ok(()));
+
+        event.as_mut_log().insert("level", "info");
+        event.as_mut_log().insert("status", 204);
+        assert_eq!(cond.check(&event), false);
+        assert_eq!(
+            cond.check_with_context(&event),
+            Err(
+                "predicates failed: [ level.in: [\"error\", \"warn\"], status.not_in: [200, 204] ]"
+                    .to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn check_field_ip_cidr() {
+        let mut preds: IndexMap<String, CheckFieldsPredicateArg> = IndexMap::new();
+        preds.insert(
+            "client.ip_cidr".into(),
+            CheckFieldsPredicateArg::Array(vec![
+                CheckFieldsPredicateArg::String("10.0.0.0/8".into()),
+                CheckFieldsPredicateArg::String("2001:db8::/32".into()),
+            ]),
+        );
+
+        let cond = CheckFieldsConfig { predicates: preds }.build().unwrap();
+
+        let mut event = Event::from("ignored field");
+        for (ip, expected) in &[
+            ("10.1.2.3", true),
+            ("11.1.2.3", false),
+            ("2001:db8:1::1", true),
+            ("2001:db9::1", false),
+            ("not an ip", false),
+        ] {
+            event.as_mut_log().insert("client", *ip);
+            assert_eq!(cond.check(&event), *expected, "{}", ip);
+        }
+
+        let mut preds: IndexMap<String, CheckFieldsPredicateArg> = IndexMap::new();
+        preds.insert(
+            "client.ip_cidr".into(),
+            CheckFieldsPredicateArg::String("0.0.0.0/0".into()),
+        );
+        let cond = CheckFieldsConfig { predicates: preds }.build().unwrap();
+        event.as_mut_log().insert("client", "192.168.0.1");
+        assert_eq!(cond.check(&event), true);
+    }
+
+    #[test]
+    fn check_new_predicate_errors() {
+        let cases = vec![
+            (
+                "foo.gt",
+                CheckFieldsPredicateArg::String("1".into()),
+                "gt predicate requires a numeric argument",
+            ),
+            (
+                "foo.in",
+                CheckFieldsPredicateArg::String("bar".into()),
+                "in predicate requires an array of scalar arguments",
+            ),
+            (
+                "foo.ip_cidr",
+                CheckFieldsPredicateArg::String("10.0.0.0/33".into()),
+                "Invalid CIDR \"10.0.0.0/33\": invalid prefix length",
+            ),
+            (
+                "foo.eq",
+                CheckFieldsPredicateArg::Array(vec![]),
+                "equals predicate requires a scalar argument, use in for arrays",
+            ),
+        ];
+
+        for (pred, arg, exp) in cases {
+            let mut preds: IndexMap<String, CheckFieldsPredicateArg> = IndexMap::new();
+            preds.insert(pred.into(), arg);
+
+            assert_eq!(
+                CheckFieldsConfig { predicates: preds }
+                    .build()
+                    .err()
+                    .unwrap()
+                    .to_string(),
+                exp.to_owned()
+            );
+        }
+    }
 }
@@ -3,9 +3,12 @@ use crate::Event;
 use inventory;
 use serde::{Deserialize, Serialize};
 
+pub mod all;
+pub mod any;
 pub mod check_fields;
 pub mod is_log;
 pub mod is_metric;
+pub mod not;
 
 pub use check_fields::CheckFieldsConfig;
 
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    conditions::{AnyCondition, Condition, ConditionConfig, ConditionDescription},
+    Event,
+};
+
+//------------------------------------------------------------------------------
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct NotConfig {
+    condition: Box<AnyCondition>,
+}
+
+inventory::submit! {
+    ConditionDescription::new::<NotConfig>("not")
+}
+
+#[typetag::serde(name = "not")]
+impl ConditionConfig for NotConfig {
+    fn build(&self) -> crate::Result<Box<dyn Condition>> {
+        Ok(Box::new(Not {
+            condition: self.condition.build()?,
+        }))
+    }
+}
+
+//------------------------------------------------------------------------------
+
+pub struct Not {
+    condition: Box<dyn Condition>,
+}
+
+impl Condition for Not {
+    fn check(&self, e: &Event) -> bool {
+        !self.condition.check(e)
+    }
+
+    fn check_with_context(&self, e: &Event) -> Result<(), String> {
+        if self.check(e) {
+            Ok(())
+        } else {
+            Err("negated condition matched".to_string())
+        }
+    }
+}
+
+//------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Event;
+
+    #[test]
+    fn not_negates_nested_condition() {
+        let cond = toml::from_str::<AnyCondition>(
+            r#"
+            type = "not"
+            [condition]
+              "message.starts_with" = "debug"
+            "#,
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+
+        assert_eq!(cond.check(&Event::from("info: started")), true);
+        assert_eq!(
+            cond.check_with_context(&Event::from("info: started")),
+            Ok(())
+        );
+        assert_eq!(cond.check(&Event::from("debug: started")), false);
+        assert_eq!(
+            cond.check_with_context(&Event::from("debug: started")),
+            Err("negated condition matched".to_owned())
+        );
+    }
+}
@@ -0,0 +1,564 @@
+//! Enrichment tables: reference datasets loaded from local files that
+//! transforms can look rows up in. See [`EnrichmentTable`].
+
+use crate::event::Value;
+use arc_swap::ArcSwap;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use snafu::{ResultExt, Snafu};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// How often a table checks whether its file has changed on disk.
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct EnrichmentTableConfig {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub encoding: Encoding,
+    #[serde(default)]
+    pub index: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    /// CSV with a header row naming the columns.
+    Csv,
+    /// One JSON object per line.
+    Json,
+}
+
+impl Default for Encoding {
+    fn default() -> Self {
+        Encoding::Csv
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Matching {
+    Exact,
+    CaseInsensitive,
+}
+
+impl Default for Matching {
+    fn default() -> Self {
+        Matching::Exact
+    }
+}
+
+#[derive(Debug, Snafu)]
+pub enum LoadError {
+    #[snafu(display("Could not open {:?}: {}", path, source))]
+    OpenFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[snafu(display("Could not read {:?}: {}", path, source))]
+    ReadFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[snafu(display("Invalid CSV in {:?}: {}", path, source))]
+    ParseCsv { path: PathBuf, source: csv::Error },
+    #[snafu(display("Invalid JSON on line {} of {:?}: {}", line, path, source))]
+    ParseJson {
+        path: PathBuf,
+        line: usize,
+        source: serde_json::Error,
+    },
+    #[snafu(display("Line {} of {:?} is not a JSON object", line, path))]
+    NotAnObject { path: PathBuf, line: usize },
+    #[snafu(display("Index column {:?} is not present in {:?}", column, path))]
+    MissingIndexColumn { path: PathBuf, column: String },
+}
+
+/// A single row of an enrichment table, keyed by column name.
+pub type Row = BTreeMap<String, Value>;
+
+/// Identifies a version of the file, so that changes can be detected
+/// without reading it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Fingerprint {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl Fingerprint {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+struct TableData {
+    fingerprint: Option<Fingerprint>,
+    rows: Vec<Row>,
+    /// Row positions keyed by the lowercased values of the index columns.
+    index: HashMap<Vec<String>, Vec<usize>>,
+}
+
+impl TableData {
+    fn load(config: &EnrichmentTableConfig) -> Result<Self, LoadError> {
+        let fingerprint = Fingerprint::of(&config.path);
+        let rows = match config.encoding {
+            Encoding::Csv => read_csv(config)?,
+            Encoding::Json => read_json(config)?,
+        };
+
+        let mut index: HashMap<Vec<String>, Vec<usize>> = HashMap::new();
+        if !config.index.is_empty() {
+            for (position, row) in rows.iter().enumerate() {
+                let key = config
+                    .index
+                    .iter()
+                    .map(|column| row.get(column).map(lowercase))
+                    .collect::<Option<Vec<_>>>();
+                // Rows lacking an index column can never match a lookup.
+                if let Some(key) = key {
+                    index.entry(key).or_default().push(position);
+                }
+            }
+        }
+
+        Ok(Self {
+            fingerprint,
+            rows,
+            index,
+        })
+    }
+
+    fn find(
+        &self,
+        index_columns: &[String],
+        criteria: &[(&str, String)],
+        matching: Matching,
+    ) -> Option<&Row> {
+        let indexed = !index_columns.is_empty()
+            && index_columns.len() == criteria.len()
+            && criteria
+                .iter()
+                .all(|(column, _)| index_columns.iter().any(|c| c == column));
+
+        if indexed {
+            let key = index_columns
+                .iter()
+                .map(|column| {
+                    criteria
+                        .iter()
+                        .find(|(c, _)| c == column)
+                        .map(|(_, value)| value.to_lowercase())
+                })
+                .collect::<Option<Vec<_>>>()?;
+            self.index
+                .get(&key)?
+                .iter()
+                .map(|&position| &self.rows[position])
+                .find(|row| row_matches(row, criteria, matching))
+        } else {
+            self.rows
+                .iter()
+                .find(|row| row_matches(row, criteria, matching))
+        }
+    }
+}
+
+fn lowercase(value: &Value) -> String {
+    value.to_string_lossy().to_lowercase()
+}
+
+fn row_matches(row: &Row, criteria: &[(&str, String)], matching: Matching) -> bool {
+    criteria.iter().all(|(column, expected)| {
+        row.get(*column).map_or(false, |value| {
+            let value = value.to_string_lossy();
+            match matching {
+                Matching::Exact => &value == expected,
+                Matching::CaseInsensitive => value.to_lowercase() == expected.to_lowercase(),
+            }
+        })
+    })
+}
+
+fn read_csv(config: &EnrichmentTableConfig) -> Result<Vec<Row>, LoadError> {
+    let path = &config.path;
+    let mut reader = csv::Reader::from_path(path).context(ParseCsv { path })?;
+    let headers = reader.headers().context(ParseCsv { path })?.clone();
+
+    if let Some(column) = config
+        .index
+        .iter()
+        .find(|column| !headers.iter().any(|header| header == column.as_str()))
+    {
+        return Err(LoadError::MissingIndexColumn {
+            path: path.clone(),
+            column: column.clone(),
+        });
+    }
+
+    reader
+        .records()
+        .map(|record| {
+            let record = record.context(ParseCsv { path })?;
+            Ok(headers
+                .iter()
+                .zip(record.iter())
+                .map(|(header, field)| (header.to_owned(), Value::from(field)))
+                .collect())
+        })
+        .collect()
+}
+
+fn read_json(config: &EnrichmentTableConfig) -> Result<Vec<Row>, LoadError> {
+    let path = &config.path;
+    let file = File::open(path).context(OpenFile { path })?;
+
+    let mut rows = Vec::new();
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.context(ReadFile { path })?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let line_number = number + 1;
+        match serde_json::from_str(&line).context(ParseJson {
+            path,
+            line: line_number,
+        })? {
+            JsonValue::Object(object) => rows.push(
+                object
+                    .into_iter()
+                    .map(|(key, value)| (key, Value::from(value)))
+                    .collect(),
+            ),
+            _ => {
+                return Err(LoadError::NotAnObject {
+                    path: path.clone(),
+                    line: line_number,
+                })
+            }
+        }
+    }
+    Ok(rows)
+}
+
+struct Inner {
+    name: String,
+    config: EnrichmentTableConfig,
+    data: ArcSwap<TableData>,
+}
+
+/// A handle to a loaded enrichment table. Handles are cheap to clone and
+/// share the same data, which tables loaded for a config reload in the
+/// background when the underlying file changes.
+#[derive(Clone)]
+pub struct EnrichmentTable {
+    inner: Arc<Inner>,
+}
+
+impl EnrichmentTable {
+    /// Load the table from its file.
+    pub fn load(name: &str, config: &EnrichmentTableConfig) -> Result<Self, LoadError> {
+        let data = TableData::load(config)?;
+        Ok(Self {
+            inner: Arc::new(Inner {
+                name: name.to_owned(),
+                config: config.clone(),
+                data: ArcSwap::from_pointee(data),
+            }),
+        })
+    }
+
+    /// Checks the file for changes on a background thread, so lookups never
+    /// wait for a reload. The thread ends once all handles are dropped.
+    fn watch(&self) {
+        let inner = Arc::downgrade(&self.inner);
+        let spawned = thread::Builder::new()
+            .name(format!("enrichment-table-{}", self.inner.name))
+            .spawn(move || loop {
+                thread::sleep(RELOAD_CHECK_INTERVAL);
+                match inner.upgrade() {
+                    Some(inner) => EnrichmentTable { inner }.reload_if_changed(),
+                    None => break,
+                };
+            });
+        if let Err(error) = spawned {
+            error!(
+                message = "Failed to watch enrichment table for changes.",
+                name = %self.inner.name,
+                %error
+            );
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    /// Find the first row whose columns match all the `(column, value)`
+    /// criteria. Values are compared by their string representation.
+    pub fn find(&self, criteria: &[(&str, String)], matching: Matching) -> Option<Row> {
+        self.inner
+            .data
+            .load()
+            .find(&self.inner.config.index, criteria, matching)
+            .cloned()
+    }
+
+    /// Reload the data if the file changed since it was last loaded.
+    /// Returns whether the data was replaced.
+    fn reload_if_changed(&self) -> bool {
+        let fingerprint = Fingerprint::of(&self.inner.config.path);
+        if fingerprint.is_none() || fingerprint == self.inner.data.load().fingerprint {
+            return false;
+        }
+
+        match TableData::load(&self.inner.config) {
+            Ok(data) => {
+                info!(
+                    message = "Reloaded enrichment table.",
+                    name = %self.inner.name,
+                    rows = data.rows.len()
+                );
+                self.inner.data.store(Arc::new(data));
+                true
+            }
+            Err(error) => {
+                error!(
+                    message = "Failed to reload enrichment table, keeping previous data.",
+                    name = %self.inner.name,
+                    %error,
+                    rate_limit_secs = 30
+                );
+                false
+            }
+        }
+    }
+}
+
+impl fmt::Debug for EnrichmentTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EnrichmentTable")
+            .field("name", &self.inner.name)
+            .field("path", &self.inner.config.path)
+            .finish()
+    }
+}
+
+/// All the enrichment tables of a config, by name.
+#[derive(Clone, Debug, Default)]
+pub struct EnrichmentTables {
+    tables: HashMap<String, EnrichmentTable>,
+}
+
+impl EnrichmentTables {
+    pub fn load(configs: &IndexMap<String, EnrichmentTableConfig>) -> Result<Self, Vec<String>> {
+        let mut tables = HashMap::new();
+        let mut errors = Vec::new();
+
+        for (name, config) in configs {
+            match EnrichmentTable::load(name, config) {
+                Ok(table) => {
+                    table.watch();
+                    tables.insert(name.clone(), table);
+                }
+                Err(error) => errors.push(format!("Enrichment table \"{}\": {}", name, error)),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(Self { tables })
+        } else {
+            Err(errors)
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<EnrichmentTable> {
+        self.tables.get(name).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::temp_file;
+    use std::fs;
+
+    fn table(contents: &str, encoding: Encoding, index: &[&str]) -> EnrichmentTable {
+        let path = temp_file();
+        fs::write(&path, contents).unwrap();
+        let config = EnrichmentTableConfig {
+            path,
+            encoding,
+            index: index.iter().map(|&column| column.to_owned()).collect(),
+        };
+        EnrichmentTable::load("test", &config).unwrap()
+    }
+
+    fn criteria(pairs: &[(&'static str, &str)]) -> Vec<(&'static str, String)> {
+        pairs
+            .iter()
+            .map(|&(column, value)| (column, value.to_owned()))
+            .collect()
+    }
+
+    const USERS_CSV: &str = "id,name,team\n1,Alice,Platform\n2,Bob,Security\n3,alice,Sales\n";
+
+    #[test]
+    fn csv_exact_lookup() {
+        let table = table(USERS_CSV, Encoding::Csv, &["id"]);
+
+        let row = table
+            .find(&criteria(&[("id", "2")]), Matching::Exact)
+            .unwrap();
+        assert_eq!(row["name"], Value::from("Bob"));
+        assert_eq!(row["team"], Value::from("Security"));
+
+        assert!(table
+            .find(&criteria(&[("id", "4")]), Matching::Exact)
+            .is_none());
+    }
+
+    #[test]
+    fn csv_case_insensitive_lookup() {
+        let table = table(USERS_CSV, Encoding::Csv, &["name"]);
+
+        let row = table
+            .find(&criteria(&[("name", "alice")]), Matching::Exact)
+            .unwrap();
+        assert_eq!(row["team"], Value::from("Sales"));
+
+        let row = table
+            .find(&criteria(&[("name", "ALICE")]), Matching::CaseInsensitive)
+            .unwrap();
+        assert_eq!(row["team"], Value::from("Platform"));
+
+        assert!(table
+            .find(&criteria(&[("name", "ALICE")]), Matching::Exact)
+            .is_none());
+    }
+
+    #[test]
+    fn lookup_on_unindexed_columns() {
+        let table = table(USERS_CSV, Encoding::Csv, &["id"]);
+
+        let row = table
+            .find(
+                &criteria(&[("name", "Bob"), ("team", "Security")]),
+                Matching::Exact,
+            )
+            .unwrap();
+        assert_eq!(row["id"], Value::from("2"));
+    }
+
+    #[test]
+    fn json_lines_lookup() {
+        let table = table(
+            "{\"code\": 404, \"reason\": \"Not Found\"}\n\n{\"code\": 500, \"reason\": \"Internal Server Error\"}\n",
+            Encoding::Json,
+            &["code"],
+        );
+
+        let row = table
+            .find(&criteria(&[("code", "500")]), Matching::Exact)
+            .unwrap();
+        assert_eq!(row["reason"], Value::from("Internal Server Error"));
+        assert_eq!(row["code"], Value::from(500));
+    }
+
+    #[test]
+    fn load_errors() {
+        let path = temp_file();
+        fs::write(&path, USERS_CSV).unwrap();
+        let config = EnrichmentTableConfig {
+            path: path.clone(),
+            encoding: Encoding::Csv,
+            index: vec!["email".into()],
+        };
+        assert!(EnrichmentTable::load("test", &config).is_err());
+
+        fs::write(&path, "{\"code\": 404}\n[1, 2]\n").unwrap();
+        let config = EnrichmentTableConfig {
+            path,
+            encoding: Encoding::Json,
+            index: vec![],
+        };
+        let error = EnrichmentTable::load("test", &config).unwrap_err();
+        assert!(error.to_string().contains("Line 2"));
+
+        let config = EnrichmentTableConfig {
+            path: temp_file(),
+            encoding: Encoding::Json,
+            index: vec![],
+        };
+        assert!(EnrichmentTable::load("test", &config).is_err());
+    }
+
+    #[test]
+    fn reloads_changed_file() {
+        let path = temp_file();
+        fs::write(&path, USERS_CSV).unwrap();
+        let config = EnrichmentTableConfig {
+            path: path.clone(),
+            encoding: Encoding::Csv,
+            index: vec!["id".into()],
+        };
+        let table = EnrichmentTable::load("test", &config).unwrap();
+        assert!(!table.reload_if_changed());
+
+        fs::write(&path, "id,name,team\n4,Carol,Data Engineering\n").unwrap();
+        assert!(table.reload_if_changed());
+        let row = table
+            .find(&criteria(&[("id", "4")]), Matching::Exact)
+            .unwrap();
+        assert_eq!(row["name"], Value::from("Carol"));
+
+        // An invalid file keeps the previous data around.
+        fs::write(&path, "id,name\n5,Dave,extra,fields\n").unwrap();
+        assert!(!table.reload_if_changed());
+        assert!(table
+            .find(&criteria(&[("id", "4")]), Matching::Exact)
+            .is_some());
+    }
+
+    #[test]
+    fn reloads_in_background() {
+        let path = temp_file();
+        fs::write(&path, USERS_CSV).unwrap();
+        let mut configs = IndexMap::new();
+        configs.insert(
+            "users".to_owned(),
+            EnrichmentTableConfig {
+                path: path.clone(),
+                encoding: Encoding::Csv,
+                index: vec!["id".into()],
+            },
+        );
+        let table = EnrichmentTables::load(&configs)
+            .unwrap()
+            .get("users")
+            .unwrap();
+
+        fs::write(&path, "id,name,team\n4,Carol,Data Engineering\n").unwrap();
+        let lookup = criteria(&[("id", "4")]);
+        let reloaded = (0..50).any(|_| {
+            thread::sleep(Duration::from_millis(100));
+            table.find(&lookup, Matching::Exact).is_some()
+        });
+        assert!(reloaded);
+    }
+}
@@ -88,6 +88,13 @@ where
         Some((value, expired))
     }
 
+    /// Remove all the keys, returning the values and the expiration
+    /// information in no particular order.
+    pub fn drain(&mut self) -> Vec<ExpiredItem<K, V>> {
+        let keys: Vec<K> = self.map.keys().cloned().collect();
+        keys.iter().filter_map(|key| self.remove(key)).collect()
+    }
+
     /// Check whether the [`ExpiringHashMap`] is empty.
     /// If it's empty, the `next_expired` function immediately resolves to
     /// [`None`]. Be aware that this may cause a spinlock behaviour if the
@@ -286,4 +293,23 @@ mod tests {
         let mut fut = task::spawn(map.next_expired());
         assert_pending!(fut.poll());
     }
+
+    #[tokio::test]
+    async fn drain_removes_all_items() {
+        let mut map = ExpiringHashMap::<String, String>::new();
+
+        let ttl = Duration::from_secs(1000);
+        map.insert("a".to_owned(), "val a".to_owned(), ttl);
+        map.insert("b".to_owned(), "val b".to_owned(), ttl);
+
+        let mut values = map
+            .drain()
+            .into_iter()
+            .map(|(value, _)| value)
+            .collect::<Vec<_>>();
+        values.sort();
+        assert_eq!(values, vec!["val a".to_owned(), "val b".to_owned()]);
+        assert!(map.is_empty());
+        assert!(map.get("a").is_none());
+    }
 }
@@ -1,5 +1,7 @@
 use super::InternalEvent;
+use file_source::{FileFingerprint, FilePosition, FileSourceInternalEvents};
 use metrics::counter;
+use std::{path::Path, time::Instant};
 
 #[derive(Debug)]
 pub struct FileEventReceived<'a> {
@@ -29,3 +31,82 @@ impl InternalEvent for FileEventReceived<'_> {
         );
     }
 }
+
+#[derive(Debug)]
+pub struct FileWatchState<'a> {
+    pub path: &'a Path,
+    pub fingerprint: FileFingerprint,
+    pub file_position: FilePosition,
+    pub last_read_success: Instant,
+}
+
+impl InternalEvent for FileWatchState<'_> {
+    fn emit_logs(&self) {
+        debug!(
+            message = "watching file.",
+            path = ?self.path,
+            fingerprint = %format!("{:x}", self.fingerprint),
+            file_position = %self.file_position,
+            last_read_secs_ago = %self.last_read_success.elapsed().as_secs(),
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct FileTruncated<'a> {
+    pub path: &'a Path,
+    pub fingerprint: FileFingerprint,
+    pub old_position: FilePosition,
+}
+
+impl InternalEvent for FileTruncated<'_> {
+    fn emit_logs(&self) {
+        info!(
+            message = "file was truncated; reading from the beginning.",
+            path = ?self.path,
+            fingerprint = %format!("{:x}", self.fingerprint),
+            old_position = %self.old_position,
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!(
+            "files_truncated", 1,
+            "component_kind" => "source",
+            "component_type" => "file",
+        );
+    }
+}
+
+#[derive(Clone)]
+pub struct FileSourceInternalEventsEmitter;
+
+impl FileSourceInternalEvents for FileSourceInternalEventsEmitter {
+    fn emit_file_watch_state(
+        &self,
+        path: &Path,
+        fingerprint: FileFingerprint,
+        file_position: FilePosition,
+        last_read_success: Instant,
+    ) {
+        emit!(FileWatchState {
+            path,
+            fingerprint,
+            file_position,
+            last_read_success,
+        });
+    }
+
+    fn emit_file_truncated(
+        &self,
+        path: &Path,
+        fingerprint: FileFingerprint,
+        old_position: FilePosition,
+    ) {
+        emit!(FileTruncated {
+            path,
+            fingerprint,
+            old_position,
+        });
+    }
+}
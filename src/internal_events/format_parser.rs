@@ -0,0 +1,59 @@
+use super::InternalEvent;
+use metrics::counter;
+use string_cache::DefaultAtom as Atom;
+
+#[derive(Debug)]
+pub struct FormatParserEventProcessed;
+
+impl InternalEvent for FormatParserEventProcessed {
+    fn emit_metrics(&self) {
+        counter!("events_processed", 1,
+            "component_kind" => "transform",
+            "component_type" => "format_parser",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct FormatParserFailedParse<'a> {
+    pub format: &'static str,
+    pub value: &'a str,
+}
+
+impl InternalEvent for FormatParserFailedParse<'_> {
+    fn emit_logs(&self) {
+        warn!(
+            message = "field does not match the format.",
+            format = self.format,
+            value = %self.value,
+            rate_limit_secs = 30
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!("processing_error", 1,
+            "component_kind" => "transform",
+            "component_type" => "format_parser",
+            "error_type" => "failed_parse",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct FormatParserMissingField<'a> {
+    pub field: &'a Atom,
+}
+
+impl InternalEvent for FormatParserMissingField<'_> {
+    fn emit_logs(&self) {
+        debug!(message = "field does not exist.", field = %self.field);
+    }
+
+    fn emit_metrics(&self) {
+        counter!("processing_error", 1,
+            "component_kind" => "transform",
+            "component_type" => "format_parser",
+            "error_type" => "missing_field",
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use super::InternalEvent;
+use metrics::counter;
+use string_cache::DefaultAtom as Atom;
+
+#[derive(Debug)]
+pub struct KeyValueParserEventProcessed;
+
+impl InternalEvent for KeyValueParserEventProcessed {
+    fn emit_metrics(&self) {
+        counter!("events_processed", 1,
+            "component_kind" => "transform",
+            "component_type" => "key_value_parser",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct KeyValueParserFailedParse<'a> {
+    pub value: &'a str,
+}
+
+impl InternalEvent for KeyValueParserFailedParse<'_> {
+    fn emit_logs(&self) {
+        warn!(
+            message = "field does not contain any key/value pairs.",
+            value = %self.value,
+            rate_limit_secs = 30
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!("processing_error", 1,
+            "component_kind" => "transform",
+            "component_type" => "key_value_parser",
+            "error_type" => "failed_parse",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct KeyValueParserMissingField<'a> {
+    pub field: &'a Atom,
+}
+
+impl InternalEvent for KeyValueParserMissingField<'_> {
+    fn emit_logs(&self) {
+        debug!(message = "field does not exist.", field = %self.field);
+    }
+
+    fn emit_metrics(&self) {
+        counter!("processing_error", 1,
+            "component_kind" => "transform",
+            "component_type" => "key_value_parser",
+            "error_type" => "missing_field",
+        );
+    }
+}
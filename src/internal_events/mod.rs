@@ -3,15 +3,27 @@ mod aws_kinesis_streams;
 mod blackhole;
 mod elasticsearch;
 mod file;
+#[cfg(feature = "transforms-format_parser")]
+mod format_parser;
 mod json;
+#[cfg(feature = "transforms-key_value_parser")]
+mod key_value_parser;
 #[cfg(feature = "transforms-lua")]
 mod lua;
 #[cfg(feature = "sources-prometheus")]
 mod prometheus;
+#[cfg(feature = "transforms-redact")]
+mod redact;
 mod regex;
+#[cfg(feature = "transforms-route")]
+mod route;
 mod splunk_hec;
 mod syslog;
 mod tcp;
+#[cfg(feature = "transforms-throttle")]
+mod throttle;
+#[cfg(feature = "transforms-timestamp_parser")]
+mod timestamp_parser;
 mod udp;
 mod unix;
 mod vector;
@@ -21,15 +33,27 @@ pub use self::aws_kinesis_streams::*;
 pub use self::blackhole::*;
 pub use self::elasticsearch::*;
 pub use self::file::*;
+#[cfg(feature = "transforms-format_parser")]
+pub use self::format_parser::*;
 pub use self::json::*;
+#[cfg(feature = "transforms-key_value_parser")]
+pub use self::key_value_parser::*;
 #[cfg(feature = "transforms-lua")]
 pub use self::lua::*;
 #[cfg(feature = "sources-prometheus")]
 pub use self::prometheus::*;
+#[cfg(feature = "transforms-redact")]
+pub use self::redact::*;
 pub use self::regex::*;
+#[cfg(feature = "transforms-route")]
+pub use self::route::*;
 pub use self::splunk_hec::*;
 pub use self::syslog::*;
 pub use self::tcp::*;
+#[cfg(feature = "transforms-throttle")]
+pub use self::throttle::*;
+#[cfg(feature = "transforms-timestamp_parser")]
+pub use self::timestamp_parser::*;
 pub use self::udp::*;
 pub use self::unix::*;
 pub use self::vector::*;
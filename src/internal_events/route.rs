@@ -0,0 +1,29 @@
+use super::InternalEvent;
+use metrics::counter;
+
+#[derive(Debug)]
+pub struct RouteEventProcessed;
+
+impl InternalEvent for RouteEventProcessed {
+    fn emit_metrics(&self) {
+        counter!("events_processed", 1,
+            "component_kind" => "transform",
+            "component_type" => "route",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct RouteEventRouted<'a> {
+    pub lane: &'a str,
+}
+
+impl<'a> InternalEvent for RouteEventRouted<'a> {
+    fn emit_metrics(&self) {
+        counter!("events_routed", 1,
+            "component_kind" => "transform",
+            "component_type" => "route",
+            "lane" => self.lane.to_owned(),
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use super::InternalEvent;
+use metrics::counter;
+use string_cache::DefaultAtom as Atom;
+
+#[derive(Debug)]
+pub struct TimestampParserEventProcessed;
+
+impl InternalEvent for TimestampParserEventProcessed {
+    fn emit_metrics(&self) {
+        counter!("events_processed", 1,
+            "component_kind" => "transform",
+            "component_type" => "timestamp_parser",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct TimestampParserFailedParse<'a> {
+    pub field: &'a Atom,
+    pub value: &'a str,
+}
+
+impl InternalEvent for TimestampParserFailedParse<'_> {
+    fn emit_logs(&self) {
+        warn!(
+            message = "timestamp did not match any of the formats.",
+            field = %self.field,
+            value = %self.value,
+            rate_limit_secs = 30
+        );
+    }
+
+    fn emit_metrics(&self) {
+        counter!("processing_error", 1,
+            "component_kind" => "transform",
+            "component_type" => "timestamp_parser",
+            "error_type" => "failed_parse",
+        );
+    }
+}
+
+#[derive(Debug)]
+pub struct TimestampParserMissingField<'a> {
+    pub field: &'a Atom,
+}
+
+impl InternalEvent for TimestampParserMissingField<'_> {
+    fn emit_logs(&self) {
+        debug!(message = "field does not exist.", field = %self.field);
+    }
+
+    fn emit_metrics(&self) {
+        counter!("processing_error", 1,
+            "component_kind" => "transform",
+            "component_type" => "timestamp_parser",
+            "error_type" => "missing_field",
+        );
+    }
+}
@@ -13,6 +13,7 @@ pub mod buffers;
 pub mod conditions;
 pub mod config_paths;
 pub mod dns;
+pub mod enrichment_tables;
 pub mod event;
 pub mod expiring_hash_map;
 pub mod generate;
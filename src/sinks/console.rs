@@ -4,7 +4,9 @@ use crate::{
         encoding::{EncodingConfig, EncodingConfiguration},
         StreamSink,
     },
-    topology::config::{DataType, SinkConfig, SinkContext, SinkDescription},
+    topology::config::{
+        DataType, EncodedEvent, SinkConfig, SinkContext, SinkDescription, TestEncoder,
+    },
 };
 use async_trait::async_trait;
 use futures::pin_mut;
@@ -71,6 +73,16 @@ impl SinkConfig for ConsoleSinkConfig {
     fn sink_type(&self) -> &'static str {
         "console"
     }
+
+    fn build_test_encoder(&self) -> Option<TestEncoder> {
+        let encoding = self.encoding.clone();
+
+        Some(Box::new(move |event: Event| {
+            let mut line = encode_event(event, &encoding).ok()?;
+            line.push('\n');
+            Some(EncodedEvent::new(line))
+        }))
+    }
 }
 
 fn encode_event(
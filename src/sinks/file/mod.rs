@@ -6,7 +6,9 @@ use crate::{
         StreamSink,
     },
     template::Template,
-    topology::config::{DataType, SinkConfig, SinkContext, SinkDescription},
+    topology::config::{
+        DataType, EncodedEvent, SinkConfig, SinkContext, SinkDescription, TestEncoder,
+    },
 };
 use async_trait::async_trait;
 use bytes::Bytes;
@@ -69,6 +71,21 @@ impl SinkConfig for FileSinkConfig {
     fn sink_type(&self) -> &'static str {
         "file"
     }
+
+    fn build_test_encoder(&self) -> Option<TestEncoder> {
+        let path = self.path.clone();
+        let encoding = self.encoding.clone();
+
+        Some(Box::new(move |event: Event| {
+            let key = path.render_string(&event).ok()?;
+            let mut body = encode_event(&encoding, event);
+            body.push(b'\n');
+            Some(EncodedEvent {
+                key: Some(key),
+                body,
+            })
+        }))
+    }
 }
 
 #[derive(Debug)]
@@ -7,7 +7,9 @@ use crate::{
         BatchBytesConfig, Buffer, Compression, TowerRequestConfig, UriSerde,
     },
     tls::{TlsOptions, TlsSettings},
-    topology::config::{DataType, SinkConfig, SinkContext, SinkDescription},
+    topology::config::{
+        DataType, EncodedEvent, SinkConfig, SinkContext, SinkDescription, TestEncoder,
+    },
 };
 use futures01::{future, Future, Sink};
 use http::{
@@ -141,6 +143,13 @@ impl SinkConfig for HttpSinkConfig {
     fn sink_type(&self) -> &'static str {
         "http"
     }
+
+    fn build_test_encoder(&self) -> Option<TestEncoder> {
+        let config = self.clone();
+        Some(Box::new(move |event: Event| {
+            config.encode_event(event).map(EncodedEvent::new)
+        }))
+    }
 }
 
 impl HttpSink for HttpSinkConfig {
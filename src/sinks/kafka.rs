@@ -13,6 +13,7 @@ use futures01::{
 };
 use rdkafka::{
     consumer::{BaseConsumer, Consumer},
+    message::OwnedHeaders,
     producer::{DeliveryFuture, FutureProducer, FutureRecord},
 };
 use serde::{Deserialize, Serialize};
@@ -27,16 +28,25 @@ type MetadataFuture<F, M> = future::Join<F, future::FutureResult<M, <F as Future
 enum BuildError {
     #[snafu(display("creating kafka producer failed: {}", source))]
     KafkaCreateFailed { source: rdkafka::error::KafkaError },
+    #[snafu(display(
+        "transactional producers are not supported by the bundled librdkafka, remove `transactional.id` from `librdkafka_options`"
+    ))]
+    TransactionsUnsupported,
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct KafkaSinkConfig {
     bootstrap_servers: String,
-    topic: String,
+    topic: Template,
     key_field: Option<Atom>,
+    #[serde(default)]
+    headers: HashMap<String, Atom>,
     encoding: EncodingConfigWithDefault<Encoding>,
     #[serde(default)]
     compression: KafkaCompression,
+    partitioner: Option<Partitioner>,
+    #[serde(default)]
+    idempotent: bool,
     tls: Option<KafkaTlsConfig>,
     #[serde(default = "default_socket_timeout_ms")]
     socket_timeout_ms: u64,
@@ -62,10 +72,29 @@ pub enum Encoding {
     Json,
 }
 
+/// The `partitioner` used by librdkafka to pick the partition of messages.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Partitioner {
+    /// Random distribution.
+    Random,
+    /// CRC32 hash of the key, empty and null keys are mapped to one partition.
+    Consistent,
+    /// CRC32 hash of the key, empty and null keys are randomly partitioned.
+    ConsistentRandom,
+    /// Java producer compatible murmur2 hash of the key, null keys are mapped
+    /// to one partition.
+    Murmur2,
+    /// Java producer compatible murmur2 hash of the key, null keys are
+    /// randomly partitioned.
+    Murmur2Random,
+}
+
 pub struct KafkaSink {
     producer: FutureProducer,
     topic: Template,
     key_field: Option<Atom>,
+    headers: HashMap<String, Atom>,
     encoding: EncodingConfig<Encoding>,
     in_flight: FuturesUnordered<MetadataFuture<Compat<DeliveryFuture>, usize>>,
 
@@ -106,7 +135,20 @@ impl KafkaSinkConfig {
         client_config.set("compression.codec", &to_string(self.compression));
         client_config.set("socket.timeout.ms", &self.socket_timeout_ms.to_string());
         client_config.set("message.timeout.ms", &self.message_timeout_ms.to_string());
+        if let Some(partitioner) = self.partitioner {
+            client_config.set("partitioner", &to_string(partitioner));
+        }
+        if self.idempotent {
+            // Ensures messages are written exactly once and in order per
+            // partition, even when librdkafka retries them.
+            client_config.set("enable.idempotence", "true");
+        }
         if let Some(ref librdkafka_options) = self.librdkafka_options {
+            // librdkafka 1.3 has no transaction API, so batches can't be
+            // committed atomically. Only the idempotent producer is offered.
+            if librdkafka_options.contains_key("transactional.id") {
+                return Err(BuildError::TransactionsUnsupported.into());
+            }
             for (key, value) in librdkafka_options.iter() {
                 client_config.set(key.as_str(), value.as_str());
             }
@@ -120,8 +162,9 @@ impl KafkaSink {
         let producer = config.to_rdkafka()?.create().context(KafkaCreateFailed)?;
         Ok(KafkaSink {
             producer,
-            topic: Template::from(config.topic),
+            topic: config.topic,
             key_field: config.key_field,
+            headers: config.headers,
             encoding: config.encoding.into(),
             in_flight: FuturesUnordered::new(),
             acker,
@@ -137,14 +180,28 @@ impl Sink for KafkaSink {
     type SinkError = ();
 
     fn start_send(&mut self, item: Self::SinkItem) -> StartSend<Self::SinkItem, Self::SinkError> {
-        let topic = self.topic.render_string(&item).map_err(|missing_keys| {
-            error!(message = "Missing keys for topic", ?missing_keys);
-            ()
-        })?;
+        let topic = match self.topic.render_string(&item) {
+            Ok(topic) => topic,
+            Err(missing_keys) => {
+                warn!(
+                    message = "Missing keys for topic, dropping event.",
+                    ?missing_keys,
+                    rate_limit_secs = 30
+                );
+                let seqno = self.seq_head;
+                self.seq_head += 1;
+                self.ack(seqno);
+                return Ok(AsyncSink::Ready);
+            }
+        };
 
+        let headers = encode_headers(&item, &self.headers);
         let (key, body) = encode_event(item.clone(), &self.key_field, &self.encoding);
 
-        let record = FutureRecord::to(&topic).key(&key).payload(&body[..]);
+        let mut record = FutureRecord::to(&topic).key(&key).payload(&body[..]);
+        if let Some(headers) = headers {
+            record = record.headers(headers);
+        }
 
         debug!(message = "sending event.", count = 1);
         let future = match self.producer.send_result(record) {
@@ -193,14 +250,7 @@ impl Sink for KafkaSink {
                         Err((e, _msg)) => error!("kafka error: {}", e),
                     };
 
-                    self.pending_acks.insert(seqno);
-
-                    let mut num_to_ack = 0;
-                    while self.pending_acks.remove(&self.seq_tail) {
-                        num_to_ack += 1;
-                        self.seq_tail += 1
-                    }
-                    self.acker.ack(num_to_ack);
+                    self.ack(seqno);
                 }
 
                 // request got canceled (according to docs)
@@ -210,9 +260,23 @@ impl Sink for KafkaSink {
     }
 }
 
+impl KafkaSink {
+    /// Acknowledges all events up to `seqno` which are done.
+    fn ack(&mut self, seqno: usize) {
+        self.pending_acks.insert(seqno);
+
+        let mut num_to_ack = 0;
+        while self.pending_acks.remove(&self.seq_tail) {
+            num_to_ack += 1;
+            self.seq_tail += 1
+        }
+        self.acker.ack(num_to_ack);
+    }
+}
+
 fn healthcheck(config: KafkaSinkConfig) -> super::Healthcheck {
     let client = config.to_rdkafka().unwrap();
-    let topic = match Template::from(config.topic).render_string(&Event::from("")) {
+    let topic = match config.topic.render_string(&Event::from("")) {
         Ok(topic) => Some(topic),
         Err(missing_keys) => {
             warn!(
@@ -238,6 +302,23 @@ fn healthcheck(config: KafkaSinkConfig) -> super::Healthcheck {
     Box::new(check)
 }
 
+/// Builds the message headers from event fields, fields missing from the
+/// event are left out.
+fn encode_headers(event: &Event, headers: &HashMap<String, Atom>) -> Option<OwnedHeaders> {
+    if headers.is_empty() {
+        return None;
+    }
+
+    let log = event.as_log();
+    let encoded = headers
+        .iter()
+        .filter_map(|(name, field)| log.get(field).map(|value| (name, value.as_bytes())))
+        .fold(OwnedHeaders::new(), |encoded, (name, value)| {
+            encoded.add(name, &value[..])
+        });
+    Some(encoded)
+}
+
 fn encode_event(
     mut event: Event,
     key_field: &Option<Atom>,
@@ -266,6 +347,7 @@ fn encode_event(
 mod tests {
     use super::*;
     use crate::event::{self, Event};
+    use rdkafka::message::Headers;
     use std::collections::BTreeMap;
 
     #[test]
@@ -282,6 +364,84 @@ mod tests {
         assert_eq!(&bytes[..], message.as_bytes());
     }
 
+    #[test]
+    fn kafka_encode_headers() {
+        let mut event = Event::from("hello world");
+        event.as_mut_log().insert("tenant", "acme");
+
+        let mut headers = HashMap::new();
+        headers.insert("x-tenant".to_owned(), Atom::from("tenant"));
+        headers.insert("x-missing".to_owned(), Atom::from("missing"));
+
+        let encoded = encode_headers(&event, &headers).unwrap();
+
+        assert_eq!(encoded.count(), 1);
+        assert_eq!(encoded.get(0), Some(("x-tenant", "acme".as_bytes())));
+        assert!(encode_headers(&event, &HashMap::new()).is_none());
+    }
+
+    #[test]
+    fn kafka_parse_config() {
+        let config: KafkaSinkConfig = toml::from_str(
+            r#"
+            bootstrap_servers = "localhost:9092"
+            topic = "logs-{{ tenant }}"
+            partitioner = "murmur2_random"
+            idempotent = true
+
+            [headers]
+            x-tenant = "tenant"
+        "#,
+        )
+        .unwrap();
+
+        assert!(config.topic.is_dynamic());
+        assert_eq!(config.partitioner, Some(Partitioner::Murmur2Random));
+        assert!(config.idempotent);
+        assert_eq!(config.headers["x-tenant"], Atom::from("tenant"));
+    }
+
+    #[test]
+    fn kafka_creates_producer_for_every_partitioner() {
+        for partitioner in &[
+            "random",
+            "consistent",
+            "consistent_random",
+            "murmur2",
+            "murmur2_random",
+        ] {
+            let config: KafkaSinkConfig = toml::from_str(&format!(
+                r#"
+                bootstrap_servers = "localhost:9092"
+                topic = "logs"
+                partitioner = "{}"
+                "#,
+                partitioner
+            ))
+            .unwrap();
+
+            let producer = config.to_rdkafka().unwrap().create::<FutureProducer>();
+            assert!(producer.is_ok(), "{}", partitioner);
+        }
+    }
+
+    #[test]
+    fn kafka_rejects_transactional_producer() {
+        let config: KafkaSinkConfig = toml::from_str(
+            r#"
+            bootstrap_servers = "localhost:9092"
+            topic = "logs"
+
+            [librdkafka_options]
+            "transactional.id" = "vector"
+        "#,
+        )
+        .unwrap();
+
+        let error = config.to_rdkafka().err().unwrap();
+        assert!(error.to_string().contains("transactional producers"));
+    }
+
     #[test]
     fn kafka_encode_event_json() {
         let message = "hello world".to_string();
@@ -332,7 +492,7 @@ mod integration_test {
 
         let config = KafkaSinkConfig {
             bootstrap_servers: "localhost:9092".into(),
-            topic: topic.clone(),
+            topic: topic.clone().into(),
             compression: KafkaCompression::None,
             encoding: EncodingConfigWithDefault::from(Encoding::Text),
             key_field: None,
@@ -412,7 +572,7 @@ mod integration_test {
         let tls_enabled = tls.as_ref().map(|tls| tls.enabled()).unwrap_or(false);
         let config = KafkaSinkConfig {
             bootstrap_servers: server.to_string(),
-            topic: format!("{}-%Y%m%d", topic),
+            topic: format!("{}-%Y%m%d", topic).into(),
             compression,
             encoding: EncodingConfigWithDefault::from(Encoding::Text),
             key_field: None,
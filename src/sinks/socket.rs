@@ -1,9 +1,14 @@
 #[cfg(unix)]
 use crate::sinks::util::unix::UnixSinkConfig;
 use crate::{
-    sinks::util::{encoding::EncodingConfig, tcp::TcpSinkConfig, udp::UdpSinkConfig, Encoding},
+    sinks::util::{
+        encode_event, encoding::EncodingConfig, tcp::TcpSinkConfig, udp::UdpSinkConfig, Encoding,
+    },
     tls::TlsConfig,
-    topology::config::{DataType, SinkConfig, SinkContext, SinkDescription},
+    topology::config::{
+        DataType, EncodedEvent, SinkConfig, SinkContext, SinkDescription, TestEncoder,
+    },
+    Event,
 };
 use serde::{Deserialize, Serialize};
 
@@ -81,6 +86,19 @@ impl SinkConfig for SocketSinkConfig {
     fn sink_type(&self) -> &'static str {
         "socket"
     }
+
+    fn build_test_encoder(&self) -> Option<TestEncoder> {
+        let encoding = match &self.mode {
+            Mode::Tcp(config) => config.encoding.clone(),
+            Mode::Udp(config) => config.encoding.clone(),
+            #[cfg(unix)]
+            Mode::Unix(config) => config.encoding.clone(),
+        };
+
+        Some(Box::new(move |event: Event| {
+            encode_event(event, &encoding).map(|bytes| EncodedEvent::new(&bytes[..]))
+        }))
+    }
 }
 
 #[cfg(test)]
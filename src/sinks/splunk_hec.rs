@@ -8,7 +8,9 @@ use crate::{
         BatchBytesConfig, Buffer, Compression, TowerRequestConfig,
     },
     tls::{TlsOptions, TlsSettings},
-    topology::config::{DataType, SinkConfig, SinkContext, SinkDescription},
+    topology::config::{
+        DataType, EncodedEvent, SinkConfig, SinkContext, SinkDescription, TestEncoder,
+    },
 };
 use bytes::Bytes;
 use futures01::{Future, Sink};
@@ -106,6 +108,13 @@ impl SinkConfig for HecSinkConfig {
     fn sink_type(&self) -> &'static str {
         "splunk_hec"
     }
+
+    fn build_test_encoder(&self) -> Option<TestEncoder> {
+        let config = self.clone();
+        Some(Box::new(move |event: Event| {
+            config.encode_event(event).map(EncodedEvent::new)
+        }))
+    }
 }
 
 impl HttpSink for HecSinkConfig {
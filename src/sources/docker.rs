@@ -2,33 +2,57 @@ use crate::{
     event::merge_state::LogEventMergeState,
     event::{self, Event, LogEvent, Value},
     shutdown::ShutdownSignal,
+    sources::file::{
+        line_agg::{self, LineAgg},
+        MultilineConfig,
+    },
     stream::StreamExt,
     topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
 };
 use bytes::{Bytes, BytesMut};
-use chrono::{DateTime, FixedOffset, Utc};
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
 use futures01::{
+    future::{self, Either},
     sync::mpsc::{self, Sender, UnboundedReceiver, UnboundedSender},
-    Async, Future, Sink, Stream,
+    Async, AsyncSink, Future, Poll, Sink, StartSend, Stream,
 };
 use http::StatusCode;
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 use shiplift::{
     builder::{ContainerFilter, EventFilter, LogsOptions},
-    rep::ContainerDetails,
+    rep::{ContainerDetails, ImageDetails},
     tty::{Chunk, StreamType},
     Docker, Error,
 };
 use std::borrow::Borrow;
-use std::sync::Arc;
+use std::convert::TryInto;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{
+    atomic::{AtomicI64, Ordering},
+    Arc, Mutex,
+};
+use std::time::{Duration, Instant};
 use std::{collections::HashMap, env};
 use string_cache::DefaultAtom as Atom;
+use tokio01::timer::Interval;
 use tracing::field;
 
 /// The begining of image names of vector docker images packaged by vector.
 const VECTOR_IMAGE_NAME: &str = "timberio/vector";
 
+/// Labels set by `docker-compose` on the containers it creates.
+const COMPOSE_PROJECT_LABEL: &str = "com.docker.compose.project";
+const COMPOSE_SERVICE_LABEL: &str = "com.docker.compose.service";
+
+const CHECKPOINT_FILENAME: &str = "checkpoints.json";
+const CHECKPOINT_TMP_FILENAME: &str = "checkpoints.new.json";
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(1);
+/// Stored by a `ContainerCheckpoint` while no log was sent.
+const CHECKPOINT_UNSET: i64 = i64::MIN;
+
 lazy_static! {
     static ref STDERR: Bytes = "stderr".into();
     static ref STDOUT: Bytes = "stdout".into();
@@ -37,6 +61,9 @@ lazy_static! {
     static ref NAME: Atom = Atom::from("container_name");
     static ref STREAM: Atom = Atom::from("stream");
     static ref CONTAINER: Atom = Atom::from("container_id");
+    static ref IMAGE_DIGEST: Atom = Atom::from("image_digest");
+    static ref COMPOSE_PROJECT: Atom = Atom::from("compose_project");
+    static ref COMPOSE_SERVICE: Atom = Atom::from("compose_service");
 }
 
 type DockerEvent = shiplift::rep::Event;
@@ -47,8 +74,13 @@ pub struct DockerConfig {
     include_containers: Option<Vec<String>>,
     include_labels: Option<Vec<String>>,
     include_images: Option<Vec<String>>,
+    exclude_containers: Option<Vec<String>>,
+    exclude_labels: Option<Vec<String>>,
+    exclude_images: Option<Vec<String>>,
     partial_event_marker_field: Option<Atom>,
     auto_partial_merge: bool,
+    multiline: Option<MultilineConfig>,
+    data_dir: Option<PathBuf>,
 }
 
 impl Default for DockerConfig {
@@ -57,8 +89,13 @@ impl Default for DockerConfig {
             include_containers: None,
             include_labels: None,
             include_images: None,
+            exclude_containers: None,
+            exclude_labels: None,
+            exclude_images: None,
             partial_event_marker_field: Some(event::PARTIAL.clone()),
             auto_partial_merge: true,
+            multiline: None,
+            data_dir: None,
         }
     }
 }
@@ -86,6 +123,39 @@ impl DockerConfig {
         }
     }
 
+    /// True if the container matches any of the `exclude_*` options.
+    /// Docker API filters can only include, so these are applied here.
+    fn container_excluded(&self, details: &ContainerDetails) -> bool {
+        let name = remove_slash(details.name.as_str());
+        let container_excluded = self
+            .exclude_containers
+            .as_ref()
+            .map(|excludes| {
+                excludes
+                    .iter()
+                    .any(|exclude| details.id.starts_with(exclude) || name.starts_with(exclude))
+            })
+            .unwrap_or(false);
+
+        let image_excluded = self
+            .exclude_images
+            .as_ref()
+            .map(|images| images.iter().any(|image| &details.config.image == image))
+            .unwrap_or(false);
+
+        let label_excluded = self
+            .exclude_labels
+            .as_ref()
+            .map(|excludes| {
+                excludes
+                    .iter()
+                    .any(|exclude| label_matches(exclude, details.config.labels.as_ref()))
+            })
+            .unwrap_or(false);
+
+        container_excluded || image_excluded || label_excluded
+    }
+
     fn with_empty_partial_event_marker_field_as_none(mut self) -> Self {
         if let Some(val) = &self.partial_event_marker_field {
             if val.is_empty() {
@@ -104,13 +174,32 @@ inventory::submit! {
 impl SourceConfig for DockerConfig {
     fn build(
         &self,
-        _name: &str,
-        _globals: &GlobalOptions,
+        name: &str,
+        globals: &GlobalOptions,
         shutdown: ShutdownSignal,
         out: Sender<Event>,
     ) -> crate::Result<super::Source> {
+        if let Some(ref multiline) = self.multiline {
+            let _: line_agg::Config = multiline.try_into()?;
+        }
+
+        // Checkpoints are optional, so only a data_dir configured for this
+        // source has to be usable.
+        let data_dir = match globals.resolve_and_make_data_subdir(self.data_dir.as_ref(), name) {
+            Ok(data_dir) => Some(data_dir),
+            Err(error) if self.data_dir.is_some() => return Err(error),
+            Err(error) => {
+                warn!(
+                    message = "No usable data_dir, logs written while Vector isn't running won't be read.",
+                    %error,
+                );
+                None
+            }
+        };
+
         DockerSource::new(
             self.clone().with_empty_partial_event_marker_field_as_none(),
+            data_dir,
             out,
             shutdown,
         )
@@ -130,12 +219,15 @@ impl SourceConfig for DockerConfig {
 struct DockerSourceCore {
     config: DockerConfig,
     docker: Docker,
-    /// Only logs created at, or after this moment are logged.
+    /// Only logs created at, or after this moment are logged,
+    /// unless a container has a checkpoint.
     now_timestamp: DateTime<Utc>,
+    /// None if there is no data_dir to keep checkpoints in.
+    checkpointer: Option<Checkpointer>,
 }
 
 impl DockerSourceCore {
-    fn new(config: DockerConfig) -> crate::Result<Self> {
+    fn new(config: DockerConfig, checkpointer: Option<Checkpointer>) -> crate::Result<Self> {
         // ?NOTE: Constructs a new Docker instance for a docker host listening at url specified by an env var DOCKER_HOST.
         // ?      Otherwise connects to unix socket which requires sudo privileges, or docker group membership.
         let docker = Docker::new();
@@ -151,6 +243,7 @@ impl DockerSourceCore {
             config,
             docker,
             now_timestamp: now.into(),
+            checkpointer,
         })
     }
 
@@ -169,8 +262,9 @@ impl DockerSourceCore {
         // upause | docker unpause
         // die    | docker restart, docker stop, docker kill, process exited, oom
         // pause  | docker pause
+        // destroy| docker rm
         options.filter(
-            vec!["start", "upause", "die", "pause"]
+            vec!["start", "upause", "die", "pause", "destroy"]
                 .into_iter()
                 .map(|s| EventFilter::Event(s.into()))
                 .collect(),
@@ -229,6 +323,7 @@ struct DockerSource {
 impl DockerSource {
     fn new(
         config: DockerConfig,
+        data_dir: Option<PathBuf>,
         out: Sender<Event>,
         shutdown: ShutdownSignal,
     ) -> crate::Result<impl Future<Item = (), Error = ()>> {
@@ -245,8 +340,13 @@ impl DockerSource {
             .is_empty()
             && config.include_labels.clone().unwrap_or_default().is_empty();
 
+        let checkpointer = data_dir.map(Checkpointer::new);
+        let checkpoint_writer = checkpointer
+            .clone()
+            .map(|checkpointer| checkpointer.run(shutdown.clone()));
+
         // Only logs created at, or after this moment are logged.
-        let core = DockerSourceCore::new(config)?;
+        let core = DockerSourceCore::new(config, checkpointer)?;
 
         // main event stream, with whom only newly started/restarted containers will be loged.
         let events = core.docker_event_stream();
@@ -276,7 +376,12 @@ impl DockerSource {
         }
         .running_containers()
         // Once this ShutdownSignal resolves it will drop DockerSource and by extension it's ShutdownSignal.
-        .and_then(move |source| source.select(shutdown.map(|_| ())).then(|_| Ok(()))))
+        .and_then(move |source| {
+            if let Some(checkpoint_writer) = checkpoint_writer {
+                tokio01::spawn(checkpoint_writer);
+            }
+            source.select(shutdown.map(|_| ())).then(|_| Ok(()))
+        }))
     }
 
     /// Future that captures currently running containers, and starts event streams for them.
@@ -457,6 +562,12 @@ impl Future for DockerSource {
                                             }
                                         }
                                     }
+                                    "destroy" => {
+                                        // Logs of a removed container can't be read anymore.
+                                        if let Some(checkpointer) = &self.esb.core.checkpointer {
+                                            checkpointer.remove(&id);
+                                        }
+                                    }
                                     // Ignore
                                     _ => (),
                                 }
@@ -516,6 +627,7 @@ impl EventStreamBuilder {
 
     /// Constructs and runs event stream until shutdown.
     fn start(&self, id: ContainerId) -> ContainerState {
+        let core = Arc::clone(&self.core);
         let metadata_fetch = self
             .core
             .docker
@@ -523,14 +635,38 @@ impl EventStreamBuilder {
             .get(id.as_str())
             .inspect()
             .map_err(|error| error!(message="Fetching container details failed",%error))
-            .and_then(|details| {
-                ContainerMetadata::from_details(&details)
-                    .map_err(|error| error!(message="Metadata extraction failed",%error))
+            .and_then(move |details| {
+                if core.config.container_excluded(&details) {
+                    trace!(
+                        message = "Container excluded",
+                        id = field::display(&details.id)
+                    );
+                    return Either::A(future::err(()));
+                }
+
+                // Image details are only used for enrichment, so failing
+                // to fetch them doesn't stop collecting logs.
+                let image_fetch = core.docker.images().get(&details.image).inspect();
+                Either::B(image_fetch.then(move |image| {
+                    let image = image
+                        .map_err(|error| debug!(message="Fetching image details failed",%error))
+                        .ok();
+                    ContainerMetadata::from_details(&details, image.as_ref())
+                        .map_err(|error| error!(message="Metadata extraction failed",%error))
+                }))
             });
 
         let this = self.clone();
         let task = metadata_fetch.and_then(move |metadata| {
-            this.start_event_stream(ContainerLogInfo::new(id, metadata, this.core.now_timestamp))
+            // Continue after the last log read before a restart, if any.
+            let since = this
+                .core
+                .checkpointer
+                .as_ref()
+                .and_then(|checkpointer| checkpointer.get(&id))
+                .map(|last_log| last_log + chrono::Duration::nanoseconds(1))
+                .unwrap_or(this.core.now_timestamp);
+            this.start_event_stream(ContainerLogInfo::new(id, metadata, since))
         });
 
         tokio01::spawn(task);
@@ -566,25 +702,34 @@ impl EventStreamBuilder {
             id = field::display(info.id.as_str())
         );
 
+        let checkpoint = self
+            .core
+            .checkpointer
+            .as_ref()
+            .map(|checkpointer| checkpointer.container(&info.id));
+
         // Create event streamer
         let mut state = Some((self.main_send.clone(), info));
         let partial_event_marker_field = self.core.config.partial_event_marker_field.clone();
         let auto_partial_merge = self.core.config.auto_partial_merge;
         let mut partial_event_merge_state = None;
-        tokio01::prelude::stream::poll_fn(move || {
+        let events = tokio01::prelude::stream::poll_fn(move || {
             // !Hot code: from here
             if let Some(&mut (_, ref mut info)) = state.as_mut() {
                 // Main event loop
                 loop {
                     return match stream.poll() {
                         Ok(Async::Ready(Some(message))) => {
-                            if let Some(event) = info.new_event(
-                                message,
-                                partial_event_marker_field.clone(),
-                                auto_partial_merge,
-                                &mut partial_event_merge_state,
+                            if let (Some(event), Some((timestamp, _))) = (
+                                info.new_event(
+                                    message,
+                                    partial_event_marker_field.clone(),
+                                    auto_partial_merge,
+                                    &mut partial_event_merge_state,
+                                ),
+                                info.last_log,
                             ) {
-                                Ok(Async::Ready(Some(event)))
+                                Ok(Async::Ready(Some((event, timestamp.with_timezone(&Utc)))))
                             } else {
                                 continue;
                             }
@@ -626,9 +771,221 @@ impl EventStreamBuilder {
 
             Ok(Async::Ready(None))
         })
-        .take_until(self.shutdown.clone())
-        .forward(self.out.clone().sink_map_err(|_| ()))
-        .map(|_| ())
+        .take_until(self.shutdown.clone());
+
+        let events: Box<dyn Stream<Item = (Event, DateTime<Utc>), Error = ()> + Send> =
+            match self.core.config.multiline.as_ref() {
+                Some(config) => Box::new(aggregate_lines(
+                    events,
+                    config.try_into().unwrap(), // validated in build
+                )),
+                None => Box::new(events),
+            };
+
+        events
+            .forward(CheckpointSink {
+                inner: self.out.clone().sink_map_err(|_| ()),
+                checkpoint,
+                accepted: None,
+            })
+            .map(|_| ())
+    }
+}
+
+/// Merges the messages of consecutive events from the same stream of a
+/// container into one event, which keeps the other fields of the first event.
+fn aggregate_lines(
+    events: impl Stream<Item = (Event, DateTime<Utc>), Error = ()>,
+    config: line_agg::Config,
+) -> impl Stream<Item = (Event, DateTime<Utc>), Error = ()> {
+    let lines = events.map(|(event, timestamp)| {
+        let mut log = event.into_log();
+        let message = log
+            .remove(&event::log_schema().message_key())
+            .map(|message| message.as_bytes())
+            .unwrap_or_default();
+        let stream = log.get(&STREAM).map(|stream| stream.as_bytes());
+        (message, stream, (log, timestamp))
+    });
+
+    LineAgg::new(lines, config).map(|(message, _stream, (mut log, timestamp))| {
+        log.insert(event::log_schema().message_key().clone(), message);
+        // Only the timestamp of the first line is kept, so checkpoint just
+        // before it. After a restart the whole event is read again, instead
+        // of only its remaining lines.
+        (
+            Event::Log(log),
+            timestamp - chrono::Duration::nanoseconds(1),
+        )
+    })
+}
+
+/// Sends events to the output, and checkpoints the timestamp of the last
+/// event once the output has accepted it.
+struct CheckpointSink<S> {
+    inner: S,
+    checkpoint: Option<ContainerCheckpoint>,
+    /// Timestamp of the last event accepted, but not yet flushed.
+    accepted: Option<DateTime<Utc>>,
+}
+
+impl<S> CheckpointSink<S> {
+    fn checkpoint_if_ready(&mut self, poll: &Async<()>) {
+        if poll.is_ready() {
+            if let (Some(checkpoint), Some(timestamp)) = (&self.checkpoint, self.accepted.take()) {
+                checkpoint.set(timestamp);
+            }
+        }
+    }
+}
+
+impl<S: Sink<SinkItem = Event>> Sink for CheckpointSink<S> {
+    type SinkItem = (Event, DateTime<Utc>);
+    type SinkError = S::SinkError;
+
+    fn start_send(
+        &mut self,
+        (event, timestamp): Self::SinkItem,
+    ) -> StartSend<Self::SinkItem, Self::SinkError> {
+        match self.inner.start_send(event)? {
+            AsyncSink::Ready => {
+                self.accepted = Some(timestamp);
+                Ok(AsyncSink::Ready)
+            }
+            AsyncSink::NotReady(event) => Ok(AsyncSink::NotReady((event, timestamp))),
+        }
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        let poll = self.inner.poll_complete()?;
+        self.checkpoint_if_ready(&poll);
+        Ok(poll)
+    }
+
+    fn close(&mut self) -> Poll<(), Self::SinkError> {
+        let poll = self.inner.close()?;
+        self.checkpoint_if_ready(&poll);
+        Ok(poll)
+    }
+}
+
+/// Persists the timestamp of the last log sent from each container, so that
+/// logs written while Vector isn't running are read once it's started again.
+#[derive(Clone)]
+struct Checkpointer {
+    directory: PathBuf,
+    checkpoints: Arc<Mutex<HashMap<String, ContainerCheckpoint>>>,
+}
+
+impl Checkpointer {
+    fn new(directory: PathBuf) -> Self {
+        let checkpoints = match Self::read(&directory.join(CHECKPOINT_FILENAME)) {
+            Ok(checkpoints) => checkpoints
+                .into_iter()
+                .map(|(id, timestamp)| {
+                    let checkpoint = ContainerCheckpoint::new();
+                    checkpoint.set(timestamp.with_timezone(&Utc));
+                    (id, checkpoint)
+                })
+                .collect(),
+            Err(error) => {
+                warn!(message = "Unable to read docker checkpoints, starting without them", %error);
+                HashMap::new()
+            }
+        };
+
+        Checkpointer {
+            directory,
+            checkpoints: Arc::new(Mutex::new(checkpoints)),
+        }
+    }
+
+    fn read(path: &Path) -> io::Result<HashMap<String, DateTime<FixedOffset>>> {
+        match fs::read(path) {
+            Ok(data) => Ok(serde_json::from_slice(&data)?),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn get(&self, id: &ContainerId) -> Option<DateTime<Utc>> {
+        self.checkpoints
+            .lock()
+            .unwrap()
+            .get(id.as_str())
+            .and_then(ContainerCheckpoint::get)
+    }
+
+    /// The checkpoint of a container, to be updated as its logs are sent.
+    fn container(&self, id: &ContainerId) -> ContainerCheckpoint {
+        self.checkpoints
+            .lock()
+            .unwrap()
+            .entry(id.as_str().to_owned())
+            .or_insert_with(ContainerCheckpoint::new)
+            .clone()
+    }
+
+    fn remove(&self, id: &ContainerId) {
+        self.checkpoints.lock().unwrap().remove(id.as_str());
+    }
+
+    /// Writes checkpoints to a temporary file first, so a crash while writing
+    /// doesn't lose the previous ones.
+    fn write(&self) -> io::Result<()> {
+        let data = {
+            let checkpoints = self.checkpoints.lock().unwrap();
+            let timestamps = checkpoints
+                .iter()
+                .filter_map(|(id, checkpoint)| Some((id, checkpoint.get()?)))
+                .collect::<HashMap<_, _>>();
+            serde_json::to_vec(&timestamps)?
+        };
+        let tmp_path = self.directory.join(CHECKPOINT_TMP_FILENAME);
+        fs::write(&tmp_path, data)?;
+        fs::rename(tmp_path, self.directory.join(CHECKPOINT_FILENAME))
+    }
+
+    /// Periodically writes checkpoints until shutdown, and once more after it.
+    fn run(self, shutdown: ShutdownSignal) -> impl Future<Item = (), Error = ()> {
+        let last = self.clone();
+        Interval::new(Instant::now() + CHECKPOINT_INTERVAL, CHECKPOINT_INTERVAL)
+            .map_err(|error| error!(message = "Checkpoint timer failed", %error))
+            .take_until(shutdown)
+            .for_each(move |_| {
+                if let Err(error) = self.write() {
+                    warn!(message = "Unable to write docker checkpoints", %error, rate_limit_secs = 30);
+                }
+                Ok(())
+            })
+            .then(move |_| {
+                if let Err(error) = last.write() {
+                    warn!(message = "Unable to write docker checkpoints", %error);
+                }
+                Ok(())
+            })
+    }
+}
+
+/// The checkpoint of a single container, shared with its event stream so that
+/// it's updated without locking for every event.
+#[derive(Clone)]
+struct ContainerCheckpoint(Arc<AtomicI64>);
+
+impl ContainerCheckpoint {
+    fn new() -> Self {
+        ContainerCheckpoint(Arc::new(AtomicI64::new(CHECKPOINT_UNSET)))
+    }
+
+    fn get(&self) -> Option<DateTime<Utc>> {
+        match self.0.load(Ordering::Relaxed) {
+            CHECKPOINT_UNSET => None,
+            nanos => Some(Utc.timestamp_nanos(nanos)),
+        }
+    }
+
+    fn set(&self, timestamp: DateTime<Utc>) {
+        self.0.store(timestamp.timestamp_nanos(), Ordering::Relaxed);
     }
 }
 
@@ -841,6 +1198,19 @@ impl ContainerLogInfo {
             // Container image.
             log_event.insert(IMAGE.clone(), self.metadata.image.clone());
 
+            // Content digest of the container image.
+            if let Some(image_digest) = &self.metadata.image_digest {
+                log_event.insert(IMAGE_DIGEST.clone(), image_digest.clone());
+            }
+
+            // Compose project and service of the container.
+            if let Some(compose_project) = &self.metadata.compose_project {
+                log_event.insert(COMPOSE_PROJECT.clone(), compose_project.clone());
+            }
+            if let Some(compose_service) = &self.metadata.compose_service {
+                log_event.insert(COMPOSE_SERVICE.clone(), compose_service.clone());
+            }
+
             // Timestamp of the container creation.
             log_event.insert(CREATED_AT.clone(), self.metadata.created_at.clone());
 
@@ -908,12 +1278,21 @@ struct ContainerMetadata {
     name: Value,
     /// image -> String
     image: Value,
+    /// image repo digest -> String
+    image_digest: Option<Value>,
+    /// com.docker.compose.project label -> String
+    compose_project: Option<Value>,
+    /// com.docker.compose.service label -> String
+    compose_service: Option<Value>,
     /// created_at
     created_at: DateTime<Utc>,
 }
 
 impl ContainerMetadata {
-    fn from_details(details: &ContainerDetails) -> Result<Self, chrono::format::ParseError> {
+    fn from_details(
+        details: &ContainerDetails,
+        image: Option<&ImageDetails>,
+    ) -> Result<Self, chrono::format::ParseError> {
         let labels = details
             .config
             .labels
@@ -925,10 +1304,28 @@ impl ContainerMetadata {
             })
             .unwrap_or_default();
 
+        let label = |key: &str| -> Option<Value> {
+            details
+                .config
+                .labels
+                .as_ref()
+                .and_then(|labels| labels.get(key))
+                .map(|value| value.as_str().into())
+        };
+
+        // Repo digests look like `busybox@sha256:...`, only the digest is kept.
+        let image_digest = image
+            .and_then(|image| image.repo_digests.as_ref())
+            .and_then(|digests| digests.first())
+            .map(|digest| digest.rsplit('@').next().unwrap_or(digest).into());
+
         Ok(ContainerMetadata {
             labels,
             name: remove_slash(details.name.as_str()).into(),
             image: details.config.image.as_str().into(),
+            image_digest,
+            compose_project: label(COMPOSE_PROJECT_LABEL),
+            compose_service: label(COMPOSE_SERVICE_LABEL),
             created_at: DateTime::parse_from_rfc3339(details.created.as_str())?
                 .with_timezone(&Utc)
                 .into(),
@@ -941,6 +1338,17 @@ fn remove_slash(s: &str) -> &str {
     s.trim_start_matches("/")
 }
 
+/// True if the labels match a filter in docker's `key` or `key=value` syntax.
+fn label_matches(filter: &str, labels: Option<&HashMap<String, String>>) -> bool {
+    let mut parts = filter.splitn(2, '=');
+    let key = parts.next().unwrap_or_default();
+    match (labels.and_then(|labels| labels.get(key)), parts.next()) {
+        (Some(value), Some(expected)) => value == expected,
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
+
 #[cfg(all(test, feature = "docker-integration-tests"))]
 mod tests {
     use super::*;
@@ -1001,10 +1409,13 @@ mod tests {
 
     /// None if docker is not present on the system
     fn source_with_config(
-        config: DockerConfig,
+        mut config: DockerConfig,
         rt: &mut runtime::Runtime,
     ) -> mpsc::Receiver<Event> {
         trace_init();
+        if config.data_dir.is_none() {
+            config.data_dir = Some(data_dir());
+        }
         let (sender, recv) = mpsc::channel(100);
         rt.spawn(
             config
@@ -1023,6 +1434,12 @@ mod tests {
         Docker::new()
     }
 
+    fn data_dir() -> PathBuf {
+        let data_dir = test_util::temp_dir();
+        fs::create_dir_all(&data_dir).unwrap();
+        data_dir
+    }
+
     /// Users should ensure to remove container before exiting.
     fn log_container<'a, L: Into<Option<&'a str>>>(
         name: &str,
@@ -1419,4 +1836,255 @@ mod tests {
 
         assert!(rt.block_on(is_empty(exclude_out)).unwrap());
     }
+
+    #[test]
+    fn exclude_containers() {
+        let message = "18";
+        let name0 = "vector_test_exclude_container_0";
+        let name1 = "vector_test_exclude_container_1";
+        let config = DockerConfig {
+            include_containers: Some(vec![name0.to_owned(), name1.to_owned()]),
+            exclude_containers: Some(vec![name0.to_owned()]),
+            ..DockerConfig::default()
+        };
+
+        let mut rt = test_util::runtime();
+        let out = source_with_config(config, &mut rt);
+        let docker = docker();
+
+        let id0 = container_log_n(1, name0, None, "19", &docker, &mut rt);
+        let id1 = container_log_n(1, name1, None, message, &docker, &mut rt);
+
+        let events = rt.block_on(collect_n(out, 1)).ok().unwrap();
+
+        container_remove(&id0, &docker, &mut rt);
+        container_remove(&id1, &docker, &mut rt);
+
+        assert_eq!(
+            events[0].as_log()[&event::log_schema().message_key()],
+            message.into()
+        )
+    }
+
+    #[test]
+    fn exclude_labels() {
+        let name = "vector_test_exclude_labels";
+        let label = "vector_test_exclude_label";
+        let config = DockerConfig {
+            include_containers: Some(vec![name.to_owned()]),
+            exclude_labels: Some(vec![label.to_owned()]),
+            ..DockerConfig::default()
+        };
+
+        let mut rt = test_util::runtime();
+        let exclude_out = source_with_config(config, &mut rt);
+        let docker = docker();
+
+        let id = container_log_n(1, name, label, "20", &docker, &mut rt);
+        container_remove(&id, &docker, &mut rt);
+
+        assert!(rt.block_on(is_empty(exclude_out)).unwrap());
+    }
+
+    #[test]
+    fn multiline() {
+        let name = "vector_test_multiline";
+        let config = DockerConfig {
+            include_containers: Some(vec![name.to_owned()]),
+            multiline: Some(MultilineConfig {
+                start_pattern: "^[^\\s]".to_owned(),
+                condition_pattern: "^[\\s]+".to_owned(),
+                mode: line_agg::Mode::ContinueThrough,
+                timeout_ms: 1000,
+            }),
+            ..DockerConfig::default()
+        };
+
+        let mut rt = test_util::runtime();
+        let out = source_with_config(config, &mut rt);
+        let docker = docker();
+
+        let command = "printf 'Exception\\n  at one\\n  at two\\nnext\\n'";
+        let id = cmd_container(
+            name,
+            None,
+            vec!["sh".to_owned(), "-c".to_owned(), command.to_owned()],
+            &docker,
+            &mut rt,
+        );
+        if let Err(error) = container_run(&id, &docker, &mut rt) {
+            container_remove(&id, &docker, &mut rt);
+            panic!("Container failed to start with error: {:?}", error);
+        }
+
+        let events = rt.block_on(collect_n(out, 2)).ok().unwrap();
+
+        container_remove(&id, &docker, &mut rt);
+
+        let log = events[0].as_log();
+        assert_eq!(
+            log[&event::log_schema().message_key()],
+            "Exception\n  at one\n  at two".into()
+        );
+        assert_eq!(log[&super::NAME], name.into());
+        assert_eq!(
+            events[1].as_log()[&event::log_schema().message_key()],
+            "next".into()
+        );
+    }
+
+    #[test]
+    fn resumes_from_checkpoint() {
+        let message = "21";
+        let name = "vector_test_resumes_from_checkpoint";
+        let data_dir = data_dir();
+        let config = DockerConfig {
+            include_containers: Some(vec![name.to_owned()]),
+            data_dir: Some(data_dir.clone()),
+            ..DockerConfig::default()
+        };
+
+        let docker = docker();
+
+        let mut rt = test_util::runtime();
+        let out = source_with_config(config.clone(), &mut rt);
+        let id = container_log_n(1, name, None, message, &docker, &mut rt);
+        let _ = rt.block_on(collect_n(out, 1)).ok().unwrap();
+        // Let the checkpoint be written.
+        std::thread::sleep(CHECKPOINT_INTERVAL * 2);
+        rt.shutdown_now().wait().unwrap();
+
+        // Logged while the source isn't running.
+        let mut rt = test_util::runtime();
+        if let Err(error) = container_run(&id, &docker, &mut rt) {
+            container_remove(&id, &docker, &mut rt);
+            panic!("Container failed to start with error: {:?}", error);
+        }
+
+        let out = source_with_config(config, &mut rt);
+        if let Err(error) = container_run(&id, &docker, &mut rt) {
+            container_remove(&id, &docker, &mut rt);
+            panic!("Container failed to start with error: {:?}", error);
+        }
+
+        let events = rt.block_on(collect_n(out, 2)).ok().unwrap();
+
+        container_remove(&id, &docker, &mut rt);
+
+        assert_eq!(
+            events[0].as_log()[&event::log_schema().message_key()],
+            message.into()
+        );
+        assert_eq!(
+            events[1].as_log()[&event::log_schema().message_key()],
+            message.into()
+        );
+    }
+}
+
+#[cfg(test)]
+mod checkpointer_tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn builds_without_data_dir() {
+        let (sender, _receiver) = mpsc::channel(1);
+        let globals = GlobalOptions {
+            data_dir: None,
+            ..GlobalOptions::default()
+        };
+        assert!(DockerConfig::default()
+            .build("default", &globals, ShutdownSignal::noop(), sender)
+            .is_ok());
+    }
+
+    #[test]
+    fn checkpoints_sent_events() {
+        let timestamp = Utc.timestamp(1_585_742_400, 0);
+        let events = vec![
+            (Event::from("first"), timestamp),
+            (
+                Event::from("second"),
+                timestamp + chrono::Duration::seconds(1),
+            ),
+        ];
+
+        let (sender, _receiver) = mpsc::channel(10);
+        let checkpoint = ContainerCheckpoint::new();
+        let sink = CheckpointSink {
+            inner: sender.sink_map_err(|_| ()),
+            checkpoint: Some(checkpoint.clone()),
+            accepted: None,
+        };
+        assert!(futures01::stream::iter_ok::<_, ()>(events.clone())
+            .forward(sink)
+            .wait()
+            .is_ok());
+        assert_eq!(
+            checkpoint.get(),
+            Some(timestamp + chrono::Duration::seconds(1))
+        );
+
+        // Events the output doesn't accept aren't checkpointed.
+        let (sender, receiver) = mpsc::channel(10);
+        drop(receiver);
+        let checkpoint = ContainerCheckpoint::new();
+        let sink = CheckpointSink {
+            inner: sender.sink_map_err(|_| ()),
+            checkpoint: Some(checkpoint.clone()),
+            accepted: None,
+        };
+        assert!(futures01::stream::iter_ok::<_, ()>(events)
+            .forward(sink)
+            .wait()
+            .is_err());
+        assert!(checkpoint.get().is_none());
+    }
+
+    #[test]
+    fn docker_checkpointer_works() {
+        let tempdir = tempdir().unwrap();
+        let id = ContainerId::new("715ebfcee040".to_owned());
+        let timestamp = DateTime::parse_from_rfc3339("2020-04-01T12:00:00.123456789Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        let checkpointer = Checkpointer::new(tempdir.path().to_path_buf());
+        assert!(checkpointer.get(&id).is_none());
+
+        // Containers without logs sent yet aren't checkpointed.
+        let checkpoint = checkpointer.container(&id);
+        checkpointer.write().unwrap();
+        assert!(Checkpointer::new(tempdir.path().to_path_buf())
+            .get(&id)
+            .is_none());
+
+        checkpoint.set(timestamp);
+        checkpointer.write().unwrap();
+
+        let checkpointer = Checkpointer::new(tempdir.path().to_path_buf());
+        assert_eq!(checkpointer.get(&id), Some(timestamp));
+
+        checkpointer.remove(&id);
+        checkpointer.write().unwrap();
+
+        let checkpointer = Checkpointer::new(tempdir.path().to_path_buf());
+        assert!(checkpointer.get(&id).is_none());
+    }
+
+    #[test]
+    fn label_matches_key_and_value() {
+        let mut labels = HashMap::new();
+        labels.insert("com.example.vendor".to_owned(), "Timber Inc.".to_owned());
+
+        assert!(label_matches("com.example.vendor", Some(&labels)));
+        assert!(label_matches(
+            "com.example.vendor=Timber Inc.",
+            Some(&labels)
+        ));
+        assert!(!label_matches("com.example.vendor=Other", Some(&labels)));
+        assert!(!label_matches("com.example.name", Some(&labels)));
+        assert!(!label_matches("com.example.vendor", None));
+    }
 }
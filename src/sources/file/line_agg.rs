@@ -39,7 +39,7 @@ pub enum Mode {
 }
 
 #[derive(Debug, Clone)]
-pub(super) struct Config {
+pub(crate) struct Config {
     /// Start pattern to look for as a beginning of the message.
     pub start_pattern: Regex,
     /// Condition pattern to look for. Exact behavior is configured via `mode`.
@@ -53,7 +53,7 @@ pub(super) struct Config {
 }
 
 impl Config {
-    pub(super) fn for_legacy(marker: Regex, timeout_ms: u64) -> Self {
+    pub(crate) fn for_legacy(marker: Regex, timeout_ms: u64) -> Self {
         let start_pattern = marker;
         let condition_pattern = start_pattern.clone();
         let mode = Mode::HaltBefore;
@@ -68,7 +68,12 @@ impl Config {
     }
 }
 
-pub(super) struct LineAgg<T, K> {
+/// Aggregates lines coming from a stream, per key, into multi-line messages.
+///
+/// Every line carries a context `C` alongside its key. The context of the
+/// first line of a group is the one returned with the aggregated message,
+/// which lets callers keep metadata such as the line's timestamp.
+pub(crate) struct LineAgg<T, K, C> {
     /// The stream from which we read the lines.
     inner: T,
 
@@ -77,12 +82,12 @@ pub(super) struct LineAgg<T, K> {
 
     /// Line per key.
     /// Key is usually a filename or other line source identifier.
-    buffers: HashMap<K, BytesMut>,
+    buffers: HashMap<K, (BytesMut, C)>,
 
     /// Draining queue. We switch to draining mode when we get `None` from
     /// the inner stream. In this mode we stop polling `inner` for new lines
     /// and just flush all the buffered data.
-    draining: Option<Vec<(Bytes, K)>>,
+    draining: Option<Vec<(Bytes, K, C)>>,
 
     /// A queue of key timeouts.
     timeouts: DelayQueue<K>,
@@ -91,11 +96,11 @@ pub(super) struct LineAgg<T, K> {
     expired: VecDeque<K>,
 }
 
-impl<T, K> LineAgg<T, K>
+impl<T, K, C> LineAgg<T, K, C>
 where
     K: Hash + Eq + Clone,
 {
-    pub(super) fn new(inner: T, config: Config) -> Self {
+    pub(crate) fn new(inner: T, config: Config) -> Self {
         Self {
             inner,
 
@@ -109,21 +114,22 @@ where
     }
 }
 
-impl<T, K> Stream for LineAgg<T, K>
+impl<T, K, C> Stream for LineAgg<T, K, C>
 where
-    T: Stream<Item = (Bytes, K), Error = ()>,
+    T: Stream<Item = (Bytes, K, C), Error = ()>,
     K: Hash + Eq + Clone,
 {
-    /// `Bytes` - the line data; `K` - file name, or other line source.
-    type Item = (Bytes, K);
+    /// `Bytes` - the line data; `K` - file name, or other line source;
+    /// `C` - context of the first line.
+    type Item = (Bytes, K, C);
     type Error = ();
 
     fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
         loop {
             // If we're in draining mode, short circut here.
             if let Some(to_drain) = &mut self.draining {
-                if let Some((line, src)) = to_drain.pop() {
-                    return Ok(Async::Ready(Some((line, src))));
+                if let Some((line, src, context)) = to_drain.pop() {
+                    return Ok(Async::Ready(Some((line, src, context))));
                 } else {
                     return Ok(Async::Ready(None));
                 }
@@ -135,26 +141,30 @@ where
             }
 
             match self.inner.poll() {
-                Ok(Async::Ready(Some((line, src)))) => {
+                Ok(Async::Ready(Some((line, src, context)))) => {
                     // Handle the incoming line we got from `inner`. If the
                     // handler gave us something - return it, otherwise continue
                     // with the flow.
-                    if let Some(val) = self.handle_line(line, src) {
+                    if let Some(val) = self.handle_line(line, src, context) {
                         return Ok(Async::Ready(Some(val)));
                     }
                 }
                 Ok(Async::Ready(None)) => {
                     // We got `None`, this means the `inner` stream has ended.
                     // Start flushing all existing data, stop polling `inner`.
-                    self.draining =
-                        Some(self.buffers.drain().map(|(k, v)| (v.into(), k)).collect());
+                    self.draining = Some(
+                        self.buffers
+                            .drain()
+                            .map(|(k, (v, context))| (v.into(), k, context))
+                            .collect(),
+                    );
                 }
                 Ok(Async::NotReady) => {
                     // We didn't get any lines from `inner`, so we just give
                     // a line from the expired lines queue.
                     if let Some(key) = self.expired.pop_front() {
-                        if let Some(buffered) = self.buffers.remove(&key) {
-                            return Ok(Async::Ready(Some((buffered.freeze(), key))));
+                        if let Some((buffered, context)) = self.buffers.remove(&key) {
+                            return Ok(Async::Ready(Some((buffered.freeze(), key, context))));
                         }
                     }
 
@@ -166,13 +176,13 @@ where
     }
 }
 
-impl<T, K> LineAgg<T, K>
+impl<T, K, C> LineAgg<T, K, C>
 where
-    T: Stream<Item = (Bytes, K), Error = ()>,
+    T: Stream<Item = (Bytes, K, C), Error = ()>,
     K: Hash + Eq + Clone,
 {
     /// Handle line, if we have something to output - return it.
-    fn handle_line(&mut self, line: Bytes, src: K) -> Option<(Bytes, K)> {
+    fn handle_line(&mut self, line: Bytes, src: K, context: C) -> Option<(Bytes, K, C)> {
         // Check if we already have the buffered data for the source.
         match self.buffers.entry(src) {
             Entry::Occupied(mut entry) => {
@@ -182,35 +192,43 @@ where
                     // the group.
                     Mode::ContinueThrough => {
                         if condition_matched {
-                            let buffered = entry.get_mut();
+                            let (buffered, _) = entry.get_mut();
                             add_next_line(buffered, line);
                             return None;
                         } else {
-                            let buffered = entry.insert(line.into());
-                            return Some((buffered.freeze(), entry.key().clone()));
+                            let (buffered, buffered_context) = entry.insert((line.into(), context));
+                            return Some((
+                                buffered.freeze(),
+                                entry.key().clone(),
+                                buffered_context,
+                            ));
                         }
                     }
                     // All consecutive lines matching this pattern, plus one
                     // additional line, are included in the group.
                     Mode::ContinuePast => {
                         if condition_matched {
-                            let buffered = entry.get_mut();
+                            let (buffered, _) = entry.get_mut();
                             add_next_line(buffered, line);
                             return None;
                         } else {
-                            let (src, mut buffered) = entry.remove_entry();
+                            let (src, (mut buffered, buffered_context)) = entry.remove_entry();
                             add_next_line(&mut buffered, line);
-                            return Some((buffered.freeze(), src));
+                            return Some((buffered.freeze(), src, buffered_context));
                         }
                     }
                     // All consecutive lines not matching this pattern are included
                     // in the group.
                     Mode::HaltBefore => {
                         if condition_matched {
-                            let buffered = entry.insert(line.into());
-                            return Some((buffered.freeze(), entry.key().clone()));
+                            let (buffered, buffered_context) = entry.insert((line.into(), context));
+                            return Some((
+                                buffered.freeze(),
+                                entry.key().clone(),
+                                buffered_context,
+                            ));
                         } else {
-                            let buffered = entry.get_mut();
+                            let (buffered, _) = entry.get_mut();
                             add_next_line(buffered, line);
                             return None;
                         }
@@ -219,11 +237,11 @@ where
                     // matching this pattern, are included in the group.
                     Mode::HaltWith => {
                         if condition_matched {
-                            let (src, mut buffered) = entry.remove_entry();
+                            let (src, (mut buffered, buffered_context)) = entry.remove_entry();
                             add_next_line(&mut buffered, line);
-                            return Some((buffered.freeze(), src));
+                            return Some((buffered.freeze(), src, buffered_context));
                         } else {
-                            let buffered = entry.get_mut();
+                            let (buffered, _) = entry.get_mut();
                             add_next_line(buffered, line);
                             return None;
                         }
@@ -237,11 +255,11 @@ where
                     // Set the timeout and buffer this line.
                     self.timeouts
                         .insert(entry.key().clone(), self.config.timeout.clone());
-                    entry.insert(line.into());
+                    entry.insert((line.into(), context));
                     return None;
                 } else {
                     // It's just a regular line we don't really care about.
-                    return Some((line, entry.into_key()));
+                    return Some((line, entry.into_key(), context));
                 }
             }
         }
@@ -460,6 +478,32 @@ mod tests {
         assert_results(results, &expected);
     }
 
+    #[test]
+    fn keeps_context_of_first_line() {
+        let lines = vec!["first", " second", "third", " fourth", " fifth"];
+        let config = Config {
+            start_pattern: Regex::new("^[^\\s]").unwrap(),
+            condition_pattern: Regex::new("^[\\s]+").unwrap(),
+            mode: Mode::ContinueThrough,
+            timeout: Duration::from_millis(10),
+        };
+
+        let stream = futures01::stream::iter_ok::<_, ()>(
+            lines
+                .iter()
+                .enumerate()
+                .map(|(i, line)| (Bytes::from_static(line.as_bytes()), "test.log", i)),
+        );
+        let results = collect_results(LineAgg::new(stream, config));
+        assert_eq!(
+            results,
+            vec![
+                (Bytes::from("first\n second"), "test.log", 0),
+                (Bytes::from("third\n fourth\n fifth"), "test.log", 2),
+            ]
+        );
+    }
+
     // Test helpers.
 
     /// Private type alias to be more expressive in the internal implementation.
@@ -467,27 +511,35 @@ mod tests {
 
     fn stream_from_lines<'a>(
         lines: &'a [&'static str],
-    ) -> impl Stream<Item = (Bytes, Filename), Error = ()> + 'a {
-        futures01::stream::iter_ok::<_, ()>(
-            lines
-                .iter()
-                .map(|line| (Bytes::from_static(line.as_bytes()), "test.log".to_owned())),
-        )
+    ) -> impl Stream<Item = (Bytes, Filename, ()), Error = ()> + 'a {
+        futures01::stream::iter_ok::<_, ()>(lines.iter().map(|line| {
+            (
+                Bytes::from_static(line.as_bytes()),
+                "test.log".to_owned(),
+                (),
+            )
+        }))
     }
 
-    fn collect_results<T, K>(line_agg: LineAgg<T, K>) -> Vec<(Bytes, K)>
+    fn collect_results<T, K, C>(line_agg: LineAgg<T, K, C>) -> Vec<(Bytes, K, C)>
     where
-        T: Stream<Item = (Bytes, K), Error = ()>,
+        T: Stream<Item = (Bytes, K, C), Error = ()>,
         K: Hash + Eq + Clone,
     {
         futures01::future::Future::wait(futures01::stream::Stream::collect(line_agg))
             .expect("Failed to collect test results")
     }
 
-    fn assert_results(actual: Vec<(Bytes, Filename)>, expected: &[&'static str]) {
-        let expected_mapped: Vec<(Bytes, Filename)> = expected
+    fn assert_results(actual: Vec<(Bytes, Filename, ())>, expected: &[&'static str]) {
+        let expected_mapped: Vec<(Bytes, Filename, ())> = expected
             .iter()
-            .map(|line| (Bytes::from_static(line.as_bytes()), "test.log".to_owned()))
+            .map(|line| {
+                (
+                    Bytes::from_static(line.as_bytes()),
+                    "test.log".to_owned(),
+                    (),
+                )
+            })
             .collect();
 
         assert_eq!(actual, expected_mapped);
@@ -1,6 +1,6 @@
 use crate::{
     event::{self, Event},
-    internal_events::FileEventReceived,
+    internal_events::{FileEventReceived, FileSourceInternalEventsEmitter},
     shutdown::ShutdownSignal,
     topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
     trace::{current_span, Instrument},
@@ -23,7 +23,7 @@ use std::path::PathBuf;
 use std::time::{Duration, SystemTime};
 use tokio::task::spawn_blocking;
 
-mod line_agg;
+pub(crate) mod line_agg;
 use line_agg::LineAgg;
 
 #[derive(Debug, Snafu)]
@@ -137,6 +137,12 @@ pub enum FingerprintingConfig {
         fingerprint_bytes: usize,
         ignored_header_bytes: usize,
     },
+    FirstLineChecksum {
+        #[serde(default = "default_max_line_length")]
+        max_line_length: usize,
+        #[serde(default)]
+        ignored_header_bytes: usize,
+    },
     #[serde(rename = "device_and_inode")]
     DevInode,
 }
@@ -151,6 +157,13 @@ impl From<FingerprintingConfig> for Fingerprinter {
                 fingerprint_bytes,
                 ignored_header_bytes,
             },
+            FingerprintingConfig::FirstLineChecksum {
+                max_line_length,
+                ignored_header_bytes,
+            } => Fingerprinter::FirstLineChecksum {
+                max_line_length,
+                ignored_header_bytes,
+            },
             FingerprintingConfig::DevInode => Fingerprinter::DevInode,
         }
     }
@@ -160,6 +173,10 @@ fn default_max_line_bytes() -> usize {
     bytesize::kib(100u64) as usize
 }
 
+fn default_max_line_length() -> usize {
+    bytesize::kib(4u64) as usize
+}
+
 impl Default for FileConfig {
     fn default() -> Self {
         Self {
@@ -248,6 +265,7 @@ pub fn file_source(
         glob_minimum_cooldown,
         fingerprinter: config.fingerprinting.clone().into(),
         oldest_first: config.oldest_first,
+        emitter: FileSourceInternalEventsEmitter,
     };
 
     let file_key = config.file_key.clone();
@@ -270,18 +288,24 @@ pub fn file_source(
 
         let messages: Box<dyn Stream<Item = (Bytes, String), Error = ()> + Send> =
             if let Some(ref multiline_config) = multiline_config {
-                Box::new(LineAgg::new(
-                    rx,
-                    multiline_config.try_into().unwrap(), // validated in build
-                ))
+                Box::new(
+                    LineAgg::new(
+                        rx.map(|(line, file)| (line, file, ())),
+                        multiline_config.try_into().unwrap(), // validated in build
+                    )
+                    .map(|(line, file, _)| (line, file)),
+                )
             } else if let Some(msi) = message_start_indicator {
-                Box::new(LineAgg::new(
-                    rx,
-                    line_agg::Config::for_legacy(
-                        Regex::new(&msi).unwrap(), // validated in build
-                        multi_line_timeout,
-                    ),
-                ))
+                Box::new(
+                    LineAgg::new(
+                        rx.map(|(line, file)| (line, file, ())),
+                        line_agg::Config::for_legacy(
+                            Regex::new(&msi).unwrap(), // validated in build
+                            multi_line_timeout,
+                        ),
+                    )
+                    .map(|(line, file, _)| (line, file)),
+                )
             } else {
                 Box::new(rx)
             };
@@ -436,6 +460,21 @@ mod tests {
                 ignored_header_bytes: 512,
             }
         );
+
+        let config: FileConfig = toml::from_str(
+            r#"
+        [fingerprinting]
+        strategy = "first_line_checksum"
+        "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.fingerprinting,
+            FingerprintingConfig::FirstLineChecksum {
+                max_line_length: 4096,
+                ignored_header_bytes: 0,
+            }
+        );
     }
 
     #[test]
@@ -609,6 +648,67 @@ mod tests {
         }
     }
 
+    #[test]
+    fn file_copy_truncate_with_first_line_checksum() {
+        let n = 5;
+        let (tx, rx) = futures01::sync::mpsc::channel(2 * n + 2);
+        let (trigger_shutdown, shutdown, _) = ShutdownSignal::new_wired();
+
+        let dir = tempdir().unwrap();
+        let config = file::FileConfig {
+            include: vec![dir.path().join("*")],
+            fingerprinting: FingerprintingConfig::FirstLineChecksum {
+                max_line_length: 64,
+                ignored_header_bytes: 0,
+            },
+            ..test_default_file_config(&dir)
+        };
+        let source = file::file_source(&config, config.data_dir.clone().unwrap(), shutdown, tx);
+
+        let mut rt = runtime::Runtime::new().unwrap();
+
+        rt.spawn(source);
+
+        let path = dir.path().join("file");
+        let mut file = File::create(&path).unwrap();
+
+        // Both generations of the file start with the same line, so they share
+        // a fingerprint and only truncation detection can tell them apart.
+        writeln!(&mut file, "header").unwrap();
+        for i in 0..n {
+            writeln!(&mut file, "pretrunc {}", i).unwrap();
+        }
+
+        sleep(); // The writes must be observed before truncating
+
+        file.set_len(0).unwrap();
+        file.seek(std::io::SeekFrom::Start(0)).unwrap();
+
+        sleep(); // The truncate must be observed before writing again
+
+        writeln!(&mut file, "header").unwrap();
+        for i in 0..n {
+            writeln!(&mut file, "posttrunc {}", i).unwrap();
+        }
+
+        sleep();
+
+        drop(trigger_shutdown);
+        shutdown_on_idle(rt);
+
+        let received = wait_with_timeout(rx.collect());
+        let lines = received
+            .into_iter()
+            .map(|event| event.as_log()[&event::log_schema().message_key()].to_string_lossy())
+            .collect::<Vec<_>>();
+
+        let mut expected = vec!["header".to_owned()];
+        expected.extend((0..n).map(|i| format!("pretrunc {}", i)));
+        expected.push("header".to_owned());
+        expected.extend((0..n).map(|i| format!("posttrunc {}", i)));
+        assert_eq!(lines, expected);
+    }
+
     #[test]
     fn file_rotate() {
         let n = 5;
@@ -0,0 +1,591 @@
+//! Reader for the systemd journal file format.
+//!
+//! The format is documented at
+//! https://systemd.io/JOURNAL_FILE_FORMAT/. Rather than walking the entry
+//! arrays and hash tables we scan objects in file order: objects are only
+//! ever appended, so entries appear in the order they were written and a
+//! file which is still being written can be followed by remembering the
+//! offset we stopped at.
+
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Read},
+    os::unix::fs::FileExt,
+    path::{Path, PathBuf},
+};
+
+const SIGNATURE: &[u8; 8] = b"LPKSHHRH";
+
+// The fixed part of the header which every supported version has.
+const MIN_HEADER_SIZE: u64 = 208;
+const OBJECT_HEADER_SIZE: u64 = 16;
+
+const HEADER_INCOMPATIBLE_COMPRESSED_XZ: u32 = 1 << 0;
+const HEADER_INCOMPATIBLE_COMPRESSED_LZ4: u32 = 1 << 1;
+const HEADER_INCOMPATIBLE_KEYED_HASH: u32 = 1 << 2;
+const HEADER_INCOMPATIBLE_COMPRESSED_ZSTD: u32 = 1 << 3;
+const HEADER_INCOMPATIBLE_COMPACT: u32 = 1 << 4;
+// Files which may contain XZ compressed data are rejected. systemd has not
+// used XZ by default since version 229.
+const HEADER_INCOMPATIBLE_SUPPORTED: u32 = HEADER_INCOMPATIBLE_COMPRESSED_LZ4
+    | HEADER_INCOMPATIBLE_KEYED_HASH
+    | HEADER_INCOMPATIBLE_COMPRESSED_ZSTD
+    | HEADER_INCOMPATIBLE_COMPACT;
+
+const OBJECT_DATA: u8 = 1;
+const OBJECT_ENTRY: u8 = 3;
+const OBJECT_COMPRESSED_XZ: u8 = 1 << 0;
+const OBJECT_COMPRESSED_LZ4: u8 = 1 << 1;
+const OBJECT_COMPRESSED_ZSTD: u8 = 1 << 2;
+
+// The largest data object journald writes, which bounds decompression.
+const DATA_SIZE_MAX: u64 = 768 * 1024 * 1024;
+
+/// A 128 bit systemd ID, such as a boot ID or machine ID.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Id128(pub [u8; 16]);
+
+impl fmt::Display for Id128 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for Id128 {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, ()> {
+        let s = s.trim().replace('-', "");
+        if s.len() != 32 {
+            return Err(());
+        }
+        let mut id = [0u8; 16];
+        for (i, byte) in id.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).map_err(|_| ())?;
+        }
+        Ok(Id128(id))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Header {
+    pub incompatible_flags: u32,
+    pub file_id: Id128,
+    pub machine_id: Id128,
+    pub seqnum_id: Id128,
+    pub header_size: u64,
+    pub arena_size: u64,
+    pub tail_object_offset: u64,
+    pub n_entries: u64,
+}
+
+impl Header {
+    fn is_compact(&self) -> bool {
+        self.incompatible_flags & HEADER_INCOMPATIBLE_COMPACT != 0
+    }
+}
+
+/// A single journal entry with all of its fields resolved.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub seqnum_id: Id128,
+    pub seqnum: u64,
+    pub realtime: u64,
+    pub monotonic: u64,
+    pub boot_id: Id128,
+    pub xor_hash: u64,
+    pub fields: Vec<(String, Vec<u8>)>,
+}
+
+impl Entry {
+    /// The cursor for this entry, in the same format as `journalctl` uses, so
+    /// checkpoints written by either can be resumed from.
+    pub fn cursor(&self) -> String {
+        format!(
+            "s={};i={:x};b={};m={:x};t={:x};x={:x}",
+            self.seqnum_id, self.seqnum, self.boot_id, self.monotonic, self.realtime, self.xor_hash
+        )
+    }
+
+    pub fn get(&self, name: &str) -> Option<&[u8]> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| &value[..])
+    }
+}
+
+/// The parts of a cursor needed to find our place again.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Cursor {
+    pub seqnum_id: Option<Id128>,
+    pub seqnum: Option<u64>,
+    pub realtime: Option<u64>,
+}
+
+impl Cursor {
+    pub fn parse(cursor: &str) -> Self {
+        let mut parsed = Cursor::default();
+        for part in cursor.trim().split(';') {
+            let mut kv = part.splitn(2, '=');
+            match (kv.next(), kv.next()) {
+                (Some("s"), Some(value)) => parsed.seqnum_id = value.parse().ok(),
+                (Some("i"), Some(value)) => parsed.seqnum = u64::from_str_radix(value, 16).ok(),
+                (Some("t"), Some(value)) => parsed.realtime = u64::from_str_radix(value, 16).ok(),
+                _ => {}
+            }
+        }
+        parsed
+    }
+
+    /// Has the given entry already been read, according to this cursor?
+    pub fn is_after(&self, entry: &Entry) -> bool {
+        match (self.seqnum_id, self.seqnum, self.realtime) {
+            (Some(seqnum_id), Some(seqnum), _) if seqnum_id == entry.seqnum_id => {
+                entry.seqnum <= seqnum
+            }
+            (_, _, Some(realtime)) => entry.realtime <= realtime,
+            _ => false,
+        }
+    }
+}
+
+pub struct JournalFile {
+    path: PathBuf,
+    file: File,
+    header: Header,
+}
+
+impl JournalFile {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let header = read_header(&file)?;
+        Ok(JournalFile {
+            path: path.into(),
+            file,
+            header,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// The offset of the first object in the file.
+    pub fn first_object_offset(&self) -> u64 {
+        align8(self.header.header_size)
+    }
+
+    /// Re-read the header to pick up entries appended since the file was
+    /// opened.
+    pub fn refresh(&mut self) -> io::Result<()> {
+        self.header = read_header(&self.file)?;
+        Ok(())
+    }
+
+    /// Read up to `max_entries` entries, starting with the object at `offset`.
+    /// Returns the entries and the offset to continue from next time.
+    pub fn read_entries(
+        &self,
+        mut offset: u64,
+        max_entries: usize,
+    ) -> io::Result<(Vec<Entry>, u64)> {
+        let mut entries = Vec::new();
+
+        while offset != 0
+            && offset <= self.header.tail_object_offset
+            && self.contains(offset, OBJECT_HEADER_SIZE)
+            && entries.len() < max_entries
+        {
+            let (object_type, _, size) = self.read_object_header(offset)?;
+            if size < OBJECT_HEADER_SIZE || !self.contains(offset, size) {
+                // An object which has been allocated but not yet written.
+                break;
+            }
+            if object_type == OBJECT_ENTRY {
+                entries.push(self.read_entry(offset, size)?);
+            }
+            offset += align8(size);
+        }
+
+        Ok((entries, offset))
+    }
+
+    /// Does an object of `size` bytes at `offset` lie within the arena?
+    fn contains(&self, offset: u64, size: u64) -> bool {
+        let end = self
+            .header
+            .header_size
+            .saturating_add(self.header.arena_size);
+        offset
+            .checked_add(size)
+            .map_or(false, |object_end| object_end <= end)
+    }
+
+    fn read_object_header(&self, offset: u64) -> io::Result<(u8, u8, u64)> {
+        let mut buf = [0u8; OBJECT_HEADER_SIZE as usize];
+        self.file.read_exact_at(&mut buf, offset)?;
+        Ok((buf[0], buf[1], le64(&buf[8..16])))
+    }
+
+    fn read_entry(&self, offset: u64, size: u64) -> io::Result<Entry> {
+        let mut buf = vec![0u8; size as usize];
+        self.file.read_exact_at(&mut buf, offset)?;
+        if buf.len() < 64 {
+            return Err(invalid_data("entry object is too small"));
+        }
+
+        let mut boot_id = [0u8; 16];
+        boot_id.copy_from_slice(&buf[40..56]);
+
+        let item_size = if self.header.is_compact() { 4 } else { 16 };
+        let fields = buf[64..]
+            .chunks_exact(item_size)
+            .filter_map(|item| {
+                let data_offset = if self.header.is_compact() {
+                    u64::from(le32(&item[0..4]))
+                } else {
+                    le64(&item[0..8])
+                };
+                match self.read_data(data_offset) {
+                    Ok(field) => Some(field),
+                    Err(error) => {
+                        warn!(
+                            message = "Skipping unreadable journal field.",
+                            path = ?self.path,
+                            %error,
+                            rate_limit_secs = 30
+                        );
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        Ok(Entry {
+            seqnum_id: self.header.seqnum_id,
+            seqnum: le64(&buf[16..24]),
+            realtime: le64(&buf[24..32]),
+            monotonic: le64(&buf[32..40]),
+            boot_id: Id128(boot_id),
+            xor_hash: le64(&buf[56..64]),
+            fields,
+        })
+    }
+
+    fn read_data(&self, offset: u64) -> io::Result<(String, Vec<u8>)> {
+        let (object_type, flags, size) = self.read_object_header(offset)?;
+        if object_type != OBJECT_DATA {
+            return Err(invalid_data("entry item does not refer to a data object"));
+        }
+        let payload_offset = if self.header.is_compact() { 72 } else { 64 };
+        if size < payload_offset {
+            return Err(invalid_data("data object is too small"));
+        }
+        // Checked before allocating, as a corrupt size could be anything.
+        if size - payload_offset > DATA_SIZE_MAX || !self.contains(offset, size) {
+            return Err(invalid_data("data object is out of bounds"));
+        }
+        let mut payload = vec![0u8; (size - payload_offset) as usize];
+        self.file
+            .read_exact_at(&mut payload, offset + payload_offset)?;
+        let mut payload = decompress(flags, payload)?;
+
+        let eq = payload
+            .iter()
+            .position(|&b| b == b'=')
+            .ok_or_else(|| invalid_data("data object has no field name"))?;
+        let value = payload.split_off(eq + 1);
+        payload.truncate(eq);
+        let name = String::from_utf8(payload).map_err(|_| invalid_data("invalid field name"))?;
+
+        Ok((name, value))
+    }
+}
+
+fn decompress(flags: u8, payload: Vec<u8>) -> io::Result<Vec<u8>> {
+    if flags & OBJECT_COMPRESSED_LZ4 != 0 {
+        decompress_lz4(&payload)
+    } else if flags & OBJECT_COMPRESSED_ZSTD != 0 {
+        decompress_zstd(&payload)
+    } else if flags & OBJECT_COMPRESSED_XZ != 0 {
+        Err(invalid_data("XZ compressed journal data is not supported"))
+    } else {
+        Ok(payload)
+    }
+}
+
+/// Decodes an LZ4 block, which systemd prefixes with the little endian size
+/// of the decompressed data.
+fn decompress_lz4(src: &[u8]) -> io::Result<Vec<u8>> {
+    if src.len() < 8 {
+        return Err(invalid_data("LZ4 compressed data is too small"));
+    }
+    let size = le64(&src[..8]);
+    if size > DATA_SIZE_MAX {
+        return Err(invalid_data("LZ4 compressed data is too large"));
+    }
+    let size = size as usize;
+
+    let truncated = || invalid_data("LZ4 compressed data is truncated");
+    let corrupt = || invalid_data("LZ4 compressed data is corrupt");
+    let mut out = Vec::with_capacity(size);
+    let mut pos = 8;
+    loop {
+        let token = *src.get(pos).ok_or_else(truncated)?;
+        pos += 1;
+
+        let literals = lz4_length(src, &mut pos, usize::from(token >> 4))?;
+        let literals = src.get(pos..pos + literals).ok_or_else(truncated)?;
+        if out.len() + literals.len() > size {
+            return Err(corrupt());
+        }
+        out.extend_from_slice(literals);
+        pos += literals.len();
+
+        // The last sequence of a block only has literals.
+        if pos == src.len() {
+            break;
+        }
+
+        let offset = src.get(pos..pos + 2).ok_or_else(truncated)?;
+        let offset = usize::from(u16::from_le_bytes([offset[0], offset[1]]));
+        pos += 2;
+        let length = lz4_length(src, &mut pos, usize::from(token & 0xf))? + 4;
+        if offset == 0 || offset > out.len() || out.len() + length > size {
+            return Err(corrupt());
+        }
+        // Matches may overlap the bytes they produce, so copy byte by byte.
+        let start = out.len() - offset;
+        for index in start..start + length {
+            let byte = out[index];
+            out.push(byte);
+        }
+    }
+
+    if out.len() != size {
+        return Err(corrupt());
+    }
+    Ok(out)
+}
+
+/// Reads the rest of an LZ4 length whose token nibble is 15.
+fn lz4_length(src: &[u8], pos: &mut usize, mut length: usize) -> io::Result<usize> {
+    if length == 15 {
+        loop {
+            let byte = *src
+                .get(*pos)
+                .ok_or_else(|| invalid_data("LZ4 compressed data is truncated"))?;
+            *pos += 1;
+            length += usize::from(byte);
+            if byte != 255 {
+                break;
+            }
+        }
+    }
+    Ok(length)
+}
+
+fn decompress_zstd(src: &[u8]) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    zstd::stream::read::Decoder::new(src)?
+        .take(DATA_SIZE_MAX + 1)
+        .read_to_end(&mut out)
+        .map_err(|_| invalid_data("ZSTD compressed data is corrupt"))?;
+    if out.len() as u64 > DATA_SIZE_MAX {
+        return Err(invalid_data("ZSTD compressed data is too large"));
+    }
+    Ok(out)
+}
+
+fn read_header(file: &File) -> io::Result<Header> {
+    let mut buf = [0u8; MIN_HEADER_SIZE as usize];
+    file.read_exact_at(&mut buf, 0)?;
+
+    if &buf[0..8] != SIGNATURE {
+        return Err(invalid_data("not a journal file"));
+    }
+
+    let incompatible_flags = le32(&buf[12..16]);
+    if incompatible_flags & HEADER_INCOMPATIBLE_COMPRESSED_XZ != 0 {
+        return Err(invalid_data(
+            "XZ compressed journal files are not supported",
+        ));
+    }
+    if incompatible_flags & !HEADER_INCOMPATIBLE_SUPPORTED != 0 {
+        return Err(invalid_data("journal file uses unsupported features"));
+    }
+
+    let header_size = le64(&buf[88..96]);
+    if header_size < MIN_HEADER_SIZE {
+        return Err(invalid_data("journal file header is too small"));
+    }
+
+    Ok(Header {
+        incompatible_flags,
+        file_id: id128(&buf[24..40]),
+        machine_id: id128(&buf[40..56]),
+        seqnum_id: id128(&buf[72..88]),
+        header_size,
+        arena_size: le64(&buf[96..104]),
+        tail_object_offset: le64(&buf[136..144]),
+        n_entries: le64(&buf[152..160]),
+    })
+}
+
+fn invalid_data(message: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn align8(n: u64) -> u64 {
+    (n + 7) & !7
+}
+
+fn le32(bytes: &[u8]) -> u32 {
+    let mut buf = [0u8; 4];
+    buf.copy_from_slice(bytes);
+    u32::from_le_bytes(buf)
+}
+
+fn le64(bytes: &[u8]) -> u64 {
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    u64::from_le_bytes(buf)
+}
+
+fn id128(bytes: &[u8]) -> Id128 {
+    let mut id = [0u8; 16];
+    id.copy_from_slice(bytes);
+    Id128(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JOURNAL: &str = "tests/data/journald/system.journal";
+
+    fn read_all() -> Vec<Entry> {
+        let journal = JournalFile::open(Path::new(JOURNAL)).unwrap();
+        let (entries, _) = journal
+            .read_entries(journal.first_object_offset(), usize::max_value())
+            .unwrap();
+        entries
+    }
+
+    #[test]
+    fn reads_header() {
+        let journal = JournalFile::open(Path::new(JOURNAL)).unwrap();
+        let header = journal.header();
+        assert_eq!(header.n_entries, 8);
+        assert_eq!(
+            header.machine_id.to_string(),
+            "3d1219c7c4c5404aaa1f6d2a48adfda4"
+        );
+    }
+
+    #[test]
+    fn reads_entries() {
+        let entries = read_all();
+        assert_eq!(entries.len(), 8);
+        assert_eq!(
+            entries.iter().map(|entry| entry.seqnum).collect::<Vec<_>>(),
+            (1..=8).collect::<Vec<_>>()
+        );
+
+        let first = &entries[2];
+        assert_eq!(first.get("MESSAGE"), Some(&b"first message"[..]));
+        assert_eq!(first.get("SYSLOG_IDENTIFIER"), Some(&b"vector-test"[..]));
+        assert_eq!(first.get("CUSTOM_FIELD"), Some(&b"alpha"[..]));
+        assert_eq!(first.realtime, 1792400057789192);
+    }
+
+    #[test]
+    fn reads_binary_fields() {
+        let entries = read_all();
+        assert_eq!(entries[4].get("MESSAGE"), Some(&b"\xc2\xbfHello?"[..]));
+        assert_eq!(
+            entries[4].get("BINARY_FIELD"),
+            Some(&b"\x00\x01\xfe\xff"[..])
+        );
+        assert_eq!(entries[5].get("MESSAGE"), Some(&b"multi\nline message"[..]));
+    }
+
+    #[test]
+    fn resumes_from_offset() {
+        let journal = JournalFile::open(Path::new(JOURNAL)).unwrap();
+        let (first, offset) = journal
+            .read_entries(journal.first_object_offset(), 3)
+            .unwrap();
+        assert_eq!(first.len(), 3);
+        let (rest, _) = journal.read_entries(offset, usize::max_value()).unwrap();
+        assert_eq!(rest.len(), 5);
+        assert_eq!(rest[0].seqnum, 4);
+    }
+
+    #[test]
+    fn formats_and_parses_cursors() {
+        let entries = read_all();
+        assert_eq!(
+            entries[0].cursor(),
+            "s=c36668c6db624ec0bcccd9971df7e4c0;i=1;b=0688030eac8043eebd1849c4e8bb5328;m=2513530f;t=65e2da89c5c1c;x=ff39fa0c1043ba5b"
+        );
+
+        let cursor = Cursor::parse(&entries[3].cursor());
+        assert_eq!(cursor.seqnum, Some(4));
+        assert!(entries[..4].iter().all(|entry| cursor.is_after(entry)));
+        assert!(entries[4..].iter().all(|entry| !cursor.is_after(entry)));
+    }
+
+    #[test]
+    fn decompresses_data() {
+        let expected = &include_bytes!("../../../tests/data/journald/message")[..];
+        // Compressed by systemd itself, see `tests/data/README.md`.
+        let lz4 = include_bytes!("../../../tests/data/journald/message.lz4");
+        let zstd = include_bytes!("../../../tests/data/journald/message.zst");
+
+        assert_eq!(
+            decompress(OBJECT_COMPRESSED_LZ4, lz4.to_vec()).unwrap(),
+            expected
+        );
+        assert_eq!(
+            decompress(OBJECT_COMPRESSED_ZSTD, zstd.to_vec()).unwrap(),
+            expected
+        );
+        assert!(decompress(OBJECT_COMPRESSED_LZ4, lz4[..100].to_vec()).is_err());
+        assert!(decompress(OBJECT_COMPRESSED_ZSTD, zstd[..100].to_vec()).is_err());
+        assert!(decompress(OBJECT_COMPRESSED_XZ, vec![1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn rejects_data_out_of_bounds() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("system.journal");
+        std::fs::copy(JOURNAL, &path).unwrap();
+
+        // Corrupt the size of the first data object, which is referenced by
+        // the first entry.
+        let journal = JournalFile::open(&path).unwrap();
+        let mut offset = journal.first_object_offset();
+        loop {
+            let (object_type, _, size) = journal.read_object_header(offset).unwrap();
+            if object_type == OBJECT_DATA {
+                break;
+            }
+            offset += align8(size);
+        }
+        let file = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+        file.write_all_at(&u64::max_value().to_le_bytes(), offset + 8)
+            .unwrap();
+
+        assert!(journal.read_data(offset).is_err());
+    }
+}
@@ -4,6 +4,7 @@ use crate::{
     shutdown::ShutdownSignal,
     topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
 };
+use bytes::Bytes;
 use chrono::TimeZone;
 use futures::{
     compat::Future01CompatExt,
@@ -12,24 +13,24 @@ use futures::{
 };
 use futures01::{future, sync::mpsc, Future, Sink};
 use lazy_static::lazy_static;
-use nix::{
-    sys::signal::{kill, Signal},
-    unistd::Pid,
-};
 use serde::{Deserialize, Serialize};
-use serde_json::{Error as JsonError, Value as JsonValue};
-use snafu::{ResultExt, Snafu};
+use snafu::Snafu;
 use std::collections::{HashMap, HashSet};
 use std::fs::{File, OpenOptions};
-use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::iter::FromIterator;
 use std::path::PathBuf;
-use std::process::{Child, ChildStdout, Command, Stdio};
 use std::time;
 use string_cache::DefaultAtom as Atom;
 use tokio::{task::spawn_blocking, time::delay_for};
 use tracing::{dispatcher, field};
 
+mod journal_file;
+mod reader;
+
+use journal_file::Entry;
+use reader::{JournalLocations, JournalReader};
+
 const DEFAULT_BATCH_SIZE: usize = 16;
 
 lazy_static! {
@@ -39,13 +40,13 @@ lazy_static! {
     static ref SYSTEMD_UNIT: Atom = Atom::from("_SYSTEMD_UNIT");
     static ref SOURCE_TIMESTAMP: Atom = Atom::from("_SOURCE_REALTIME_TIMESTAMP");
     static ref RECEIVED_TIMESTAMP: Atom = Atom::from("__REALTIME_TIMESTAMP");
-    static ref JOURNALCTL: PathBuf = "journalctl".into();
+    static ref MONOTONIC_TIMESTAMP: Atom = Atom::from("__MONOTONIC_TIMESTAMP");
+    static ref JOURNAL_DIRECTORIES: Vec<PathBuf> =
+        vec!["/var/log/journal".into(), "/run/log/journal".into()];
 }
 
 #[derive(Debug, Snafu)]
 enum BuildError {
-    #[snafu(display("journalctl failed to execute: {}", source))]
-    JournalctlSpawn { source: io::Error },
     #[snafu(display("Cannot use both `units` and `include_units`"))]
     BothUnitsAndIncludeUnits,
     #[snafu(display(
@@ -62,8 +63,13 @@ pub struct JournaldConfig {
     pub units: Vec<String>,
     pub include_units: Vec<String>,
     pub exclude_units: Vec<String>,
+    pub include_matches: HashMap<String, HashSet<String>>,
+    pub exclude_matches: HashMap<String, HashSet<String>>,
     pub data_dir: Option<PathBuf>,
     pub batch_size: Option<usize>,
+    pub journal_directories: Vec<PathBuf>,
+    pub namespace: Option<String>,
+    pub include_remote: bool,
     pub journalctl_path: Option<PathBuf>,
 }
 
@@ -71,7 +77,8 @@ inventory::submit! {
     SourceDescription::new::<JournaldConfig>("journald")
 }
 
-type Record = HashMap<Atom, String>;
+type Record = HashMap<Atom, Bytes>;
+type Matches = HashMap<Atom, HashSet<Bytes>>;
 
 #[typetag::serde(name = "journald")]
 impl SourceConfig for JournaldConfig {
@@ -85,6 +92,12 @@ impl SourceConfig for JournaldConfig {
         let data_dir = globals.resolve_and_make_data_subdir(self.data_dir.as_ref(), name)?;
         let batch_size = self.batch_size.unwrap_or(DEFAULT_BATCH_SIZE);
 
+        if self.journalctl_path.is_some() {
+            warn!(
+                "The `journalctl_path` setting is no longer used, journal files are read directly"
+            );
+        }
+
         let include_units = match (!self.units.is_empty(), !self.include_units.is_empty()) {
             (true, true) => return Err(BuildError::BothUnitsAndIncludeUnits.into()),
             (true, false) => {
@@ -105,15 +118,18 @@ impl SourceConfig for JournaldConfig {
             return Err(BuildError::DuplicatedUnit { unit }.into());
         }
 
+        let include_matches = create_matches(&self.include_matches, include_units);
+        let exclude_matches = create_matches(&self.exclude_matches, exclude_units);
+
         let checkpointer = Checkpointer::new(data_dir)
             .map_err(|err| format!("Unable to open checkpoint file: {}", err))?;
 
-        self.source::<Journalctl>(
+        self.source::<Journal>(
             out,
             shutdown,
             checkpointer,
-            include_units,
-            exclude_units,
+            include_matches,
+            exclude_matches,
             batch_size,
         )
     }
@@ -133,8 +149,8 @@ impl JournaldConfig {
         out: mpsc::Sender<Event>,
         shutdown: ShutdownSignal,
         mut checkpointer: Checkpointer,
-        include_units: HashSet<String>,
-        exclude_units: HashSet<String>,
+        include_matches: Matches,
+        exclude_matches: Matches,
         batch_size: usize,
     ) -> crate::Result<super::Source>
     where
@@ -156,15 +172,15 @@ impl JournaldConfig {
             }
         };
 
-        let (journal, close) = J::new(self, cursor)?;
+        let journal = J::new(self, cursor)?;
 
         Ok(Box::new(future::lazy(move || {
             info!(message = "Starting journald server.",);
 
             let journald_server = JournaldServer {
                 journal,
-                include_units,
-                exclude_units,
+                include_matches,
+                exclude_matches,
                 channel: out,
                 shutdown: shutdown.clone(),
                 checkpointer,
@@ -178,7 +194,7 @@ impl JournaldConfig {
             .boxed()
             .compat()
             .map_err(|error| error!(message="Journald server unexpectedly stopped.",%error))
-            .select(shutdown.map(move |_| close()))
+            .select(shutdown.map(|_| ()))
             .map(|_| ())
             .map_err(|_| ())
         })))
@@ -227,82 +243,95 @@ fn fixup_unit(unit: &String) -> String {
     }
 }
 
+/// Combine the configured field matches with the unit names, which are
+/// matches on the `_SYSTEMD_UNIT` field.
+fn create_matches(matches: &HashMap<String, HashSet<String>>, units: HashSet<String>) -> Matches {
+    let mut matches: Matches = matches
+        .iter()
+        .map(|(field, values)| {
+            let values = values.iter().map(|value| value.as_str().into()).collect();
+            (Atom::from(field.as_str()), values)
+        })
+        .collect();
+    if !units.is_empty() {
+        matches
+            .entry(SYSTEMD_UNIT.clone())
+            .or_default()
+            .extend(units.into_iter().map(Bytes::from));
+    }
+    matches
+}
+
 /// A `JournalSource` is a data source that works as an `Iterator`
-/// producing lines that resemble journald JSON format records. These
-/// trait functions is an addition to the standard iteration methods for
-/// initializing the source.
-trait JournalSource: Iterator<Item = Result<String, io::Error>> + Sized {
-    /// (source, close_underlying_stream)
-    fn new(
-        config: &JournaldConfig,
-        cursor: Option<String>,
-    ) -> crate::Result<(Self, Box<dyn FnOnce() + Send>)>;
+/// producing journal records. `None` signals that no more records are
+/// available for now, after which the source is polled again later.
+trait JournalSource: Iterator<Item = Result<Record, io::Error>> + Sized {
+    fn new(config: &JournaldConfig, cursor: Option<String>) -> crate::Result<Self>;
 }
 
-struct Journalctl {
-    #[allow(dead_code)]
-    child: Child,
-    stdout: BufReader<ChildStdout>,
+/// Reads journal files from disk, see `reader::JournalReader`.
+struct Journal {
+    reader: JournalReader,
 }
 
-impl JournalSource for Journalctl {
-    fn new(
-        config: &JournaldConfig,
-        cursor: Option<String>,
-    ) -> crate::Result<(Self, Box<dyn FnOnce() + Send>)> {
-        let journalctl = config.journalctl_path.as_ref().unwrap_or(&JOURNALCTL);
-        let mut command = Command::new(journalctl);
-        command.stdout(Stdio::piped());
-        command.arg("--follow");
-        command.arg("--all");
-        command.arg("--show-cursor");
-        command.arg("--output=json");
-
-        let current_boot = config.current_boot_only.unwrap_or(true);
-        if current_boot {
-            command.arg("--boot");
-        }
+impl JournalSource for Journal {
+    fn new(config: &JournaldConfig, cursor: Option<String>) -> crate::Result<Self> {
+        let directories = if config.journal_directories.is_empty() {
+            JOURNAL_DIRECTORIES.clone()
+        } else {
+            config.journal_directories.clone()
+        };
+        let locations = JournalLocations {
+            directories,
+            namespace: config.namespace.clone(),
+            include_remote: config.include_remote,
+            machine_id: reader::current_machine_id(),
+        };
 
-        if let Some(cursor) = cursor {
-            command.arg(format!("--after-cursor={}", cursor));
+        let boot_id = if config.current_boot_only.unwrap_or(true) {
+            Some(reader::current_boot_id().ok_or("Unable to determine the current boot ID")?)
         } else {
-            // journalctl --follow only outputs a few lines without a starting point
-            command.arg("--since=2000-01-01");
-        }
+            None
+        };
 
-        let mut child = command.spawn().context(JournalctlSpawn)?;
-        let stdout = child.stdout.take().unwrap();
-        let stdout = BufReader::new(stdout);
-
-        let pid = Pid::from_raw(child.id() as i32);
-        Ok((
-            Journalctl { child, stdout },
-            Box::new(move || {
-                // Signal the child process to terminate so that the
-                // blocking future can be unblocked sooner rather
-                // than later.
-                let _ = kill(pid, Signal::SIGTERM);
-            }),
-        ))
+        let reader = JournalReader::new(locations, cursor.as_deref(), boot_id);
+        Ok(Journal { reader })
     }
 }
 
-impl Iterator for Journalctl {
-    type Item = Result<String, io::Error>;
+impl Iterator for Journal {
+    type Item = Result<Record, io::Error>;
+
     fn next(&mut self) -> Option<Self::Item> {
-        let mut line = Vec::<u8>::new();
-        match self.stdout.read_until(b'\n', &mut line) {
-            Ok(0) => None,
-            Ok(_) => Some(Ok(String::from_utf8_lossy(&line).into())),
-            Err(err) => Some(Err(err)),
-        }
+        self.reader.next().map(|entry| entry.map(create_record))
     }
 }
 
+/// Converts a journal entry into a record with the same fields `journalctl
+/// --output=json` produces, so events look the same as they always did.
+fn create_record(entry: Entry) -> Record {
+    let cursor = entry.cursor();
+    let mut record = entry
+        .fields
+        .into_iter()
+        .map(|(name, value)| (Atom::from(name), Bytes::from(value)))
+        .collect::<Record>();
+    record.insert(CURSOR.clone(), cursor.into());
+    record.insert(
+        RECEIVED_TIMESTAMP.clone(),
+        entry.realtime.to_string().into(),
+    );
+    record.insert(
+        MONOTONIC_TIMESTAMP.clone(),
+        entry.monotonic.to_string().into(),
+    );
+    record
+}
+
 struct JournaldServer<J, T> {
     journal: J,
-    include_units: HashSet<String>,
-    exclude_units: HashSet<String>,
+    include_matches: Matches,
+    exclude_matches: Matches,
     channel: T,
     shutdown: ShutdownSignal,
     checkpointer: Checkpointer,
@@ -325,36 +354,29 @@ where
             let mut cursor: Option<String> = None;
 
             for _ in 0..self.batch_size {
-                let text = match self.journal.next() {
+                let mut record = match self.journal.next() {
                     None => {
                         at_end = true;
                         break;
                     }
-                    Some(Ok(text)) => text,
+                    Some(Ok(record)) => record,
                     Some(Err(err)) => {
                         error!(
                             message = "Could not read from journald source",
                             error = field::display(&err),
                         );
+                        at_end = true;
                         break;
                     }
                 };
 
-                let mut record = match decode_record(&text) {
-                    Ok(record) => record,
-                    Err(error) => {
-                        error!(message = "Invalid record from journald, discarding", %error, %text);
-                        continue;
-                    }
-                };
                 if let Some(tmp) = record.remove(&CURSOR) {
-                    cursor = Some(tmp);
+                    cursor = Some(String::from_utf8_lossy(&tmp).into_owned());
                 }
 
                 saw_record = true;
 
-                let unit = record.get(&SYSTEMD_UNIT);
-                if filter_unit(unit, &self.include_units, &self.exclude_units) {
+                if filter_matches(&record, &self.include_matches, &self.exclude_matches) {
                     continue;
                 }
 
@@ -390,48 +412,21 @@ where
     }
 }
 
-fn decode_record(text: &str) -> Result<Record, JsonError> {
-    let mut record = serde_json::from_str::<JsonValue>(&text)?;
-    // journalctl will output non-ASCII messages using an array
-    // of integers. Look for those messages and re-parse them.
-    record.get_mut("MESSAGE").and_then(|message| {
-        message
-            .as_array()
-            .and_then(decode_array)
-            .map(|decoded| *message = decoded)
-    });
-    serde_json::from_value(record)
-}
-
-fn decode_array(array: &Vec<JsonValue>) -> Option<JsonValue> {
-    // From the array of values, turn all the numbers into bytes, and
-    // then the bytes into a string, but return None if any value in the
-    // array was not a valid byte.
-    array
-        .into_iter()
-        .map(|item| {
-            item.as_u64().and_then(|num| match num {
-                num if num <= u8::max_value() as u64 => Some(num as u8),
-                _ => None,
-            })
-        })
-        .collect::<Option<Vec<u8>>>()
-        .map(|array| String::from_utf8_lossy(&array).into())
-}
-
-/// Should the given unit name be filtered (excluded)?
-fn filter_unit(
-    unit: Option<&String>,
-    includes: &HashSet<String>,
-    excludes: &HashSet<String>,
-) -> bool {
-    match (unit, includes.is_empty(), excludes.is_empty()) {
-        (None, empty, _) => !empty,
-        (Some(_), true, true) => false,
-        (Some(unit), false, true) => !includes.contains(unit),
-        (Some(unit), true, false) => excludes.contains(unit),
-        (Some(unit), false, false) => !includes.contains(unit) || excludes.contains(unit),
-    }
+/// Should the given record be filtered (excluded)? Every field in
+/// `includes` must have one of its listed values, and no field in `excludes`
+/// may have one of its listed values.
+fn filter_matches(record: &Record, includes: &Matches, excludes: &Matches) -> bool {
+    let matches = |field: &Atom, values: &HashSet<Bytes>| {
+        record
+            .get(field)
+            .map_or(false, |value| values.contains(value))
+    };
+    includes
+        .iter()
+        .any(|(field, values)| !matches(field, values))
+        || excludes
+            .iter()
+            .any(|(field, values)| matches(field, values))
 }
 
 const CHECKPOINT_FILENAME: &str = "checkpoint.txt";
@@ -522,7 +517,8 @@ mod tests {
     use super::*;
     use crate::test_util::{block_on, runtime, shutdown_on_idle};
     use futures01::stream::Stream;
-    use std::io::{self, BufReader, Cursor};
+    use serde_json::Value as JsonValue;
+    use std::io::{self, BufRead, BufReader, Cursor};
     use std::iter::FromIterator;
     use std::time::Duration;
     use tempfile::tempdir;
@@ -540,25 +536,40 @@ mod tests {
     }
 
     impl Iterator for FakeJournal {
-        type Item = Result<String, io::Error>;
+        type Item = Result<Record, io::Error>;
         fn next(&mut self) -> Option<Self::Item> {
             let mut line = String::new();
             match self.reader.read_line(&mut line) {
                 Ok(0) => None,
-                Ok(_) => {
-                    line.pop();
-                    Some(Ok(line))
-                }
+                Ok(_) => Some(Ok(decode_record(&line))),
                 Err(err) => Some(Err(err)),
             }
         }
     }
 
+    // Decodes records in the `journalctl --output=json` format, in which
+    // non-UTF-8 values are written as arrays of bytes.
+    fn decode_record(text: &str) -> Record {
+        let record: HashMap<String, JsonValue> = serde_json::from_str(text).unwrap();
+        record
+            .into_iter()
+            .map(|(field, value)| {
+                let value = match value {
+                    JsonValue::String(string) => Bytes::from(string),
+                    JsonValue::Array(array) => array
+                        .into_iter()
+                        .map(|item| item.as_u64().unwrap() as u8)
+                        .collect::<Vec<u8>>()
+                        .into(),
+                    value => panic!("Unexpected value {:?}", value),
+                };
+                (Atom::from(field), value)
+            })
+            .collect()
+    }
+
     impl JournalSource for FakeJournal {
-        fn new(
-            _: &JournaldConfig,
-            checkpoint: Option<String>,
-        ) -> crate::Result<(Self, Box<dyn FnOnce() + Send>)> {
+        fn new(_: &JournaldConfig, checkpoint: Option<String>) -> crate::Result<Self> {
             let cursor = Cursor::new(FAKE_JOURNAL);
             let reader = BufReader::new(cursor);
             let mut journal = FakeJournal { reader };
@@ -571,7 +582,7 @@ mod tests {
                 }
             }
 
-            Ok((journal, Box::new(|| ())))
+            Ok(journal)
         }
     }
 
@@ -583,6 +594,8 @@ mod tests {
             Checkpointer::new(tempdir.path().to_path_buf()).expect("Creating checkpointer failed!");
         let include_units = HashSet::<String>::from_iter(iunits.into_iter().map(|&s| s.into()));
         let exclude_units = HashSet::<String>::from_iter(xunits.into_iter().map(|&s| s.into()));
+        let include_matches = create_matches(&HashMap::new(), include_units);
+        let exclude_matches = create_matches(&HashMap::new(), exclude_units);
 
         if let Some(cursor) = cursor {
             checkpointer.set(cursor).expect("Could not set checkpoint");
@@ -594,8 +607,8 @@ mod tests {
                 tx,
                 shutdown,
                 checkpointer,
-                include_units,
-                exclude_units,
+                include_matches,
+                exclude_matches,
                 DEFAULT_BATCH_SIZE,
             )
             .expect("Creating journald source failed");
@@ -676,25 +689,132 @@ mod tests {
     }
 
     #[test]
-    fn filter_unit_works_correctly() {
-        let empty: HashSet<String> = vec![].into_iter().collect();
-        let includes: HashSet<String> = vec!["one", "two"].into_iter().map(Into::into).collect();
-        let excludes: HashSet<String> = vec!["foo", "bar"].into_iter().map(Into::into).collect();
-
-        assert_eq!(filter_unit(None, &empty, &empty), false);
-        assert_eq!(filter_unit(None, &includes, &empty), true);
-        assert_eq!(filter_unit(None, &empty, &excludes), false);
-        assert_eq!(filter_unit(None, &includes, &excludes), true);
-        let one = String::from("one");
-        assert_eq!(filter_unit(Some(&one), &empty, &empty), false);
-        assert_eq!(filter_unit(Some(&one), &includes, &empty), false);
-        assert_eq!(filter_unit(Some(&one), &empty, &excludes), false);
-        assert_eq!(filter_unit(Some(&one), &includes, &excludes), false);
-        let bar = String::from("bar");
-        assert_eq!(filter_unit(Some(&bar), &empty, &empty), false);
-        assert_eq!(filter_unit(Some(&bar), &includes, &empty), true);
-        assert_eq!(filter_unit(Some(&bar), &empty, &excludes), true);
-        assert_eq!(filter_unit(Some(&bar), &includes, &excludes), true);
+    fn filter_matches_works_correctly() {
+        let units = |units: &[&str]| units.iter().map(|&unit| unit.into()).collect();
+        let empty = create_matches(&HashMap::new(), units(&[]));
+        let includes = create_matches(&HashMap::new(), units(&["one", "two"]));
+        let excludes = create_matches(&HashMap::new(), units(&["foo", "bar"]));
+        let record = |unit: Option<&str>| {
+            unit.into_iter()
+                .map(|unit| (SYSTEMD_UNIT.clone(), Bytes::from(unit)))
+                .collect::<Record>()
+        };
+
+        let none = record(None);
+        assert_eq!(filter_matches(&none, &empty, &empty), false);
+        assert_eq!(filter_matches(&none, &includes, &empty), true);
+        assert_eq!(filter_matches(&none, &empty, &excludes), false);
+        assert_eq!(filter_matches(&none, &includes, &excludes), true);
+        let one = record(Some("one"));
+        assert_eq!(filter_matches(&one, &empty, &empty), false);
+        assert_eq!(filter_matches(&one, &includes, &empty), false);
+        assert_eq!(filter_matches(&one, &empty, &excludes), false);
+        assert_eq!(filter_matches(&one, &includes, &excludes), false);
+        let bar = record(Some("bar"));
+        assert_eq!(filter_matches(&bar, &empty, &empty), false);
+        assert_eq!(filter_matches(&bar, &includes, &empty), true);
+        assert_eq!(filter_matches(&bar, &empty, &excludes), true);
+        assert_eq!(filter_matches(&bar, &includes, &excludes), true);
+    }
+
+    #[test]
+    fn filter_matches_ands_fields_and_ors_values() {
+        let mut config = HashMap::new();
+        config.insert(
+            "PRIORITY".to_string(),
+            vec!["3".to_string(), "4".to_string()].into_iter().collect(),
+        );
+        let includes = create_matches(&config, vec!["one".into()].into_iter().collect());
+        let empty = HashMap::new();
+        let record = |unit: &str, priority: &str| {
+            vec![
+                (SYSTEMD_UNIT.clone(), Bytes::from(unit)),
+                (Atom::from("PRIORITY"), Bytes::from(priority)),
+            ]
+            .into_iter()
+            .collect::<Record>()
+        };
+
+        assert_eq!(
+            filter_matches(&record("one.service", "3"), &includes, &empty),
+            false
+        );
+        assert_eq!(
+            filter_matches(&record("one.service", "4"), &includes, &empty),
+            false
+        );
+        assert_eq!(
+            filter_matches(&record("one.service", "6"), &includes, &empty),
+            true
+        );
+        assert_eq!(
+            filter_matches(&record("two.service", "3"), &includes, &empty),
+            true
+        );
+        assert_eq!(
+            filter_matches(&record("two.service", "3"), &empty, &includes),
+            true
+        );
+    }
+
+    #[test]
+    fn reads_journal_files() {
+        let (tx, rx) = futures01::sync::mpsc::channel(10);
+        let (trigger, shutdown, _) = ShutdownSignal::new_wired();
+        let tempdir = tempdir().unwrap();
+        let checkpointer =
+            Checkpointer::new(tempdir.path().to_path_buf()).expect("Creating checkpointer failed!");
+
+        let config: JournaldConfig = toml::from_str(
+            r#"
+            current_boot_only = false
+            journal_directories = ["tests/data/journald"]
+
+            [include_matches]
+            SYSLOG_IDENTIFIER = ["vector-test"]
+
+            [exclude_matches]
+            PRIORITY = ["4"]
+            "#,
+        )
+        .unwrap();
+        let include_matches = create_matches(&config.include_matches, HashSet::new());
+        let exclude_matches = create_matches(&config.exclude_matches, HashSet::new());
+        let source = config
+            .source::<Journal>(
+                tx,
+                shutdown,
+                checkpointer,
+                include_matches,
+                exclude_matches,
+                DEFAULT_BATCH_SIZE,
+            )
+            .expect("Creating journald source failed");
+        let mut rt = runtime();
+        rt.spawn(source);
+
+        std::thread::sleep(Duration::from_millis(100));
+        drop(trigger);
+        shutdown_on_idle(rt);
+
+        let received: Vec<Event> =
+            block_on(rx.collect().timeout(Duration::from_secs(1))).expect("Unclosed channel");
+        assert_eq!(received.len(), 2);
+        assert_eq!(message(&received[0]), Value::Bytes("first message".into()));
+        assert_eq!(
+            received[0].as_log()[&"CUSTOM_FIELD".into()],
+            Value::Bytes("alpha".into())
+        );
+        assert_eq!(timestamp(&received[0]), value_ts(1792400057, 789159000));
+        assert_eq!(
+            received[0].as_log()[&event::log_schema().host_key()],
+            Value::Bytes("vm".into())
+        );
+        assert_eq!(message(&received[1]), Value::Bytes("¿Hello?".into()));
+        assert_eq!(
+            received[1].as_log()[&"BINARY_FIELD".into()],
+            Value::Bytes(vec![0u8, 1, 0xfe, 0xff].into())
+        );
     }
 
     fn message(event: &Event) -> Value {
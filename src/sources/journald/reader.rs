@@ -0,0 +1,468 @@
+use super::journal_file::{Cursor, Entry, Id128, JournalFile};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet, VecDeque},
+    fs, io,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+// Bounds how much of a single file is read into memory at once, which
+// matters when starting from the beginning of a large journal.
+const MAX_ENTRIES_PER_FILE: usize = 1024;
+
+/// Which journal files a `JournalReader` follows.
+#[derive(Clone, Debug)]
+pub struct JournalLocations {
+    pub directories: Vec<PathBuf>,
+    pub namespace: Option<String>,
+    pub include_remote: bool,
+    pub machine_id: Option<String>,
+}
+
+impl JournalLocations {
+    /// List the journal files currently present in the configured
+    /// directories. This follows the layout used by `journald`:
+    /// `<dir>/<machine-id>/` for the default namespace,
+    /// `<dir>/<machine-id>.<namespace>/` for other namespaces and
+    /// `<dir>/remote/` for files received by `systemd-journal-remote`.
+    /// Journal files placed directly in a directory are always read.
+    pub fn journal_files(&self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for directory in &self.directories {
+            files.extend(list_journal_files(directory));
+
+            let entries = match fs::read_dir(directory) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if !path.is_dir() {
+                    continue;
+                }
+                let name = entry.file_name().to_string_lossy().into_owned();
+                if self.is_wanted_directory(&name) {
+                    files.extend(list_journal_files(&path));
+                }
+            }
+        }
+        files
+    }
+
+    fn is_wanted_directory(&self, name: &str) -> bool {
+        if name == "remote" {
+            return self.include_remote;
+        }
+
+        let mut parts = name.splitn(2, '.');
+        let machine_id = parts.next().unwrap_or_default();
+        let namespace = parts.next();
+
+        let machine_matches = match &self.machine_id {
+            Some(id) => id == machine_id,
+            None => machine_id.parse::<Id128>().is_ok(),
+        };
+        machine_matches && namespace == self.namespace.as_deref()
+    }
+}
+
+fn list_journal_files(directory: &Path) -> Vec<PathBuf> {
+    match fs::read_dir(directory) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|entry| entry.path())
+            // Files ending in `.journal~` were not closed cleanly and are
+            // skipped, as `journalctl` does.
+            .filter(|path| path.extension().map_or(false, |ext| ext == "journal"))
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+struct FollowedFile {
+    journal: JournalFile,
+    offset: u64,
+    // Entries read but not returned yet, in the order they were written.
+    buffer: VecDeque<Entry>,
+    // Set once everything written so far has been read, until the
+    // directories are scanned again.
+    drained: bool,
+    // Set once the file could not be read, after which it is left alone
+    // until it is removed, rather than failing every pass.
+    failed: bool,
+}
+
+impl FollowedFile {
+    fn new(journal: JournalFile) -> Self {
+        let offset = journal.first_object_offset();
+        FollowedFile {
+            journal,
+            offset,
+            buffer: VecDeque::new(),
+            drained: false,
+            failed: false,
+        }
+    }
+
+    fn read(&mut self) -> io::Result<Vec<Entry>> {
+        self.journal.refresh()?;
+        let (entries, offset) = self
+            .journal
+            .read_entries(self.offset, MAX_ENTRIES_PER_FILE)?;
+        self.offset = offset;
+        self.drained = entries.len() < MAX_ENTRIES_PER_FILE;
+        Ok(entries)
+    }
+}
+
+/// Reads entries from all journal files in a set of directories, following
+/// them as they are written, rotated and removed.
+///
+/// Files are identified by the file ID in their header rather than by path,
+/// so a file which is renamed on rotation is not read twice. Entries are
+/// merged from every file one at a time, in sequence number order, so the
+/// cursor of each returned entry also covers every entry returned before it
+/// and can be resumed from. Once every file has been read to its end the
+/// directories are scanned again. `None` means there is nothing new yet.
+pub struct JournalReader {
+    locations: JournalLocations,
+    files: HashMap<Id128, FollowedFile>,
+    // Remembers which file lives at each path, keyed by inode so that a new
+    // file created at the same path after rotation is noticed.
+    paths: HashMap<PathBuf, (u64, Id128)>,
+    cursor: Option<Cursor>,
+    boot_id: Option<Id128>,
+}
+
+impl JournalReader {
+    pub fn new(locations: JournalLocations, cursor: Option<&str>, boot_id: Option<Id128>) -> Self {
+        JournalReader {
+            locations,
+            files: HashMap::new(),
+            paths: HashMap::new(),
+            cursor: cursor.map(Cursor::parse),
+            boot_id,
+        }
+    }
+
+    fn scan(&mut self) {
+        let mut seen = HashSet::new();
+        let mut paths = HashMap::new();
+
+        for path in self.locations.journal_files() {
+            let inode = match fs::metadata(&path) {
+                Ok(metadata) => metadata.ino(),
+                Err(_) => continue,
+            };
+            let file_id = match self.paths.get(&path) {
+                Some((known_inode, file_id))
+                    if *known_inode == inode && self.files.contains_key(file_id) =>
+                {
+                    *file_id
+                }
+                _ => match JournalFile::open(&path) {
+                    Ok(journal) => {
+                        let file_id = journal.header().file_id;
+                        if !self.files.contains_key(&file_id) {
+                            debug!(message = "Found journal file.", ?path);
+                            self.files.insert(file_id, FollowedFile::new(journal));
+                        }
+                        file_id
+                    }
+                    Err(error) => {
+                        warn!(
+                            message = "Could not open journal file.",
+                            ?path,
+                            %error,
+                            rate_limit_secs = 30
+                        );
+                        continue;
+                    }
+                },
+            };
+            seen.insert(file_id);
+            paths.insert(path, (inode, file_id));
+        }
+
+        // Files which disappeared have been vacuumed; anything in them that
+        // was not read yet is gone.
+        self.files.retain(|file_id, _| seen.contains(file_id));
+        self.paths = paths;
+
+        for followed in self.files.values_mut() {
+            followed.drained = false;
+        }
+    }
+
+    /// Read more entries from every file whose buffer ran out, as its next
+    /// entry may come before the entries buffered from other files.
+    fn fill(&mut self) {
+        let cursor = &self.cursor;
+        let boot_id = &self.boot_id;
+        for followed in self.files.values_mut() {
+            while followed.buffer.is_empty() && !followed.drained && !followed.failed {
+                match followed.read() {
+                    Ok(entries) => followed.buffer.extend(entries.into_iter().filter(|entry| {
+                        !cursor
+                            .as_ref()
+                            .map_or(false, |cursor| cursor.is_after(entry))
+                            && boot_id.map_or(true, |boot_id| boot_id == entry.boot_id)
+                    })),
+                    Err(error) => {
+                        warn!(
+                            message = "Could not read journal file, skipping the rest of it.",
+                            path = ?followed.journal.path(),
+                            %error
+                        );
+                        followed.failed = true;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Take the earliest of the entries at the front of each file's buffer.
+    fn pop(&mut self) -> Option<Entry> {
+        let file_id = self
+            .files
+            .iter()
+            .filter_map(|(file_id, followed)| followed.buffer.front().map(|entry| (file_id, entry)))
+            .min_by(|(_, a), (_, b)| compare_entries(a, b))
+            .map(|(file_id, _)| *file_id)?;
+        self.files.get_mut(&file_id)?.buffer.pop_front()
+    }
+}
+
+/// Orders entries the way `journalctl` does: by sequence number within the
+/// same sequence number space, which is shared by the system and user
+/// journals of a machine, and otherwise by wallclock time.
+fn compare_entries(a: &Entry, b: &Entry) -> Ordering {
+    if a.seqnum_id == b.seqnum_id {
+        a.seqnum.cmp(&b.seqnum)
+    } else {
+        a.realtime.cmp(&b.realtime)
+    }
+}
+
+impl Iterator for JournalReader {
+    type Item = io::Result<Entry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.fill();
+        if let Some(entry) = self.pop() {
+            return Some(Ok(entry));
+        }
+
+        self.scan();
+        self.fill();
+        self.pop().map(Ok)
+    }
+}
+
+/// The boot ID of the running system, used to only read the current boot.
+pub fn current_boot_id() -> Option<Id128> {
+    fs::read_to_string("/proc/sys/kernel/random/boot_id")
+        .ok()
+        .and_then(|id| id.parse().ok())
+}
+
+/// The machine ID of the running system, used to find its journal directory.
+pub fn current_machine_id() -> Option<String> {
+    fs::read_to_string("/etc/machine-id")
+        .ok()
+        .map(|id| id.trim().to_owned())
+        .filter(|id| id.parse::<Id128>().is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const MACHINE_ID: &str = "3d1219c7c4c5404aaa1f6d2a48adfda4";
+
+    fn locations(directory: &Path) -> JournalLocations {
+        JournalLocations {
+            directories: vec![directory.into()],
+            namespace: None,
+            include_remote: false,
+            machine_id: Some(MACHINE_ID.into()),
+        }
+    }
+
+    fn copy_journal(directory: &Path, name: &str) {
+        fs::create_dir_all(directory).unwrap();
+        fs::copy("tests/data/journald/system.journal", directory.join(name)).unwrap();
+    }
+
+    /// Writes a minimal journal file holding one `MESSAGE` for each of the
+    /// given sequence numbers, all in the same sequence number space.
+    fn write_journal(path: &Path, file_id: u8, seqnums: &[u64]) {
+        const HEADER_SIZE: u64 = 208;
+
+        let mut arena = Vec::new();
+        let mut tail_object_offset = 0;
+        for &seqnum in seqnums {
+            let data_offset = HEADER_SIZE + arena.len() as u64;
+            let payload = format!("MESSAGE={}", seqnum);
+            arena.extend_from_slice(&[1, 0, 0, 0, 0, 0, 0, 0]);
+            arena.extend_from_slice(&(64 + payload.len() as u64).to_le_bytes());
+            arena.extend_from_slice(&[0; 48]);
+            arena.extend_from_slice(payload.as_bytes());
+            arena.resize((arena.len() + 7) & !7, 0);
+
+            tail_object_offset = HEADER_SIZE + arena.len() as u64;
+            arena.extend_from_slice(&[3, 0, 0, 0, 0, 0, 0, 0]);
+            arena.extend_from_slice(&80u64.to_le_bytes());
+            arena.extend_from_slice(&seqnum.to_le_bytes());
+            arena.extend_from_slice(&(1_600_000_000_000_000 + seqnum * 1000).to_le_bytes());
+            arena.extend_from_slice(&(seqnum * 1000).to_le_bytes());
+            arena.extend_from_slice(&[0; 24]);
+            arena.extend_from_slice(&data_offset.to_le_bytes());
+            arena.extend_from_slice(&[0; 8]);
+        }
+
+        let mut header = vec![0; HEADER_SIZE as usize];
+        header[0..8].copy_from_slice(b"LPKSHHRH");
+        header[24..40].copy_from_slice(&[file_id; 16]);
+        header[40..56].copy_from_slice(&MACHINE_ID.parse::<Id128>().unwrap().0);
+        header[72..88].copy_from_slice(&[0x5e; 16]);
+        header[88..96].copy_from_slice(&HEADER_SIZE.to_le_bytes());
+        header[96..104].copy_from_slice(&(arena.len() as u64).to_le_bytes());
+        header[136..144].copy_from_slice(&tail_object_offset.to_le_bytes());
+        header[152..160].copy_from_slice(&(seqnums.len() as u64).to_le_bytes());
+
+        header.extend(arena);
+        fs::write(path, header).unwrap();
+    }
+
+    #[test]
+    fn finds_journal_files_by_layout() {
+        let dir = tempdir().unwrap();
+        copy_journal(&dir.path().join(MACHINE_ID), "system.journal");
+        copy_journal(&dir.path().join(MACHINE_ID), "system.journal~");
+        copy_journal(
+            &dir.path().join(format!("{}.vector", MACHINE_ID)),
+            "system.journal",
+        );
+        copy_journal(&dir.path().join("remote"), "remote-host.journal");
+        copy_journal(
+            &dir.path().join("0123456789abcdef0123456789abcdef"),
+            "system.journal",
+        );
+
+        let mut locations = locations(dir.path());
+        assert_eq!(
+            locations.journal_files(),
+            vec![dir.path().join(MACHINE_ID).join("system.journal")]
+        );
+
+        locations.namespace = Some("vector".into());
+        assert_eq!(
+            locations.journal_files(),
+            vec![dir
+                .path()
+                .join(format!("{}.vector", MACHINE_ID))
+                .join("system.journal")]
+        );
+
+        locations.namespace = None;
+        locations.include_remote = true;
+        let mut files = locations.journal_files();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                dir.path().join(MACHINE_ID).join("system.journal"),
+                dir.path().join("remote").join("remote-host.journal"),
+            ]
+        );
+    }
+
+    #[test]
+    fn does_not_reread_rotated_files() {
+        let dir = tempdir().unwrap();
+        copy_journal(dir.path(), "system.journal");
+
+        let mut reader = JournalReader::new(locations(dir.path()), None, None);
+        assert_eq!(reader.by_ref().count(), 8);
+
+        fs::rename(
+            dir.path().join("system.journal"),
+            dir.path().join("system@0001.journal"),
+        )
+        .unwrap();
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn skips_entries_before_cursor_and_other_boots() {
+        let dir = tempdir().unwrap();
+        copy_journal(dir.path(), "system.journal");
+
+        let cursor = "s=c36668c6db624ec0bcccd9971df7e4c0;i=5;b=0688030eac8043eebd1849c4e8bb5328;m=2535ecf0;t=65e2da8bef5fd;x=e722624ebd39c0fa";
+        let reader = JournalReader::new(locations(dir.path()), Some(cursor), None);
+        let seqnums = reader
+            .map(|entry| entry.unwrap().seqnum)
+            .collect::<Vec<_>>();
+        assert_eq!(seqnums, vec![6, 7, 8]);
+
+        let reader = JournalReader::new(locations(dir.path()), None, Some(Id128::default()));
+        assert_eq!(reader.count(), 0);
+    }
+
+    #[test]
+    fn merges_files_in_seqnum_order_across_restarts() {
+        let dir = tempdir().unwrap();
+        // The user journal holds a few entries interleaved with a system
+        // journal backlog larger than a single read, and the latest ones.
+        let (user, system): (Vec<u64>, Vec<u64>) =
+            (1..=1500).partition(|seqnum| seqnum % 100 == 0 || *seqnum > 1490);
+        write_journal(&dir.path().join("system.journal"), 1, &system);
+        write_journal(&dir.path().join("user-1000.journal"), 2, &user);
+
+        let mut reader = JournalReader::new(locations(dir.path()), None, None);
+        let read = reader
+            .by_ref()
+            .take(1100)
+            .map(|entry| entry.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            read.iter().map(|entry| entry.seqnum).collect::<Vec<_>>(),
+            (1..=1100).collect::<Vec<_>>()
+        );
+        assert_eq!(read[99].get("MESSAGE"), Some(&b"100"[..]));
+
+        // Restarting from the checkpoint of the last entry read continues
+        // with the rest of the backlog.
+        let cursor = read.last().unwrap().cursor();
+        let reader = JournalReader::new(locations(dir.path()), Some(&cursor), None);
+        assert_eq!(
+            reader
+                .map(|entry| entry.unwrap().seqnum)
+                .collect::<Vec<_>>(),
+            (1101..=1500).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn skips_unreadable_files() {
+        let dir = tempdir().unwrap();
+        copy_journal(dir.path(), "system.journal");
+        let mut reader = JournalReader::new(locations(dir.path()), None, None);
+        assert_eq!(reader.by_ref().count(), 8);
+
+        // A truncated file can no longer be read, which is reported once
+        // instead of as an error on every pass.
+        fs::OpenOptions::new()
+            .write(true)
+            .open(dir.path().join("system.journal"))
+            .unwrap()
+            .set_len(100)
+            .unwrap();
+        assert!(reader.next().is_none());
+        assert!(reader.files.values().all(|followed| followed.failed));
+        assert!(reader.next().is_none());
+    }
+}
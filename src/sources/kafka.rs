@@ -1,25 +1,36 @@
 use crate::{
-    event::{self, Event},
+    event::{self, Event, Value},
     kafka::{KafkaCompression, KafkaTlsConfig},
     shutdown::ShutdownSignal,
     stream::StreamExt,
     topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
 };
 use bytes::Bytes;
+use chrono::{TimeZone, Utc};
 use futures::compat::Compat;
 use futures01::{future, sync::mpsc, Future, Poll, Sink, Stream};
 use owning_ref::OwningHandle;
 use rdkafka::{
+    client::ClientContext,
     config::ClientConfig,
-    consumer::{Consumer, DefaultConsumerContext, MessageStream, StreamConsumer},
-    error::KafkaError,
-    message::{BorrowedMessage, Message},
+    consumer::{CommitMode, Consumer, ConsumerContext, MessageStream, Rebalance, StreamConsumer},
+    error::{KafkaError, RDKafkaError},
+    message::{BorrowedMessage, Headers, Message},
 };
 use serde::{Deserialize, Serialize};
 use snafu::{ResultExt, Snafu};
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Condvar, Mutex, Weak},
+    time::Duration,
+};
 use tokio::task::block_in_place;
 
+/// How long a partition revocation waits for the event being sent to be
+/// accepted, so that its offset is committed before the partition is handed
+/// over to another consumer.
+const REVOKE_WAIT_TIMEOUT: Duration = Duration::from_secs(5);
+
 #[derive(Debug, Snafu)]
 enum BuildError {
     #[snafu(display("Could not create Kafka consumer: {}", source))]
@@ -47,6 +58,11 @@ pub struct KafkaSourceConfig {
     commit_interval_ms: u64,
     host_key: Option<String>,
     key_field: Option<String>,
+    topic_key: Option<String>,
+    partition_key: Option<String>,
+    offset_key: Option<String>,
+    timestamp_key: Option<String>,
+    headers_key: Option<String>,
     librdkafka_options: Option<HashMap<String, String>>,
     tls: Option<KafkaTlsConfig>,
 }
@@ -101,7 +117,12 @@ fn kafka_source(
     shutdown: ShutdownSignal,
     out: mpsc::Sender<Event>,
 ) -> crate::Result<super::Source> {
-    let consumer = Arc::new(create_consumer(config.clone())?);
+    let context = KafkaSourceContext::default();
+    let in_flight = Arc::clone(&context.in_flight);
+    let consumer_handle = Arc::clone(&context.consumer);
+    let consumer = Arc::new(create_consumer(config.clone(), context)?);
+    *consumer_handle.lock().unwrap() = Arc::downgrade(&consumer);
+
     let source = future::lazy(move || {
         let consumer_ref = Arc::clone(&consumer);
 
@@ -115,51 +136,105 @@ fn kafka_source(
 
         stream
             .take_until(shutdown.map(move |_| block_in_place(|| consumer.stop())))
-            .then(move |message| {
+            .then(move |message| -> Result<_, ()> {
                 match message {
-                    Err(e) => Err(error!(message = "Error reading message from Kafka", error = ?e)),
-                    Ok(msg) => {
-                        let payload = match msg.payload_view::<[u8]>() {
-                            None => return Err(()), // skip messages with empty payload
-                            Some(Err(e)) => {
-                                return Err(error!(message = "Cannot extract payload", error = ?e))
-                            }
-                            Some(Ok(payload)) => Bytes::from(payload),
-                        };
-                        let mut event = Event::from(payload);
-
-                        // Add source type
-                        event
-                            .as_mut_log()
-                            .insert(event::log_schema().source_type_key(), "kafka");
-
-                        if let Some(key_field) = &config.key_field {
-                            match msg.key_view::<[u8]>() {
-                                None => (),
-                                Some(Err(e)) => {
-                                    return Err(error!(message = "Cannot extract key", error = ?e))
-                                }
-                                Some(Ok(key)) => {
-                                    event.as_mut_log().insert(key_field.clone(), key);
-                                }
-                            }
-                        }
-
-                        consumer_ref.store_offset(&msg).map_err(
-                            |e| error!(message = "Cannot store offset for the message", error = ?e),
-                        )?;
-                        Ok(event)
+                    Err(e) => {
+                        error!(message = "Error reading message from Kafka", error = ?e);
+                        Ok(None)
                     }
+                    Ok(msg) => Ok(create_event(&msg, &config).map(|event| (event, msg))),
                 }
             })
-            .forward(out.sink_map_err(|e| error!(message = "Error sending to sink", error = ?e)))
+            .filter_map(|message| message)
+            // Offsets are stored only once the event has been sent, so a
+            // message is never skipped if Vector stops before sending it.
+            .fold(out, move |out, (event, msg)| {
+                let consumer = Arc::clone(&consumer_ref);
+                let in_flight = Arc::clone(&in_flight);
+                in_flight.start();
+                out.send(event).then(move |result| {
+                    if result.is_ok() {
+                        if let Err(e) = consumer.store_offset(&msg) {
+                            error!(message = "Cannot store offset for the message", error = ?e);
+                        }
+                    }
+                    in_flight.finish();
+                    result.map_err(|e| error!(message = "Error sending to sink", error = ?e))
+                })
+            })
             .map(|_| ())
     });
 
     Ok(Box::new(source))
 }
 
-fn create_consumer(config: KafkaSourceConfig) -> crate::Result<StreamConsumer> {
+fn create_event(msg: &BorrowedMessage, config: &KafkaSourceConfig) -> Option<Event> {
+    let payload = match msg.payload_view::<[u8]>() {
+        None => return None, // skip messages with empty payload
+        Some(Err(e)) => {
+            error!(message = "Cannot extract payload", error = ?e);
+            return None;
+        }
+        Some(Ok(payload)) => Bytes::from(payload),
+    };
+    let mut event = Event::from(payload);
+    let log = event.as_mut_log();
+
+    // Add source type
+    log.insert(event::log_schema().source_type_key(), "kafka");
+
+    if let Some(key_field) = &config.key_field {
+        match msg.key_view::<[u8]>() {
+            None => (),
+            Some(Err(e)) => {
+                error!(message = "Cannot extract key", error = ?e);
+                return None;
+            }
+            Some(Ok(key)) => {
+                log.insert(key_field.clone(), key);
+            }
+        }
+    }
+
+    if let Some(topic_key) = &config.topic_key {
+        log.insert(topic_key.clone(), msg.topic());
+    }
+
+    if let Some(partition_key) = &config.partition_key {
+        log.insert(partition_key.clone(), msg.partition());
+    }
+
+    if let Some(offset_key) = &config.offset_key {
+        log.insert(offset_key.clone(), msg.offset());
+    }
+
+    if let Some(timestamp_key) = &config.timestamp_key {
+        if let Some(millis) = msg.timestamp().to_millis() {
+            log.insert(timestamp_key.clone(), Utc.timestamp_millis(millis));
+        }
+    }
+
+    if let Some(headers_key) = &config.headers_key {
+        // Inserted as a map, so that header names containing dots aren't
+        // treated as nested fields.
+        let mut headers_map = BTreeMap::<String, Value>::new();
+        if let Some(headers) = msg.headers() {
+            for i in 0..headers.count() {
+                if let Some((name, value)) = headers.get(i) {
+                    headers_map.insert(name.to_owned(), Bytes::from(value).into());
+                }
+            }
+        }
+        log.insert(headers_key.clone(), headers_map);
+    }
+
+    Some(event)
+}
+
+fn create_consumer(
+    config: KafkaSourceConfig,
+    context: KafkaSourceContext,
+) -> crate::Result<StreamConsumer<KafkaSourceContext>> {
     let mut client_config = ClientConfig::new();
     client_config
         .set("group.id", &config.group_id)
@@ -187,17 +262,92 @@ fn create_consumer(config: KafkaSourceConfig) -> crate::Result<StreamConsumer> {
         }
     }
 
-    let consumer: StreamConsumer = client_config.create().context(KafkaCreateError)?;
+    let consumer: StreamConsumer<KafkaSourceContext> = client_config
+        .create_with_context(context)
+        .context(KafkaCreateError)?;
     let topics: Vec<&str> = config.topics.iter().map(|s| s.as_str()).collect();
     consumer.subscribe(&topics).context(KafkaSubscribeError)?;
 
     Ok(consumer)
 }
 
+/// Commits the stored offsets before partitions are revoked during a
+/// rebalance, so the consumer they're assigned to next continues right
+/// after the last event sent by this one.
+#[derive(Default)]
+struct KafkaSourceContext {
+    /// Set once the consumer is created, which owns this context.
+    consumer: Arc<Mutex<Weak<StreamConsumer<KafkaSourceContext>>>>,
+    in_flight: Arc<InFlight>,
+}
+
+impl ClientContext for KafkaSourceContext {}
+
+impl ConsumerContext for KafkaSourceContext {
+    fn pre_rebalance(&self, rebalance: &Rebalance) {
+        if let Rebalance::Revoke { .. } = rebalance {
+            if !self.in_flight.wait(REVOKE_WAIT_TIMEOUT) {
+                warn!(message = "Revoking partitions while an event is still being sent, it may be consumed again.");
+            }
+
+            // The consumer is gone while it's being dropped.
+            let consumer = self.consumer.lock().unwrap().upgrade();
+            if let Some(consumer) = consumer {
+                match consumer.commit_consumer_state(CommitMode::Sync) {
+                    Ok(()) => debug!(message = "Committed offsets before revoking partitions."),
+                    // Nothing has been stored since the last commit.
+                    Err(KafkaError::ConsumerCommit(RDKafkaError::NoOffset)) => (),
+                    Err(e) => {
+                        error!(message = "Cannot commit offsets before revoking partitions", error = ?e)
+                    }
+                }
+            }
+        }
+    }
+
+    fn post_rebalance(&self, rebalance: &Rebalance) {
+        match rebalance {
+            Rebalance::Assign(partitions) => {
+                info!(message = "Assigned partitions.", count = partitions.count())
+            }
+            Rebalance::Error(e) => error!(message = "Kafka rebalance failed", error = %e),
+            _ => (),
+        }
+    }
+}
+
+/// Tracks whether an event is being sent into the pipeline.
+#[derive(Default)]
+struct InFlight {
+    sending: Mutex<bool>,
+    done: Condvar,
+}
+
+impl InFlight {
+    fn start(&self) {
+        *self.sending.lock().unwrap() = true;
+    }
+
+    fn finish(&self) {
+        *self.sending.lock().unwrap() = false;
+        self.done.notify_all();
+    }
+
+    /// Returns `false` if an event is still being sent after `timeout`.
+    fn wait(&self, timeout: Duration) -> bool {
+        let sending = self.sending.lock().unwrap();
+        let (sending, _) = self
+            .done
+            .wait_timeout_while(sending, timeout, |sending| *sending)
+            .unwrap();
+        !*sending
+    }
+}
+
 struct OwnedConsumerStream {
     upstream: OwningHandle<
-        Arc<StreamConsumer>,
-        Box<Compat<MessageStream<'static, DefaultConsumerContext>>>,
+        Arc<StreamConsumer<KafkaSourceContext>>,
+        Box<Compat<MessageStream<'static, KafkaSourceContext>>>,
     >,
 }
 
@@ -212,9 +362,10 @@ impl Stream for OwnedConsumerStream {
 
 #[cfg(test)]
 mod test {
-    use super::{kafka_source, KafkaSourceConfig};
+    use super::{kafka_source, InFlight, KafkaSourceConfig};
     use crate::shutdown::ShutdownSignal;
     use futures01::sync::mpsc;
+    use std::{sync::Arc, thread, time::Duration};
 
     fn make_config() -> KafkaSourceConfig {
         KafkaSourceConfig {
@@ -245,6 +396,23 @@ mod test {
         };
         assert!(kafka_source(config, ShutdownSignal::noop(), mpsc::channel(1).0).is_err());
     }
+
+    #[test]
+    fn in_flight_waits_for_send() {
+        let in_flight = Arc::new(InFlight::default());
+        assert!(in_flight.wait(Duration::from_millis(10)));
+
+        in_flight.start();
+        assert!(!in_flight.wait(Duration::from_millis(10)));
+
+        let sender = Arc::clone(&in_flight);
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            sender.finish();
+        });
+        assert!(in_flight.wait(Duration::from_secs(5)));
+        handle.join().unwrap();
+    }
 }
 
 #[cfg(feature = "kafka-integration-tests")]
@@ -252,7 +420,7 @@ mod test {
 mod integration_test {
     use super::{kafka_source, KafkaSourceConfig};
     use crate::{
-        event,
+        event::{self, Value},
         shutdown::ShutdownSignal,
         test_util::{collect_n, random_string, runtime},
     };
@@ -260,6 +428,7 @@ mod integration_test {
     use futures01::{sync::mpsc, Future};
     use rdkafka::{
         config::ClientConfig,
+        message::OwnedHeaders,
         producer::{FutureProducer, FutureRecord},
     };
     use string_cache::DefaultAtom as Atom;
@@ -267,6 +436,15 @@ mod integration_test {
     const BOOTSTRAP_SERVER: &str = "localhost:9092";
 
     fn send_event(topic: &str, key: &str, text: &str) -> impl Future<Item = (), Error = ()> {
+        send_event_with_headers(topic, key, text, OwnedHeaders::new())
+    }
+
+    fn send_event_with_headers(
+        topic: &str,
+        key: &str,
+        text: &str,
+        headers: OwnedHeaders,
+    ) -> impl Future<Item = (), Error = ()> {
         let producer: FutureProducer = ClientConfig::new()
             .set("bootstrap.servers", BOOTSTRAP_SERVER)
             .set("produce.offset.report", "true")
@@ -274,7 +452,10 @@ mod integration_test {
             .create()
             .expect("Producer creation error");
 
-        let record = FutureRecord::to(topic).payload(text).key(key);
+        let record = FutureRecord::to(topic)
+            .payload(text)
+            .key(key)
+            .headers(headers);
 
         Compat::new(producer.send(record, 0))
             .map(|_| ())
@@ -322,4 +503,53 @@ mod integration_test {
             "kafka".into()
         );
     }
+
+    #[test]
+    #[ignore]
+    fn kafka_source_adds_message_metadata() {
+        let topic = format!("test-topic-{}", random_string(10));
+        let group_id = format!("test-group-{}", random_string(10));
+
+        let config = KafkaSourceConfig {
+            bootstrap_servers: BOOTSTRAP_SERVER.into(),
+            topics: vec![topic.clone()],
+            group_id,
+            auto_offset_reset: "beginning".into(),
+            session_timeout_ms: 6000,
+            commit_interval_ms: 5000,
+            socket_timeout_ms: 60000,
+            fetch_wait_max_ms: 100,
+            topic_key: Some("topic".into()),
+            partition_key: Some("partition".into()),
+            offset_key: Some("offset".into()),
+            timestamp_key: Some("message_timestamp".into()),
+            headers_key: Some("headers".into()),
+            ..Default::default()
+        };
+
+        let mut rt = runtime();
+        let headers = OwnedHeaders::new().add("header.key", "header value");
+        rt.block_on(send_event_with_headers(
+            &topic,
+            "my key",
+            "my message",
+            headers,
+        ))
+        .unwrap();
+        let (tx, rx) = mpsc::channel(1);
+        rt.spawn(kafka_source(config, ShutdownSignal::noop(), tx).unwrap());
+        let events = rt.block_on(collect_n(rx, 1)).ok().unwrap();
+
+        let log = events[0].as_log();
+        assert_eq!(log[&Atom::from("topic")], topic.into());
+        assert_eq!(log[&Atom::from("partition")], 0.into());
+        assert_eq!(log[&Atom::from("offset")], 0.into());
+        assert!(log.get(&Atom::from("message_timestamp")).is_some());
+        match &log[&Atom::from("headers")] {
+            Value::Map(headers) => {
+                assert_eq!(headers["header.key"], "header value".into());
+            }
+            value => panic!("Unexpected headers value: {:?}", value),
+        }
+    }
 }
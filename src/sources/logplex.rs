@@ -3,14 +3,14 @@ use crate::{
     shutdown::ShutdownSignal,
     sources::util::{ErrorMessage, HttpSource},
     tls::TlsConfig,
-    topology::config::{DataType, GlobalOptions, SourceConfig},
+    topology::config::{DataType, GlobalOptions, SourceConfig, TestDecoder},
 };
 use bytes::Buf;
 use chrono::{DateTime, Utc};
 use futures01::sync::mpsc;
 use serde::{Deserialize, Serialize};
 use std::{
-    io::{BufRead, BufReader},
+    io::{BufRead, BufReader, Read},
     net::SocketAddr,
     str::FromStr,
 };
@@ -56,6 +56,10 @@ impl SourceConfig for LogplexConfig {
     fn source_type(&self) -> &'static str {
         "logplex"
     }
+
+    fn build_test_decoder(&self) -> Option<TestDecoder> {
+        Some(Box::new(|input: &[u8]| Ok(body_to_events(input))))
+    }
 }
 
 fn decode_message(body: FullBody, header_map: HeaderMap) -> Result<Vec<Event>, ErrorMessage> {
@@ -69,7 +73,7 @@ fn decode_message(body: FullBody, header_map: HeaderMap) -> Result<Vec<Event>, E
     info!(message = "Handling logplex request", %msg_count, %frame_id, %drain_token);
 
     // Deal with body
-    let events = body_to_events(body);
+    let events = body_to_events(body.reader());
 
     if events.len() != msg_count {
         let error_msg = format!(
@@ -106,8 +110,8 @@ fn header_error_message(name: &str, msg: &str) -> ErrorMessage {
     )
 }
 
-fn body_to_events(body: FullBody) -> Vec<Event> {
-    let rdr = BufReader::new(body.reader());
+fn body_to_events(body: impl Read) -> Vec<Event> {
+    let rdr = BufReader::new(body);
     rdr.lines()
         .filter_map(|res| {
             res.map_err(|error| error!(message = "Error reading request body", ?error))
@@ -2,7 +2,7 @@ use crate::{
     event::{self, Event, LogEvent, Value},
     shutdown::ShutdownSignal,
     tls::{MaybeTlsSettings, TlsConfig},
-    topology::config::{DataType, GlobalOptions, SourceConfig},
+    topology::config::{DataType, GlobalOptions, SourceConfig, TestDecoder},
 };
 use bytes::{Buf, Bytes};
 use chrono::{DateTime, TimeZone, Utc};
@@ -109,6 +109,16 @@ impl SourceConfig for SplunkConfig {
     fn source_type(&self) -> &'static str {
         "splunk_hec"
     }
+
+    /// Decodes the body of a request to the event endpoint.
+    fn build_test_decoder(&self) -> Option<TestDecoder> {
+        Some(Box::new(|input: &[u8]| -> crate::Result<Vec<Event>> {
+            EventStream::new(input, None, None)
+                .collect()
+                .wait()
+                .map_err(|rejection| format!("Invalid request body: {:?}", rejection).into())
+        }))
+    }
 }
 
 /// Shared data for responding to requests.
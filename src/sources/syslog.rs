@@ -7,7 +7,7 @@ use crate::{
     shutdown::ShutdownSignal,
     stream::StreamExt,
     tls::{MaybeTlsSettings, TlsConfig},
-    topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription},
+    topology::config::{DataType, GlobalOptions, SourceConfig, SourceDescription, TestDecoder},
 };
 use bytes::Bytes;
 use chrono::{Datelike, Utc};
@@ -114,6 +114,21 @@ impl SourceConfig for SyslogConfig {
     fn source_type(&self) -> &'static str {
         "syslog"
     }
+
+    fn build_test_decoder(&self) -> Option<TestDecoder> {
+        let host_key = self
+            .host_key
+            .clone()
+            .unwrap_or(event::log_schema().host_key().to_string());
+
+        Some(Box::new(move |input: &[u8]| {
+            Ok(String::from_utf8_lossy(input)
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .filter_map(|line| event_from_str(&host_key, None, line))
+                .collect::<Vec<_>>())
+        }))
+    }
 }
 
 #[derive(Debug, Clone)]
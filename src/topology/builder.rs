@@ -1,10 +1,13 @@
 use super::{
     config::{DataType, SinkContext, TransformContext},
-    fanout::{self, Fanout},
+    fanout::{self, Fanout, RoutedFanout},
     task::Task,
     ConfigDiff,
 };
-use crate::{buffers, dns::Resolver, event::Event, runtime, shutdown::SourceShutdownCoordinator};
+use crate::{
+    buffers, dns::Resolver, enrichment_tables::EnrichmentTables, event::Event, runtime,
+    shutdown::SourceShutdownCoordinator,
+};
 use futures01::{
     future::{lazy, Either},
     sync::mpsc,
@@ -15,7 +18,7 @@ use tokio01::util::FutureExt;
 
 pub struct Pieces {
     pub inputs: HashMap<String, (buffers::BufferInputCloner, Vec<String>)>,
-    pub outputs: HashMap<String, fanout::ControlChannel>,
+    pub outputs: HashMap<String, HashMap<String, fanout::ControlChannel>>,
     pub tasks: HashMap<String, Task>,
     pub source_tasks: HashMap<String, Task>,
     pub healthchecks: HashMap<String, Task>,
@@ -45,7 +48,7 @@ pub fn check(config: &super::Config) -> Result<Vec<String>, Vec<String>> {
         }
 
         for input in inputs {
-            if !config.sources.contains_key(&input) && !config.transforms.contains_key(&input) {
+            if config.input_component(&input).is_none() {
                 errors.push(format!(
                     "Input {:?} for {} {:?} doesn't exist.",
                     input, output_type, name
@@ -60,14 +63,15 @@ pub fn check(config: &super::Config) -> Result<Vec<String>, Vec<String>> {
         .keys()
         .map(|name| ("transform", name.clone()));
     for (input_type, name) in transform_names.chain(source_names) {
+        let outputs = config.component_outputs(&name);
         if !config
             .transforms
             .iter()
-            .any(|(_, transform)| transform.inputs.contains(&name))
+            .any(|(_, transform)| transform.inputs.iter().any(|i| outputs.contains(i)))
             && !config
                 .sinks
                 .iter()
-                .any(|(_, sink)| sink.inputs.contains(&name))
+                .any(|(_, sink)| sink.inputs.iter().any(|i| outputs.contains(i)))
         {
             warnings.push(format!(
                 "{} {:?} has no consumers",
@@ -114,6 +118,14 @@ pub fn build_pieces(
     // TODO: remove the unimplemented
     let resolver = Resolver::new(config.global.dns_servers.clone(), exec.clone()).unwrap();
 
+    let enrichment_tables = match EnrichmentTables::load(&config.enrichment_tables) {
+        Ok(tables) => tables,
+        Err(mut table_errors) => {
+            errors.append(&mut table_errors);
+            EnrichmentTables::default()
+        }
+    };
+
     // Build sources
     for (name, source) in config
         .sources
@@ -149,7 +161,7 @@ pub fn build_pieces(
             .map_err(|_| ());
         let server = Task::new(&name, &typetag, server);
 
-        outputs.insert(name.clone(), control);
+        outputs.insert(name.clone(), single_output(name, control));
         tasks.insert(name.clone(), pump);
         source_tasks.insert(name.clone(), server);
     }
@@ -161,12 +173,16 @@ pub fn build_pieces(
         .filter(|(name, _)| diff.transforms.contains_new(&name))
     {
         let trans_inputs = &transform.inputs;
+        let output_names = transform.output_names(name);
+        let named_outputs = !transform.inner.named_outputs().is_empty();
 
         let typetag = &transform.inner.transform_type();
 
         let cx = TransformContext {
             resolver: resolver.clone(),
             exec: exec.clone(),
+            enrichment_tables: enrichment_tables.clone(),
+            name: Some(name.clone()),
         };
 
         let input_type = transform.inner.input_type();
@@ -181,16 +197,38 @@ pub fn build_pieces(
         let (input_tx, input_rx) = futures01::sync::mpsc::channel(100);
         let input_tx = buffers::BufferInputCloner::Memory(input_tx, buffers::WhenFull::Block);
 
-        let (output, control) = Fanout::new();
+        let input_rx = filter_event_type(input_rx, input_type);
+        let (task, controls) = if named_outputs {
+            let (fanouts, controls): (Vec<_>, Vec<_>) =
+                output_names.iter().map(|_| Fanout::new()).unzip();
+
+            let mut transform = transform;
+            let transform = input_rx
+                .map(move |event| {
+                    let mut output = Vec::with_capacity(1);
+                    transform.transform_routed(&mut output, event);
+                    futures01::stream::iter_ok(output.into_iter())
+                })
+                .flatten()
+                .forward(RoutedFanout::new(fanouts))
+                .map(|_| ());
+            let task = Task::new(&name, &typetag, transform);
+
+            (task, output_names.into_iter().zip(controls).collect())
+        } else {
+            let (output, control) = Fanout::new();
 
-        let transform = transform
-            .transform_stream(filter_event_type(input_rx, input_type))
-            .forward(output)
-            .map(|_| ());
-        let task = Task::new(&name, &typetag, transform);
+            let transform = transform
+                .transform_stream(input_rx)
+                .forward(output)
+                .map(|_| ());
+            let task = Task::new(&name, &typetag, transform);
+
+            (task, single_output(name, control))
+        };
 
         inputs.insert(name.clone(), (input_tx, trans_inputs.clone()));
-        outputs.insert(name.clone(), control);
+        outputs.insert(name.clone(), controls);
         tasks.insert(name.clone(), task);
     }
 
@@ -278,6 +316,15 @@ pub fn build_pieces(
     }
 }
 
+fn single_output(
+    name: &str,
+    control: fanout::ControlChannel,
+) -> HashMap<String, fanout::ControlChannel> {
+    let mut outputs = HashMap::new();
+    outputs.insert(name.to_owned(), control);
+    outputs
+}
+
 fn capitalize(s: &str) -> String {
     let mut s = s.to_owned();
     if let Some(r) = s.get_mut(0..1) {
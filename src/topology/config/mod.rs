@@ -2,6 +2,7 @@ use crate::{
     buffers::Acker,
     conditions,
     dns::Resolver,
+    enrichment_tables::{EnrichmentTable, EnrichmentTableConfig, EnrichmentTables},
     event::{self, Event, Metric},
     runtime::TaskExecutor,
     shutdown::ShutdownSignal,
@@ -32,6 +33,8 @@ pub struct Config {
     #[serde(default)]
     pub transforms: IndexMap<String, TransformOuter>,
     #[serde(default)]
+    pub enrichment_tables: IndexMap<String, EnrichmentTableConfig>,
+    #[serde(default)]
     pub tests: Vec<TestDefinition>,
 }
 
@@ -138,8 +141,18 @@ pub trait SourceConfig: core::fmt::Debug {
     fn output_type(&self) -> DataType;
 
     fn source_type(&self) -> &'static str;
+
+    /// Builds a decoder producing the events the source would emit for raw
+    /// input, allowing unit tests to feed bytes through the source.
+    fn build_test_decoder(&self) -> Option<TestDecoder> {
+        None
+    }
 }
 
+/// Decodes raw input into events the way a source would, see
+/// `SourceConfig::build_test_decoder`.
+pub type TestDecoder = Box<dyn Fn(&[u8]) -> crate::Result<Vec<Event>> + Send + Sync>;
+
 pub type SourceDescription = ComponentDescription<Box<dyn SourceConfig>>;
 
 inventory::collect!(SourceDescription);
@@ -162,6 +175,35 @@ pub trait SinkConfig: core::fmt::Debug {
     fn input_type(&self) -> DataType;
 
     fn sink_type(&self) -> &'static str;
+
+    /// Builds an encoder producing what the sink would write or send for an
+    /// event, allowing unit tests to capture the sink output without sending
+    /// it.
+    fn build_test_encoder(&self) -> Option<TestEncoder> {
+        None
+    }
+}
+
+/// Encodes an event the way a sink would, see `SinkConfig::build_test_encoder`.
+/// Events the sink would drop are encoded as `None`.
+pub type TestEncoder = Box<dyn Fn(Event) -> Option<EncodedEvent> + Send + Sync>;
+
+/// The output of a sink for a single event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodedEvent {
+    /// Where the event is written to, such as the rendered path of a file.
+    pub key: Option<String>,
+    /// The bytes written or sent for the event, including any framing.
+    pub body: Vec<u8>,
+}
+
+impl EncodedEvent {
+    pub fn new(body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            key: None,
+            body: body.into(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -225,12 +267,44 @@ pub trait TransformConfig: core::fmt::Debug {
     fn expand(&mut self) -> crate::Result<Option<IndexMap<String, Box<dyn TransformConfig>>>> {
         Ok(None)
     }
+
+    /// Allows a transform to route events to several named outputs instead
+    /// of a single one. Other components consume each output as
+    /// `<transform name>.<output>`, and events are produced with
+    /// `Transform::transform_routed` using the index of the output.
+    fn named_outputs(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// The names of the enrichment tables the transform looks rows up in,
+    /// so that it is rebuilt when one of them changes on reload.
+    fn enrichment_tables(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+impl TransformOuter {
+    /// The names other components use to consume the output of this
+    /// transform.
+    pub fn output_names(&self, name: &str) -> Vec<String> {
+        let outputs = self.inner.named_outputs();
+        if outputs.is_empty() {
+            vec![name.to_owned()]
+        } else {
+            outputs
+                .iter()
+                .map(|output| format!("{}.{}", name, output))
+                .collect()
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct TransformContext {
     pub(super) exec: TaskExecutor,
     pub(super) resolver: Resolver,
+    pub(super) enrichment_tables: EnrichmentTables,
+    pub(super) name: Option<String>,
 }
 
 impl TransformContext {
@@ -238,6 +312,8 @@ impl TransformContext {
         Self {
             resolver: Resolver::new(Vec::new(), exec.clone()).unwrap(),
             exec,
+            enrichment_tables: EnrichmentTables::default(),
+            name: None,
         }
     }
 
@@ -248,6 +324,16 @@ impl TransformContext {
     pub fn resolver(&self) -> Resolver {
         self.resolver.clone()
     }
+
+    pub fn enrichment_table(&self, name: &str) -> Option<EnrichmentTable> {
+        self.enrichment_tables.get(name)
+    }
+
+    /// The name of the transform within a running topology, which stays the
+    /// same across config reloads. Not set for transforms built by tests.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
 }
 
 pub type TransformDescription = ComponentDescription<Box<dyn TransformConfig>>;
@@ -274,6 +360,9 @@ pub enum TestInputValue {
     Integer(i64),
     Float(f64),
     Boolean(bool),
+    Timestamp(toml::value::Datetime),
+    Array(Vec<TestInputValue>),
+    Map(IndexMap<String, TestInputValue>),
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -285,6 +374,7 @@ pub struct TestInput {
     pub value: Option<String>,
     pub log_fields: Option<IndexMap<String, TestInputValue>>,
     pub metric: Option<Metric>,
+    pub fixture: Option<PathBuf>,
 }
 
 fn default_test_input_type() -> String {
@@ -296,6 +386,19 @@ fn default_test_input_type() -> String {
 pub struct TestOutput {
     pub extract_from: String,
     pub conditions: Option<Vec<TestCondition>>,
+    #[serde(default)]
+    pub encoded: Vec<TestEncodedOutput>,
+    pub snapshot: Option<PathBuf>,
+    pub snapshot_ignore_fields: Option<Vec<String>>,
+}
+
+/// The expected output of a sink for one event.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TestEncodedOutput {
+    pub key: Option<String>,
+    pub bytes: Option<String>,
+    pub json: Option<serde_json::Value>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -318,6 +421,7 @@ impl Config {
             sources: IndexMap::new(),
             sinks: IndexMap::new(),
             transforms: IndexMap::new(),
+            enrichment_tables: IndexMap::new(),
             tests: Vec::new(),
         }
     }
@@ -353,6 +457,26 @@ impl Config {
         self.transforms.insert(name.to_string(), transform);
     }
 
+    /// The names other components use to consume the output of a source or
+    /// transform.
+    pub fn component_outputs(&self, name: &str) -> Vec<String> {
+        match self.transforms.get(name) {
+            Some(transform) => transform.output_names(name),
+            None => vec![name.to_owned()],
+        }
+    }
+
+    /// Resolves an input to the name of the source or transform producing it.
+    pub fn input_component(&self, input: &str) -> Option<&str> {
+        if let Some((_, name, _)) = self.sources.get_full(input) {
+            return Some(name);
+        }
+        self.transforms
+            .iter()
+            .find(|(name, transform)| transform.output_names(name).iter().any(|o| o == input))
+            .map(|(name, _)| name.as_str())
+    }
+
     /// Some component configs can act like macros and expand themselves into
     /// multiple replacement configs. Returns a map of components to their
     /// expanded child names.
@@ -477,6 +601,11 @@ impl Config {
                 errors.push(format!("duplicate transform name found: {}", k));
             }
         });
+        with.enrichment_tables.keys().for_each(|k| {
+            if self.enrichment_tables.contains_key(k) {
+                errors.push(format!("duplicate enrichment table name found: {}", k));
+            }
+        });
         with.tests.iter().for_each(|wt| {
             if self.tests.iter().any(|t| t.name == wt.name) {
                 errors.push(format!("duplicate test name found: {}", wt.name));
@@ -489,6 +618,7 @@ impl Config {
         self.sources.extend(with.sources);
         self.sinks.extend(with.sinks);
         self.transforms.extend(with.transforms);
+        self.enrichment_tables.extend(with.enrichment_tables);
         self.tests.extend(with.tests);
 
         Ok(())
@@ -131,17 +131,30 @@ impl From<&Config> for Graph {
             graph.add_source(name, config.output_type());
         }
 
-        for (name, config) in config.transforms.iter() {
+        // Named outputs of a transform are edges from the transform itself.
+        let components = |inputs: &[String]| {
+            inputs
+                .iter()
+                .map(|input| {
+                    config
+                        .input_component(input)
+                        .unwrap_or(input.as_str())
+                        .to_owned()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for (name, transform) in config.transforms.iter() {
             graph.add_transform(
                 name,
-                config.inner.input_type(),
-                config.inner.output_type(),
-                config.inputs.clone(),
+                transform.inner.input_type(),
+                transform.inner.output_type(),
+                components(&transform.inputs),
             );
         }
 
-        for (name, config) in config.sinks.iter() {
-            graph.add_sink(name, config.inner.input_type(), config.inputs.clone());
+        for (name, sink) in config.sinks.iter() {
+            graph.add_sink(name, sink.inner.input_type(), components(&sink.inputs));
         }
 
         graph
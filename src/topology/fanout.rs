@@ -140,9 +140,62 @@ impl Sink for Fanout {
     }
 }
 
+/// Sends each event to the fanout of the named output it was routed to.
+pub struct RoutedFanout {
+    outputs: Vec<Fanout>,
+}
+
+impl RoutedFanout {
+    pub fn new(outputs: Vec<Fanout>) -> Self {
+        Self { outputs }
+    }
+}
+
+impl Sink for RoutedFanout {
+    type SinkItem = (usize, Event);
+    type SinkError = ();
+
+    fn start_send(&mut self, item: Self::SinkItem) -> StartSend<Self::SinkItem, Self::SinkError> {
+        let (index, event) = item;
+        let output = match self.outputs.get_mut(index) {
+            Some(output) => output,
+            None => {
+                // Transforms only route to their own outputs, so this is a
+                // bug in the transform, which shouldn't take the topology down.
+                error!(
+                    message = "Event routed to an unknown output, dropping it.",
+                    index,
+                    rate_limit_secs = 30
+                );
+                return Ok(AsyncSink::Ready);
+            }
+        };
+        match output.start_send(event)? {
+            AsyncSink::Ready => Ok(AsyncSink::Ready),
+            AsyncSink::NotReady(event) => Ok(AsyncSink::NotReady((index, event))),
+        }
+    }
+
+    fn poll_complete(&mut self) -> Poll<(), Self::SinkError> {
+        let mut all_complete = true;
+
+        for output in &mut self.outputs {
+            if output.poll_complete()?.is_not_ready() {
+                all_complete = false;
+            }
+        }
+
+        if all_complete {
+            Ok(Async::Ready(()))
+        } else {
+            Ok(Async::NotReady)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{ControlMessage, Fanout};
+    use super::{ControlMessage, Fanout, RoutedFanout};
     use crate::runtime;
     use crate::test_util::{self, CollectCurrent};
     use crate::Event;
@@ -177,6 +230,52 @@ mod tests {
         );
     }
 
+    #[test]
+    fn routed_fanout_writes_to_output() {
+        let (tx_a, rx_a) = mpsc::unbounded();
+        let tx_a = Box::new(tx_a.sink_map_err(|_| unreachable!()));
+        let (tx_b, rx_b) = mpsc::unbounded();
+        let tx_b = Box::new(tx_b.sink_map_err(|_| unreachable!()));
+
+        let mut output_a = Fanout::new().0;
+        output_a.add("a".to_string(), tx_a);
+        let mut output_b = Fanout::new().0;
+        output_b.add("b".to_string(), tx_b);
+        let fanout = RoutedFanout::new(vec![output_a, output_b]);
+
+        let rec1 = Event::from("line 1".to_string());
+        let rec2 = Event::from("line 2".to_string());
+        let rec3 = Event::from("line 3".to_string());
+
+        let fanout = fanout.send((0, rec1.clone())).wait().unwrap();
+        let fanout = fanout.send((1, rec2.clone())).wait().unwrap();
+        let _fanout = fanout.send((0, rec3.clone())).wait().unwrap();
+
+        assert_eq!(
+            CollectCurrent::new(rx_a).wait().unwrap().1,
+            vec![rec1, rec3]
+        );
+        assert_eq!(CollectCurrent::new(rx_b).wait().unwrap().1, vec![rec2]);
+    }
+
+    #[test]
+    fn routed_fanout_drops_unknown_output() {
+        let (tx_a, rx_a) = mpsc::unbounded();
+        let tx_a = Box::new(tx_a.sink_map_err(|_| unreachable!()));
+
+        let mut output_a = Fanout::new().0;
+        output_a.add("a".to_string(), tx_a);
+        let fanout = RoutedFanout::new(vec![output_a]);
+
+        let rec1 = Event::from("line 1".to_string());
+        let rec2 = Event::from("line 2".to_string());
+
+        let fanout = fanout.send((1, rec1)).wait().unwrap();
+        let _fanout = fanout.send((0, rec2.clone())).wait().unwrap();
+
+        assert_eq!(CollectCurrent::new(rx_a).wait().unwrap().1, vec![rec2]);
+    }
+
     #[test]
     fn fanout_notready() {
         let (tx_a, rx_a) = mpsc::channel(1);
@@ -487,7 +487,9 @@ impl RunningTopology {
     }
 
     fn remove_outputs(&mut self, name: &str) {
-        self.outputs.remove(name);
+        for output in self.config.component_outputs(name) {
+            self.outputs.remove(&output);
+        }
     }
 
     fn remove_inputs(&mut self, name: &str) {
@@ -510,10 +512,19 @@ impl RunningTopology {
     }
 
     fn setup_outputs(&mut self, name: &str, new_pieces: &mut builder::Pieces) {
-        let output = new_pieces.outputs.remove(name).unwrap();
+        let outputs = new_pieces.outputs.remove(name).unwrap();
+
+        // Named outputs of the previous version of the component may be gone.
+        self.remove_outputs(name);
+
+        for (output_name, output) in outputs {
+            self.setup_output(output_name, output);
+        }
+    }
 
+    fn setup_output(&mut self, name: String, output: fanout::ControlChannel) {
         for (sink_name, sink) in &self.config.sinks {
-            if sink.inputs.iter().any(|i| i == name) {
+            if sink.inputs.iter().any(|i| i == &name) {
                 // Sink may have been removed with the new config so it may not be present.
                 if let Some(input) = self.inputs.get(sink_name) {
                     output
@@ -523,7 +534,7 @@ impl RunningTopology {
             }
         }
         for (transform_name, transform) in &self.config.transforms {
-            if transform.inputs.iter().any(|i| i == name) {
+            if transform.inputs.iter().any(|i| i == &name) {
                 // Transform may have been removed with the new config so it may not be present.
                 if let Some(input) = self.inputs.get(transform_name) {
                     output
@@ -536,7 +547,7 @@ impl RunningTopology {
             }
         }
 
-        self.outputs.insert(name.to_string(), output);
+        self.outputs.insert(name, output);
     }
 
     fn setup_inputs(&mut self, name: &str, new_pieces: &mut builder::Pieces) {
@@ -604,9 +615,33 @@ impl ConfigDiff {
     }
 
     fn new(old: &Config, new: &Config) -> Self {
+        let mut transforms = Difference::new(&old.transforms, &new.transforms);
+
+        // Transforms hold on to the tables they were built with, so those
+        // using a changed table are rebuilt even if their config is the same.
+        let changed_tables = old
+            .enrichment_tables
+            .keys()
+            .chain(new.enrichment_tables.keys())
+            .filter(|&name| old.enrichment_tables.get(name) != new.enrichment_tables.get(name))
+            .collect::<HashSet<_>>();
+        if !changed_tables.is_empty() {
+            for (name, transform) in &new.transforms {
+                if old.transforms.contains_key(name)
+                    && transform
+                        .inner
+                        .enrichment_tables()
+                        .iter()
+                        .any(|table| changed_tables.contains(table))
+                {
+                    transforms.to_change.insert(name.clone());
+                }
+            }
+        }
+
         ConfigDiff {
             sources: Difference::new(&old.sources, &new.sources),
-            transforms: Difference::new(&old.transforms, &new.transforms),
+            transforms,
             sinks: Difference::new(&old.sinks, &new.sinks),
         }
     }
@@ -964,3 +999,49 @@ mod transient_state_tests {
             .unwrap());
     }
 }
+
+#[cfg(all(test, feature = "transforms-enrich"))]
+mod enrichment_table_tests {
+    use super::ConfigDiff;
+    use crate::enrichment_tables::{Encoding, EnrichmentTableConfig, Matching};
+    use crate::topology::config::Config;
+    use crate::transforms::enrich::{EnrichConfig, MissPolicy};
+
+    fn config(users_path: &str) -> Config {
+        let mut config = Config::empty();
+        for (name, path) in &[("users", users_path), ("codes", "codes.csv")] {
+            config.enrichment_tables.insert(
+                (*name).to_owned(),
+                EnrichmentTableConfig {
+                    path: (*path).into(),
+                    encoding: Encoding::Csv,
+                    index: vec![],
+                },
+            );
+            config.add_transform(
+                &format!("enrich_{}", name),
+                &["in"],
+                EnrichConfig {
+                    table: (*name).to_owned(),
+                    lookup: vec![("id".to_owned(), "id".into())].into_iter().collect(),
+                    matching: Matching::Exact,
+                    target: None,
+                    on_miss: MissPolicy::Pass,
+                },
+            );
+        }
+        config
+    }
+
+    #[test]
+    fn rebuilds_transforms_using_changed_tables() {
+        let diff = ConfigDiff::new(&config("users.csv"), &config("users.csv"));
+        assert!(diff.transforms.to_change.is_empty());
+
+        let diff = ConfigDiff::new(&config("users.csv"), &config("other_users.csv"));
+        assert_eq!(
+            diff.transforms.to_change.into_iter().collect::<Vec<_>>(),
+            vec!["enrich_users".to_owned()]
+        );
+    }
+}
@@ -1,31 +1,83 @@
 use crate::{
     conditions::{Condition, ConditionConfig},
-    event::{Event, Value},
+    enrichment_tables::EnrichmentTables,
+    event::{self, Event, Value},
     runtime::Runtime,
     topology::config::{
-        TestCondition, TestDefinition, TestInput, TestInputValue, TransformContext,
+        EncodedEvent, TestCondition, TestDecoder, TestDefinition, TestEncodedOutput, TestEncoder,
+        TestInput, TestInputValue, TransformContext,
     },
     transforms::Transform,
 };
+use chrono::{DateTime, Utc};
 use indexmap::IndexMap;
-use std::collections::HashMap;
+use serde_json::Value as JsonValue;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
 
 //------------------------------------------------------------------------------
 
 pub struct UnitTestCheck {
     extract_from: String,
     conditions: Vec<Box<dyn Condition>>,
+    encoded: Vec<TestEncodedOutput>,
+    snapshot: Option<Snapshot>,
+}
+
+/// A file recording the expected output events of a check.
+struct Snapshot {
+    path: PathBuf,
+    ignore_fields: Vec<String>,
+}
+
+/// Whether snapshots are compared with the outputs of a test or recorded from
+/// them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SnapshotMode {
+    Compare,
+    Update,
+}
+
+enum UnitTestStage {
+    /// Decodes raw inputs the way the source would.
+    Source(TestDecoder),
+    Transform(Box<dyn Transform>),
+    /// Captures the encoded output of the sink instead of sending it.
+    Sink(TestEncoder),
 }
 
-pub struct UnitTestTransform {
-    transform: Box<dyn Transform>,
+pub struct UnitTestComponent {
+    stage: UnitTestStage,
+    outputs: Vec<String>,
     next: Vec<String>,
 }
 
+#[derive(Default)]
+struct UnitTestResults {
+    /// The input and output events of each component.
+    events: HashMap<String, (Vec<Event>, Vec<Event>)>,
+    /// The encoded output of each sink.
+    encoded: HashMap<String, Vec<EncodedEvent>>,
+    errors: Vec<String>,
+}
+
+/// Stands in for a named output of a transform, forwarding the events routed
+/// to it.
+struct NamedOutput;
+
+impl Transform for NamedOutput {
+    fn transform(&mut self, event: Event) -> Option<Event> {
+        Some(event)
+    }
+}
+
 pub struct UnitTest {
     pub name: String,
     inputs: Vec<(Vec<String>, Event)>,
-    transforms: IndexMap<String, UnitTestTransform>,
+    components: IndexMap<String, UnitTestComponent>,
     checks: Vec<UnitTestCheck>,
     no_outputs_from: Vec<String>,
 }
@@ -58,53 +110,383 @@ fn events_to_string(name: &str, events: &Vec<Event>) -> String {
     }
 }
 
+/// Strips the framing of a sink output, such as line or array delimiters.
+fn trim_framing(body: &[u8]) -> &[u8] {
+    let end = body
+        .iter()
+        .rposition(|b| !matches!(b, b'\n' | b'\r' | b','))
+        .map_or(0, |i| i + 1);
+    &body[..end]
+}
+
+fn parse_json(body: &[u8]) -> Option<serde_json::Value> {
+    serde_json::from_slice(trim_framing(body)).ok()
+}
+
+/// Parses the output of a sink back into an event for conditions to check.
+/// JSON objects become the fields of the event, and anything else its
+/// message.
+fn parse_encoded(encoded: &EncodedEvent) -> Event {
+    match parse_json(&encoded.body) {
+        Some(serde_json::Value::Object(object)) => {
+            let mut event = Event::new_empty_log();
+            for (key, value) in object {
+                event.as_mut_log().insert_flat(key, value);
+            }
+            event
+        }
+        _ => Event::from(String::from_utf8_lossy(trim_framing(&encoded.body)).into_owned()),
+    }
+}
+
 fn walk(
     node: &str,
     mut inputs: Vec<Event>,
-    transforms: &mut IndexMap<String, UnitTestTransform>,
-    aggregated_results: &mut HashMap<String, (Vec<Event>, Vec<Event>)>,
+    components: &mut IndexMap<String, UnitTestComponent>,
+    aggregated_results: &mut UnitTestResults,
 ) {
     let mut results = Vec::new();
     let mut targets = Vec::new();
 
-    if let Some(target) = transforms.get_mut(node) {
-        for input in inputs.clone() {
-            target.transform.transform_into(&mut results, input);
+    if let Some(target) = components.get_mut(node) {
+        match &mut target.stage {
+            UnitTestStage::Source(decoder) => {
+                for input in &inputs {
+                    let bytes = input
+                        .as_log()
+                        .get(&event::log_schema().message_key())
+                        .map(|value| value.as_bytes())
+                        .unwrap_or_default();
+                    match decoder(&bytes[..]) {
+                        Ok(mut events) => results.append(&mut events),
+                        Err(error) => aggregated_results.errors.push(format!(
+                            "source '{}' failed to decode input: {}",
+                            node, error
+                        )),
+                    }
+                }
+            }
+            UnitTestStage::Transform(transform) => {
+                if target.outputs.is_empty() {
+                    for input in inputs.clone() {
+                        transform.transform_into(&mut results, input);
+                    }
+                } else {
+                    let mut routed = Vec::new();
+                    for input in inputs.clone() {
+                        transform.transform_routed(&mut routed, input);
+                    }
+                    targets = target
+                        .outputs
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, output)| target.next.contains(output))
+                        .map(|(index, output)| {
+                            let events = routed
+                                .iter()
+                                .filter(|(i, _)| *i == index)
+                                .map(|(_, event)| event.clone())
+                                .collect();
+                            (output.clone(), events)
+                        })
+                        .collect();
+                    results = routed.into_iter().map(|(_, event)| event).collect();
+                }
+            }
+            UnitTestStage::Sink(encoder) => {
+                for input in inputs.clone() {
+                    if let Some(encoded) = encoder(input) {
+                        results.push(parse_encoded(&encoded));
+                        aggregated_results
+                            .encoded
+                            .entry(node.into())
+                            .or_insert_with(Vec::new)
+                            .push(encoded);
+                    }
+                }
+            }
+        }
+
+        if target.outputs.is_empty() {
+            targets = target
+                .next
+                .iter()
+                .map(|child| (child.clone(), results.clone()))
+                .collect();
         }
-        targets = target.next.clone();
     }
 
-    for child in targets {
-        walk(&child, results.clone(), transforms, aggregated_results);
+    for (child, events) in targets {
+        walk(&child, events, components, aggregated_results);
     }
 
-    if let Some((mut e_inputs, mut e_results)) = aggregated_results.remove(node) {
+    if let Some((mut e_inputs, mut e_results)) = aggregated_results.events.remove(node) {
         inputs.append(&mut e_inputs);
         results.append(&mut e_results);
     }
-    aggregated_results.insert(node.into(), (inputs, results));
+    aggregated_results
+        .events
+        .insert(node.into(), (inputs, results));
+}
+
+/// Compares the encoded output of a sink with the expected one.
+fn check_encoded(
+    name: &str,
+    expected: &[TestEncodedOutput],
+    encoded: &[EncodedEvent],
+) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    if expected.len() != encoded.len() {
+        errors.push(format!(
+            "check sink '{}' failed: expected {} encoded events, received {}.",
+            name,
+            expected.len(),
+            encoded.len()
+        ));
+    }
+
+    for (i, (expected, encoded)) in expected.iter().zip(encoded).enumerate() {
+        if let Some(key) = &expected.key {
+            if encoded.key.as_ref() != Some(key) {
+                errors.push(format!(
+                    "check sink '{}' failed: encoded[{}]: expected key {:?}, received {:?}",
+                    name, i, key, encoded.key
+                ));
+            }
+        }
+        if let Some(bytes) = &expected.bytes {
+            if bytes.as_bytes() != &encoded.body[..] {
+                errors.push(format!(
+                    "check sink '{}' failed: encoded[{}]: expected bytes {:?}, received {:?}",
+                    name,
+                    i,
+                    bytes,
+                    String::from_utf8_lossy(&encoded.body)
+                ));
+            }
+        }
+        if let Some(json) = &expected.json {
+            if parse_json(&encoded.body).as_ref() != Some(json) {
+                errors.push(format!(
+                    "check sink '{}' failed: encoded[{}]: expected JSON {}, received {:?}",
+                    name,
+                    i,
+                    json,
+                    String::from_utf8_lossy(&encoded.body)
+                ));
+            }
+        }
+    }
+
+    errors
+}
+
+/// Removes a dotted field path from a JSON object.
+fn remove_json_path(value: &mut JsonValue, path: &str) {
+    let mut parts = path.splitn(2, '.');
+    let key = parts.next().unwrap_or_default();
+    if let JsonValue::Object(object) = value {
+        match parts.next() {
+            Some(rest) => {
+                if let Some(child) = object.get_mut(key) {
+                    remove_json_path(child, rest);
+                }
+            }
+            None => {
+                object.remove(key);
+            }
+        }
+    }
+}
+
+/// Flattens a JSON value into its leaf values keyed by field path.
+fn flatten_json(prefix: String, value: &JsonValue, flat: &mut BTreeMap<String, JsonValue>) {
+    match value {
+        JsonValue::Object(object) if !object.is_empty() => {
+            for (key, value) in object {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten_json(path, value, flat);
+            }
+        }
+        JsonValue::Array(array) if !array.is_empty() => {
+            for (i, value) in array.iter().enumerate() {
+                flatten_json(format!("{}[{}]", prefix, i), value, flat);
+            }
+        }
+        _ => {
+            flat.insert(prefix, value.clone());
+        }
+    }
+}
+
+impl Snapshot {
+    fn events_to_json(&self, events: &[Event]) -> Vec<JsonValue> {
+        events
+            .iter()
+            .map(|event| {
+                let mut value = match event {
+                    Event::Log(log) => serde_json::to_value(log),
+                    Event::Metric(metric) => serde_json::to_value(metric),
+                }
+                .unwrap_or(JsonValue::Null);
+                for field in &self.ignore_fields {
+                    remove_json_path(&mut value, field);
+                }
+                value
+            })
+            .collect()
+    }
+
+    fn update(&self, events: &[Event]) -> Result<(), String> {
+        let mut contents = String::new();
+        for value in self.events_to_json(events) {
+            contents.push_str(&value.to_string());
+            contents.push('\n');
+        }
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|error| format!("failed to create {:?}: {}", parent, error))?;
+        }
+        fs::write(&self.path, contents)
+            .map_err(|error| format!("failed to write snapshot {:?}: {}", self.path, error))
+    }
+
+    /// Compares events with the snapshot, returning a field level diff of
+    /// each event that differs.
+    fn compare(&self, events: &[Event]) -> Result<Vec<String>, String> {
+        let contents = fs::read_to_string(&self.path).map_err(|error| {
+            if error.kind() == std::io::ErrorKind::NotFound {
+                format!(
+                    "snapshot {:?} does not exist, run `vector test --update-snapshots` to record it",
+                    self.path
+                )
+            } else {
+                format!("failed to read snapshot {:?}: {}", self.path, error)
+            }
+        })?;
+        let mut expected = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<Vec<JsonValue>, _>>()
+            .map_err(|error| format!("invalid snapshot {:?}: {}", self.path, error))?;
+        for value in &mut expected {
+            for field in &self.ignore_fields {
+                remove_json_path(value, field);
+            }
+        }
+        let actual = self.events_to_json(events);
+
+        let mut diff = Vec::new();
+        for i in 0..expected.len().max(actual.len()) {
+            match (expected.get(i), actual.get(i)) {
+                (Some(expected), Some(actual)) => {
+                    let mut expected_fields = BTreeMap::new();
+                    flatten_json(String::new(), expected, &mut expected_fields);
+                    let mut actual_fields = BTreeMap::new();
+                    flatten_json(String::new(), actual, &mut actual_fields);
+
+                    for (path, value) in &expected_fields {
+                        match actual_fields.get(path) {
+                            Some(actual) if actual == value => (),
+                            Some(actual) => diff
+                                .push(format!("event[{}]: ~ {}: {} -> {}", i, path, value, actual)),
+                            None => diff.push(format!("event[{}]: - {}: {}", i, path, value)),
+                        }
+                    }
+                    for (path, value) in &actual_fields {
+                        if !expected_fields.contains_key(path) {
+                            diff.push(format!("event[{}]: + {}: {}", i, path, value));
+                        }
+                    }
+                }
+                (Some(expected), None) => diff.push(format!("event[{}]: - {}", i, expected)),
+                (None, Some(actual)) => diff.push(format!("event[{}]: + {}", i, actual)),
+                (None, None) => (),
+            }
+        }
+        Ok(diff)
+    }
 }
 
 impl UnitTest {
     // Executes each test and provides a tuple of inspections and error lists.
     pub fn run(&mut self) -> (Vec<String>, Vec<String>) {
+        self.run_with(SnapshotMode::Compare)
+    }
+
+    pub fn run_with(&mut self, snapshot_mode: SnapshotMode) -> (Vec<String>, Vec<String>) {
         let mut errors = Vec::new();
         let mut inspections = Vec::new();
-        let mut results = HashMap::new();
+        let mut results = UnitTestResults::default();
 
         for input in &self.inputs {
             for target in &input.0 {
                 walk(
                     target,
                     vec![input.1.clone()],
-                    &mut self.transforms,
+                    &mut self.components,
                     &mut results,
                 );
             }
         }
+        errors.append(&mut results.errors);
 
         for check in &self.checks {
-            if let Some((inputs, outputs)) = results.get(&check.extract_from) {
+            if let Some(snapshot) = &check.snapshot {
+                let outputs = results
+                    .events
+                    .get(&check.extract_from)
+                    .map(|(_, outputs)| outputs.as_slice())
+                    .unwrap_or_default();
+                match snapshot_mode {
+                    SnapshotMode::Update => match snapshot.update(outputs) {
+                        Ok(()) => inspections.push(format!(
+                            "check transform '{}' updated snapshot {:?}",
+                            check.extract_from, snapshot.path
+                        )),
+                        Err(error) => errors.push(format!(
+                            "check transform '{}' failed: {}",
+                            check.extract_from, error
+                        )),
+                    },
+                    SnapshotMode::Compare => match snapshot.compare(outputs) {
+                        Ok(diff) if diff.is_empty() => (),
+                        Ok(diff) => errors.push(format!(
+                            "check transform '{}' failed snapshot {:?}:\n  {}",
+                            check.extract_from,
+                            snapshot.path,
+                            diff.join("\n  ")
+                        )),
+                        Err(error) => errors.push(format!(
+                            "check transform '{}' failed: {}",
+                            check.extract_from, error
+                        )),
+                    },
+                }
+            }
+
+            if !check.encoded.is_empty() {
+                let encoded = results
+                    .encoded
+                    .get(&check.extract_from)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                errors.extend(check_encoded(&check.extract_from, &check.encoded, encoded));
+            }
+            if check.conditions.is_empty()
+                && (!check.encoded.is_empty() || check.snapshot.is_some())
+            {
+                continue;
+            }
+
+            if let Some((inputs, outputs)) = results.events.get(&check.extract_from) {
                 if check.conditions.is_empty() {
                     inspections.push(format!(
                         "check transform '{}' payloads (events encoded as JSON):\n{}\n{}",
@@ -164,7 +546,7 @@ impl UnitTest {
         }
 
         for tform in &self.no_outputs_from {
-            if let Some((inputs, outputs)) = results.get(tform) {
+            if let Some((inputs, outputs)) = results.events.get(tform) {
                 if !outputs.is_empty() {
                     errors.push(format!(
                         "check transform '{}' failed: expected no outputs.\npayloads (events encoded as JSON):\n{}\n{}",
@@ -236,10 +618,67 @@ fn reduce_transforms(
     });
 }
 
+fn build_input_value(value: &TestInputValue) -> Result<Value, String> {
+    Ok(match value {
+        TestInputValue::String(s) => s.as_bytes().into(),
+        TestInputValue::Boolean(b) => (*b).into(),
+        TestInputValue::Integer(i) => (*i).into(),
+        TestInputValue::Float(f) => (*f).into(),
+        TestInputValue::Timestamp(t) => DateTime::parse_from_rfc3339(&t.to_string())
+            .map_err(|_| {
+                format!(
+                    "invalid timestamp '{}', expected a date-time with an offset",
+                    t
+                )
+            })?
+            .with_timezone(&Utc)
+            .into(),
+        TestInputValue::Array(values) => Value::Array(
+            values
+                .iter()
+                .map(build_input_value)
+                .collect::<Result<_, _>>()?,
+        ),
+        TestInputValue::Map(values) => Value::Map(
+            values
+                .iter()
+                .map(|(k, v)| Ok((k.clone(), build_input_value(v)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+    })
+}
+
+/// Reads a fixture file of newline delimited JSON objects into log events.
+fn load_fixture(path: &Path) -> Result<Vec<Event>, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|error| format!("failed to read fixture {:?}: {}", path, error))?;
+
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| match serde_json::from_str(line) {
+            Ok(serde_json::Value::Object(object)) => {
+                let mut event = Event::new_empty_log();
+                for (key, value) in object {
+                    event.as_mut_log().insert_flat(key, value);
+                }
+                Ok(event)
+            }
+            Ok(_) => Err(format!(
+                "fixture {:?} line {}: expected a JSON object",
+                path,
+                i + 1
+            )),
+            Err(error) => Err(format!("fixture {:?} line {}: {}", path, i + 1, error)),
+        })
+        .collect()
+}
+
 fn build_input(
     input: &TestInput,
     expansions: &IndexMap<String, Vec<String>>,
-) -> Result<(Vec<String>, Event), String> {
+) -> Result<Vec<(Vec<String>, Event)>, String> {
     let target = if let Some(children) = expansions.get(&input.insert_at) {
         children.clone()
     } else {
@@ -247,35 +686,41 @@ fn build_input(
     };
     match input.type_str.as_ref() {
         "raw" => match input.value.as_ref() {
-            Some(v) => Ok((target, Event::from(v.clone()))),
+            Some(v) => Ok(vec![(target, Event::from(v.clone()))]),
             None => Err("input type 'raw' requires the field 'value'".to_string()),
         },
         "log" => {
             if let Some(log_fields) = &input.log_fields {
                 let mut event = Event::from("");
                 for (path, value) in log_fields {
-                    let value: Value = match value {
-                        TestInputValue::String(s) => s.as_bytes().into(),
-                        TestInputValue::Boolean(b) => (*b).into(),
-                        TestInputValue::Integer(i) => (*i).into(),
-                        TestInputValue::Float(f) => (*f).into(),
-                    };
-                    event.as_mut_log().insert(path.to_owned(), value);
+                    event
+                        .as_mut_log()
+                        .insert(path.to_owned(), build_input_value(value)?);
                 }
-                Ok((target, event))
+                Ok(vec![(target, event)])
             } else {
                 Err("input type 'log' requires the field 'log_fields'".to_string())
             }
         }
         "metric" => {
             if let Some(metric) = &input.metric {
-                Ok((target, Event::Metric(metric.clone())))
+                Ok(vec![(target, Event::Metric(metric.clone()))])
             } else {
                 Err("input type 'metric' requires the field 'metric'".to_string())
             }
         }
+        "fixture" => {
+            if let Some(fixture) = &input.fixture {
+                Ok(load_fixture(fixture)?
+                    .into_iter()
+                    .map(|event| (target.clone(), event))
+                    .collect())
+            } else {
+                Err("input type 'fixture' requires the field 'fixture'".to_string())
+            }
+        }
         _ => Err(format!(
-            "unrecognized input type '{}', expected one of: 'raw', 'log' or 'metric'",
+            "unrecognized input type '{}', expected one of: 'raw', 'log', 'metric' or 'fixture'",
             input.type_str
         )),
     }
@@ -290,7 +735,7 @@ fn build_inputs(
 
     if let Some(input_def) = &definition.input {
         match build_input(input_def, &expansions) {
-            Ok(input_event) => inputs.push(input_event),
+            Ok(mut input_events) => inputs.append(&mut input_events),
             Err(err) => errors.push(err),
         }
     } else if definition.inputs.is_empty() {
@@ -298,7 +743,7 @@ fn build_inputs(
     }
     for input_def in &definition.inputs {
         match build_input(input_def, &expansions) {
-            Ok(input_event) => inputs.push(input_event),
+            Ok(mut input_events) => inputs.append(&mut input_events),
             Err(err) => errors.push(err),
         }
     }
@@ -326,21 +771,50 @@ fn build_unit_test(
         }
     };
 
-    // Maps transform names with their output targets (transforms that use it as
-    // an input).
-    let mut transform_outputs: IndexMap<String, IndexMap<String, ()>> = config
+    // Maps component names with their output targets (transforms and sinks
+    // that use it as an input). Named outputs of a transform are targets of
+    // their own.
+    let mut transform_outputs: IndexMap<String, IndexMap<String, ()>> = IndexMap::new();
+    for k in config.sources.keys() {
+        transform_outputs.insert(k.clone(), IndexMap::new());
+    }
+    for (k, t) in &config.transforms {
+        if t.inner.named_outputs().is_empty() {
+            transform_outputs.insert(k.clone(), IndexMap::new());
+        } else {
+            let outputs = t.output_names(k);
+            transform_outputs.insert(k.clone(), outputs.iter().map(|o| (o.clone(), ())).collect());
+            for output in outputs {
+                transform_outputs.insert(output, IndexMap::new());
+            }
+        }
+    }
+    for k in config.sinks.keys() {
+        transform_outputs.insert(k.clone(), IndexMap::new());
+    }
+
+    let consumers = config
         .transforms
         .iter()
-        .map(|(k, _)| (k.clone(), IndexMap::new()))
-        .collect();
-
-    config.transforms.iter().for_each(|(k, t)| {
-        t.inputs.iter().for_each(|i| {
+        .map(|(k, t)| (k, &t.inputs))
+        .chain(config.sinks.iter().map(|(k, s)| (k, &s.inputs)));
+    for (k, component_inputs) in consumers {
+        component_inputs.iter().for_each(|i| {
             if let Some(outputs) = transform_outputs.get_mut(i) {
                 outputs.insert(k.to_string(), ());
             }
         })
-    });
+    }
+
+    // Sources decode raw inputs only.
+    for input in definition.input.iter().chain(definition.inputs.iter()) {
+        if input.type_str != "raw" && config.sources.contains_key(&input.insert_at) {
+            errors.push(format!(
+                "input type '{}' can not be inserted at source '{}', expected 'raw'",
+                input.type_str, input.insert_at
+            ));
+        }
+    }
 
     for (i, (input_target, _)) in inputs.iter().enumerate() {
         for target in input_target {
@@ -377,19 +851,68 @@ fn build_unit_test(
         &mut transform_outputs,
     );
 
-    // Build reduced transforms.
-    let mut transforms: IndexMap<String, UnitTestTransform> = IndexMap::new();
+    let enrichment_tables = match EnrichmentTables::load(&config.enrichment_tables) {
+        Ok(tables) => tables,
+        Err(mut errs) => {
+            errors.append(&mut errs);
+            EnrichmentTables::default()
+        }
+    };
+
+    // Build reduced components.
+    let mut components: IndexMap<String, UnitTestComponent> = IndexMap::new();
+    for (name, source_config) in &config.sources {
+        if let Some(outputs) = transform_outputs.remove(name) {
+            match source_config.build_test_decoder() {
+                Some(decoder) => {
+                    components.insert(
+                        name.clone(),
+                        UnitTestComponent {
+                            stage: UnitTestStage::Source(decoder),
+                            outputs: Vec::new(),
+                            next: outputs.into_iter().map(|(k, _)| k).collect(),
+                        },
+                    );
+                }
+                None => {
+                    errors.push(format!(
+                        "source '{}' does not support unit test inputs",
+                        name
+                    ));
+                }
+            }
+        }
+    }
+
     for (name, transform_config) in &config.transforms {
+        let named_outputs = if transform_config.inner.named_outputs().is_empty() {
+            Vec::new()
+        } else {
+            transform_config.output_names(name)
+        };
+        for output in &named_outputs {
+            if let Some(next) = transform_outputs.remove(output) {
+                components.insert(
+                    output.clone(),
+                    UnitTestComponent {
+                        stage: UnitTestStage::Transform(Box::new(NamedOutput)),
+                        outputs: Vec::new(),
+                        next: next.into_iter().map(|(k, _)| k).collect(),
+                    },
+                );
+            }
+        }
+
         if let Some(outputs) = transform_outputs.remove(name) {
-            match transform_config
-                .inner
-                .build(TransformContext::new_test(rt.executor()))
-            {
+            let mut cx = TransformContext::new_test(rt.executor());
+            cx.enrichment_tables = enrichment_tables.clone();
+            match transform_config.inner.build(cx) {
                 Ok(transform) => {
-                    transforms.insert(
+                    components.insert(
                         name.clone(),
-                        UnitTestTransform {
-                            transform,
+                        UnitTestComponent {
+                            stage: UnitTestStage::Transform(transform),
+                            outputs: named_outputs,
                             next: outputs.into_iter().map(|(k, _)| k).collect(),
                         },
                     );
@@ -401,12 +924,35 @@ fn build_unit_test(
         }
     }
 
+    for (name, sink) in &config.sinks {
+        if transform_outputs.remove(name).is_some() {
+            match sink.inner.build_test_encoder() {
+                Some(encoder) => {
+                    components.insert(
+                        name.clone(),
+                        UnitTestComponent {
+                            stage: UnitTestStage::Sink(encoder),
+                            outputs: Vec::new(),
+                            next: Vec::new(),
+                        },
+                    );
+                }
+                None => {
+                    errors.push(format!(
+                        "sink '{}' does not support unit test outputs",
+                        name
+                    ));
+                }
+            }
+        }
+    }
+
     if !errors.is_empty() {
         return Err(errors);
     }
 
     definition.outputs.iter().for_each(|o| {
-        if !transforms.contains_key(&o.extract_from) {
+        if !components.contains_key(&o.extract_from) {
             let targets = inputs.iter().map(|(i, _)| i).flatten().collect::<Vec<_>>();
             if targets.len() == 1 {
                 errors.push(format!(
@@ -469,6 +1015,13 @@ fn build_unit_test(
             UnitTestCheck {
                 extract_from: o.extract_from.clone(),
                 conditions,
+                encoded: o.encoded.clone(),
+                snapshot: o.snapshot.clone().map(|path| Snapshot {
+                    path,
+                    ignore_fields: o.snapshot_ignore_fields.clone().unwrap_or_else(|| {
+                        vec![event::log_schema().timestamp_key().to_string()]
+                    }),
+                }),
             }
         })
         .collect();
@@ -485,13 +1038,36 @@ fn build_unit_test(
         Ok(UnitTest {
             name: definition.name.clone(),
             inputs,
-            transforms,
+            components,
             checks,
             no_outputs_from: definition.no_outputs_from.clone(),
         })
     }
 }
 
+/// Builds the tests of a config loaded from the file at `path`, resolving
+/// relative fixture and snapshot paths against the directory of that file.
+pub fn build_unit_tests_for_file(
+    config: &mut super::Config,
+    path: &Path,
+) -> Result<Vec<UnitTest>, Vec<String>> {
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    for test in &mut config.tests {
+        for input in test.input.iter_mut().chain(test.inputs.iter_mut()) {
+            if let Some(fixture) = &mut input.fixture {
+                *fixture = dir.join(&*fixture);
+            }
+        }
+        for output in &mut test.outputs {
+            if let Some(snapshot) = &mut output.snapshot {
+                *snapshot = dir.join(&*snapshot);
+            }
+        }
+    }
+
+    build_unit_tests(config)
+}
+
 pub fn build_unit_tests(config: &mut super::Config) -> Result<Vec<UnitTest>, Vec<String>> {
     let mut tests = vec![];
     let mut errors = vec![];
@@ -790,7 +1366,7 @@ mod tests {
         assert_eq!(
             errs,
             vec![r#"Failed to build test 'broken test':
-  unrecognized input type 'nah', expected one of: 'raw', 'log' or 'metric'"#
+  unrecognized input type 'nah', expected one of: 'raw', 'log', 'metric' or 'fixture'"#
                 .to_owned(),]
         );
     }
@@ -1003,6 +1579,189 @@ mod tests {
         assert_eq!(tests[0].run().1, Vec::<String>::new());
     }
 
+    #[cfg(feature = "transforms-route")]
+    #[test]
+    fn test_route() {
+        let mut config: Config = toml::from_str(
+            r#"
+[transforms.foo]
+  inputs = ["ignored"]
+  type = "route"
+  [transforms.foo.lanes.first]
+    "message.eq" = "test route 1"
+
+[transforms.bar]
+  inputs = ["foo.first"]
+  type = "add_fields"
+  [transforms.bar.fields]
+    new_field = "new field added"
+
+[transforms.baz]
+  inputs = ["foo._unmatched"]
+  type = "add_fields"
+  [transforms.baz.fields]
+    unmatched = "true"
+
+[[tests]]
+  name = "successful route test 1"
+  no_outputs_from = ["baz"]
+
+  [tests.input]
+    insert_at = "foo"
+    value = "test route 1"
+
+  [[tests.outputs]]
+    extract_from = "bar"
+    [[tests.outputs.conditions]]
+      type = "check_fields"
+      "message.equals" = "test route 1"
+      "new_field.equals" = "new field added"
+
+[[tests]]
+  name = "successful route test 2"
+  no_outputs_from = ["bar"]
+
+  [tests.input]
+    insert_at = "foo"
+    value = "test route 2"
+
+  [[tests.outputs]]
+    extract_from = "foo._unmatched"
+    [[tests.outputs.conditions]]
+      type = "check_fields"
+      "message.equals" = "test route 2"
+
+  [[tests.outputs]]
+    extract_from = "baz"
+    [[tests.outputs.conditions]]
+      type = "check_fields"
+      "unmatched.equals" = "true"
+      "#,
+        )
+        .unwrap();
+
+        let mut tests = build_unit_tests(&mut config).unwrap();
+        assert_eq!(tests[0].run().1, Vec::<String>::new());
+        assert_eq!(tests[1].run().1, Vec::<String>::new());
+    }
+
+    #[cfg(all(
+        feature = "sources-syslog",
+        feature = "sinks-console",
+        feature = "sinks-file"
+    ))]
+    #[test]
+    fn test_source_and_sinks() {
+        let mut config: Config = toml::from_str(
+            r#"
+[sources.in]
+  type = "syslog"
+  mode = "tcp"
+  address = "0.0.0.0:5140"
+
+[transforms.foo]
+  inputs = ["in"]
+  type = "add_fields"
+  [transforms.foo.fields]
+    env = "test"
+
+[sinks.out_console]
+  inputs = ["foo"]
+  type = "console"
+  encoding.codec = "json"
+  encoding.only_fields = ["appname", "env", "message"]
+
+[sinks.out_file]
+  inputs = ["foo"]
+  type = "file"
+  path = "/var/log/{{ appname }}.log"
+  encoding = "text"
+
+[[tests]]
+  name = "successful source and sinks test"
+
+  [tests.input]
+    insert_at = "in"
+    value = "<34>1 2020-03-13T20:45:38.119Z dynamicwireless.name non 2426 ID931 - test syslog message"
+
+  [[tests.outputs]]
+    extract_from = "out_console"
+    [[tests.outputs.encoded]]
+      json = { appname = "non", env = "test", message = "test syslog message" }
+    [[tests.outputs.conditions]]
+      type = "check_fields"
+      "env.equals" = "test"
+
+  [[tests.outputs]]
+    extract_from = "out_file"
+    [[tests.outputs.encoded]]
+      key = "/var/log/non.log"
+      bytes = "test syslog message\n"
+
+[[tests]]
+  name = "failing sink test"
+
+  [tests.input]
+    insert_at = "in"
+    value = "<34>1 2020-03-13T20:45:38.119Z dynamicwireless.name non 2426 ID931 - test syslog message"
+
+  [[tests.outputs]]
+    extract_from = "out_file"
+    [[tests.outputs.encoded]]
+      bytes = "other message\n"
+      "#,
+        )
+        .unwrap();
+
+        let mut tests = build_unit_tests(&mut config).unwrap();
+        assert_eq!(tests[0].run().1, Vec::<String>::new());
+        assert_eq!(
+            tests[1].run().1,
+            vec![r#"check sink 'out_file' failed: encoded[0]: expected bytes "other message\n", received "test syslog message\n""#.to_owned()]
+        );
+    }
+
+    #[cfg(feature = "sources-stdin")]
+    #[test]
+    fn test_source_requires_raw_input() {
+        let mut config: Config = toml::from_str(
+            r#"
+[sources.in]
+  type = "stdin"
+
+[transforms.foo]
+  inputs = ["in"]
+  type = "add_fields"
+  [transforms.foo.fields]
+    env = "test"
+
+[[tests]]
+  name = "source test"
+
+  [tests.input]
+    insert_at = "in"
+    type = "log"
+    [tests.input.log_fields]
+      message = "test"
+
+  [[tests.outputs]]
+    extract_from = "foo"
+    [[tests.outputs.conditions]]
+      type = "check_fields"
+      "env.equals" = "test"
+      "#,
+        )
+        .unwrap();
+
+        let errs = build_unit_tests(&mut config).err().unwrap();
+        assert_eq!(
+            errs,
+            vec![r#"Failed to build test 'source test':
+  input type 'log' can not be inserted at source 'in', expected 'raw'"#
+                .to_owned()]
+        );
+    }
+
     #[test]
     fn test_fail_no_outputs() {
         let mut config: Config = toml::from_str(
@@ -1219,6 +1978,192 @@ mod tests {
         assert_eq!(tests[0].run().1, Vec::<String>::new());
     }
 
+    #[test]
+    fn test_nested_log_input() {
+        let mut config: Config = toml::from_str(
+            r#"
+[transforms.foo]
+  inputs = ["ignored"]
+  type = "add_fields"
+  [transforms.foo.fields]
+    new_field = "string value"
+
+[[tests]]
+  name = "successful test with nested log event"
+
+  [tests.input]
+    insert_at = "foo"
+    type = "log"
+    [tests.input.log_fields]
+      message = "this is the message"
+      tags = ["a", "b"]
+      created_at = 2020-05-01T12:00:00Z
+      parent.child = "nested value"
+
+  [[tests.outputs]]
+    extract_from = "foo"
+    [[tests.outputs.conditions]]
+      type = "check_fields"
+      "parent.child.equals" = "nested value"
+      "tags.exists" = true
+      "created_at.exists" = true
+
+[[tests]]
+  name = "failing test with local timestamp"
+
+  [tests.input]
+    insert_at = "foo"
+    type = "log"
+    [tests.input.log_fields]
+      created_at = 2020-05-01T12:00:00
+
+  [[tests.outputs]]
+    extract_from = "foo"
+      "#,
+        )
+        .unwrap();
+
+        let errs = build_unit_tests(&mut config).err().unwrap();
+        assert_eq!(
+            errs,
+            vec![r#"Failed to build test 'failing test with local timestamp':
+  invalid timestamp '2020-05-01T12:00:00', expected a date-time with an offset"#
+                .to_owned()]
+        );
+
+        config.tests.remove(1);
+        let mut tests = build_unit_tests(&mut config).unwrap();
+        assert_eq!(tests[0].run().1, Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_fixture_input_and_snapshot() {
+        let dir = crate::test_util::temp_dir();
+        fs::create_dir_all(&dir).unwrap();
+        let fixture = dir.join("input.ndjson");
+        let snapshot = dir.join("snapshots").join("foo.ndjson");
+        fs::write(
+            &fixture,
+            "{\"message\":\"a\",\"nested\":{\"x\":1}}\n\n{\"message\":\"b\"}\n{\"message\":\"c\"}\n",
+        )
+        .unwrap();
+
+        let mut config: Config = toml::from_str(&format!(
+            r#"
+[transforms.foo]
+  inputs = ["ignored"]
+  type = "add_fields"
+  [transforms.foo.fields]
+    new_field = "string value"
+
+[[tests]]
+  name = "snapshot test"
+
+  [tests.input]
+    insert_at = "foo"
+    type = "fixture"
+    fixture = {:?}
+
+  [[tests.outputs]]
+    extract_from = "foo"
+    snapshot = {:?}
+      "#,
+            fixture, snapshot
+        ))
+        .unwrap();
+
+        let mut tests = build_unit_tests(&mut config).unwrap();
+        assert_eq!(
+            tests[0].run().1,
+            vec![format!(
+                "check transform 'foo' failed: snapshot {:?} does not exist, run `vector test --update-snapshots` to record it",
+                snapshot
+            )]
+        );
+
+        let (inspections, errors) = tests[0].run_with(SnapshotMode::Update);
+        assert_eq!(errors, Vec::<String>::new());
+        assert_eq!(
+            inspections,
+            vec![format!(
+                "check transform 'foo' updated snapshot {:?}",
+                snapshot
+            )]
+        );
+        let recorded = fs::read_to_string(&snapshot)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect::<Vec<JsonValue>>();
+        assert_eq!(
+            recorded,
+            vec![
+                serde_json::json!({"message": "a", "nested": {"x": 1}, "new_field": "string value"}),
+                serde_json::json!({"message": "b", "new_field": "string value"}),
+                serde_json::json!({"message": "c", "new_field": "string value"}),
+            ]
+        );
+        assert_eq!(tests[0].run(), (Vec::new(), Vec::new()));
+
+        fs::write(
+            &snapshot,
+            "{\"message\":\"a\",\"nested\":{\"x\":2},\"old\":true}\n{\"message\":\"b\",\"new_field\":\"string value\"}\n",
+        )
+        .unwrap();
+        assert_eq!(
+            tests[0].run().1,
+            vec![format!(
+                r#"check transform 'foo' failed snapshot {:?}:
+  event[0]: ~ nested.x: 2 -> 1
+  event[0]: - old: true
+  event[0]: + new_field: "string value"
+  event[2]: + {{"message":"c","new_field":"string value"}}"#,
+                snapshot
+            )]
+        );
+    }
+
+    #[test]
+    fn test_fixture_and_snapshot_relative_to_config() {
+        let dir = crate::test_util::temp_dir();
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("input.ndjson"), "{\"message\":\"a\"}\n").unwrap();
+
+        let mut config: Config = toml::from_str(
+            r#"
+[transforms.foo]
+  inputs = ["ignored"]
+  type = "add_fields"
+  [transforms.foo.fields]
+    new_field = "string value"
+
+[[tests]]
+  name = "relative paths"
+
+  [tests.input]
+    insert_at = "foo"
+    type = "fixture"
+    fixture = "input.ndjson"
+
+  [[tests.outputs]]
+    extract_from = "foo"
+    snapshot = "snapshots/foo.ndjson"
+      "#,
+        )
+        .unwrap();
+
+        let mut tests = build_unit_tests_for_file(&mut config, &dir.join("vector.toml")).unwrap();
+        assert_eq!(
+            tests[0].run_with(SnapshotMode::Update).1,
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("snapshots").join("foo.ndjson")).unwrap(),
+            "{\"message\":\"a\",\"new_field\":\"string value\"}\n"
+        );
+        assert_eq!(tests[0].run(), (Vec::new(), Vec::new()));
+    }
+
     #[test]
     fn test_metric_input() {
         let mut config: Config = toml::from_str(
@@ -0,0 +1,923 @@
+use super::util::runtime_transform::{RuntimeTransform, Timer};
+use super::Transform;
+use crate::{
+    event::metric::{Metric, MetricKind, MetricValue},
+    topology::config::{DataType, TransformConfig, TransformContext, TransformDescription},
+    Event,
+};
+use chrono::Utc;
+use indexmap::{map::Entry, IndexMap};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AggregateConfig {
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    pub group_by: Option<Vec<String>>,
+    #[serde(default)]
+    pub gauge_aggregation: GaugeAggregation,
+    #[serde(default = "default_output_kind")]
+    pub output_kind: MetricKind,
+    #[serde(default = "default_expire_after_secs")]
+    pub expire_after_secs: u64,
+    pub quantiles: Option<Vec<f64>>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GaugeAggregation {
+    Last,
+    Min,
+    Max,
+    Avg,
+}
+
+impl Default for GaugeAggregation {
+    fn default() -> Self {
+        GaugeAggregation::Last
+    }
+}
+
+fn default_interval_secs() -> u64 {
+    10
+}
+
+fn default_output_kind() -> MetricKind {
+    MetricKind::Incremental
+}
+
+fn default_expire_after_secs() -> u64 {
+    300
+}
+
+inventory::submit! {
+    TransformDescription::new_without_default::<AggregateConfig>("aggregate")
+}
+
+#[typetag::serde(name = "aggregate")]
+impl TransformConfig for AggregateConfig {
+    fn build(&self, _cx: TransformContext) -> crate::Result<Box<dyn Transform>> {
+        if self.interval_secs == 0 {
+            return Err("interval_secs must be greater than zero".into());
+        }
+        if let Some(quantiles) = &self.quantiles {
+            if quantiles.is_empty() || quantiles.iter().any(|q| !(0.0..=1.0).contains(q)) {
+                return Err("quantiles must be between 0 and 1".into());
+            }
+        }
+        Ok(Box::new(Aggregate::new(self)))
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Metric
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Metric
+    }
+
+    fn transform_type(&self) -> &'static str {
+        "aggregate"
+    }
+}
+
+/// Identifies an aggregated series: the metric name, the tags left after
+/// grouping and the type of the metric value.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+struct MetricKey {
+    name: String,
+    tags: Option<BTreeMap<String, String>>,
+    value_type: &'static str,
+}
+
+/// Identifies an incoming series before grouping, used to turn absolute
+/// counters into increments and to merge absolute values.
+type SeriesKey = (String, Option<BTreeMap<String, String>>);
+
+#[derive(Clone, Debug)]
+struct GaugeStats {
+    last: f64,
+    min: f64,
+    max: f64,
+    sum: f64,
+    count: u64,
+}
+
+impl GaugeStats {
+    fn new(value: f64) -> Self {
+        Self {
+            last: value,
+            min: value,
+            max: value,
+            sum: value,
+            count: 1,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        self.last = value;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn value(&self, aggregation: GaugeAggregation) -> f64 {
+        match aggregation {
+            GaugeAggregation::Last => self.last,
+            GaugeAggregation::Min => self.min,
+            GaugeAggregation::Max => self.max,
+            GaugeAggregation::Avg => self.sum / self.count as f64,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Aggregated {
+    Counter(f64),
+    Gauge(GaugeStats),
+    // Absolute values are kept per series in `absolute_values`, and merged
+    // at flush.
+    Absolute,
+    Other(MetricValue),
+}
+
+pub struct Aggregate {
+    interval_secs: u64,
+    group_by: Option<HashSet<String>>,
+    gauge_aggregation: GaugeAggregation,
+    output_kind: MetricKind,
+    expire_after: Duration,
+    quantiles: Option<Vec<f64>>,
+    aggregated: IndexMap<MetricKey, Aggregated>,
+    // Running counter totals, only kept when emitting absolute metrics.
+    counter_totals: IndexMap<MetricKey, f64>,
+    // Last value of each absolute counter series, and when it was received.
+    counter_baselines: HashMap<SeriesKey, (f64, Instant)>,
+    // Last value of each gauge, incremental gauges are applied to it.
+    gauge_values: HashMap<MetricKey, f64>,
+    // Last value of each series of absolute sets, distributions and
+    // histograms, and when it was received.
+    absolute_values: HashMap<MetricKey, IndexMap<SeriesKey, (MetricValue, Instant)>>,
+    // When each aggregated series was last received, the state of series
+    // not received for `expire_after` is dropped.
+    last_seen: HashMap<MetricKey, Instant>,
+}
+
+impl Aggregate {
+    pub fn new(config: &AggregateConfig) -> Self {
+        Self {
+            interval_secs: config.interval_secs,
+            group_by: config
+                .group_by
+                .as_ref()
+                .map(|tags| tags.iter().cloned().collect()),
+            gauge_aggregation: config.gauge_aggregation,
+            output_kind: config.output_kind.clone(),
+            expire_after: Duration::from_secs(config.expire_after_secs),
+            quantiles: config.quantiles.clone(),
+            aggregated: IndexMap::new(),
+            counter_totals: IndexMap::new(),
+            counter_baselines: HashMap::new(),
+            gauge_values: HashMap::new(),
+            absolute_values: HashMap::new(),
+            last_seen: HashMap::new(),
+        }
+    }
+
+    fn record(&mut self, metric: Metric, now: Instant) {
+        let tags = match (&self.group_by, &metric.tags) {
+            (Some(group_by), Some(tags)) => Some(
+                tags.iter()
+                    .filter(|(k, _)| group_by.contains(*k))
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect::<BTreeMap<_, _>>(),
+            )
+            .filter(|tags| !tags.is_empty()),
+            (_, tags) => tags.clone(),
+        };
+        let key = MetricKey {
+            name: metric.name.clone(),
+            tags,
+            value_type: value_type(&metric.value),
+        };
+        self.last_seen.insert(key.clone(), now);
+
+        match metric.value {
+            MetricValue::Counter { value } => {
+                let delta = if metric.kind.is_absolute() {
+                    let series = (metric.name, metric.tags);
+                    match self
+                        .counter_baselines
+                        .insert(series, (value, now))
+                        .map(|(previous, _)| previous)
+                    {
+                        Some(previous) if value >= previous => value - previous,
+                        // The counter was reset.
+                        Some(_) => value,
+                        // The first value only sets the baseline.
+                        None => 0.0,
+                    }
+                } else {
+                    value
+                };
+                match self
+                    .aggregated
+                    .entry(key)
+                    .or_insert(Aggregated::Counter(0.0))
+                {
+                    Aggregated::Counter(total) => *total += delta,
+                    _ => unreachable!("counter keys only hold counters"),
+                }
+            }
+            MetricValue::Gauge { value } => {
+                let last = self.gauge_values.entry(key.clone()).or_insert(0.0);
+                if metric.kind.is_absolute() {
+                    *last = value;
+                } else {
+                    *last += value;
+                }
+                let value = *last;
+                match self.aggregated.entry(key) {
+                    Entry::Occupied(mut entry) => match entry.get_mut() {
+                        Aggregated::Gauge(stats) => stats.observe(value),
+                        _ => unreachable!("gauge keys only hold gauges"),
+                    },
+                    Entry::Vacant(entry) => {
+                        entry.insert(Aggregated::Gauge(GaugeStats::new(value)));
+                    }
+                }
+            }
+            value if metric.kind.is_absolute() => {
+                // Series grouped together would otherwise replace each
+                // other's values.
+                self.absolute_values
+                    .entry(key.clone())
+                    .or_insert_with(IndexMap::new)
+                    .insert((metric.name, metric.tags), (value, now));
+                self.aggregated.insert(key, Aggregated::Absolute);
+            }
+            value => match self.aggregated.entry(key) {
+                Entry::Occupied(mut entry) => match entry.get_mut() {
+                    Aggregated::Other(existing) => merge_value(existing, value),
+                    aggregated => *aggregated = Aggregated::Other(value),
+                },
+                Entry::Vacant(entry) => {
+                    entry.insert(Aggregated::Other(value));
+                }
+            },
+        }
+    }
+
+    fn flush<F>(&mut self, emit_fn: F)
+    where
+        F: FnMut(Event) -> (),
+    {
+        self.flush_at(Instant::now(), emit_fn);
+    }
+
+    fn flush_at<F>(&mut self, now: Instant, mut emit_fn: F)
+    where
+        F: FnMut(Event) -> (),
+    {
+        self.expire(now);
+
+        let timestamp = Some(Utc::now());
+        let absolute = self.output_kind.is_absolute();
+
+        for (key, aggregated) in self.aggregated.drain(..) {
+            let aggregated = match aggregated {
+                Aggregated::Absolute => {
+                    let merged = self.absolute_values.get(&key).and_then(merge_series);
+                    match merged {
+                        Some(value) => Aggregated::Other(value),
+                        None => continue,
+                    }
+                }
+                aggregated => aggregated,
+            };
+            let (kind, value) = match aggregated {
+                Aggregated::Counter(value) if absolute => {
+                    *self.counter_totals.entry(key).or_insert(0.0) += value;
+                    continue;
+                }
+                Aggregated::Counter(value) => {
+                    (MetricKind::Incremental, MetricValue::Counter { value })
+                }
+                Aggregated::Gauge(stats) => (
+                    MetricKind::Absolute,
+                    MetricValue::Gauge {
+                        value: stats.value(self.gauge_aggregation),
+                    },
+                ),
+                Aggregated::Other(value) => match (value, &self.quantiles) {
+                    // Summaries describe the interval, and are not merged
+                    // further downstream.
+                    (
+                        MetricValue::Distribution {
+                            values,
+                            sample_rates,
+                        },
+                        Some(quantiles),
+                    ) => (
+                        MetricKind::Absolute,
+                        summarize(quantiles, values, &sample_rates),
+                    ),
+                    (value, _) => (self.output_kind.clone(), value),
+                },
+                Aggregated::Absolute => unreachable!("absolute values are merged above"),
+            };
+            emit_fn(Event::Metric(Metric {
+                name: key.name,
+                timestamp,
+                tags: key.tags,
+                kind,
+                value,
+            }));
+        }
+
+        for (key, value) in &self.counter_totals {
+            emit_fn(Event::Metric(Metric {
+                name: key.name.clone(),
+                timestamp,
+                tags: key.tags.clone(),
+                kind: MetricKind::Absolute,
+                value: MetricValue::Counter { value: *value },
+            }));
+        }
+    }
+
+    /// Drops the state of series that weren't received for `expire_after`,
+    /// so that series which went away are no longer emitted or kept.
+    fn expire(&mut self, now: Instant) {
+        let expire_after = self.expire_after;
+        let expired = |last_seen: &Instant| {
+            now.checked_duration_since(*last_seen)
+                .map_or(false, |elapsed| elapsed >= expire_after)
+        };
+
+        let expired_keys = self
+            .last_seen
+            .iter()
+            .filter(|(_, last_seen)| expired(last_seen))
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        for key in expired_keys {
+            self.last_seen.remove(&key);
+            self.counter_totals.shift_remove(&key);
+            self.gauge_values.remove(&key);
+        }
+        self.counter_baselines
+            .retain(|_, (_, last_seen)| !expired(last_seen));
+        for series in self.absolute_values.values_mut() {
+            series.retain(|_, (_, last_seen)| !expired(last_seen));
+        }
+        self.absolute_values.retain(|_, series| !series.is_empty());
+    }
+}
+
+/// Summarizes the samples of a distribution received during an interval,
+/// picking the nearest sample for each quantile.
+fn summarize(quantiles: &[f64], values: Vec<f64>, sample_rates: &[u32]) -> MetricValue {
+    let mut samples = values
+        .into_iter()
+        .zip(sample_rates.iter().cloned())
+        .collect::<Vec<_>>();
+    samples.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+    let count = samples.iter().map(|(_, rate)| *rate).sum::<u32>();
+    let sum = samples
+        .iter()
+        .map(|(value, rate)| value * f64::from(*rate))
+        .sum();
+    let values = quantiles
+        .iter()
+        .map(|quantile| {
+            let rank = (quantile * f64::from(count)).ceil().max(1.0);
+            let mut seen = 0;
+            samples
+                .iter()
+                .find(|(_, rate)| {
+                    seen += rate;
+                    f64::from(seen) >= rank
+                })
+                .map_or(0.0, |(value, _)| *value)
+        })
+        .collect();
+
+    MetricValue::AggregatedSummary {
+        quantiles: quantiles.to_vec(),
+        values,
+        count,
+        sum,
+    }
+}
+
+fn value_type(value: &MetricValue) -> &'static str {
+    match value {
+        MetricValue::Counter { .. } => "counter",
+        MetricValue::Gauge { .. } => "gauge",
+        MetricValue::Set { .. } => "set",
+        MetricValue::Distribution { .. } => "distribution",
+        MetricValue::AggregatedHistogram { .. } => "aggregated_histogram",
+        MetricValue::AggregatedSummary { .. } => "aggregated_summary",
+    }
+}
+
+/// Merges the latest values of the series aggregated together.
+fn merge_series(series: &IndexMap<SeriesKey, (MetricValue, Instant)>) -> Option<MetricValue> {
+    let mut values = series.values().map(|(value, _)| value.clone());
+    let mut merged = values.next()?;
+    for value in values {
+        merge_value(&mut merged, value);
+    }
+    Some(merged)
+}
+
+/// Merges sets, distributions and histograms, values which can't be merged
+/// replace the existing one.
+fn merge_value(existing: &mut MetricValue, value: MetricValue) {
+    match (&mut *existing, &value) {
+        (MetricValue::Set { values }, MetricValue::Set { values: values2 }) => {
+            values.extend(values2.iter().cloned());
+            return;
+        }
+        (
+            MetricValue::Distribution {
+                values,
+                sample_rates,
+            },
+            MetricValue::Distribution {
+                values: values2,
+                sample_rates: sample_rates2,
+            },
+        ) => {
+            values.extend_from_slice(values2);
+            sample_rates.extend_from_slice(sample_rates2);
+            return;
+        }
+        (
+            MetricValue::AggregatedHistogram {
+                buckets,
+                counts,
+                count,
+                sum,
+            },
+            MetricValue::AggregatedHistogram {
+                buckets: buckets2,
+                counts: counts2,
+                count: count2,
+                sum: sum2,
+            },
+        ) if buckets == buckets2 && counts.len() == counts2.len() => {
+            for (c, c2) in counts.iter_mut().zip(counts2) {
+                *c += c2;
+            }
+            *count += count2;
+            *sum += sum2;
+            return;
+        }
+        _ => {}
+    }
+    *existing = value;
+}
+
+impl RuntimeTransform for Aggregate {
+    fn hook_process<F>(&mut self, event: Event, _emit_fn: F)
+    where
+        F: FnMut(Event) -> (),
+    {
+        self.record(event.into_metric(), Instant::now());
+    }
+
+    fn hook_shutdown<F>(&mut self, emit_fn: F)
+    where
+        F: FnMut(Event) -> (),
+    {
+        self.flush(emit_fn);
+    }
+
+    fn timer_handler<F>(&mut self, _timer: Timer, emit_fn: F)
+    where
+        F: FnMut(Event) -> (),
+    {
+        self.flush(emit_fn);
+    }
+
+    fn timers(&self) -> Vec<Timer> {
+        vec![Timer {
+            id: 0,
+            interval_seconds: self.interval_secs,
+        }]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_config(s: &str) -> AggregateConfig {
+        toml::from_str(s).unwrap()
+    }
+
+    fn metric(name: &str, tags: &[(&str, &str)], kind: MetricKind, value: MetricValue) -> Event {
+        Event::Metric(Metric {
+            name: name.into(),
+            timestamp: None,
+            tags: Some(
+                tags.iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect(),
+            ),
+            kind,
+            value,
+        })
+    }
+
+    fn flush(aggregate: &mut Aggregate) -> Vec<Metric> {
+        let mut out = Vec::new();
+        aggregate.flush(|event| out.push(event.into_metric()));
+        out.into_iter()
+            .map(|mut metric| {
+                assert!(metric.timestamp.is_some());
+                metric.timestamp = None;
+                metric
+            })
+            .collect()
+    }
+
+    fn tags(tags: &[(&str, &str)]) -> Option<BTreeMap<String, String>> {
+        Some(
+            tags.iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn aggregate_sums_counters_by_group() {
+        let mut aggregate = Aggregate::new(&parse_config(r#"group_by = ["region"]"#));
+
+        for (host, region, value) in &[
+            ("a", "us", 1.0),
+            ("b", "us", 2.0),
+            ("c", "eu", 5.0),
+            ("a", "us", 3.0),
+        ] {
+            aggregate.transform(metric(
+                "requests",
+                &[("host", host), ("region", region)],
+                MetricKind::Incremental,
+                MetricValue::Counter { value: *value },
+            ));
+        }
+
+        assert_eq!(
+            flush(&mut aggregate),
+            vec![
+                Metric {
+                    name: "requests".into(),
+                    timestamp: None,
+                    tags: tags(&[("region", "us")]),
+                    kind: MetricKind::Incremental,
+                    value: MetricValue::Counter { value: 6.0 },
+                },
+                Metric {
+                    name: "requests".into(),
+                    timestamp: None,
+                    tags: tags(&[("region", "eu")]),
+                    kind: MetricKind::Incremental,
+                    value: MetricValue::Counter { value: 5.0 },
+                },
+            ]
+        );
+        assert_eq!(flush(&mut aggregate), vec![]);
+    }
+
+    #[test]
+    fn aggregate_converts_absolute_counters() {
+        let mut aggregate = Aggregate::new(&parse_config(
+            r#"
+            group_by = []
+            output_kind = "absolute"
+            "#,
+        ));
+
+        for (host, value) in &[("a", 10.0), ("b", 100.0), ("a", 15.0), ("b", 120.0)] {
+            aggregate.transform(metric(
+                "requests",
+                &[("host", host)],
+                MetricKind::Absolute,
+                MetricValue::Counter { value: *value },
+            ));
+        }
+
+        let expected = |value| Metric {
+            name: "requests".into(),
+            timestamp: None,
+            tags: None,
+            kind: MetricKind::Absolute,
+            value: MetricValue::Counter { value },
+        };
+        assert_eq!(flush(&mut aggregate), vec![expected(25.0)]);
+
+        // Counter resets count from zero, totals are kept between flushes.
+        aggregate.transform(metric(
+            "requests",
+            &[("host", "a")],
+            MetricKind::Absolute,
+            MetricValue::Counter { value: 3.0 },
+        ));
+        assert_eq!(flush(&mut aggregate), vec![expected(28.0)]);
+        assert_eq!(flush(&mut aggregate), vec![expected(28.0)]);
+    }
+
+    #[test]
+    fn aggregate_expires_series() {
+        let mut aggregate = Aggregate::new(&parse_config(
+            r#"
+            output_kind = "absolute"
+            expire_after_secs = 60
+            "#,
+        ));
+        let record = |aggregate: &mut Aggregate, host: &str, value: f64, at: Instant| {
+            aggregate.record(
+                metric(
+                    "requests",
+                    &[("host", host)],
+                    MetricKind::Absolute,
+                    MetricValue::Counter { value },
+                )
+                .into_metric(),
+                at,
+            );
+            aggregate.record(
+                metric(
+                    "temperature",
+                    &[("host", host)],
+                    MetricKind::Incremental,
+                    MetricValue::Gauge { value },
+                )
+                .into_metric(),
+                at,
+            );
+        };
+        let flush_at = |aggregate: &mut Aggregate, at: Instant| {
+            let mut out = Vec::new();
+            aggregate.flush_at(at, |event| out.push(event.into_metric()));
+            out.into_iter()
+                .map(|metric| (metric.name, metric.tags.unwrap()["host"].clone()))
+                .collect::<Vec<(String, String)>>()
+        };
+        let start = Instant::now();
+        let after = |secs| start + Duration::from_secs(secs);
+
+        record(&mut aggregate, "a", 10.0, start);
+        record(&mut aggregate, "a", 15.0, start);
+        record(&mut aggregate, "b", 1.0, start);
+        assert_eq!(
+            flush_at(&mut aggregate, start),
+            vec![
+                ("temperature".into(), "a".into()),
+                ("temperature".into(), "b".into()),
+                ("requests".into(), "a".into()),
+                ("requests".into(), "b".into()),
+            ]
+        );
+
+        // Only `b` is emitted once `a` expired.
+        record(&mut aggregate, "b", 2.0, after(40));
+        assert_eq!(
+            flush_at(&mut aggregate, after(70)),
+            vec![
+                ("temperature".into(), "b".into()),
+                ("requests".into(), "b".into()),
+            ]
+        );
+        assert_eq!(aggregate.counter_totals.len(), 1);
+        assert_eq!(aggregate.counter_baselines.len(), 1);
+        assert_eq!(aggregate.gauge_values.len(), 1);
+
+        // Expired series start over.
+        record(&mut aggregate, "a", 20.0, after(80));
+        let incoming = aggregate
+            .aggregated
+            .values()
+            .filter_map(|aggregated| match aggregated {
+                Aggregated::Counter(value) => Some(*value),
+                Aggregated::Gauge(stats) => Some(stats.last),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(incoming, vec![0.0, 20.0]);
+    }
+
+    #[test]
+    fn aggregate_gauges() {
+        for (aggregation, expected) in &[("last", 4.0), ("min", 1.0), ("max", 6.0), ("avg", 3.5)] {
+            let mut aggregate = Aggregate::new(&parse_config(&format!(
+                "gauge_aggregation = \"{}\"",
+                aggregation
+            )));
+
+            aggregate.transform(metric(
+                "temperature",
+                &[],
+                MetricKind::Absolute,
+                MetricValue::Gauge { value: 3.0 },
+            ));
+            for value in &[-2.0, 5.0, -2.0] {
+                aggregate.transform(metric(
+                    "temperature",
+                    &[],
+                    MetricKind::Incremental,
+                    MetricValue::Gauge { value: *value },
+                ));
+            }
+
+            assert_eq!(
+                flush(&mut aggregate),
+                vec![Metric {
+                    name: "temperature".into(),
+                    timestamp: None,
+                    tags: tags(&[]),
+                    kind: MetricKind::Absolute,
+                    value: MetricValue::Gauge { value: *expected },
+                }],
+                "{}",
+                aggregation
+            );
+        }
+    }
+
+    #[test]
+    fn aggregate_merges_sets_and_distributions() {
+        let mut aggregate = Aggregate::new(&parse_config(""));
+
+        for values in &[vec!["a", "b"], vec!["b", "c"]] {
+            aggregate.transform(metric(
+                "users",
+                &[],
+                MetricKind::Incremental,
+                MetricValue::Set {
+                    values: values.iter().map(|v| v.to_string()).collect(),
+                },
+            ));
+        }
+        for (values, sample_rates) in &[(vec![1.0, 2.0], vec![1, 1]), (vec![3.0], vec![10])] {
+            aggregate.transform(metric(
+                "latency",
+                &[],
+                MetricKind::Incremental,
+                MetricValue::Distribution {
+                    values: values.clone(),
+                    sample_rates: sample_rates.clone(),
+                },
+            ));
+        }
+
+        assert_eq!(
+            flush(&mut aggregate),
+            vec![
+                Metric {
+                    name: "users".into(),
+                    timestamp: None,
+                    tags: tags(&[]),
+                    kind: MetricKind::Incremental,
+                    value: MetricValue::Set {
+                        values: vec!["a", "b", "c"].into_iter().map(Into::into).collect(),
+                    },
+                },
+                Metric {
+                    name: "latency".into(),
+                    timestamp: None,
+                    tags: tags(&[]),
+                    kind: MetricKind::Incremental,
+                    value: MetricValue::Distribution {
+                        values: vec![1.0, 2.0, 3.0],
+                        sample_rates: vec![1, 1, 10],
+                    },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn aggregate_merges_absolute_sets_by_series() {
+        let mut aggregate = Aggregate::new(&parse_config(
+            r#"
+            group_by = ["region"]
+            output_kind = "absolute"
+            "#,
+        ));
+        let record = |aggregate: &mut Aggregate, host: &str, values: &[&str]| {
+            aggregate.transform(metric(
+                "users",
+                &[("host", host), ("region", "us")],
+                MetricKind::Absolute,
+                MetricValue::Set {
+                    values: values.iter().map(|v| v.to_string()).collect(),
+                },
+            ));
+        };
+        let expected = |values: &[&str]| {
+            vec![Metric {
+                name: "users".into(),
+                timestamp: None,
+                tags: tags(&[("region", "us")]),
+                kind: MetricKind::Absolute,
+                value: MetricValue::Set {
+                    values: values.iter().map(|v| v.to_string()).collect(),
+                },
+            }]
+        };
+
+        record(&mut aggregate, "a", &["x", "y"]);
+        record(&mut aggregate, "b", &["y", "z"]);
+        // Replaces the previous value of `a`.
+        record(&mut aggregate, "a", &["x"]);
+        assert_eq!(flush(&mut aggregate), expected(&["x", "y", "z"]));
+
+        // The latest value of `a` still counts when only `b` is received.
+        record(&mut aggregate, "b", &["w"]);
+        assert_eq!(flush(&mut aggregate), expected(&["w", "x"]));
+    }
+
+    #[test]
+    fn aggregate_summarizes_distributions() {
+        let mut aggregate = Aggregate::new(&parse_config(
+            r#"
+            group_by = []
+            quantiles = [0.0, 0.5, 0.9]
+            "#,
+        ));
+
+        for (host, values, sample_rates) in &[
+            ("a", vec![4.0, 2.0, 1.0, 3.0], vec![1, 1, 1, 1]),
+            ("b", vec![10.0], vec![4]),
+        ] {
+            aggregate.transform(metric(
+                "latency",
+                &[("host", host)],
+                MetricKind::Incremental,
+                MetricValue::Distribution {
+                    values: values.clone(),
+                    sample_rates: sample_rates.clone(),
+                },
+            ));
+        }
+
+        assert_eq!(
+            flush(&mut aggregate),
+            vec![Metric {
+                name: "latency".into(),
+                timestamp: None,
+                tags: None,
+                kind: MetricKind::Absolute,
+                value: MetricValue::AggregatedSummary {
+                    quantiles: vec![0.0, 0.5, 0.9],
+                    values: vec![1.0, 4.0, 10.0],
+                    count: 8,
+                    sum: 50.0,
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn aggregate_rejects_invalid_quantiles() {
+        let rt = crate::runtime::Runtime::single_threaded().unwrap();
+        for quantiles in &["[]", "[0.5, 1.5]"] {
+            let config = parse_config(&format!("quantiles = {}", quantiles));
+            assert!(config
+                .build(TransformContext::new_test(rt.executor()))
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn aggregate_keeps_value_types_apart() {
+        let mut aggregate = Aggregate::new(&parse_config(""));
+
+        aggregate.transform(metric(
+            "mixed",
+            &[],
+            MetricKind::Incremental,
+            MetricValue::Counter { value: 1.0 },
+        ));
+        aggregate.transform(metric(
+            "mixed",
+            &[],
+            MetricKind::Absolute,
+            MetricValue::Gauge { value: 2.0 },
+        ));
+
+        assert_eq!(flush(&mut aggregate).len(), 2);
+    }
+}
@@ -9,7 +9,7 @@ use futures::compat::Future01CompatExt;
 use futures01::Stream;
 use http::{uri::PathAndQuery, Request, StatusCode, Uri};
 use hyper::Body;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::collections::{hash_map::RandomState, HashSet};
 use std::time::{Duration, Instant};
 use string_cache::DefaultAtom as Atom;
@@ -56,6 +56,9 @@ lazy_static::lazy_static! {
     static ref ROLE_NAME: PathAndQuery = PathAndQuery::from_static("/latest/meta-data/iam/security-credentials/");
     static ref ROLE_NAME_KEY: Atom = Atom::from("role-name");
 
+    static ref TAGS: PathAndQuery = PathAndQuery::from_static("/latest/meta-data/tags/instance");
+    static ref TAGS_KEY: Atom = Atom::from("tags");
+
     static ref MAC: PathAndQuery = PathAndQuery::from_static("/latest/meta-data/mac");
 
     static ref DYNAMIC_DOCUMENT: PathAndQuery = PathAndQuery::from_static("/latest/dynamic/instance-identity/document");
@@ -75,17 +78,60 @@ lazy_static::lazy_static! {
         ROLE_NAME_KEY.clone(),
     ];
 
+    static ref CLUSTER_KEY: Atom = Atom::from("cluster");
+    static ref TASK_ARN_KEY: Atom = Atom::from("task-arn");
+    static ref FAMILY_KEY: Atom = Atom::from("family");
+    static ref REVISION_KEY: Atom = Atom::from("revision");
+    static ref CONTAINER_NAME_KEY: Atom = Atom::from("container-name");
+
+    static ref DEFAULT_ECS_FIELD_WHITELIST: Vec<Atom> = vec![
+        CLUSTER_KEY.clone(),
+        TASK_ARN_KEY.clone(),
+        FAMILY_KEY.clone(),
+        REVISION_KEY.clone(),
+        CONTAINER_NAME_KEY.clone(),
+    ];
+
     static ref API_TOKEN: PathAndQuery = PathAndQuery::from_static("/latest/api/token");
     static ref TOKEN_HEADER: Bytes = Bytes::from("X-aws-ec2-metadata-token");
     static ref HOST: Uri = Uri::from_static("http://169.254.169.254");
 }
 
+/// The maximum lifetime IMDSv2 accepts for session tokens.
+const MAX_TOKEN_TTL_SECS: u64 = 21600;
+
+/// The environment variables the ECS agent sets to the container metadata
+/// endpoint, in order of preference.
+const ECS_METADATA_URI_ENV_VARS: [&str; 2] = [
+    "ECS_CONTAINER_METADATA_URI_V4",
+    "ECS_CONTAINER_METADATA_URI",
+];
+
 #[derive(Default, Debug, Serialize, Deserialize)]
 pub struct Ec2Metadata {
     host: Option<String>,
     namespace: Option<String>,
     refresh_interval_secs: Option<u64>,
     fields: Option<Vec<String>>,
+    #[serde(default)]
+    mode: Mode,
+    token_ttl_secs: Option<u64>,
+}
+
+/// Where the metadata is read from.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Mode {
+    /// The EC2 instance metadata service.
+    Ec2,
+    /// The ECS task metadata endpoint, versions 3 and 4.
+    Ecs,
+}
+
+impl Default for Mode {
+    fn default() -> Self {
+        Mode::Ec2
+    }
 }
 
 pub struct Ec2MetadataTransform {
@@ -106,6 +152,12 @@ struct Keys {
     subnet_id_key: Atom,
     vpc_id_key: Atom,
     role_name_key: Atom,
+    tags_key: Atom,
+    cluster_key: Atom,
+    task_arn_key: Atom,
+    family_key: Atom,
+    revision_key: Atom,
+    container_name_key: Atom,
 }
 
 inventory::submit! {
@@ -129,11 +181,21 @@ impl TransformConfig for Ec2Metadata {
 
         let keys = Keys::new(&namespace);
 
-        let host = self
-            .host
-            .clone()
-            .map(|s| Uri::from_shared(s.into()).unwrap())
-            .unwrap_or(HOST.clone());
+        let host = match (&self.host, self.mode) {
+            (Some(host), _) => Uri::from_shared(host.clone().into()).unwrap(),
+            (None, Mode::Ec2) => HOST.clone(),
+            (None, Mode::Ecs) => ECS_METADATA_URI_ENV_VARS
+                .iter()
+                .find_map(|var| std::env::var(var).ok())
+                .ok_or(Ec2MetadataError::MissingEcsEndpoint)?
+                .parse()?,
+        };
+
+        let token_ttl = self.token_ttl_secs.unwrap_or(MAX_TOKEN_TTL_SECS);
+        if token_ttl == 0 || token_ttl > MAX_TOKEN_TTL_SECS {
+            return Err(Ec2MetadataError::InvalidTokenTtl.into());
+        }
+        let token_ttl = Duration::from_secs(token_ttl);
 
         let refresh_interval = self
             .refresh_interval_secs
@@ -143,14 +205,26 @@ impl TransformConfig for Ec2Metadata {
             .fields
             .clone()
             .map(|v| v.into_iter().map(Atom::from).collect())
-            .unwrap_or_else(|| DEFAULT_FIELD_WHITELIST.clone());
+            .unwrap_or_else(|| match self.mode {
+                Mode::Ec2 => DEFAULT_FIELD_WHITELIST.clone(),
+                Mode::Ecs => DEFAULT_ECS_FIELD_WHITELIST.clone(),
+            });
+        let mode = self.mode;
 
         let http_client = HttpClient::new(cx.resolver(), None)?;
 
         cx.executor().spawn_std(
             async move {
-                let mut client =
-                    MetadataClient::new(http_client, host, keys, write, refresh_interval, fields);
+                let mut client = MetadataClient::new(
+                    http_client,
+                    host,
+                    mode,
+                    token_ttl,
+                    keys,
+                    write,
+                    refresh_interval,
+                    fields,
+                );
 
                 client.run().await;
             }
@@ -191,11 +265,15 @@ impl Transform for Ec2MetadataTransform {
 struct MetadataClient {
     client: HttpClient<Body>,
     host: Uri,
+    mode: Mode,
     token: Option<(Bytes, Instant)>,
+    token_ttl: Duration,
     keys: Keys,
     state: WriteHandle,
     refresh_interval: Duration,
     fields: HashSet<Atom>,
+    /// The tag keys set by the last refresh, to remove tags that were deleted.
+    tags: HashSet<Atom>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -211,10 +289,29 @@ struct IdentityDocument {
     version: String,
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct EcsTask {
+    cluster: String,
+    #[serde(rename = "TaskARN")]
+    task_arn: String,
+    family: String,
+    revision: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct EcsContainer {
+    name: String,
+}
+
 impl MetadataClient {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         client: HttpClient<Body>,
         host: Uri,
+        mode: Mode,
+        token_ttl: Duration,
         keys: Keys,
         state: WriteHandle,
         refresh_interval: Duration,
@@ -223,18 +320,25 @@ impl MetadataClient {
         Self {
             client,
             host,
+            mode,
             token: None,
+            token_ttl,
             keys,
             state,
             refresh_interval,
             fields: fields.into_iter().collect(),
+            tags: HashSet::new(),
         }
     }
 
     async fn run(&mut self) {
         loop {
-            if let Err(error) = self.refresh_metadata().await {
-                error!(message="Unable to fetch EC2 metadata; Retrying.", %error);
+            let result = match self.mode {
+                Mode::Ec2 => self.refresh_metadata().await,
+                Mode::Ecs => self.refresh_ecs_metadata().await,
+            };
+            if let Err(error) = result {
+                error!(message="Unable to fetch metadata; Retrying.", mode = ?self.mode, %error);
 
                 Delay::new(Instant::now() + Duration::from_secs(1))
                     .compat()
@@ -265,7 +369,10 @@ impl MetadataClient {
         let uri = Uri::from_parts(parts)?;
 
         let req = Request::put(uri)
-            .header("X-aws-ec2-metadata-token-ttl-seconds", "21600")
+            .header(
+                "X-aws-ec2-metadata-token-ttl-seconds",
+                self.token_ttl.as_secs().to_string(),
+            )
             .body(Body::empty())?;
 
         let res = self.client.send(req).await?;
@@ -277,7 +384,8 @@ impl MetadataClient {
         let body = res.into_body().concat2().compat().await?;
         let token = body.into_bytes();
 
-        let next_refresh = Instant::now() + Duration::from_secs(21600);
+        // Refresh ahead of the expiry, so requests never carry an expired token.
+        let next_refresh = Instant::now() + self.token_ttl * 9 / 10;
         self.token = Some((token.clone(), next_refresh));
 
         Ok(token)
@@ -298,6 +406,7 @@ impl MetadataClient {
 
         if res.status() != StatusCode::OK {
             warn!(message="Identity document request failed.", status = %res.status());
+            self.check_token(res.status());
             return Ok(None);
         }
 
@@ -308,6 +417,14 @@ impl MetadataClient {
             .map(Some)
     }
 
+    /// Drops a token the metadata service no longer accepts, so the next
+    /// refresh requests a new one.
+    fn check_token(&mut self, status: StatusCode) {
+        if status == StatusCode::UNAUTHORIZED {
+            self.token = None;
+        }
+    }
+
     pub async fn get_metadata(
         &mut self,
         path: &PathAndQuery,
@@ -330,6 +447,7 @@ impl MetadataClient {
 
         if StatusCode::OK != res.status() {
             warn!(message="Metadata request failed.", status = %res.status());
+            self.check_token(res.status());
             return Ok(None);
         }
 
@@ -443,6 +561,92 @@ impl MetadataClient {
             }
         }
 
+        if self.fields.contains(&TAGS_KEY) {
+            // Instance tags are only available if enabled for the instance.
+            if let Some(tag_keys) = self.get_metadata(&TAGS).await? {
+                let tag_keys = String::from_utf8_lossy(&tag_keys[..]).into_owned();
+                let mut tags = HashSet::new();
+
+                for tag_key in tag_keys.lines() {
+                    let path = format!("/latest/meta-data/tags/instance/{}", tag_key).parse()?;
+                    if let Some(value) = self.get_metadata(&path).await? {
+                        let key = Atom::from(format!("{}.{}", self.keys.tags_key, tag_key));
+                        self.state.update(key.clone(), value);
+                        tags.insert(key);
+                    }
+                }
+
+                for key in self.tags.difference(&tags) {
+                    self.state.empty(key.clone());
+                }
+                self.tags = tags;
+            }
+        }
+
+        // Make changes viewable to the transform. This may block if
+        // readers are still reading.
+        self.state.refresh();
+
+        Ok(())
+    }
+
+    async fn get_ecs_document<T: DeserializeOwned>(
+        &mut self,
+        path: &str,
+    ) -> Result<Option<T>, crate::Error> {
+        // The endpoint is specific to the container, so paths are appended
+        // to it rather than replacing its path.
+        let uri: Uri =
+            format!("{}{}", self.host.to_string().trim_end_matches('/'), path).parse()?;
+
+        debug!(message = "Sending ECS metadata request.", %uri);
+
+        let req = Request::get(uri).body(Body::empty())?;
+
+        let res = self.client.send(req).await?;
+
+        if StatusCode::OK != res.status() {
+            warn!(message="ECS metadata request failed.", status = %res.status());
+            return Ok(None);
+        }
+
+        let body = res.into_body().concat2().compat().await?;
+
+        serde_json::from_slice(&body[..])
+            .map_err(Into::into)
+            .map(Some)
+    }
+
+    pub async fn refresh_ecs_metadata(&mut self) -> Result<(), crate::Error> {
+        if let Some(task) = self.get_ecs_document::<EcsTask>("/task").await? {
+            if self.fields.contains(&CLUSTER_KEY) {
+                self.state
+                    .update(self.keys.cluster_key.clone(), task.cluster.into());
+            }
+
+            if self.fields.contains(&TASK_ARN_KEY) {
+                self.state
+                    .update(self.keys.task_arn_key.clone(), task.task_arn.into());
+            }
+
+            if self.fields.contains(&FAMILY_KEY) {
+                self.state
+                    .update(self.keys.family_key.clone(), task.family.into());
+            }
+
+            if self.fields.contains(&REVISION_KEY) {
+                self.state
+                    .update(self.keys.revision_key.clone(), task.revision.into());
+            }
+        }
+
+        if self.fields.contains(&CONTAINER_NAME_KEY) {
+            if let Some(container) = self.get_ecs_document::<EcsContainer>("").await? {
+                self.state
+                    .update(self.keys.container_name_key.clone(), container.name.into());
+            }
+        }
+
         // Make changes viewable to the transform. This may block if
         // readers are still reading.
         self.state.refresh();
@@ -468,7 +672,13 @@ impl Keys {
                 region_key: format!("{}.{}", namespace, REGION_KEY.clone()).into(),
                 subnet_id_key: format!("{}.{}", namespace, SUBNET_ID_KEY.clone()).into(),
                 vpc_id_key: format!("{}.{}", namespace, VPC_ID_KEY.clone()).into(),
-                role_name_key: format!("{}.{}", namespace, VPC_ID_KEY.clone()).into(),
+                role_name_key: format!("{}.{}", namespace, ROLE_NAME_KEY.clone()).into(),
+                tags_key: format!("{}.{}", namespace, TAGS_KEY.clone()).into(),
+                cluster_key: format!("{}.{}", namespace, CLUSTER_KEY.clone()).into(),
+                task_arn_key: format!("{}.{}", namespace, TASK_ARN_KEY.clone()).into(),
+                family_key: format!("{}.{}", namespace, FAMILY_KEY.clone()).into(),
+                revision_key: format!("{}.{}", namespace, REVISION_KEY.clone()).into(),
+                container_name_key: format!("{}.{}", namespace, CONTAINER_NAME_KEY.clone()).into(),
             }
         } else {
             Keys {
@@ -484,6 +694,12 @@ impl Keys {
                 subnet_id_key: SUBNET_ID_KEY.clone(),
                 vpc_id_key: VPC_ID_KEY.clone(),
                 role_name_key: ROLE_NAME_KEY.clone(),
+                tags_key: TAGS_KEY.clone(),
+                cluster_key: CLUSTER_KEY.clone(),
+                task_arn_key: TASK_ARN_KEY.clone(),
+                family_key: FAMILY_KEY.clone(),
+                revision_key: REVISION_KEY.clone(),
+                container_name_key: CONTAINER_NAME_KEY.clone(),
             }
         }
     }
@@ -493,6 +709,160 @@ impl Keys {
 enum Ec2MetadataError {
     #[snafu(display("Unable to fetch token."))]
     UnableToFetchToken,
+    #[snafu(display("No ECS metadata endpoint found, `host` must be set outside of ECS tasks."))]
+    MissingEcsEndpoint,
+    #[snafu(display("`token_ttl_secs` must be between 1 and {}.", MAX_TOKEN_TTL_SECS))]
+    InvalidTokenTtl,
+}
+
+#[cfg(test)]
+mod local_tests {
+    use super::*;
+    use crate::{
+        event::Event,
+        test_util::{next_addr, runtime},
+    };
+    use futures01::Future;
+    use hyper::service::{make_service_fn, service_fn_ok};
+    use hyper::{Response, Server};
+    use std::net::SocketAddr;
+
+    /// Serves the given paths, requiring a session token like IMDSv2.
+    fn serve(
+        rt: &mut crate::runtime::Runtime,
+        routes: &'static [(&'static str, &'static str)],
+    ) -> SocketAddr {
+        let addr = next_addr();
+        let make_svc = make_service_fn(move |_| {
+            service_fn_ok(move |req: Request<Body>| {
+                if req.uri().path() == "/latest/api/token" {
+                    assert_eq!(req.method(), http::Method::PUT);
+                    assert_eq!(req.headers()["X-aws-ec2-metadata-token-ttl-seconds"], "60");
+                    return Response::new(Body::from("mock-token"));
+                }
+
+                let ecs = req.uri().path().starts_with("/v4/");
+                let token = req.headers().get("X-aws-ec2-metadata-token");
+                if !ecs && token.map_or(true, |token| token != "mock-token") {
+                    return Response::builder()
+                        .status(StatusCode::UNAUTHORIZED)
+                        .body(Body::empty())
+                        .unwrap();
+                }
+
+                match routes.iter().find(|(path, _)| *path == req.uri().path()) {
+                    Some((_, body)) => Response::new(Body::from(*body)),
+                    None => Response::builder()
+                        .status(StatusCode::NOT_FOUND)
+                        .body(Body::empty())
+                        .unwrap(),
+                }
+            })
+        });
+
+        rt.spawn(Server::bind(&addr).serve(make_svc).map_err(|error| {
+            error!("server error: {:?}", error);
+        }));
+        addr
+    }
+
+    fn fetch(rt: &mut crate::runtime::Runtime, config: Ec2Metadata) -> Event {
+        let mut transform = config
+            .build(TransformContext::new_test(rt.executor()))
+            .unwrap();
+
+        // We need to sleep to let the background task fetch the data.
+        std::thread::sleep(std::time::Duration::from_secs(1));
+
+        transform.transform(Event::new_empty_log()).unwrap()
+    }
+
+    #[test]
+    fn ec2_token_and_tags() {
+        let mut rt = runtime();
+        let addr = serve(
+            &mut rt,
+            &[
+                (
+                    "/latest/dynamic/instance-identity/document",
+                    r#"{"accountId":"0123","architecture":"x86_64","imageId":"ami-1","instanceId":"i-1","instanceType":"m5.large","privateIp":"10.0.0.1","region":"us-east-1","version":"2017-09-30"}"#,
+                ),
+                ("/latest/meta-data/tags/instance", "Name\nteam"),
+                ("/latest/meta-data/tags/instance/Name", "web-1"),
+                ("/latest/meta-data/tags/instance/team", "core"),
+            ],
+        );
+
+        let event = fetch(
+            &mut rt,
+            Ec2Metadata {
+                host: Some(format!("http://{}", addr)),
+                namespace: Some("ec2".into()),
+                fields: Some(vec!["instance-id".into(), "region".into(), "tags".into()]),
+                token_ttl_secs: Some(60),
+                ..Default::default()
+            },
+        );
+        let log = event.as_log();
+
+        assert_eq!(log.get(&"ec2.instance-id".into()), Some(&"i-1".into()));
+        assert_eq!(log.get(&"ec2.region".into()), Some(&"us-east-1".into()));
+        assert_eq!(log.get(&"ec2.tags.Name".into()), Some(&"web-1".into()));
+        assert_eq!(log.get(&"ec2.tags.team".into()), Some(&"core".into()));
+        assert_eq!(log.get(&"ec2.ami-id".into()), None);
+    }
+
+    #[test]
+    fn ecs_task_metadata() {
+        let mut rt = runtime();
+        let addr = serve(
+            &mut rt,
+            &[
+                (
+                    "/v4/container-1/task",
+                    r#"{"Cluster":"prod","TaskARN":"arn:aws:ecs:us-east-1:0123:task/prod/abc","Family":"api","Revision":"7","Containers":[]}"#,
+                ),
+                (
+                    "/v4/container-1",
+                    r#"{"DockerId":"abc","Name":"api-server","Image":"api:latest"}"#,
+                ),
+            ],
+        );
+
+        let event = fetch(
+            &mut rt,
+            Ec2Metadata {
+                host: Some(format!("http://{}/v4/container-1", addr)),
+                mode: Mode::Ecs,
+                ..Default::default()
+            },
+        );
+        let log = event.as_log();
+
+        assert_eq!(log.get(&"cluster".into()), Some(&"prod".into()));
+        assert_eq!(
+            log.get(&"task-arn".into()),
+            Some(&"arn:aws:ecs:us-east-1:0123:task/prod/abc".into())
+        );
+        assert_eq!(log.get(&"family".into()), Some(&"api".into()));
+        assert_eq!(log.get(&"revision".into()), Some(&"7".into()));
+        assert_eq!(
+            log.get(&"container-name".into()),
+            Some(&"api-server".into())
+        );
+    }
+
+    #[test]
+    fn invalid_token_ttl() {
+        let rt = runtime();
+        let config = Ec2Metadata {
+            token_ttl_secs: Some(MAX_TOKEN_TTL_SECS + 1),
+            ..Default::default()
+        };
+        assert!(config
+            .build(TransformContext::new_test(rt.executor()))
+            .is_err());
+    }
 }
 
 #[cfg(test)]
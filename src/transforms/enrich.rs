@@ -0,0 +1,232 @@
+use super::Transform;
+use crate::{
+    enrichment_tables::{EnrichmentTable, Matching},
+    event::Event,
+    topology::config::{DataType, TransformConfig, TransformContext, TransformDescription},
+};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use string_cache::DefaultAtom as Atom;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct EnrichConfig {
+    pub table: String,
+    pub lookup: IndexMap<String, Atom>,
+    #[serde(default)]
+    pub matching: Matching,
+    pub target: Option<Atom>,
+    #[serde(default)]
+    pub on_miss: MissPolicy,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MissPolicy {
+    Pass,
+    Drop,
+}
+
+impl Default for MissPolicy {
+    fn default() -> Self {
+        MissPolicy::Pass
+    }
+}
+
+inventory::submit! {
+    TransformDescription::new_without_default::<EnrichConfig>("enrich")
+}
+
+#[typetag::serde(name = "enrich")]
+impl TransformConfig for EnrichConfig {
+    fn build(&self, cx: TransformContext) -> crate::Result<Box<dyn Transform>> {
+        if self.lookup.is_empty() {
+            return Err("lookup must contain at least one column".into());
+        }
+        let table = cx
+            .enrichment_table(&self.table)
+            .ok_or_else(|| format!("Unknown enrichment table \"{}\"", self.table))?;
+        Ok(Box::new(Enrich::new(self, table)))
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn transform_type(&self) -> &'static str {
+        "enrich"
+    }
+
+    fn enrichment_tables(&self) -> Vec<String> {
+        vec![self.table.clone()]
+    }
+}
+
+pub struct Enrich {
+    table: EnrichmentTable,
+    lookup: Vec<(String, Atom)>,
+    matching: Matching,
+    target: Option<Atom>,
+    on_miss: MissPolicy,
+}
+
+impl Enrich {
+    pub fn new(config: &EnrichConfig, table: EnrichmentTable) -> Self {
+        Self {
+            table,
+            lookup: config
+                .lookup
+                .iter()
+                .map(|(column, field)| (column.clone(), field.clone()))
+                .collect(),
+            matching: config.matching,
+            target: config.target.clone(),
+            on_miss: config.on_miss,
+        }
+    }
+}
+
+impl Transform for Enrich {
+    fn transform(&mut self, mut event: Event) -> Option<Event> {
+        let log = event.as_mut_log();
+
+        let criteria = self
+            .lookup
+            .iter()
+            .map(|(column, field)| {
+                log.get(field)
+                    .map(|value| (column.as_str(), value.to_string_lossy()))
+            })
+            .collect::<Option<Vec<_>>>();
+
+        match criteria.and_then(|criteria| self.table.find(&criteria, self.matching)) {
+            Some(row) => {
+                for (column, value) in row {
+                    let key = match &self.target {
+                        Some(target) => Atom::from(format!("{}.{}", target, column)),
+                        None => Atom::from(column),
+                    };
+                    log.insert(key, value);
+                }
+            }
+            None => {
+                debug!(
+                    message = "No matching row in enrichment table.",
+                    table = %self.table.name(),
+                    rate_limit_secs = 30
+                );
+                if self.on_miss == MissPolicy::Drop {
+                    return None;
+                }
+            }
+        }
+
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        enrichment_tables::{Encoding, EnrichmentTableConfig},
+        event::Value,
+        test_util::temp_file,
+    };
+
+    fn build(config: &str) -> Enrich {
+        let path = temp_file();
+        std::fs::write(
+            &path,
+            "user_id,user_name,team\n1,alice,Platform\n2,bob,Security\n",
+        )
+        .unwrap();
+        let table = EnrichmentTable::load(
+            "users",
+            &EnrichmentTableConfig {
+                path,
+                encoding: Encoding::Csv,
+                index: vec!["user_id".into()],
+            },
+        )
+        .unwrap();
+
+        let config: EnrichConfig = toml::from_str(config).unwrap();
+        Enrich::new(&config, table)
+    }
+
+    fn event(fields: Vec<(&str, &str)>) -> Event {
+        let mut event = Event::new_empty_log();
+        for (key, value) in fields {
+            event.as_mut_log().insert(key, value);
+        }
+        event
+    }
+
+    #[test]
+    fn enrich_matching_row() {
+        let mut transform = build(
+            r#"
+            table = "users"
+            lookup.user_id = "user.id"
+            "#,
+        );
+
+        let event = transform
+            .transform(event(vec![("user.id", "2"), ("message", "logged in")]))
+            .unwrap();
+        let log = event.as_log();
+        assert_eq!(log[&"user_name".into()], Value::from("bob"));
+        assert_eq!(log[&"team".into()], Value::from("Security"));
+        assert_eq!(log[&"message".into()], Value::from("logged in"));
+    }
+
+    #[test]
+    fn enrich_into_target_with_case_insensitive_matching() {
+        let mut transform = build(
+            r#"
+            table = "users"
+            lookup.user_name = "name"
+            matching = "case_insensitive"
+            target = "user"
+            "#,
+        );
+
+        let event = transform.transform(event(vec![("name", "ALICE")])).unwrap();
+        let log = event.as_log();
+        assert_eq!(log[&"user.user_id".into()], Value::from("1"));
+        assert_eq!(log[&"user.team".into()], Value::from("Platform"));
+        assert!(log.get(&"team".into()).is_none());
+    }
+
+    #[test]
+    fn enrich_miss_policies() {
+        let mut transform = build(
+            r#"
+            table = "users"
+            lookup.user_id = "user_id"
+            "#,
+        );
+        let original = event(vec![("user_id", "3")]);
+        assert_eq!(transform.transform(original.clone()), Some(original));
+        let missing_field = event(vec![("message", "no user")]);
+        assert_eq!(
+            transform.transform(missing_field.clone()),
+            Some(missing_field)
+        );
+
+        let mut transform = build(
+            r#"
+            table = "users"
+            lookup.user_id = "user_id"
+            on_miss = "drop"
+            "#,
+        );
+        assert!(transform.transform(event(vec![("user_id", "3")])).is_none());
+        assert!(transform.transform(event(vec![("user_id", "1")])).is_some());
+    }
+}
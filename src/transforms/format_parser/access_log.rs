@@ -0,0 +1,226 @@
+//! Parsers for the access and error logs of web servers.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// The Common Log Format shared by Apache and Nginx. The client address and
+/// request time are not named `host` and `timestamp`, so they don't replace the
+/// fields of the log schema.
+const COMMON_PATTERN: &str = r#"^(?P<client>\S+) (?P<ident>\S+) (?P<user>\S+) \[(?P<time>[^\]]+)\] "(?P<request>(?:[^"\\]|\\.)*)" (?P<status>\d{3}|-) (?P<size>\d+|-)"#;
+
+lazy_static! {
+    static ref COMMON: Regex = Regex::new(&format!(r"{}\s*$", COMMON_PATTERN)).unwrap();
+    static ref COMBINED: Regex = Regex::new(&format!(
+        r#"{} "(?P<referrer>(?:[^"\\]|\\.)*)" "(?P<agent>(?:[^"\\]|\\.)*)"\s*$"#,
+        COMMON_PATTERN
+    ))
+    .unwrap();
+    static ref NGINX_ERROR: Regex = Regex::new(
+        r"^(?P<time>\d{4}/\d{2}/\d{2} \d{2}:\d{2}:\d{2}) \[(?P<severity>\w+)\] (?P<pid>\d+)#(?P<tid>\d+):(?: \*(?P<connid>\d+))? (?P<message>.*)$"
+    )
+    .unwrap();
+    /// The context Nginx appends to error messages. Its `host` is the Host
+    /// header of the request, kept as `http_host` like the Nginx variable.
+    static ref NGINX_ERROR_CONTEXT: Regex = Regex::new(
+        r#", (?P<key>client|server|request|upstream|host|referrer): (?:"(?P<quoted>(?:[^"\\]|\\.)*)"|(?P<value>[^,]*))"#
+    )
+    .unwrap();
+}
+
+/// Reverts the escaping of quotes and backslashes in quoted values.
+fn unescape(s: &str) -> String {
+    s.replace(r#"\""#, "\"").replace(r"\\", r"\")
+}
+
+/// Collects the named captures of a regex, leaving out the `-` placeholder of
+/// missing values.
+fn captures(regex: &Regex, line: &str) -> Option<Vec<(String, String)>> {
+    let captures = regex.captures(line)?;
+    Some(
+        regex
+            .capture_names()
+            .flatten()
+            .filter_map(|name| {
+                captures
+                    .name(name)
+                    .map(|value| value.as_str())
+                    .filter(|value| *value != "-")
+                    .map(|value| (name.to_owned(), unescape(value)))
+            })
+            .collect(),
+    )
+}
+
+/// Splits the request line into its method, path and protocol.
+fn split_request(fields: &mut Vec<(String, String)>) {
+    let request = fields
+        .iter()
+        .find(|(name, _)| name == "request")
+        .map(|(_, request)| request.split(' ').map(String::from).collect::<Vec<_>>());
+    if let Some(parts) = request {
+        if parts.len() == 3 {
+            for (name, part) in ["method", "path", "protocol"].iter().zip(parts) {
+                fields.push(((*name).to_owned(), part));
+            }
+        }
+    }
+}
+
+pub fn parse_common(line: &str) -> Option<Vec<(String, String)>> {
+    let mut fields = captures(&COMMON, line)?;
+    split_request(&mut fields);
+    Some(fields)
+}
+
+pub fn parse_combined(line: &str) -> Option<Vec<(String, String)>> {
+    let mut fields = captures(&COMBINED, line)?;
+    split_request(&mut fields);
+    Some(fields)
+}
+
+pub fn parse_nginx_error(line: &str) -> Option<Vec<(String, String)>> {
+    let mut fields = captures(&NGINX_ERROR, line)?;
+
+    let message = fields
+        .iter()
+        .position(|(name, _)| name == "message")
+        .map(|index| fields.remove(index).1)
+        .unwrap_or_default();
+    let context_start = NGINX_ERROR_CONTEXT
+        .find(&message)
+        .map_or(message.len(), |m| m.start());
+    for context in NGINX_ERROR_CONTEXT.captures_iter(&message[context_start..]) {
+        let value = context
+            .name("quoted")
+            .map(|value| unescape(value.as_str()))
+            .or_else(|| context.name("value").map(|value| value.as_str().to_owned()));
+        if let Some(value) = value {
+            let key = match &context["key"] {
+                "host" => "http_host",
+                key => key,
+            };
+            fields.push((key.to_owned(), value));
+        }
+    }
+    fields.push(("message".to_owned(), message[..context_start].to_owned()));
+
+    Some(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(parsed: Option<Vec<(String, String)>>) -> Vec<(String, String)> {
+        let mut fields = parsed.expect("failed to parse");
+        fields.sort();
+        fields
+    }
+
+    fn expected(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        let mut fields = pairs
+            .iter()
+            .map(|(name, value)| ((*name).to_owned(), (*value).to_owned()))
+            .collect::<Vec<_>>();
+        fields.sort();
+        fields
+    }
+
+    #[test]
+    fn parse_common_log() {
+        assert_eq!(
+            fields(parse_common(
+                r#"127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /apache_pb.gif HTTP/1.0" 200 2326"#
+            )),
+            expected(&[
+                ("client", "127.0.0.1"),
+                ("user", "frank"),
+                ("time", "10/Oct/2000:13:55:36 -0700"),
+                ("request", "GET /apache_pb.gif HTTP/1.0"),
+                ("method", "GET"),
+                ("path", "/apache_pb.gif"),
+                ("protocol", "HTTP/1.0"),
+                ("status", "200"),
+                ("size", "2326"),
+            ])
+        );
+
+        // Malformed requests are kept whole.
+        assert_eq!(
+            fields(parse_common(
+                r#"10.0.0.1 - - [10/Oct/2000:13:55:36 -0700] "\x16\x03\"" 400 -"#
+            )),
+            expected(&[
+                ("client", "10.0.0.1"),
+                ("time", "10/Oct/2000:13:55:36 -0700"),
+                ("request", r#"\x16\x03""#),
+                ("status", "400"),
+            ])
+        );
+
+        assert!(parse_common("not an access log").is_none());
+    }
+
+    #[test]
+    fn parse_combined_log() {
+        assert_eq!(
+            fields(parse_combined(
+                r#"93.180.71.3 - - [17/May/2015:08:05:32 +0000] "GET /downloads/product_1 HTTP/1.1" 304 0 "-" "Debian APT-HTTP/1.3 (0.8.16~exp12ubuntu10.21)""#
+            )),
+            expected(&[
+                ("client", "93.180.71.3"),
+                ("time", "17/May/2015:08:05:32 +0000"),
+                ("request", "GET /downloads/product_1 HTTP/1.1"),
+                ("method", "GET"),
+                ("path", "/downloads/product_1"),
+                ("protocol", "HTTP/1.1"),
+                ("status", "304"),
+                ("size", "0"),
+                ("agent", "Debian APT-HTTP/1.3 (0.8.16~exp12ubuntu10.21)"),
+            ])
+        );
+
+        // Common logs lack the referrer and agent.
+        assert!(parse_combined(
+            r#"127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /apache_pb.gif HTTP/1.0" 200 2326"#
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn parse_nginx_error_log() {
+        assert_eq!(
+            fields(parse_nginx_error(
+                r#"2020/05/01 12:00:00 [error] 1234#5678: *9 open() "/var/www/favicon.ico" failed (2: No such file or directory), client: 10.0.0.1, server: example.com, request: "GET /favicon.ico HTTP/1.1", host: "example.com""#
+            )),
+            expected(&[
+                ("time", "2020/05/01 12:00:00"),
+                ("severity", "error"),
+                ("pid", "1234"),
+                ("tid", "5678"),
+                ("connid", "9"),
+                (
+                    "message",
+                    r#"open() "/var/www/favicon.ico" failed (2: No such file or directory)"#
+                ),
+                ("client", "10.0.0.1"),
+                ("server", "example.com"),
+                ("request", "GET /favicon.ico HTTP/1.1"),
+                ("http_host", "example.com"),
+            ])
+        );
+
+        assert_eq!(
+            fields(parse_nginx_error(
+                "2020/05/01 12:00:00 [notice] 1#1: signal process started"
+            )),
+            expected(&[
+                ("time", "2020/05/01 12:00:00"),
+                ("severity", "notice"),
+                ("pid", "1"),
+                ("tid", "1"),
+                ("message", "signal process started"),
+            ])
+        );
+    }
+}
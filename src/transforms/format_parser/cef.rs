@@ -0,0 +1,155 @@
+//! Parser for the ArcSight Common Event Format.
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+const HEADER_FIELDS: [&str; 7] = [
+    "cef_version",
+    "device_vendor",
+    "device_product",
+    "device_version",
+    "signature_id",
+    "name",
+    "severity",
+];
+
+lazy_static! {
+    static ref EXTENSION_KEY: Regex = Regex::new(r"(?:^|\s)([\w.\[\]-]+)=").unwrap();
+}
+
+/// Splits `count` pipe delimited header fields off the start of a CEF or LEEF
+/// message, unescaping `\|` and `\\`. Returns the fields and the remainder of
+/// the message.
+pub(super) fn split_header(s: &str, count: usize) -> Option<(Vec<String>, &str)> {
+    let mut fields = Vec::with_capacity(count);
+    let mut current = String::new();
+    let mut chars = s.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, next)) if next == '|' || next == '\\' => current.push(next),
+                Some((_, next)) => {
+                    current.push('\\');
+                    current.push(next);
+                }
+                None => current.push('\\'),
+            },
+            '|' => {
+                fields.push(std::mem::take(&mut current));
+                if fields.len() == count {
+                    return Some((fields, &s[index + 1..]));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    None
+}
+
+/// Reverts the escaping of extension values.
+fn unescape_value(s: &str) -> String {
+    let mut value = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('r') => value.push('\r'),
+            Some(next) if next == '=' || next == '\\' => value.push(next),
+            Some(next) => {
+                value.push('\\');
+                value.push(next);
+            }
+            None => value.push('\\'),
+        }
+    }
+    value
+}
+
+/// Parses the space separated `key=value` pairs of the extension. Values may
+/// contain spaces, so each one runs up to the start of the next key.
+fn parse_extension(extension: &str) -> Vec<(String, String)> {
+    let keys = EXTENSION_KEY
+        .captures_iter(extension)
+        .filter_map(|captures| captures.get(1))
+        .map(|key| (key.start(), key.end()))
+        .collect::<Vec<_>>();
+
+    keys.iter()
+        .enumerate()
+        .map(|(index, &(start, end))| {
+            let value_end = keys
+                .get(index + 1)
+                .map_or(extension.len(), |&(next, _)| next);
+            (
+                extension[start..end].to_owned(),
+                unescape_value(extension[end + 1..value_end].trim_end()),
+            )
+        })
+        .collect()
+}
+
+/// Parses a CEF message, which may be preceded by a syslog header.
+pub fn parse(line: &str) -> Option<Vec<(String, String)>> {
+    let start = line.find("CEF:")?;
+    let (header, extension) = split_header(&line[start + 4..], HEADER_FIELDS.len())?;
+
+    let mut fields = HEADER_FIELDS
+        .iter()
+        .map(|name| (*name).to_owned())
+        .zip(header)
+        .collect::<Vec<_>>();
+    fields.extend(parse_extension(extension));
+    Some(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_cef() {
+        let fields = parse(
+            r"Sep 29 08:26:10 host CEF:0|Security|threatmanager|1.0|100|worm successfully stopped|10|src=10.0.0.1 dst=2.1.2.2 spt=1232 msg=Detected a \= in message\nsecond line cs1Label=Rule Name",
+        )
+        .unwrap();
+
+        assert_eq!(
+            fields,
+            vec![
+                ("cef_version", "0"),
+                ("device_vendor", "Security"),
+                ("device_product", "threatmanager"),
+                ("device_version", "1.0"),
+                ("signature_id", "100"),
+                ("name", "worm successfully stopped"),
+                ("severity", "10"),
+                ("src", "10.0.0.1"),
+                ("dst", "2.1.2.2"),
+                ("spt", "1232"),
+                ("msg", "Detected a = in message\nsecond line"),
+                ("cs1Label", "Rule Name"),
+            ]
+            .into_iter()
+            .map(|(name, value)| (name.to_owned(), value.to_owned()))
+            .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn parse_cef_escaped_header() {
+        let fields = parse(r"CEF:0|Vendor\|Inc|Product \\ X|1.0|1|name|Low|").unwrap();
+        assert_eq!(fields[1].1, "Vendor|Inc");
+        assert_eq!(fields[2].1, r"Product \ X");
+        assert_eq!(fields.len(), 7);
+    }
+
+    #[test]
+    fn parse_cef_truncated_header() {
+        assert!(parse("CEF:0|Security|threatmanager|1.0").is_none());
+        assert!(parse("not cef at all").is_none());
+    }
+}
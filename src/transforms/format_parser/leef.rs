@@ -0,0 +1,128 @@
+//! Parser for the IBM QRadar Log Event Extended Format.
+
+use super::cef::split_header;
+
+const HEADER_FIELDS: [&str; 5] = [
+    "leef_version",
+    "device_vendor",
+    "device_product",
+    "device_version",
+    "event_id",
+];
+
+/// Parses the attribute delimiter of LEEF 2.0, given either as a single
+/// character or as its hex code such as `x5E` or `0x5E`.
+fn parse_delimiter(s: &str) -> Option<char> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => {
+            let hex = s.trim_start_matches('0');
+            if !hex.starts_with('x') && !hex.starts_with('X') {
+                return None;
+            }
+            u32::from_str_radix(&hex[1..], 16)
+                .ok()
+                .and_then(std::char::from_u32)
+        }
+    }
+}
+
+/// Parses a LEEF 1.0 or 2.0 message, which may be preceded by a syslog header.
+pub fn parse(line: &str) -> Option<Vec<(String, String)>> {
+    let start = line.find("LEEF:")?;
+    let (header, attributes) = split_header(&line[start + 5..], HEADER_FIELDS.len())?;
+
+    // LEEF 2.0 has an optional delimiter field after the header, defaulting
+    // to a tab like LEEF 1.0.
+    let (delimiter, attributes) = if header[0].starts_with('2') {
+        attributes
+            .find('|')
+            .and_then(|end| {
+                parse_delimiter(&attributes[..end])
+                    .map(|delimiter| (delimiter, &attributes[end + 1..]))
+            })
+            .unwrap_or(('\t', attributes))
+    } else {
+        ('\t', attributes)
+    };
+
+    let mut fields = HEADER_FIELDS
+        .iter()
+        .map(|name| (*name).to_owned())
+        .zip(header)
+        .collect::<Vec<_>>();
+    fields.extend(attributes.split(delimiter).filter_map(|attribute| {
+        let mut parts = attribute.splitn(2, '=');
+        match (parts.next().map(str::trim), parts.next()) {
+            (Some(key), Some(value)) if !key.is_empty() => Some((
+                key.to_owned(),
+                value.trim_end_matches(&['\r', '\n'][..]).to_owned(),
+            )),
+            _ => None,
+        }
+    }));
+    Some(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(name, value)| ((*name).to_owned(), (*value).to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn parse_leef_1() {
+        assert_eq!(
+            parse("LEEF:1.0|Microsoft|MSExchange|4.0 SP1|15345|src=192.0.2.0\tdst=172.50.123.1\tsev=5\tmsg=hello world").unwrap(),
+            pairs(&[
+                ("leef_version", "1.0"),
+                ("device_vendor", "Microsoft"),
+                ("device_product", "MSExchange"),
+                ("device_version", "4.0 SP1"),
+                ("event_id", "15345"),
+                ("src", "192.0.2.0"),
+                ("dst", "172.50.123.1"),
+                ("sev", "5"),
+                ("msg", "hello world"),
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_leef_2_delimiter() {
+        let expected = pairs(&[
+            ("leef_version", "2.0"),
+            ("device_vendor", "Lancope"),
+            ("device_product", "StealthWatch"),
+            ("device_version", "1.0"),
+            ("event_id", "41"),
+            ("src", "10.0.1.8"),
+            ("dst", "10.0.0.5"),
+        ]);
+
+        assert_eq!(
+            parse("<13>Jan 18 11:07:53 host LEEF:2.0|Lancope|StealthWatch|1.0|41|^|src=10.0.1.8^dst=10.0.0.5").unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse("LEEF:2.0|Lancope|StealthWatch|1.0|41|x5E|src=10.0.1.8^dst=10.0.0.5").unwrap(),
+            expected
+        );
+        assert_eq!(
+            parse("LEEF:2.0|Lancope|StealthWatch|1.0|41|src=10.0.1.8\tdst=10.0.0.5").unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn parse_leef_invalid() {
+        assert!(parse("LEEF:1.0|Microsoft|MSExchange").is_none());
+        assert!(parse("CEF:0|a|b|c|d|e|f|").is_none());
+    }
+}
@@ -0,0 +1,346 @@
+mod access_log;
+mod cef;
+mod leef;
+
+use super::Transform;
+use crate::{
+    event::{self, Event, Value},
+    internal_events::{
+        FormatParserEventProcessed, FormatParserFailedParse, FormatParserMissingField,
+    },
+    topology::config::{DataType, TransformConfig, TransformContext, TransformDescription},
+    types::{parse_conversion_map, Conversion},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use string_cache::DefaultAtom as Atom;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    /// ArcSight Common Event Format.
+    Cef,
+    /// IBM QRadar Log Event Extended Format.
+    Leef,
+    /// Common Log Format of Apache and Nginx access logs.
+    Common,
+    /// Combined Log Format, the Common Log Format with referrer and user agent.
+    Combined,
+    /// Nginx error logs.
+    NginxError,
+}
+
+impl Format {
+    fn as_str(self) -> &'static str {
+        match self {
+            Format::Cef => "cef",
+            Format::Leef => "leef",
+            Format::Common => "common",
+            Format::Combined => "combined",
+            Format::NginxError => "nginx_error",
+        }
+    }
+
+    fn parse(self, line: &str) -> Option<Vec<(String, String)>> {
+        match self {
+            Format::Cef => cef::parse(line),
+            Format::Leef => leef::parse(line),
+            Format::Common => access_log::parse_common(line),
+            Format::Combined => access_log::parse_combined(line),
+            Format::NginxError => access_log::parse_nginx_error(line),
+        }
+    }
+
+    /// The field holding the time of the event, which can replace the
+    /// timestamp of the log schema.
+    fn time_field(self) -> Option<&'static str> {
+        match self {
+            Format::Cef | Format::Leef => None,
+            Format::Common | Format::Combined | Format::NginxError => Some("time"),
+        }
+    }
+
+    /// The conversions applied to well known fields of the format, which can
+    /// be overridden with `types`.
+    fn default_types(self) -> HashMap<Atom, String> {
+        let types: &[(&str, &str)] = match self {
+            Format::Cef => &[
+                ("cef_version", "int"),
+                ("spt", "int"),
+                ("dpt", "int"),
+                ("cnt", "int"),
+                ("in", "int"),
+                ("out", "int"),
+            ],
+            Format::Leef => &[("srcPort", "int"), ("dstPort", "int")],
+            Format::Common | Format::Combined => &[
+                ("status", "int"),
+                ("size", "int"),
+                ("time", "timestamp|%d/%b/%Y:%H:%M:%S %z"),
+            ],
+            Format::NginxError => &[
+                ("pid", "int"),
+                ("tid", "int"),
+                ("connid", "int"),
+                ("time", "timestamp|%Y/%m/%d %H:%M:%S"),
+            ],
+        };
+        types
+            .iter()
+            .map(|(name, conversion)| (Atom::from(*name), (*conversion).to_owned()))
+            .collect()
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct FormatParserConfig {
+    pub format: Format,
+    pub field: Option<Atom>,
+    #[serde(default = "crate::serde::default_true")]
+    pub drop_field: bool,
+    #[serde(default)]
+    pub drop_failed: bool,
+    pub target_field: Option<Atom>,
+    #[serde(default)]
+    pub overwrite_timestamp: bool,
+    #[serde(default)]
+    pub types: HashMap<Atom, String>,
+}
+
+inventory::submit! {
+    TransformDescription::new_without_default::<FormatParserConfig>("format_parser")
+}
+
+#[typetag::serde(name = "format_parser")]
+impl TransformConfig for FormatParserConfig {
+    fn build(&self, _cx: TransformContext) -> crate::Result<Box<dyn Transform>> {
+        Ok(Box::new(FormatParser::new(self)?))
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn transform_type(&self) -> &'static str {
+        "format_parser"
+    }
+}
+
+pub struct FormatParser {
+    format: Format,
+    field: Atom,
+    drop_field: bool,
+    drop_failed: bool,
+    target_field: Option<Atom>,
+    overwrite_timestamp: bool,
+    types: HashMap<Atom, Conversion>,
+}
+
+impl FormatParser {
+    pub fn new(config: &FormatParserConfig) -> crate::Result<Self> {
+        let mut types = parse_conversion_map(&config.format.default_types())?;
+        types.extend(parse_conversion_map(&config.types)?);
+
+        Ok(Self {
+            format: config.format,
+            field: config
+                .field
+                .clone()
+                .unwrap_or_else(|| event::log_schema().message_key().clone()),
+            drop_field: config.drop_field,
+            drop_failed: config.drop_failed,
+            target_field: config.target_field.clone(),
+            overwrite_timestamp: config.overwrite_timestamp,
+            types,
+        })
+    }
+}
+
+impl Transform for FormatParser {
+    fn transform(&mut self, mut event: Event) -> Option<Event> {
+        emit!(FormatParserEventProcessed);
+
+        let log = event.as_mut_log();
+        let value = match log.get(&self.field) {
+            Some(value) => value.to_string_lossy(),
+            None => {
+                emit!(FormatParserMissingField { field: &self.field });
+                return if self.drop_failed { None } else { Some(event) };
+            }
+        };
+
+        let fields = match self.format.parse(&value) {
+            Some(fields) => fields,
+            None => {
+                emit!(FormatParserFailedParse {
+                    format: self.format.as_str(),
+                    value: &value,
+                });
+                return if self.drop_failed { None } else { Some(event) };
+            }
+        };
+
+        // Removed first, so fields of the same name replace it.
+        if self.drop_field {
+            log.remove(&self.field);
+        }
+
+        for (name, value) in fields {
+            let name = Atom::from(name);
+            let value = match self.types.get(&name) {
+                Some(conversion) => match conversion.convert(Value::from(value)) {
+                    Ok(value) => value,
+                    Err(error) => {
+                        debug!(
+                            message = "Could not convert types.",
+                            name = &name[..],
+                            %error,
+                            rate_limit_secs = 30
+                        );
+                        continue;
+                    }
+                },
+                None => Value::from(value),
+            };
+            if self.overwrite_timestamp && self.format.time_field() == Some(&name[..]) {
+                if let Value::Timestamp(_) = value {
+                    log.insert(event::log_schema().timestamp_key().clone(), value.clone());
+                }
+            }
+            let name = match &self.target_field {
+                Some(target) => Atom::from(format!("{}.{}", target, name)),
+                None => name,
+            };
+            log.insert(name, value);
+        }
+
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FormatParserConfig;
+    use crate::{
+        event::{LogEvent, Value},
+        topology::config::{TransformConfig, TransformContext},
+        Event,
+    };
+    use chrono::{TimeZone, Utc};
+
+    fn do_transform(message: &str, config: &str) -> Option<LogEvent> {
+        let rt = crate::runtime::Runtime::single_threaded().unwrap();
+        let mut parser = toml::from_str::<FormatParserConfig>(config)
+            .unwrap()
+            .build(TransformContext::new_test(rt.executor()))
+            .unwrap();
+
+        parser
+            .transform(Event::from(message))
+            .map(|event| event.into_log())
+    }
+
+    #[test]
+    fn format_parser_combined() {
+        let log = do_transform(
+            r#"93.180.71.3 - - [17/May/2015:08:05:32 +0000] "GET /downloads/product_1 HTTP/1.1" 304 0 "-" "Debian APT-HTTP/1.3""#,
+            r#"format = "combined""#,
+        )
+        .unwrap();
+
+        assert_eq!(log[&"client".into()], "93.180.71.3".into());
+        assert_eq!(log[&"method".into()], "GET".into());
+        assert_eq!(log[&"status".into()], Value::Integer(304));
+        assert_eq!(log[&"size".into()], Value::Integer(0));
+        assert_eq!(
+            log[&"time".into()],
+            Value::Timestamp(Utc.ymd(2015, 5, 17).and_hms(8, 5, 32))
+        );
+        assert_eq!(log[&"agent".into()], "Debian APT-HTTP/1.3".into());
+        assert!(log.get(&"referrer".into()).is_none());
+        assert!(log.get(&"message".into()).is_none());
+        // The timestamp of the event is kept.
+        assert_ne!(
+            log[&"timestamp".into()],
+            Value::Timestamp(Utc.ymd(2015, 5, 17).and_hms(8, 5, 32))
+        );
+    }
+
+    #[test]
+    fn format_parser_overwrite_timestamp() {
+        let log = do_transform(
+            r#"127.0.0.1 - frank [10/Oct/2000:13:55:36 -0700] "GET /apache_pb.gif HTTP/1.0" 200 2326"#,
+            r#"
+                format = "common"
+                target_field = "access"
+                overwrite_timestamp = true
+            "#,
+        )
+        .unwrap();
+
+        let time = Value::Timestamp(Utc.ymd(2000, 10, 10).and_hms(20, 55, 36));
+        assert_eq!(log[&"access.time".into()], time);
+        assert_eq!(log[&"timestamp".into()], time);
+        assert_eq!(log[&"access.client".into()], "127.0.0.1".into());
+    }
+
+    #[test]
+    fn format_parser_cef_target_field_and_types() {
+        let log = do_transform(
+            "CEF:0|Security|threatmanager|1.0|100|worm stopped|10|src=10.0.0.1 spt=1232 cnt=x",
+            r#"
+                format = "cef"
+                target_field = "cef"
+                drop_field = false
+                types.severity = "int"
+                types.spt = "string"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(log[&"cef.cef_version".into()], Value::Integer(0));
+        assert_eq!(log[&"cef.severity".into()], Value::Integer(10));
+        assert_eq!(log[&"cef.device_vendor".into()], "Security".into());
+        assert_eq!(log[&"cef.src".into()], "10.0.0.1".into());
+        assert_eq!(log[&"cef.spt".into()], "1232".into());
+        // Values failing their conversion are left out.
+        assert!(log.get(&"cef.cnt".into()).is_none());
+        assert!(log.get(&"message".into()).is_some());
+    }
+
+    #[test]
+    fn format_parser_nginx_error_replaces_message() {
+        let log = do_transform(
+            "2020/05/01 12:00:00 [error] 1234#5678: *9 upstream timed out, client: 10.0.0.1, server: example.com",
+            r#"format = "nginx_error""#,
+        )
+        .unwrap();
+
+        assert_eq!(log[&"message".into()], "upstream timed out".into());
+        assert_eq!(log[&"severity".into()], "error".into());
+        assert_eq!(log[&"pid".into()], Value::Integer(1234));
+        assert_eq!(log[&"connid".into()], Value::Integer(9));
+        assert_eq!(log[&"client".into()], "10.0.0.1".into());
+        assert_eq!(log[&"server".into()], "example.com".into());
+    }
+
+    #[test]
+    fn format_parser_failed_parse() {
+        let log = do_transform("LEEF:1.0|truncated", r#"format = "leef""#).unwrap();
+        assert_eq!(log[&"message".into()], "LEEF:1.0|truncated".into());
+
+        assert!(do_transform(
+            "LEEF:1.0|truncated",
+            r#"
+                format = "leef"
+                drop_failed = true
+            "#,
+        )
+        .is_none());
+    }
+}
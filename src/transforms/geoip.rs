@@ -4,12 +4,26 @@ use crate::{
     event::{Event, Value},
     topology::config::{DataType, TransformConfig, TransformContext},
 };
+use arc_swap::ArcSwap;
+use maxminddb::{geoip2, MaxMindDBError};
 use serde::{Deserialize, Serialize};
+use snafu::{ResultExt, Snafu};
 use string_cache::DefaultAtom as Atom;
 
-use std::str::FromStr;
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    thread,
+    time::{Duration, SystemTime},
+};
 use tracing::field;
 
+/// How often the database file is checked for changes.
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct GeoipConfig {
@@ -17,27 +31,222 @@ pub struct GeoipConfig {
     pub database: String,
     #[serde(default = "default_geoip_target_field")]
     pub target: String,
-}
-
-pub struct Geoip {
-    pub dbreader: maxminddb::Reader<Vec<u8>>,
-    pub source: Atom,
-    pub target: String,
+    #[serde(default = "default_locale")]
+    pub locale: String,
 }
 
 fn default_geoip_target_field() -> String {
     "geoip".to_string()
 }
 
+fn default_locale() -> String {
+    "en".to_string()
+}
+
+#[derive(Debug, Snafu)]
+enum GeoipError {
+    #[snafu(display("Unable to open GeoIP database {:?}: {}", path, source))]
+    OpenDatabase {
+        path: PathBuf,
+        source: MaxMindDBError,
+    },
+    #[snafu(display("Unsupported GeoIP database type {:?}", database_type))]
+    UnsupportedDatabase { database_type: String },
+}
+
+/// The kinds of MaxMind databases the transform knows how to read.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DatabaseKind {
+    City,
+    Country,
+    Asn,
+    Isp,
+    ConnectionType,
+    AnonymousIp,
+}
+
+impl DatabaseKind {
+    fn from_database_type(database_type: &str) -> Option<Self> {
+        // Types are named like `GeoIP2-City` or `GeoLite2-ASN`, optionally
+        // followed by an edition such as `GeoIP2-City-Europe` or
+        // `DBIP-City-Lite`. Enterprise databases are a superset of City ones.
+        let kind = database_type.splitn(2, '-').nth(1).unwrap_or(database_type);
+        if kind.starts_with("City") || kind.contains("Enterprise") {
+            Some(DatabaseKind::City)
+        } else if kind.starts_with("Country") {
+            Some(DatabaseKind::Country)
+        } else if kind.starts_with("ASN") {
+            Some(DatabaseKind::Asn)
+        } else if kind.starts_with("ISP") {
+            Some(DatabaseKind::Isp)
+        } else if kind.starts_with("Connection-Type") {
+            Some(DatabaseKind::ConnectionType)
+        } else if kind.starts_with("Anonymous-IP") {
+            Some(DatabaseKind::AnonymousIp)
+        } else {
+            None
+        }
+    }
+
+    /// The fields written to the target for this kind of database.
+    fn fields(self) -> &'static [&'static str] {
+        match self {
+            DatabaseKind::City => &[
+                "city_name",
+                "continent_code",
+                "country_code",
+                "country_name",
+                "region_code",
+                "region_name",
+                "timezone",
+                "latitude",
+                "longitude",
+                "postal_code",
+            ],
+            DatabaseKind::Country => &["continent_code", "country_code", "country_name"],
+            DatabaseKind::Asn => &["autonomous_system_number", "autonomous_system_organization"],
+            DatabaseKind::Isp => &[
+                "autonomous_system_number",
+                "autonomous_system_organization",
+                "isp",
+                "organization",
+            ],
+            DatabaseKind::ConnectionType => &["connection_type"],
+            DatabaseKind::AnonymousIp => &[
+                "is_anonymous",
+                "is_anonymous_vpn",
+                "is_hosting_provider",
+                "is_public_proxy",
+                "is_tor_exit_node",
+            ],
+        }
+    }
+}
+
+/// The record layout shared by the ASN and ISP databases.
+#[derive(Deserialize, Debug)]
+struct Isp {
+    autonomous_system_number: Option<u32>,
+    autonomous_system_organization: Option<String>,
+    isp: Option<String>,
+    organization: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ConnectionType {
+    connection_type: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct AnonymousIp {
+    is_anonymous: Option<bool>,
+    is_anonymous_vpn: Option<bool>,
+    is_hosting_provider: Option<bool>,
+    is_public_proxy: Option<bool>,
+    is_tor_exit_node: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Fingerprint {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl Fingerprint {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = std::fs::metadata(path).ok()?;
+        Some(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+struct Database {
+    reader: maxminddb::Reader<Vec<u8>>,
+    kind: DatabaseKind,
+    fingerprint: Option<Fingerprint>,
+}
+
+impl Database {
+    fn open(path: &Path) -> Result<Self, GeoipError> {
+        let fingerprint = Fingerprint::of(path);
+        let reader = maxminddb::Reader::open_readfile(path).context(OpenDatabase { path })?;
+        let kind =
+            DatabaseKind::from_database_type(&reader.metadata.database_type).ok_or_else(|| {
+                GeoipError::UnsupportedDatabase {
+                    database_type: reader.metadata.database_type.clone(),
+                }
+            })?;
+        Ok(Self {
+            reader,
+            kind,
+            fingerprint,
+        })
+    }
+}
+
+/// The currently opened database, replaced whenever the file changes.
+struct WatchedDatabase {
+    path: PathBuf,
+    current: ArcSwap<Database>,
+}
+
+impl WatchedDatabase {
+    /// Checks the file for changes on a background thread, so lookups never
+    /// wait for a reload. The thread ends once the transform is dropped.
+    fn watch(self: &Arc<Self>) {
+        let watched = Arc::downgrade(self);
+        let spawned = thread::Builder::new()
+            .name("geoip-database".to_owned())
+            .spawn(move || loop {
+                thread::sleep(RELOAD_CHECK_INTERVAL);
+                match watched.upgrade() {
+                    Some(watched) => watched.reload_if_changed(),
+                    None => break,
+                };
+            });
+        if let Err(error) = spawned {
+            error!(
+                message = "Failed to watch GeoIP database for changes.",
+                path = ?self.path,
+                %error
+            );
+        }
+    }
+
+    /// Reopen the database if the file was replaced since it was last
+    /// opened. Returns whether the database was reopened.
+    fn reload_if_changed(&self) -> bool {
+        let fingerprint = Fingerprint::of(&self.path);
+        if fingerprint.is_none() || fingerprint == self.current.load().fingerprint {
+            return false;
+        }
+
+        match Database::open(&self.path) {
+            Ok(database) => {
+                info!(message = "Reloaded GeoIP database.", path = ?self.path);
+                self.current.store(Arc::new(database));
+                true
+            }
+            Err(error) => {
+                error!(
+                    message = "Failed to reload GeoIP database, keeping the previous one.",
+                    %error,
+                    rate_limit_secs = 30
+                );
+                false
+            }
+        }
+    }
+}
+
 #[typetag::serde(name = "geoip")]
 impl TransformConfig for GeoipConfig {
     fn build(&self, _cx: TransformContext) -> Result<Box<dyn Transform>, crate::Error> {
-        let reader = maxminddb::Reader::open_readfile(self.database.clone())?;
-        Ok(Box::new(Geoip::new(
-            reader,
-            self.source.clone(),
-            self.target.clone(),
-        )))
+        let geoip = Geoip::new(self)?;
+        geoip.database.watch();
+        Ok(Box::new(geoip))
     }
 
     fn input_type(&self) -> DataType {
@@ -53,88 +262,221 @@ impl TransformConfig for GeoipConfig {
     }
 }
 
+pub struct Geoip {
+    database: Arc<WatchedDatabase>,
+    pub source: Atom,
+    pub target: String,
+    pub locale: String,
+}
+
 impl Geoip {
-    pub fn new(dbreader: maxminddb::Reader<Vec<u8>>, source: Atom, target: String) -> Self {
-        Geoip {
-            dbreader,
-            source,
-            target,
+    pub fn new(config: &GeoipConfig) -> crate::Result<Self> {
+        let path = PathBuf::from(&config.database);
+        let database = Database::open(&path)?;
+
+        let localized = matches!(database.kind, DatabaseKind::City | DatabaseKind::Country);
+        if localized
+            && !database
+                .reader
+                .metadata
+                .languages
+                .iter()
+                .any(|language| language == &config.locale)
+        {
+            warn!(
+                message = "Locale is not listed in the GeoIP database metadata, names may be missing.",
+                locale = %config.locale,
+            );
         }
+
+        Ok(Geoip {
+            database: Arc::new(WatchedDatabase {
+                path,
+                current: ArcSwap::from_pointee(database),
+            }),
+            source: config.source.clone(),
+            target: config.target.clone(),
+            locale: config.locale.clone(),
+        })
     }
-}
 
-impl Transform for Geoip {
-    fn transform(&mut self, mut event: Event) -> Option<Event> {
-        let target_field = self.target.clone();
-        let ipaddress = event
-            .as_log()
-            .get(&self.source)
-            .map(|s| s.to_string_lossy());
-        if let Some(ipaddress) = &ipaddress {
-            if let Ok(ip) = FromStr::from_str(ipaddress) {
-                if let Ok(data) = self.dbreader.lookup::<maxminddb::geoip2::City>(ip) {
-                    if let Some(city_names) = data.city.and_then(|c| c.names) {
-                        if let Some(city_name_en) = city_names.get("en") {
-                            event.as_mut_log().insert(
-                                Atom::from(format!("{}.city_name", target_field)),
-                                Value::from(city_name_en.to_string()),
-                            );
-                        }
-                    }
+    fn lookup(&self, database: &Database, ip: IpAddr) -> HashMap<&'static str, Value> {
+        let mut fields = HashMap::new();
+        let reader = &database.reader;
+        let locale = self.locale.as_str();
+
+        let result = match database.kind {
+            DatabaseKind::City => reader.lookup::<geoip2::City>(ip).map(|data| {
+                let city_name = data
+                    .city
+                    .and_then(|city| city.names)
+                    .and_then(|names| names.get(locale).map(|name| name.to_string()));
+                if let Some(city_name) = city_name {
+                    fields.insert("city_name", Value::from(city_name));
+                }
 
-                    let continent_code = data.continent.and_then(|c| c.code);
-                    if let Some(continent_code) = continent_code {
-                        event.as_mut_log().insert(
-                            Atom::from(format!("{}.continent_code", target_field)),
-                            Value::from(continent_code),
-                        );
-                    }
+                if let Some(continent_code) = data.continent.and_then(|c| c.code) {
+                    fields.insert("continent_code", Value::from(continent_code));
+                }
 
-                    let iso_code = data.country.and_then(|cy| cy.iso_code);
-                    if let Some(iso_code) = iso_code {
-                        event.as_mut_log().insert(
-                            Atom::from(format!("{}.country_code", target_field)),
-                            Value::from(iso_code),
-                        );
+                if let Some(country) = data.country {
+                    if let Some(iso_code) = country.iso_code {
+                        fields.insert("country_code", Value::from(iso_code));
                     }
+                    let country_name = country
+                        .names
+                        .and_then(|names| names.get(locale).map(|name| name.to_string()));
+                    if let Some(country_name) = country_name {
+                        fields.insert("country_name", Value::from(country_name));
+                    }
+                }
 
-                    let time_zone = data.location.clone().and_then(|loc| loc.time_zone);
-                    if let Some(time_zone) = time_zone {
-                        event.as_mut_log().insert(
-                            Atom::from(format!("{}.timezone", target_field)),
-                            Value::from(time_zone),
-                        );
+                // The first subdivision is the largest one, e.g. the state.
+                let region = data
+                    .subdivisions
+                    .and_then(|subdivisions| subdivisions.into_iter().next());
+                if let Some(region) = region {
+                    if let Some(iso_code) = region.iso_code {
+                        fields.insert("region_code", Value::from(iso_code));
+                    }
+                    let region_name = region
+                        .names
+                        .and_then(|names| names.get(locale).map(|name| name.to_string()));
+                    if let Some(region_name) = region_name {
+                        fields.insert("region_name", Value::from(region_name));
                     }
+                }
 
-                    let latitude = data.location.clone().and_then(|loc| loc.latitude);
-                    if let Some(latitude) = latitude {
-                        event.as_mut_log().insert(
-                            Atom::from(format!("{}.latitude", target_field)),
-                            Value::from(latitude.to_string()),
-                        );
+                if let Some(location) = data.location {
+                    if let Some(time_zone) = location.time_zone {
+                        fields.insert("timezone", Value::from(time_zone));
+                    }
+                    if let Some(latitude) = location.latitude {
+                        fields.insert("latitude", Value::from(latitude.to_string()));
+                    }
+                    if let Some(longitude) = location.longitude {
+                        fields.insert("longitude", Value::from(longitude.to_string()));
                     }
+                }
+
+                if let Some(postal_code) = data.postal.and_then(|p| p.code) {
+                    fields.insert("postal_code", Value::from(postal_code));
+                }
+            }),
+            DatabaseKind::Country => reader.lookup::<geoip2::Country>(ip).map(|data| {
+                if let Some(continent_code) = data.continent.and_then(|c| c.code) {
+                    fields.insert("continent_code", Value::from(continent_code));
+                }
 
-                    let longitude = data.location.clone().and_then(|loc| loc.longitude);
-                    if let Some(longitude) = longitude {
-                        event.as_mut_log().insert(
-                            Atom::from(format!("{}.longitude", target_field)),
-                            Value::from(longitude.to_string()),
-                        );
+                if let Some(country) = data.country {
+                    if let Some(iso_code) = country.iso_code {
+                        fields.insert("country_code", Value::from(iso_code));
+                    }
+                    let country_name = country
+                        .names
+                        .and_then(|names| names.get(locale).map(|name| name.to_string()));
+                    if let Some(country_name) = country_name {
+                        fields.insert("country_name", Value::from(country_name));
                     }
+                }
+            }),
+            DatabaseKind::Asn | DatabaseKind::Isp => reader.lookup::<Isp>(ip).map(|data| {
+                if let Some(number) = data.autonomous_system_number {
+                    fields.insert("autonomous_system_number", Value::from(number as i64));
+                }
+                if let Some(organization) = data.autonomous_system_organization {
+                    fields.insert("autonomous_system_organization", Value::from(organization));
+                }
+                if let Some(isp) = data.isp {
+                    fields.insert("isp", Value::from(isp));
+                }
+                if let Some(organization) = data.organization {
+                    fields.insert("organization", Value::from(organization));
+                }
+            }),
+            DatabaseKind::ConnectionType => reader.lookup::<ConnectionType>(ip).map(|data| {
+                if let Some(connection_type) = data.connection_type {
+                    fields.insert("connection_type", Value::from(connection_type));
+                }
+            }),
+            DatabaseKind::AnonymousIp => reader.lookup::<AnonymousIp>(ip).map(|data| {
+                // The database only stores the flags that are set.
+                let flags = [
+                    ("is_anonymous", data.is_anonymous),
+                    ("is_anonymous_vpn", data.is_anonymous_vpn),
+                    ("is_hosting_provider", data.is_hosting_provider),
+                    ("is_public_proxy", data.is_public_proxy),
+                    ("is_tor_exit_node", data.is_tor_exit_node),
+                ];
+                for &(name, flag) in flags.iter() {
+                    fields.insert(name, Value::from(flag.unwrap_or(false)));
+                }
+            }),
+        };
+
+        match result {
+            Ok(()) | Err(MaxMindDBError::AddressNotFoundError(_)) => {}
+            Err(error) => debug!(
+                message = "GeoIP lookup failed.",
+                %error,
+                rate_limit_secs = 30
+            ),
+        }
 
-                    let postal_code = data.postal.clone().and_then(|p| p.code);
-                    if let Some(postal_code) = postal_code {
-                        event.as_mut_log().insert(
-                            Atom::from(format!("{}.postal_code", target_field)),
-                            Value::from(postal_code),
-                        );
+        fields
+    }
+}
+
+/// Parse an IP address, also accepting addresses with a port, such as
+/// `1.2.3.4:80` or `[2001:db8::1]:443`, and bracketed IPv6 addresses.
+fn parse_ip_address(address: &str) -> Option<IpAddr> {
+    let address = address.trim();
+    IpAddr::from_str(address)
+        .or_else(|_| SocketAddr::from_str(address).map(|socket| socket.ip()))
+        .or_else(|_| IpAddr::from_str(address.trim_start_matches('[').trim_end_matches(']')))
+        .ok()
+        .map(|ip| match ip {
+            IpAddr::V6(ipv6) => {
+                // IPv4-mapped addresses, i.e. `::ffff:a.b.c.d`, are looked
+                // up as IPv4 so that they also work with IPv4 databases.
+                match ipv6.segments() {
+                    [0, 0, 0, 0, 0, 0xffff, ..] => IpAddr::V4(ipv6.to_ipv4().unwrap()),
+                    _ => ip,
+                }
+            }
+            IpAddr::V4(_) => ip,
+        })
+}
+
+impl Transform for Geoip {
+    fn transform(&mut self, mut event: Event) -> Option<Event> {
+        let database = self.database.current.load();
+        let target_field = self.target.clone();
+        let ipaddress = event
+            .as_log()
+            .get(&self.source)
+            .map(|s| s.to_string_lossy());
+        if let Some(ipaddress) = &ipaddress {
+            match parse_ip_address(ipaddress) {
+                Some(IpAddr::V6(_)) if database.reader.metadata.ip_version == 4 => {
+                    debug!(
+                        message = "IPv6 address can't be looked up in an IPv4 database.",
+                        ipaddr = &field::display(&ipaddress),
+                    );
+                }
+                Some(ip) => {
+                    for (name, value) in self.lookup(&database, ip) {
+                        event
+                            .as_mut_log()
+                            .insert(Atom::from(format!("{}.{}", target_field, name)), value);
                     }
                 }
-            } else {
-                debug!(
-                    message = "IP Address not parsed correctly.",
-                    ipaddr = &field::display(&ipaddress),
-                );
+                None => {
+                    debug!(
+                        message = "IP Address not parsed correctly.",
+                        ipaddr = &field::display(&ipaddress),
+                    );
+                }
             }
         } else {
             debug!(
@@ -146,23 +488,11 @@ impl Transform for Geoip {
         // If we have any of the geoip fields missing, we insert
         // empty values so that we know that the transform was executed
         // but the lookup didn't find the result
-        let geoip_fields = [
-            format!("{}.city_name", target_field),
-            format!("{}.country_code", target_field),
-            format!("{}.continent_code", target_field),
-            format!("{}.timezone", target_field),
-            format!("{}.latitude", target_field),
-            format!("{}.longitude", target_field),
-            format!("{}.postal_code", target_field),
-        ];
-        for field in geoip_fields.iter() {
+        for name in database.kind.fields() {
+            let field = Atom::from(format!("{}.{}", target_field, name));
             let e = event.as_mut_log();
-            let d = e.get(&Atom::from(field.to_string()));
-            match d {
-                None => {
-                    e.insert(Atom::from(field.to_string()), Value::from(""));
-                }
-                _ => (),
+            if e.get(&field).is_none() {
+                e.insert(field, Value::from(""));
             }
         }
 
@@ -173,24 +503,47 @@ impl Transform for Geoip {
 #[cfg(feature = "transforms-json_parser")]
 #[cfg(test)]
 mod tests {
-    use super::Geoip;
+    use super::{DatabaseKind, Geoip, GeoipConfig};
     use crate::{
         event::Event,
         transforms::json_parser::{JsonParser, JsonParserConfig},
         transforms::Transform,
     };
-    use std::collections::HashMap;
+    use std::{collections::HashMap, thread, time::Duration};
     use string_cache::DefaultAtom as Atom;
 
-    #[test]
-    fn geoip_lookup_success() {
+    fn geoip(database: &str, locale: &str) -> Geoip {
+        Geoip::new(&GeoipConfig {
+            source: Atom::from("remote_addr"),
+            database: database.to_string(),
+            target: "geo".to_string(),
+            locale: locale.to_string(),
+        })
+        .unwrap()
+    }
+
+    fn lookup(augment: &mut Geoip, remote_addr: &str) -> Event {
         let mut parser = JsonParser::from(JsonParserConfig::default());
-        let event = Event::from(r#"{"remote_addr": "2.125.160.216", "request_path": "foo/bar"}"#);
+        let event = Event::from(format!(
+            r#"{{"remote_addr": "{}", "request_path": "foo/bar"}}"#,
+            remote_addr
+        ));
         let event = parser.transform(event).unwrap();
-        let reader = maxminddb::Reader::open_readfile("tests/data/GeoIP2-City-Test.mmdb").unwrap();
+        augment.transform(event).unwrap()
+    }
+
+    fn assert_geoip_fields(event: &Event, expected: HashMap<&str, &str>) {
+        for (field, value) in expected {
+            let k = Atom::from(format!("geo.{}", field));
+            let geodata = event.as_log().get(&k).unwrap().to_string_lossy();
+            assert_eq!(geodata, value, "field {}", field);
+        }
+    }
 
-        let mut augment = Geoip::new(reader, Atom::from("remote_addr"), "geo".to_string());
-        let new_event = augment.transform(event).unwrap();
+    #[test]
+    fn geoip_lookup_success() {
+        let mut augment = geoip("tests/data/GeoIP2-City-Test.mmdb", "en");
+        let new_event = lookup(&mut augment, "2.125.160.216");
 
         let mut exp_geoip_attr = HashMap::new();
         exp_geoip_attr.insert("city_name", "Boxford");
@@ -200,27 +553,17 @@ mod tests {
         exp_geoip_attr.insert("latitude", "51.75");
         exp_geoip_attr.insert("longitude", "-1.25");
         exp_geoip_attr.insert("postal_code", "OX1");
+        exp_geoip_attr.insert("country_name", "United Kingdom");
+        exp_geoip_attr.insert("region_code", "ENG");
+        exp_geoip_attr.insert("region_name", "England");
 
-        for field in exp_geoip_attr.keys() {
-            let k = Atom::from(format!("geo.{}", field).to_string());
-            let geodata = new_event.as_log().get(&k).unwrap().to_string_lossy();
-
-            match exp_geoip_attr.get(field) {
-                Some(&v) => assert_eq!(geodata, v),
-                _ => assert!(false),
-            }
-        }
+        assert_geoip_fields(&new_event, exp_geoip_attr);
     }
 
     #[test]
     fn geoip_lookup_partial_results() {
-        let mut parser = JsonParser::from(JsonParserConfig::default());
-        let event = Event::from(r#"{"remote_addr": "67.43.156.9", "request_path": "foo/bar"}"#);
-        let event = parser.transform(event).unwrap();
-        let reader = maxminddb::Reader::open_readfile("tests/data/GeoIP2-City-Test.mmdb").unwrap();
-
-        let mut augment = Geoip::new(reader, Atom::from("remote_addr"), "geo".to_string());
-        let new_event = augment.transform(event).unwrap();
+        let mut augment = geoip("tests/data/GeoIP2-City-Test.mmdb", "en");
+        let new_event = lookup(&mut augment, "67.43.156.9");
 
         let mut exp_geoip_attr = HashMap::new();
         exp_geoip_attr.insert("city_name", "");
@@ -230,26 +573,15 @@ mod tests {
         exp_geoip_attr.insert("latitude", "27.5");
         exp_geoip_attr.insert("longitude", "90.5");
         exp_geoip_attr.insert("postal_code", "");
+        exp_geoip_attr.insert("region_code", "");
 
-        for field in exp_geoip_attr.keys() {
-            let k = Atom::from(format!("geo.{}", field).to_string());
-            let geodata = new_event.as_log().get(&k).unwrap().to_string_lossy();
-            match exp_geoip_attr.get(field) {
-                Some(&v) => assert_eq!(geodata, v),
-                _ => assert!(false),
-            }
-        }
+        assert_geoip_fields(&new_event, exp_geoip_attr);
     }
 
     #[test]
     fn geoip_lookup_no_results() {
-        let mut parser = JsonParser::from(JsonParserConfig::default());
-        let event = Event::from(r#"{"remote_addr": "10.1.12.1", "request_path": "foo/bar"}"#);
-        let event = parser.transform(event).unwrap();
-        let reader = maxminddb::Reader::open_readfile("tests/data/GeoIP2-City-Test.mmdb").unwrap();
-
-        let mut augment = Geoip::new(reader, Atom::from("remote_addr"), "geo".to_string());
-        let new_event = augment.transform(event).unwrap();
+        let mut augment = geoip("tests/data/GeoIP2-City-Test.mmdb", "en");
+        let new_event = lookup(&mut augment, "10.1.12.1");
 
         let mut exp_geoip_attr = HashMap::new();
         exp_geoip_attr.insert("city_name", "");
@@ -260,14 +592,175 @@ mod tests {
         exp_geoip_attr.insert("longitude", "");
         exp_geoip_attr.insert("postal_code", "");
 
-        for field in exp_geoip_attr.keys() {
-            let k = Atom::from(format!("geo.{}", field).to_string());
-            println!("Looking for {:?}", k);
-            let geodata = new_event.as_log().get(&k).unwrap().to_string_lossy();
-            match exp_geoip_attr.get(field) {
-                Some(&v) => assert_eq!(geodata, v),
-                _ => assert!(false),
-            }
+        assert_geoip_fields(&new_event, exp_geoip_attr);
+    }
+
+    #[test]
+    fn geoip_lookup_with_locale() {
+        let mut augment = geoip("tests/data/GeoIP2-City-Test.mmdb", "de");
+        let new_event = lookup(&mut augment, "81.2.69.142");
+
+        let mut exp_geoip_attr = HashMap::new();
+        exp_geoip_attr.insert("city_name", "London");
+        exp_geoip_attr.insert("country_name", "Vereinigtes Königreich");
+        exp_geoip_attr.insert("country_code", "GB");
+        // Names missing in the locale are left empty.
+        exp_geoip_attr.insert("region_name", "");
+
+        assert_geoip_fields(&new_event, exp_geoip_attr);
+    }
+
+    #[test]
+    fn geoip_lookup_ipv6() {
+        let mut augment = geoip("tests/data/GeoIP2-City-Test.mmdb", "en");
+
+        for address in &["2001:218::1", "[2001:218::1]:8080"] {
+            let new_event = lookup(&mut augment, address);
+
+            let mut exp_geoip_attr = HashMap::new();
+            exp_geoip_attr.insert("country_code", "JP");
+            exp_geoip_attr.insert("country_name", "Japan");
+            exp_geoip_attr.insert("timezone", "Asia/Tokyo");
+
+            assert_geoip_fields(&new_event, exp_geoip_attr);
+        }
+
+        let new_event = lookup(&mut augment, "::ffff:2.125.160.216");
+        let mut exp_geoip_attr = HashMap::new();
+        exp_geoip_attr.insert("city_name", "Boxford");
+        assert_geoip_fields(&new_event, exp_geoip_attr);
+    }
+
+    #[test]
+    fn geoip_lookup_asn() {
+        let mut augment = geoip("tests/data/GeoLite2-ASN-Test.mmdb", "en");
+        let new_event = lookup(&mut augment, "1.128.0.1");
+
+        let mut exp_geoip_attr = HashMap::new();
+        exp_geoip_attr.insert("autonomous_system_number", "1221");
+        exp_geoip_attr.insert("autonomous_system_organization", "Telstra Pty Ltd");
+        assert_geoip_fields(&new_event, exp_geoip_attr);
+
+        let new_event = lookup(&mut augment, "10.1.12.1");
+        let mut exp_geoip_attr = HashMap::new();
+        exp_geoip_attr.insert("autonomous_system_number", "");
+        exp_geoip_attr.insert("autonomous_system_organization", "");
+        assert_geoip_fields(&new_event, exp_geoip_attr);
+    }
+
+    #[test]
+    fn geoip_lookup_isp() {
+        let mut augment = geoip("tests/data/GeoIP2-ISP-Test.mmdb", "en");
+        let new_event = lookup(&mut augment, "1.128.0.1");
+
+        let mut exp_geoip_attr = HashMap::new();
+        exp_geoip_attr.insert("autonomous_system_number", "1221");
+        exp_geoip_attr.insert("autonomous_system_organization", "Telstra Pty Ltd");
+        exp_geoip_attr.insert("isp", "Telstra Internet");
+        exp_geoip_attr.insert("organization", "Telstra Internet");
+        assert_geoip_fields(&new_event, exp_geoip_attr);
+    }
+
+    #[test]
+    fn geoip_lookup_connection_type() {
+        let mut augment = geoip("tests/data/GeoIP2-Connection-Type-Test.mmdb", "en");
+
+        for &(address, connection_type) in &[
+            ("1.0.0.1", "Dialup"),
+            ("1.0.1.1", "Cable/DSL"),
+            ("80.214.0.1", "Cellular"),
+            ("10.1.12.1", ""),
+        ] {
+            let new_event = lookup(&mut augment, address);
+            let mut exp_geoip_attr = HashMap::new();
+            exp_geoip_attr.insert("connection_type", connection_type);
+            assert_geoip_fields(&new_event, exp_geoip_attr);
+        }
+    }
+
+    #[test]
+    fn geoip_lookup_anonymous_ip() {
+        let mut augment = geoip("tests/data/GeoIP2-Anonymous-IP-Test.mmdb", "en");
+
+        // Flags missing from the record are reported as false.
+        let new_event = lookup(&mut augment, "1.2.0.1");
+        let mut exp_geoip_attr = HashMap::new();
+        exp_geoip_attr.insert("is_anonymous", "true");
+        exp_geoip_attr.insert("is_anonymous_vpn", "true");
+        exp_geoip_attr.insert("is_hosting_provider", "false");
+        exp_geoip_attr.insert("is_public_proxy", "false");
+        exp_geoip_attr.insert("is_tor_exit_node", "false");
+        assert_geoip_fields(&new_event, exp_geoip_attr);
+
+        let new_event = lookup(&mut augment, "81.2.69.142");
+        let mut exp_geoip_attr = HashMap::new();
+        exp_geoip_attr.insert("is_anonymous", "true");
+        exp_geoip_attr.insert("is_anonymous_vpn", "true");
+        exp_geoip_attr.insert("is_hosting_provider", "true");
+        exp_geoip_attr.insert("is_public_proxy", "true");
+        exp_geoip_attr.insert("is_tor_exit_node", "true");
+        assert_geoip_fields(&new_event, exp_geoip_attr);
+    }
+
+    #[test]
+    fn geoip_database_kinds() {
+        let kinds = vec![
+            ("GeoIP2-City", Some(DatabaseKind::City)),
+            ("GeoLite2-City", Some(DatabaseKind::City)),
+            ("GeoIP2-City-Europe", Some(DatabaseKind::City)),
+            ("GeoIP2-City-Asia-Pacific", Some(DatabaseKind::City)),
+            ("DBIP-City-Lite", Some(DatabaseKind::City)),
+            ("GeoIP2-Enterprise", Some(DatabaseKind::City)),
+            ("GeoIP2-Precision-Enterprise", Some(DatabaseKind::City)),
+            ("GeoLite2-Country", Some(DatabaseKind::Country)),
+            ("DBIP-Country-Lite", Some(DatabaseKind::Country)),
+            ("GeoLite2-ASN", Some(DatabaseKind::Asn)),
+            ("DBIP-ASN-Lite", Some(DatabaseKind::Asn)),
+            ("GeoIP2-ISP", Some(DatabaseKind::Isp)),
+            ("GeoIP2-Connection-Type", Some(DatabaseKind::ConnectionType)),
+            ("GeoIP2-Anonymous-IP", Some(DatabaseKind::AnonymousIp)),
+            ("GeoIP2-Domain", None),
+        ];
+        for (database_type, kind) in kinds {
+            assert_eq!(DatabaseKind::from_database_type(database_type), kind);
         }
     }
+
+    #[test]
+    fn geoip_reloads_replaced_database() {
+        let path = crate::test_util::temp_file();
+        std::fs::copy("tests/data/GeoIP2-City-Test.mmdb", &path).unwrap();
+        let mut augment = geoip(path.to_str().unwrap(), "en");
+        assert!(!augment.database.reload_if_changed());
+
+        // Replace the file with an invalid database, which is ignored.
+        std::fs::write(&path, "not a database").unwrap();
+        assert!(!augment.database.reload_if_changed());
+        let new_event = lookup(&mut augment, "2.125.160.216");
+        let mut exp_geoip_attr = HashMap::new();
+        exp_geoip_attr.insert("city_name", "Boxford");
+        assert_geoip_fields(&new_event, exp_geoip_attr);
+
+        std::fs::copy("tests/data/GeoIP2-City-Test.mmdb", &path).unwrap();
+        assert!(augment.database.reload_if_changed());
+    }
+
+    #[test]
+    fn geoip_reloads_in_background() {
+        let path = crate::test_util::temp_file();
+        std::fs::copy("tests/data/GeoIP2-City-Test.mmdb", &path).unwrap();
+        let mut augment = geoip(path.to_str().unwrap(), "en");
+        augment.database.watch();
+
+        std::fs::copy("tests/data/GeoLite2-ASN-Test.mmdb", &path).unwrap();
+        let reloaded = (0..50).any(|_| {
+            thread::sleep(Duration::from_millis(100));
+            let new_event = lookup(&mut augment, "1.128.0.1");
+            new_event
+                .as_log()
+                .get(&Atom::from("geo.autonomous_system_number"))
+                .is_some()
+        });
+        assert!(reloaded);
+    }
 }
@@ -4,17 +4,34 @@ use crate::{
     topology::config::{DataType, TransformConfig, TransformContext, TransformDescription},
     types::{parse_conversion_map_no_atoms, Conversion},
 };
-use grok::Pattern;
+use grok::{Grok, Pattern};
 use serde::{Deserialize, Serialize};
 use snafu::{ResultExt, Snafu};
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::str;
 use string_cache::DefaultAtom as Atom;
 
 #[derive(Debug, Snafu)]
 enum BuildError {
-    #[snafu(display("Invalid grok pattern: {}", source))]
-    InvalidGrok { source: grok::Error },
+    #[snafu(display("Invalid grok pattern {:?}: {}", pattern, source))]
+    InvalidGrok {
+        pattern: String,
+        source: grok::Error,
+    },
+    #[snafu(display("At least one of pattern or patterns must be set"))]
+    NoPatterns,
+    #[snafu(display("Could not read pattern file {:?}: {}", path, source))]
+    ReadPatternFile {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[snafu(display(
+        "Invalid definition on line {} of pattern file {:?}, expected \"NAME pattern\"",
+        line,
+        path
+    ))]
+    InvalidPatternDefinition { path: PathBuf, line: usize },
 }
 
 #[derive(Deserialize, Serialize, Debug, Derivative)]
@@ -22,6 +39,9 @@ enum BuildError {
 #[derivative(Default)]
 pub struct GrokParserConfig {
     pub pattern: String,
+    pub patterns: Vec<String>,
+    pub pattern_files: Vec<PathBuf>,
+    pub matched_pattern_field: Option<Atom>,
     pub field: Option<Atom>,
     #[derivative(Default(value = "true"))]
     pub drop_field: bool,
@@ -40,22 +60,36 @@ impl TransformConfig for GrokParserConfig {
             .as_ref()
             .unwrap_or(&event::log_schema().message_key());
 
-        let mut grok = grok::Grok::with_patterns();
+        let mut grok = Grok::with_patterns();
+        for path in &self.pattern_files {
+            load_pattern_file(&mut grok, path)?;
+        }
 
         let types = parse_conversion_map_no_atoms(&self.types)?;
 
-        Ok(grok
-            .compile(&self.pattern, true)
-            .map::<Box<dyn Transform>, _>(|p| {
-                Box::new(GrokParser {
-                    pattern: p,
-                    field: field.clone(),
-                    drop_field: self.drop_field,
-                    types,
-                    paths: HashMap::new(),
-                })
+        // The single `pattern` is tried first, then each of `patterns` in turn.
+        let patterns = Some(&self.pattern)
+            .filter(|pattern| !pattern.is_empty())
+            .into_iter()
+            .chain(self.patterns.iter())
+            .map(|pattern| {
+                grok.compile(pattern, true)
+                    .map(|compiled| (pattern.clone(), compiled))
+                    .context(InvalidGrok { pattern })
             })
-            .context(InvalidGrok)?)
+            .collect::<Result<Vec<_>, _>>()?;
+        if patterns.is_empty() {
+            return Err(BuildError::NoPatterns.into());
+        }
+
+        Ok(Box::new(GrokParser {
+            patterns,
+            matched_pattern_field: self.matched_pattern_field.clone(),
+            field: field.clone(),
+            drop_field: self.drop_field,
+            types,
+            paths: HashMap::new(),
+        }))
     }
 
     fn input_type(&self) -> DataType {
@@ -71,8 +105,37 @@ impl TransformConfig for GrokParserConfig {
     }
 }
 
+/// Adds the definitions of a pattern file to the library, one `NAME pattern`
+/// definition per line. Empty lines and lines starting with `#` are ignored.
+fn load_pattern_file(grok: &mut Grok, path: &Path) -> Result<(), BuildError> {
+    let contents = std::fs::read_to_string(path).context(ReadPatternFile { path })?;
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut parts = line.splitn(2, char::is_whitespace);
+        match (parts.next(), parts.next().map(str::trim)) {
+            (Some(name), Some(pattern)) if !pattern.is_empty() => {
+                grok.insert_definition(name.to_owned(), pattern.to_owned())
+            }
+            _ => {
+                return Err(BuildError::InvalidPatternDefinition {
+                    path: path.to_owned(),
+                    line: index + 1,
+                })
+            }
+        }
+    }
+
+    Ok(())
+}
+
 pub struct GrokParser {
-    pattern: Pattern,
+    patterns: Vec<(String, Pattern)>,
+    matched_pattern_field: Option<Atom>,
     field: Atom,
     drop_field: bool,
     types: HashMap<String, Conversion>,
@@ -85,7 +148,13 @@ impl Transform for GrokParser {
         let value = event.get(&self.field).map(|s| s.to_string_lossy());
 
         if let Some(value) = value {
-            if let Some(matches) = self.pattern.match_against(&value) {
+            let matched = self.patterns.iter().find_map(|(pattern, compiled)| {
+                compiled
+                    .match_against(&value)
+                    .map(|matches| (pattern, matches))
+            });
+
+            if let Some((pattern, matches)) = matched {
                 let drop_field = self.drop_field && !matches.get(&self.field).is_some();
                 for (name, value) in matches.iter() {
                     let conv = self.types.get(name).unwrap_or(&Conversion::Bytes);
@@ -113,6 +182,10 @@ impl Transform for GrokParser {
                 if drop_field {
                     event.remove(&self.field);
                 }
+
+                if let Some(matched_pattern_field) = &self.matched_pattern_field {
+                    event.insert(matched_pattern_field.clone(), pattern.clone());
+                }
             } else {
                 debug!(message = "No fields captured from grok pattern.");
             }
@@ -132,6 +205,7 @@ impl Transform for GrokParser {
 mod tests {
     use super::GrokParserConfig;
     use crate::event::LogEvent;
+    use crate::test_util::temp_file;
     use crate::{
         event,
         topology::config::{TransformConfig, TransformContext},
@@ -154,6 +228,7 @@ mod tests {
             field: field.map(|s| s.into()),
             drop_field,
             types: types.iter().map(|&(k, v)| (k.into(), v.into())).collect(),
+            ..Default::default()
         }
         .build(TransformContext::new_test(rt.executor()))
         .unwrap();
@@ -302,4 +377,98 @@ mod tests {
 
         assert_eq!(expected, serde_json::to_value(&event.all_fields()).unwrap());
     }
+
+    fn parse_log_with(message: &str, config: &str) -> LogEvent {
+        let rt = crate::runtime::Runtime::single_threaded().unwrap();
+        let mut parser = toml::from_str::<GrokParserConfig>(config)
+            .unwrap()
+            .build(TransformContext::new_test(rt.executor()))
+            .unwrap();
+        let mut event = Event::new_empty_log();
+        event
+            .as_mut_log()
+            .insert(event::log_schema().message_key().clone(), message);
+        parser.transform(event).unwrap().into_log()
+    }
+
+    #[test]
+    fn grok_parser_tries_patterns_in_order() {
+        let config = r#"
+            patterns = [
+                "%{IP:src} -> %{IP:dst} %{WORD:action}",
+                "%{WORD:action} from %{IP:src}",
+            ]
+            matched_pattern_field = "grok_pattern"
+            types.action = "string"
+        "#;
+
+        let event = parse_log_with("10.0.0.1 -> 10.0.0.2 DENY", config);
+        let expected = json!({
+            "src": "10.0.0.1",
+            "dst": "10.0.0.2",
+            "action": "DENY",
+            "grok_pattern": "%{IP:src} -> %{IP:dst} %{WORD:action}",
+        });
+        assert_eq!(expected, serde_json::to_value(&event.all_fields()).unwrap());
+
+        let event = parse_log_with("ACCEPT from 10.0.0.3", config);
+        let expected = json!({
+            "src": "10.0.0.3",
+            "action": "ACCEPT",
+            "grok_pattern": "%{WORD:action} from %{IP:src}",
+        });
+        assert_eq!(expected, serde_json::to_value(&event.all_fields()).unwrap());
+
+        let event = parse_log_with("something else", config);
+        assert_eq!(1, event.keys().count());
+        assert!(event.get(&"grok_pattern".into()).is_none());
+    }
+
+    #[test]
+    fn grok_parser_loads_pattern_files() {
+        let path = temp_file();
+        std::fs::write(
+            &path,
+            "# firewall patterns\n\nFW_ACTION (?:ACCEPT|DENY)\nFW_RULE rule=%{INT:rule} %{FW_ACTION:action}\n",
+        )
+        .unwrap();
+
+        let event = parse_log_with(
+            "rule=42 DENY",
+            &format!(
+                r#"
+                pattern = "%{{FW_RULE}}"
+                pattern_files = [{:?}]
+                types.rule = "int"
+                "#,
+                path
+            ),
+        );
+        let expected = json!({
+            "rule": 42,
+            "action": "DENY",
+        });
+        assert_eq!(expected, serde_json::to_value(&event.all_fields()).unwrap());
+    }
+
+    #[test]
+    fn grok_parser_rejects_invalid_configs() {
+        let rt = crate::runtime::Runtime::single_threaded().unwrap();
+        let build = |config: &str| {
+            toml::from_str::<GrokParserConfig>(config)
+                .unwrap()
+                .build(TransformContext::new_test(rt.executor()))
+        };
+
+        assert!(build("").is_err());
+        assert!(build(r#"patterns = ["%{NOT_A_PATTERN}"]"#).is_err());
+
+        let path = temp_file();
+        std::fs::write(&path, "JUST_A_NAME\n").unwrap();
+        assert!(build(&format!(
+            "pattern = \"%{{INT}}\"\npattern_files = [{:?}]",
+            path
+        ))
+        .is_err());
+    }
 }
@@ -1,11 +1,11 @@
 use super::Transform;
 use crate::{
-    event::{self, Event},
+    event::{self, Event, LogEvent},
     internal_events::{JsonEventProcessed, JsonFailedParse},
     topology::config::{DataType, TransformConfig, TransformContext, TransformDescription},
 };
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
 use string_cache::DefaultAtom as Atom;
 
 #[derive(Deserialize, Serialize, Debug, Clone, Derivative)]
@@ -18,6 +18,16 @@ pub struct JsonParserConfig {
     pub drop_field: bool,
     pub target_field: Option<String>,
     pub overwrite_target: Option<bool>,
+    pub max_depth: Option<usize>,
+    pub explode_arrays: bool,
+    pub flatten: bool,
+    #[derivative(Default(value = "default_flatten_separator()"))]
+    pub flatten_separator: String,
+    pub only_fields: Option<Vec<String>>,
+}
+
+fn default_flatten_separator() -> String {
+    ".".into()
 }
 
 inventory::submit! {
@@ -27,6 +37,12 @@ inventory::submit! {
 #[typetag::serde(name = "json_parser")]
 impl TransformConfig for JsonParserConfig {
     fn build(&self, _cx: TransformContext) -> crate::Result<Box<dyn Transform>> {
+        if self.max_depth == Some(0) {
+            return Err("`max_depth` must be greater than zero".into());
+        }
+        if self.flatten && self.flatten_separator.is_empty() {
+            return Err("`flatten_separator` must not be empty".into());
+        }
         Ok(Box::new(JsonParser::from(self.clone())))
     }
 
@@ -50,6 +66,10 @@ pub struct JsonParser {
     drop_field: bool,
     target_field: Option<Atom>,
     overwrite_target: bool,
+    max_depth: Option<usize>,
+    explode_arrays: bool,
+    flatten_separator: Option<String>,
+    only_fields: Option<Vec<Vec<String>>>,
 }
 
 impl From<JsonParserConfig> for JsonParser {
@@ -66,66 +86,211 @@ impl From<JsonParserConfig> for JsonParser {
             drop_field: config.drop_field,
             target_field: config.target_field.map(Atom::from),
             overwrite_target: config.overwrite_target.unwrap_or(false),
+            max_depth: config.max_depth,
+            explode_arrays: config.explode_arrays,
+            flatten_separator: if config.flatten {
+                Some(config.flatten_separator)
+            } else {
+                None
+            },
+            only_fields: config.only_fields.map(|fields| {
+                fields
+                    .iter()
+                    .map(|field| field.split('.').map(Into::into).collect())
+                    .collect()
+            }),
         }
     }
 }
 
-impl Transform for JsonParser {
-    fn transform(&mut self, mut event: Event) -> Option<Event> {
-        let log = event.as_mut_log();
-        let to_parse = log.get(&self.field).map(|s| s.as_bytes());
+/// Looks up the value at a path of nested objects.
+fn select<'a>(object: &'a Map<String, Value>, path: &[String]) -> Option<&'a Value> {
+    let (first, rest) = path.split_first()?;
+    let value = object.get(first)?;
+    if rest.is_empty() {
+        Some(value)
+    } else {
+        select(value.as_object()?, rest)
+    }
+}
 
-        emit!(JsonEventProcessed);
+/// Inserts a value at a path of nested objects, creating missing objects.
+fn insert_path(object: &mut Map<String, Value>, path: &[String], value: Value) {
+    if let Some((first, rest)) = path.split_first() {
+        if rest.is_empty() {
+            object.insert(first.clone(), value);
+        } else if let Value::Object(child) = object
+            .entry(first.clone())
+            .or_insert_with(|| Value::Object(Map::new()))
+        {
+            insert_path(child, rest, value);
+        }
+    }
+}
 
-        let parsed = to_parse
-            .and_then(|to_parse| {
-                serde_json::from_slice::<Value>(to_parse.as_ref())
-                    .map_err(|error| {
-                        emit!(JsonFailedParse {
-                            field: &self.field,
-                            error: error
-                        })
-                    })
-                    .ok()
-            })
-            .and_then(|value| {
-                if let Value::Object(object) = value {
-                    Some(object)
-                } else {
-                    None
+/// Replaces the objects and arrays nested `max_depth` levels deep with their
+/// JSON encoding.
+fn limit_depth(value: Value, depth: usize, max_depth: usize) -> Value {
+    match value {
+        Value::Object(_) | Value::Array(_) if depth >= max_depth => {
+            Value::String(value.to_string())
+        }
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .map(|(key, value)| (key, limit_depth(value, depth + 1, max_depth)))
+                .collect(),
+        ),
+        Value::Array(array) => Value::Array(
+            array
+                .into_iter()
+                .map(|value| limit_depth(value, depth + 1, max_depth))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+fn flatten_into(
+    prefix: Option<&str>,
+    object: Map<String, Value>,
+    separator: &str,
+    flat: &mut Map<String, Value>,
+) {
+    for (key, value) in object {
+        let key = match prefix {
+            Some(prefix) => format!("{}{}{}", prefix, separator, key),
+            None => key,
+        };
+        match value {
+            Value::Object(inner) if !inner.is_empty() => {
+                flatten_into(Some(&key), inner, separator, flat)
+            }
+            value => {
+                flat.insert(key, value);
+            }
+        }
+    }
+}
+
+impl JsonParser {
+    fn process(&self, mut object: Map<String, Value>) -> Map<String, Value> {
+        if let Some(only_fields) = &self.only_fields {
+            let mut selected = Map::new();
+            for path in only_fields {
+                if let Some(value) = select(&object, path) {
+                    insert_path(&mut selected, path, value.clone());
                 }
-            });
+            }
+            object = selected;
+        }
 
-        if let Some(object) = parsed {
-            match self.target_field {
-                Some(ref target_field) => {
-                    let contains_target = log.contains(&target_field);
+        if let Some(max_depth) = self.max_depth {
+            object = object
+                .into_iter()
+                .map(|(key, value)| (key, limit_depth(value, 1, max_depth)))
+                .collect();
+        }
 
-                    if contains_target && !self.overwrite_target {
-                        error!(message = "target field already exists", %target_field);
-                    } else {
-                        if self.drop_field {
-                            log.remove(&self.field);
-                        }
+        if let Some(separator) = &self.flatten_separator {
+            let mut flat = Map::new();
+            flatten_into(None, object, separator, &mut flat);
+            object = flat;
+        }
 
-                        log.insert(&target_field, Value::Object(object));
-                    }
-                }
-                None => {
+        object
+    }
+
+    fn merge(&self, log: &mut LogEvent, object: Map<String, Value>) {
+        match self.target_field {
+            Some(ref target_field) => {
+                let contains_target = log.contains(&target_field);
+
+                if contains_target && !self.overwrite_target {
+                    error!(message = "target field already exists", %target_field);
+                } else {
                     if self.drop_field {
                         log.remove(&self.field);
                     }
 
-                    for (key, value) in object {
+                    log.insert(&target_field, Value::Object(object));
+                }
+            }
+            None => {
+                if self.drop_field {
+                    log.remove(&self.field);
+                }
+
+                for (key, value) in object {
+                    // Flattened keys may contain dots, which must not be
+                    // read as paths.
+                    if self.flatten_separator.is_some() {
+                        log.insert_flat(key, value);
+                    } else {
                         log.insert(key, value);
                     }
                 }
             }
-        } else if self.drop_invalid {
-            return None;
+        }
+    }
+}
+
+impl Transform for JsonParser {
+    // Only used in tests
+    fn transform(&mut self, event: Event) -> Option<Event> {
+        let mut output = Vec::with_capacity(1);
+        self.transform_into(&mut output, event);
+        output.pop()
+    }
+
+    fn transform_into(&mut self, output: &mut Vec<Event>, mut event: Event) {
+        let log = event.as_mut_log();
+        let to_parse = log.get(&self.field).map(|s| s.as_bytes());
+
+        emit!(JsonEventProcessed);
+
+        let parsed = to_parse.and_then(|to_parse| {
+            serde_json::from_slice::<Value>(to_parse.as_ref())
+                .map_err(|error| {
+                    emit!(JsonFailedParse {
+                        field: &self.field,
+                        error: error
+                    })
+                })
+                .ok()
+        });
+
+        let objects = match parsed {
+            Some(Value::Object(object)) => vec![object],
+            Some(Value::Array(array)) if self.explode_arrays => array
+                .into_iter()
+                .filter_map(|value| match value {
+                    Value::Object(object) => Some(object),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        if objects.is_empty() {
+            if !self.drop_invalid {
+                output.push(event);
+            }
+            return;
         }
 
-        Some(event)
+        // Every object but the last gets a copy of the event.
+        let mut event = Some(event);
+        let mut objects = objects.into_iter().peekable();
+        while let Some(object) = objects.next() {
+            let mut event = if objects.peek().is_some() {
+                event.clone().unwrap()
+            } else {
+                event.take().unwrap()
+            };
+            self.merge(event.as_mut_log(), self.process(object));
+            output.push(event);
+        }
     }
 }
 
@@ -503,4 +668,104 @@ mod test {
         assert_eq!(event[&Atom::from("message.greeting")], "hello".into());
         assert_eq!(event[&Atom::from("message.name")], "bob".into());
     }
+
+    #[test]
+    fn json_parser_max_depth() {
+        let mut parser = JsonParser::from(JsonParserConfig {
+            max_depth: Some(2),
+            ..Default::default()
+        });
+
+        let event =
+            Event::from(r#"{"a": {"b": {"c": 1}, "d": [1, [2]]}, "e": [{"f": 1}], "g": 1}"#);
+        let event = parser.transform(event).unwrap();
+        let log = event.as_log();
+
+        assert_eq!(log[&Atom::from("a.b")], r#"{"c":1}"#.into());
+        assert_eq!(log[&Atom::from("a.d")], "[1,[2]]".into());
+        assert_eq!(log[&Atom::from("e[0]")], r#"{"f":1}"#.into());
+        assert_eq!(log[&Atom::from("g")], 1.into());
+    }
+
+    #[test]
+    fn json_parser_explode_arrays() {
+        let mut parser = JsonParser::from(JsonParserConfig {
+            explode_arrays: true,
+            ..Default::default()
+        });
+
+        let mut event = Event::from(r#"[{"id": 1}, "skipped", {"id": 2}]"#);
+        event.as_mut_log().insert("batch", "b1");
+        let mut output = Vec::new();
+        parser.transform_into(&mut output, event);
+
+        assert_eq!(output.len(), 2);
+        for (event, id) in output.iter().zip(&[1, 2]) {
+            let log = event.as_log();
+            assert_eq!(log[&Atom::from("id")], (*id).into());
+            assert_eq!(log[&Atom::from("batch")], "b1".into());
+            assert!(log.get(&event::log_schema().message_key()).is_none());
+        }
+
+        // Empty arrays are invalid.
+        let event = Event::from("[]");
+        assert_eq!(parser.transform(event.clone()), Some(event));
+
+        // Arrays are only exploded when enabled.
+        let mut parser = JsonParser::from(JsonParserConfig {
+            drop_invalid: true,
+            ..Default::default()
+        });
+        assert!(parser.transform(Event::from(r#"[{"id": 1}]"#)).is_none());
+    }
+
+    #[test]
+    fn json_parser_flatten() {
+        let mut parser = JsonParser::from(JsonParserConfig {
+            flatten: true,
+            ..Default::default()
+        });
+
+        let event = Event::from(r#"{"a": {"b": {"c": 1}, "d": [{"e": 2}]}, "f": {}}"#);
+        let mut event = parser.transform(event).unwrap();
+        event
+            .as_mut_log()
+            .remove(&event::log_schema().timestamp_key());
+
+        // Flattened keys are fields of their own rather than paths.
+        assert_eq!(
+            serde_json::to_value(event.as_log()).unwrap(),
+            serde_json::json!({"a.b.c": 1, "a.d": [{"e": 2}], "f": {}})
+        );
+
+        let mut parser = JsonParser::from(JsonParserConfig {
+            flatten: true,
+            flatten_separator: "_".into(),
+            target_field: Some("parsed".into()),
+            ..Default::default()
+        });
+
+        let event = Event::from(r#"{"a": {"b": 1}}"#);
+        let event = parser.transform(event).unwrap();
+
+        assert_eq!(event.as_log()[&Atom::from("parsed.a_b")], 1.into());
+    }
+
+    #[test]
+    fn json_parser_only_fields() {
+        let mut parser = JsonParser::from(JsonParserConfig {
+            only_fields: Some(vec!["a.b".into(), "c".into(), "missing.field".into()]),
+            ..Default::default()
+        });
+
+        let event = Event::from(r#"{"a": {"b": 1, "x": 2}, "c": [3], "d": 4}"#);
+        let event = parser.transform(event).unwrap();
+        let log = event.as_log();
+
+        assert_eq!(log[&Atom::from("a.b")], 1.into());
+        assert_eq!(log[&Atom::from("c[0]")], 3.into());
+        assert!(log.get(&Atom::from("a.x")).is_none());
+        assert!(log.get(&Atom::from("d")).is_none());
+        assert!(log.get(&Atom::from("missing")).is_none());
+    }
 }
@@ -0,0 +1,395 @@
+use super::Transform;
+use crate::{
+    event::{self, Event, Value},
+    internal_events::{
+        KeyValueParserEventProcessed, KeyValueParserFailedParse, KeyValueParserMissingField,
+    },
+    topology::config::{DataType, TransformConfig, TransformContext, TransformDescription},
+    types::{parse_conversion_map, Conversion},
+};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use string_cache::DefaultAtom as Atom;
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct KeyValueParserConfig {
+    pub field: Option<Atom>,
+    pub field_delimiter: Option<String>,
+    #[serde(default = "default_separator")]
+    pub separator: String,
+    #[serde(default = "default_quotes")]
+    pub quotes: Vec<char>,
+    #[serde(default = "crate::serde::default_true")]
+    pub trim_whitespace: bool,
+    #[serde(default)]
+    pub duplicate_keys: DuplicateKeys,
+    #[serde(default = "crate::serde::default_true")]
+    pub drop_field: bool,
+    #[serde(default)]
+    pub drop_failed: bool,
+    pub target_field: Option<Atom>,
+    #[serde(default)]
+    pub types: HashMap<Atom, String>,
+}
+
+fn default_separator() -> String {
+    "=".into()
+}
+
+fn default_quotes() -> Vec<char> {
+    vec!['"', '\'']
+}
+
+/// Which values to keep for keys that appear more than once.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateKeys {
+    First,
+    Last,
+    Array,
+}
+
+impl Default for DuplicateKeys {
+    fn default() -> Self {
+        DuplicateKeys::Last
+    }
+}
+
+inventory::submit! {
+    TransformDescription::new_without_default::<KeyValueParserConfig>("key_value_parser")
+}
+
+#[typetag::serde(name = "key_value_parser")]
+impl TransformConfig for KeyValueParserConfig {
+    fn build(&self, _cx: TransformContext) -> crate::Result<Box<dyn Transform>> {
+        Ok(Box::new(KeyValueParser::new(self)?))
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn transform_type(&self) -> &'static str {
+        "key_value_parser"
+    }
+}
+
+pub struct KeyValueParser {
+    field: Atom,
+    field_delimiter: Option<String>,
+    separator: String,
+    quotes: Vec<char>,
+    trim_whitespace: bool,
+    duplicate_keys: DuplicateKeys,
+    drop_field: bool,
+    drop_failed: bool,
+    target_field: Option<Atom>,
+    conversions: HashMap<Atom, Conversion>,
+}
+
+impl KeyValueParser {
+    pub fn new(config: &KeyValueParserConfig) -> crate::Result<Self> {
+        if config.separator.is_empty() {
+            return Err("`separator` must not be empty".into());
+        }
+        if config
+            .field_delimiter
+            .as_ref()
+            .map_or(false, String::is_empty)
+        {
+            return Err("`field_delimiter` must not be empty".into());
+        }
+
+        Ok(Self {
+            field: config
+                .field
+                .clone()
+                .unwrap_or_else(|| event::log_schema().message_key().clone()),
+            field_delimiter: config.field_delimiter.clone(),
+            separator: config.separator.clone(),
+            quotes: config.quotes.clone(),
+            trim_whitespace: config.trim_whitespace,
+            duplicate_keys: config.duplicate_keys,
+            drop_field: config.drop_field,
+            drop_failed: config.drop_failed,
+            target_field: config.target_field.clone(),
+            conversions: parse_conversion_map(&config.types)?,
+        })
+    }
+
+    /// Finds the first `delimiter` outside of quotes, or the first run of
+    /// whitespace without one, returning its start and end.
+    fn find_delimiter(&self, s: &str, delimiter: Option<&str>) -> Option<(usize, usize)> {
+        let mut quote = None;
+        let mut escaped = false;
+        for (index, c) in s.char_indices() {
+            if let Some(open) = quote {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == open {
+                    quote = None;
+                }
+            } else if self.quotes.contains(&c) && self.opens_quote(&s[..index]) {
+                quote = Some(c);
+            } else {
+                match delimiter {
+                    Some(delimiter) if s[index..].starts_with(delimiter) => {
+                        return Some((index, index + delimiter.len()));
+                    }
+                    None if c.is_whitespace() => {
+                        let end = s[index..]
+                            .find(|c: char| !c.is_whitespace())
+                            .map_or(s.len(), |length| index + length);
+                        return Some((index, end));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        None
+    }
+
+    /// Quotes only start at the beginning of keys and values, so apostrophes
+    /// within unquoted values are kept as they are.
+    fn opens_quote(&self, before: &str) -> bool {
+        let before = before.trim_end();
+        before.is_empty() || before.ends_with(self.separator.as_str())
+    }
+
+    fn trim<'a>(&self, s: &'a str) -> &'a str {
+        if self.trim_whitespace {
+            s.trim()
+        } else {
+            s
+        }
+    }
+
+    /// Removes the quotes around a key or value, reverting the escaping of
+    /// quotes and backslashes within.
+    fn unquote(&self, s: &str) -> String {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next_back()) {
+            (Some(first), Some(last)) if first == last && self.quotes.contains(&first) => {
+                let mut unquoted = String::with_capacity(s.len());
+                let mut chars = chars;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => match chars.next() {
+                            Some(next) if next == first || next == '\\' => unquoted.push(next),
+                            Some(next) => {
+                                unquoted.push(c);
+                                unquoted.push(next);
+                            }
+                            None => unquoted.push(c),
+                        },
+                        c => unquoted.push(c),
+                    }
+                }
+                unquoted
+            }
+            _ => s.to_owned(),
+        }
+    }
+
+    fn parse(&self, mut s: &str) -> Vec<(String, String)> {
+        let mut pairs = Vec::new();
+        while !s.is_empty() {
+            let (field, rest) = match self.find_delimiter(s, self.field_delimiter.as_deref()) {
+                Some((start, end)) => (&s[..start], &s[end..]),
+                None => (s, ""),
+            };
+            s = rest;
+
+            let field = self.trim(field);
+            if let Some((start, end)) = self.find_delimiter(field, Some(self.separator.as_str())) {
+                let key = self.unquote(self.trim(&field[..start]));
+                if !key.is_empty() {
+                    pairs.push((key, self.unquote(self.trim(&field[end..]))));
+                }
+            }
+        }
+        pairs
+    }
+
+    fn convert(&self, key: &Atom, value: String) -> Option<Value> {
+        match self.conversions.get(key) {
+            Some(conversion) => match conversion.convert(Value::from(value)) {
+                Ok(value) => Some(value),
+                Err(error) => {
+                    debug!(
+                        message = "Could not convert types.",
+                        key = &key[..],
+                        %error,
+                        rate_limit_secs = 30
+                    );
+                    None
+                }
+            },
+            None => Some(Value::from(value)),
+        }
+    }
+}
+
+impl Transform for KeyValueParser {
+    fn transform(&mut self, mut event: Event) -> Option<Event> {
+        emit!(KeyValueParserEventProcessed);
+
+        let log = event.as_mut_log();
+        let value = match log.get(&self.field) {
+            Some(value) => value.to_string_lossy(),
+            None => {
+                emit!(KeyValueParserMissingField { field: &self.field });
+                return if self.drop_failed { None } else { Some(event) };
+            }
+        };
+
+        let pairs = self.parse(&value);
+        if pairs.is_empty() {
+            emit!(KeyValueParserFailedParse { value: &value });
+            return if self.drop_failed { None } else { Some(event) };
+        }
+
+        let mut values = IndexMap::<Atom, Vec<Value>>::new();
+        for (key, value) in pairs {
+            let key = Atom::from(key);
+            if let Some(value) = self.convert(&key, value) {
+                values.entry(key).or_default().push(value);
+            }
+        }
+
+        // Removed first, so keys of the same name replace it.
+        if self.drop_field {
+            log.remove(&self.field);
+        }
+
+        for (key, mut values) in values {
+            let value = match self.duplicate_keys {
+                DuplicateKeys::First => values.swap_remove(0),
+                DuplicateKeys::Last => values.pop().unwrap(),
+                DuplicateKeys::Array if values.len() == 1 => values.pop().unwrap(),
+                DuplicateKeys::Array => Value::Array(values),
+            };
+            let key = match &self.target_field {
+                Some(target) => Atom::from(format!("{}.{}", target, key)),
+                None => key,
+            };
+            log.insert(key, value);
+        }
+
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KeyValueParserConfig;
+    use crate::{
+        event::{LogEvent, Value},
+        topology::config::{TransformConfig, TransformContext},
+        Event,
+    };
+
+    fn do_transform(message: &str, config: &str) -> Option<LogEvent> {
+        let rt = crate::runtime::Runtime::single_threaded().unwrap();
+        let mut parser = toml::from_str::<KeyValueParserConfig>(config)
+            .unwrap()
+            .build(TransformContext::new_test(rt.executor()))
+            .unwrap();
+
+        parser
+            .transform(Event::from(message))
+            .map(|event| event.into_log())
+    }
+
+    #[test]
+    fn key_value_parser_whitespace_delimited() {
+        let log = do_transform(
+            r#"user=bob action="log in" msg='it\'s done' note=it's path=/a=b empty="#,
+            "",
+        )
+        .unwrap();
+
+        assert_eq!(log[&"user".into()], "bob".into());
+        assert_eq!(log[&"action".into()], "log in".into());
+        assert_eq!(log[&"msg".into()], "it's done".into());
+        assert_eq!(log[&"note".into()], "it's".into());
+        assert_eq!(log[&"path".into()], "/a=b".into());
+        assert_eq!(log[&"empty".into()], "".into());
+        assert!(log.get(&"message".into()).is_none());
+    }
+
+    #[test]
+    fn key_value_parser_custom_delimiters_and_types() {
+        let log = do_transform(
+            r#"a: 1; b: "x; y" ;c:3.5;; flag : yes"#,
+            r#"
+                field_delimiter = ";"
+                separator = ":"
+                drop_field = false
+                types.a = "int"
+                types.c = "float"
+                types.flag = "bool"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(log[&"a".into()], Value::Integer(1));
+        assert_eq!(log[&"b".into()], "x; y".into());
+        assert_eq!(log[&"c".into()], Value::Float(3.5));
+        assert_eq!(log[&"flag".into()], Value::Boolean(true));
+        assert!(log.get(&"message".into()).is_some());
+    }
+
+    #[test]
+    fn key_value_parser_without_trimming() {
+        let log = do_transform(
+            "a = 1,b=2",
+            r#"
+                field_delimiter = ","
+                trim_whitespace = false
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(log[&"a ".into()], " 1".into());
+        assert_eq!(log[&"b".into()], "2".into());
+    }
+
+    #[test]
+    fn key_value_parser_duplicate_keys() {
+        let message = "tag=a other=x tag=b tag=c";
+
+        let log = do_transform(message, r#"duplicate_keys = "first""#).unwrap();
+        assert_eq!(log[&"tag".into()], "a".into());
+
+        let log = do_transform(message, "").unwrap();
+        assert_eq!(log[&"tag".into()], "c".into());
+
+        let log = do_transform(message, r#"duplicate_keys = "array""#).unwrap();
+        assert_eq!(
+            log[&"tag".into()],
+            Value::Array(vec!["a".into(), "b".into(), "c".into()])
+        );
+        assert_eq!(log[&"other".into()], "x".into());
+    }
+
+    #[test]
+    fn key_value_parser_target_field_and_failures() {
+        let log = do_transform("src=10.0.0.1 dst=10.0.0.2", r#"target_field = "kv""#).unwrap();
+        assert_eq!(log[&"kv.src".into()], "10.0.0.1".into());
+        assert_eq!(log[&"kv.dst".into()], "10.0.0.2".into());
+
+        let log = do_transform("no pairs here", "").unwrap();
+        assert_eq!(log[&"message".into()], "no pairs here".into());
+
+        assert!(do_transform("no pairs here", "drop_failed = true").is_none());
+    }
+}
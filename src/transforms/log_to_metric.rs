@@ -1,5 +1,6 @@
 use super::Transform;
 use crate::{
+    conditions::{AnyCondition, Condition},
     event::metric::{Metric, MetricKind, MetricValue},
     event::{self, Value},
     template::Template,
@@ -11,50 +12,62 @@ use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use string_cache::DefaultAtom as Atom;
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct LogToMetricConfig {
-    pub metrics: Vec<MetricConfig>,
+    pub metrics: Vec<MetricRuleConfig>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct MetricRuleConfig {
+    #[serde(flatten)]
+    pub metric: MetricConfig,
+    pub condition: Option<AnyCondition>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub struct CounterConfig {
     field: Atom,
-    name: Option<Atom>,
+    name: Option<Template>,
+    namespace: Option<String>,
     #[serde(default = "default_increment_by_value")]
     increment_by_value: bool,
-    tags: Option<IndexMap<Atom, String>>,
+    tags: Option<IndexMap<Atom, Template>>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub struct GaugeConfig {
     field: Atom,
-    name: Option<Atom>,
-    tags: Option<IndexMap<Atom, String>>,
+    name: Option<Template>,
+    namespace: Option<String>,
+    tags: Option<IndexMap<Atom, Template>>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub struct SetConfig {
     field: Atom,
-    name: Option<Atom>,
-    tags: Option<IndexMap<Atom, String>>,
+    name: Option<Template>,
+    namespace: Option<String>,
+    tags: Option<IndexMap<Atom, Template>>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub struct HistogramConfig {
     field: Atom,
-    name: Option<Atom>,
-    tags: Option<IndexMap<Atom, String>>,
+    name: Option<Template>,
+    namespace: Option<String>,
+    tags: Option<IndexMap<Atom, Template>>,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum MetricConfig {
     Counter(CounterConfig),
+    #[serde(alias = "distribution")]
     Histogram(HistogramConfig),
     Gauge(GaugeConfig),
     Set(SetConfig),
@@ -65,7 +78,7 @@ fn default_increment_by_value() -> bool {
 }
 
 pub struct LogToMetric {
-    config: LogToMetricConfig,
+    metrics: Vec<(MetricConfig, Option<Box<dyn Condition>>)>,
 }
 
 inventory::submit! {
@@ -75,7 +88,7 @@ inventory::submit! {
 #[typetag::serde(name = "log_to_metric")]
 impl TransformConfig for LogToMetricConfig {
     fn build(&self, _cx: TransformContext) -> crate::Result<Box<dyn Transform>> {
-        Ok(Box::new(LogToMetric::new(self.clone())))
+        Ok(Box::new(LogToMetric::new(self)?))
     }
 
     fn input_type(&self) -> DataType {
@@ -92,8 +105,16 @@ impl TransformConfig for LogToMetricConfig {
 }
 
 impl LogToMetric {
-    pub fn new(config: LogToMetricConfig) -> Self {
-        LogToMetric { config }
+    pub fn new(config: &LogToMetricConfig) -> crate::Result<Self> {
+        let mut metrics = Vec::with_capacity(config.metrics.len());
+        for rule in &config.metrics {
+            let condition = match &rule.condition {
+                Some(condition) => Some(condition.build()?),
+                None => None,
+            };
+            metrics.push((rule.metric.clone(), condition));
+        }
+        Ok(LogToMetric { metrics })
     }
 }
 
@@ -103,19 +124,33 @@ enum TransformError {
     ParseError(&'static str),
 }
 
-fn render_template(s: &str, event: &Event) -> Result<String, TransformError> {
-    let template = Template::from(s);
-    let name = template.render(&event).map_err(|e| {
+fn render_template(template: &Template, event: &Event) -> Result<String, TransformError> {
+    template.render_string(&event).map_err(|e| {
         TransformError::RenderError(format!(
             "Keys ({:?}) do not exist on the event. Dropping event.",
             e
         ))
-    })?;
-    Ok(String::from_utf8_lossy(&name.to_vec()).to_string())
+    })
+}
+
+fn render_name(
+    name: &Option<Template>,
+    namespace: &Option<String>,
+    field: &Atom,
+    event: &Event,
+) -> Result<String, TransformError> {
+    let name = match name {
+        Some(name) => render_template(name, event)?,
+        None => field.to_string(),
+    };
+    Ok(match namespace {
+        Some(namespace) => format!("{}.{}", namespace, name),
+        None => name,
+    })
 }
 
 fn render_tags(
-    tags: &Option<IndexMap<Atom, String>>,
+    tags: &Option<IndexMap<Atom, Template>>,
     event: &Event,
 ) -> Option<BTreeMap<String, String>> {
     match tags {
@@ -158,8 +193,7 @@ fn to_metric(config: &MetricConfig, event: &Event) -> Result<Metric, TransformEr
                 1.0
             };
 
-            let name = counter.name.as_ref().unwrap_or(&counter.field);
-            let name = render_template(&name, &event)?;
+            let name = render_name(&counter.name, &counter.namespace, &counter.field, &event)?;
 
             let tags = render_tags(&counter.tags, &event);
 
@@ -178,8 +212,7 @@ fn to_metric(config: &MetricConfig, event: &Event) -> Result<Metric, TransformEr
                 .parse()
                 .map_err(|_| TransformError::ParseError("histogram value"))?;
 
-            let name = hist.name.as_ref().unwrap_or(&hist.field);
-            let name = render_template(&name, &event)?;
+            let name = render_name(&hist.name, &hist.namespace, &hist.field, &event)?;
 
             let tags = render_tags(&hist.tags, &event);
 
@@ -201,8 +234,7 @@ fn to_metric(config: &MetricConfig, event: &Event) -> Result<Metric, TransformEr
                 .parse()
                 .map_err(|_| TransformError::ParseError("gauge value"))?;
 
-            let name = gauge.name.as_ref().unwrap_or(&gauge.field);
-            let name = render_template(&name, &event)?;
+            let name = render_name(&gauge.name, &gauge.namespace, &gauge.field, &event)?;
 
             let tags = render_tags(&gauge.tags, &event);
 
@@ -218,8 +250,7 @@ fn to_metric(config: &MetricConfig, event: &Event) -> Result<Metric, TransformEr
             let value = log.get(&set.field).ok_or(TransformError::FieldNotFound)?;
             let value = value.to_string_lossy();
 
-            let name = set.name.as_ref().unwrap_or(&set.field);
-            let name = render_template(&name, &event)?;
+            let name = render_name(&set.name, &set.namespace, &set.field, &event)?;
 
             let tags = render_tags(&set.tags, &event);
 
@@ -245,7 +276,12 @@ impl Transform for LogToMetric {
     }
 
     fn transform_into(&mut self, output: &mut Vec<Event>, event: Event) {
-        for config in self.config.metrics.iter() {
+        for (config, condition) in self.metrics.iter() {
+            if let Some(condition) = condition {
+                if !condition.check(&event) {
+                    continue;
+                }
+            }
             match to_metric(&config, &event) {
                 Ok(metric) => {
                     output.push(Event::Metric(metric));
@@ -299,7 +335,7 @@ mod tests {
         );
 
         let event = create_event("status", "42");
-        let mut transform = LogToMetric::new(config);
+        let mut transform = LogToMetric::new(&config).unwrap();
         let metric = transform.transform(event).unwrap();
 
         assert_eq!(
@@ -330,7 +366,7 @@ mod tests {
         event.as_mut_log().insert("method", "post");
         event.as_mut_log().insert("code", "200");
 
-        let mut transform = LogToMetric::new(config);
+        let mut transform = LogToMetric::new(&config).unwrap();
         let metric = transform.transform(event).unwrap();
 
         assert_eq!(
@@ -365,7 +401,7 @@ mod tests {
         );
 
         let event = create_event("backtrace", "message");
-        let mut transform = LogToMetric::new(config);
+        let mut transform = LogToMetric::new(&config).unwrap();
         let metric = transform.transform(event).unwrap();
 
         assert_eq!(
@@ -392,7 +428,7 @@ mod tests {
         );
 
         let event = create_event("success", "42");
-        let mut transform = LogToMetric::new(config);
+        let mut transform = LogToMetric::new(&config).unwrap();
         let metric = transform.transform(event);
 
         assert!(metric.is_none());
@@ -411,7 +447,7 @@ mod tests {
         );
 
         let event = create_event("amount", "33.99");
-        let mut transform = LogToMetric::new(config);
+        let mut transform = LogToMetric::new(&config).unwrap();
         let metric = transform.transform(event).unwrap();
 
         assert_eq!(
@@ -438,7 +474,7 @@ mod tests {
         );
 
         let event = create_event("memory_rss", "123");
-        let mut transform = LogToMetric::new(config);
+        let mut transform = LogToMetric::new(&config).unwrap();
         let metric = transform.transform(event).unwrap();
 
         assert_eq!(
@@ -466,7 +502,7 @@ mod tests {
         );
 
         let event = create_event("status", "not a number");
-        let mut transform = LogToMetric::new(config);
+        let mut transform = LogToMetric::new(&config).unwrap();
 
         assert!(transform.transform(event).is_none());
     }
@@ -483,7 +519,7 @@ mod tests {
         );
 
         let event = create_event("not foo", "not a number");
-        let mut transform = LogToMetric::new(config);
+        let mut transform = LogToMetric::new(&config).unwrap();
 
         assert!(transform.transform(event).is_none());
     }
@@ -510,7 +546,7 @@ mod tests {
         event.as_mut_log().insert("status", "42");
         event.as_mut_log().insert("backtrace", "message");
 
-        let mut transform = LogToMetric::new(config);
+        let mut transform = LogToMetric::new(&config).unwrap();
 
         let mut output = Vec::new();
         transform.transform_into(&mut output, event);
@@ -563,7 +599,7 @@ mod tests {
         event.as_mut_log().insert("worker", "abc");
         event.as_mut_log().insert("service", "xyz");
 
-        let mut transform = LogToMetric::new(config);
+        let mut transform = LogToMetric::new(&config).unwrap();
 
         let mut output = Vec::new();
         transform.transform_into(&mut output, event);
@@ -604,7 +640,7 @@ mod tests {
         );
 
         let event = create_event("user_ip", "1.2.3.4");
-        let mut transform = LogToMetric::new(config);
+        let mut transform = LogToMetric::new(&config).unwrap();
         let metric = transform.transform(event).unwrap();
 
         assert_eq!(
@@ -632,7 +668,7 @@ mod tests {
         );
 
         let event = create_event("response_time", "2.5");
-        let mut transform = LogToMetric::new(config);
+        let mut transform = LogToMetric::new(&config).unwrap();
         let metric = transform.transform(event).unwrap();
 
         assert_eq!(
@@ -649,4 +685,123 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn templated_name_with_namespace() {
+        let config = parse_config(
+            r#"
+            [[metrics]]
+            type = "counter"
+            field = "status"
+            name = "{{service}}_requests_total"
+            namespace = "app"
+            "#,
+        );
+
+        let mut event = create_event("status", "200");
+        event.as_mut_log().insert("service", "checkout");
+        let mut transform = LogToMetric::new(&config).unwrap();
+        let metric = transform.transform(event).unwrap();
+
+        assert_eq!(metric.into_metric().name, "app.checkout_requests_total");
+    }
+
+    #[test]
+    fn tags_from_nested_fields() {
+        let config = parse_config(
+            r#"
+            [[metrics]]
+            type = "counter"
+            field = "status"
+            name = "requests_total"
+            tags = {region = "{{ kubernetes.labels.region }}"}
+            "#,
+        );
+
+        let mut event = create_event("status", "200");
+        event
+            .as_mut_log()
+            .insert("kubernetes.labels.region", "us-east-1");
+        let mut transform = LogToMetric::new(&config).unwrap();
+        let metric = transform.transform(event).unwrap();
+
+        assert_eq!(
+            metric.into_metric().tags,
+            Some(
+                vec![("region".to_owned(), "us-east-1".to_owned())]
+                    .into_iter()
+                    .collect()
+            )
+        );
+    }
+
+    #[test]
+    fn metric_condition() {
+        let config = parse_config(
+            r#"
+            [[metrics]]
+            type = "counter"
+            field = "status"
+            name = "errors_total"
+            condition = {"status.gte" = 500}
+
+            [[metrics]]
+            type = "counter"
+            field = "status"
+            name = "requests_total"
+            "#,
+        );
+
+        let mut transform = LogToMetric::new(&config).unwrap();
+
+        let mut output = Vec::new();
+        transform.transform_into(&mut output, create_event("status", "200"));
+        transform.transform_into(&mut output, create_event("status", "503"));
+
+        let names = output
+            .into_iter()
+            .map(|event| event.into_metric().name)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec!["requests_total", "errors_total", "requests_total"]
+        );
+    }
+
+    #[test]
+    fn response_time_distribution() {
+        let config = parse_config(
+            r#"
+            [[metrics]]
+            type = "distribution"
+            field = "response_time"
+            "#,
+        );
+
+        let event = create_event("response_time", "2.5");
+        let mut transform = LogToMetric::new(&config).unwrap();
+        let metric = transform.transform(event).unwrap();
+
+        assert_eq!(
+            metric.into_metric().value,
+            MetricValue::Distribution {
+                values: vec![2.5],
+                sample_rates: vec![1],
+            }
+        );
+    }
+
+    #[test]
+    fn summary_is_not_a_metric_type() {
+        // A single event can't be summarized, distributions are turned into
+        // summaries by the `aggregate` transform.
+        assert!(toml::from_str::<LogToMetricConfig>(
+            r#"
+            [[metrics]]
+            type = "summary"
+            field = "response_time"
+            "#,
+        )
+        .is_err());
+    }
 }
@@ -1,4 +1,5 @@
 mod interop;
+mod module;
 
 use crate::{
     config_paths::CONFIG_PATHS,
@@ -87,8 +88,9 @@ struct TimerConfig {
 // possible configuration options for `transforms` section, but such internal name should not
 // be exposed to users.
 impl LuaConfig {
-    pub fn build(&self, _cx: TransformContext) -> crate::Result<Box<dyn Transform>> {
-        Lua::new(&self).map(|lua| Box::new(lua) as Box<dyn Transform>)
+    pub fn build(&self, cx: TransformContext) -> crate::Result<Box<dyn Transform>> {
+        let state = module::State::for_transform(cx.name());
+        Lua::new(&self, state).map(|lua| Box::new(lua) as Box<dyn Transform>)
     }
 
     pub fn input_type(&self) -> DataType {
@@ -119,7 +121,7 @@ pub struct Lua {
 }
 
 impl Lua {
-    pub fn new(config: &LuaConfig) -> crate::Result<Self> {
+    pub fn new(config: &LuaConfig, state: module::State) -> crate::Result<Self> {
         let lua = rlua::Lua::new();
 
         let additional_paths = config
@@ -131,6 +133,8 @@ impl Lua {
 
         let mut timers = Vec::new();
         lua.context(|ctx| -> crate::Result<()> {
+            module::register(ctx, state)?;
+
             if !additional_paths.is_empty() {
                 let package = ctx.globals().get::<_, rlua::Table<'_>>("package")?;
                 let current_paths = package
@@ -338,7 +342,7 @@ fn format_error(error: &rlua::Error) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::{format_error, Lua};
+    use super::{format_error, module::State, Lua};
     use crate::{
         event::{
             metric::{Metric, MetricKind, MetricValue},
@@ -346,9 +350,10 @@ mod tests {
         },
         transforms::Transform,
     };
+    use chrono::{TimeZone, Utc};
 
     fn from_config(config: &str) -> crate::Result<Lua> {
-        Lua::new(&toml::from_str(config).unwrap())
+        Lua::new(&toml::from_str(config).unwrap(), State::default())
     }
 
     #[test]
@@ -717,4 +722,108 @@ mod tests {
 
         assert_eq!(event, expected);
     }
+
+    #[test]
+    fn lua_vector_module_parsers() {
+        let mut transform = from_config(
+            r#"
+            hooks.process = """function (event, emit)
+                local vector = require("vector")
+                event.log.json = vector.parse_json(event.log.message)
+                event.log.logfmt = vector.parse_logfmt("a=1 b=\\"x y\\"")
+                event.log.grok = vector.parse_grok("%{WORD:verb} %{NUMBER:status}", "GET 200")
+                event.log.ts = vector.parse_timestamp("2020-05-01 10:20:30 +0000", "%Y-%m-%d %H:%M:%S %z")
+                local _, error = vector.parse_json("{")
+                event.log.error = error ~= nil
+                emit(event)
+            end
+            """
+            "#,
+        )
+        .unwrap();
+
+        let event = transform
+            .transform(Event::from(r#"{"nested": {"count": 3}}"#))
+            .unwrap();
+        let log = event.as_log();
+
+        assert_eq!(log[&"json.nested.count".into()], Value::Integer(3));
+        assert_eq!(log[&"logfmt.a".into()], "1".into());
+        assert_eq!(log[&"logfmt.b".into()], "x y".into());
+        assert_eq!(log[&"grok.verb".into()], "GET".into());
+        assert_eq!(log[&"grok.status".into()], "200".into());
+        assert_eq!(
+            log[&"ts".into()],
+            Value::Timestamp(Utc.ymd(2020, 5, 1).and_hms(10, 20, 30))
+        );
+        assert_eq!(log[&"error".into()], Value::Boolean(true));
+    }
+
+    #[test]
+    fn lua_vector_module_template_and_metrics() {
+        let mut transform = from_config(
+            r#"
+            hooks.process = """function (event, emit)
+                local vector = require("vector")
+                local name = vector.render_template("requests.{{ host }}", event)
+                emit(vector.counter(name, 2, { status = event.log.status }))
+                local _, error = vector.render_template("{{ missing }}", event)
+                emit(vector.gauge("errors", error ~= nil and 1 or 0))
+            end
+            """
+            "#,
+        )
+        .unwrap();
+
+        let mut event = Event::new_empty_log();
+        event.as_mut_log().insert("host", "web1");
+        event.as_mut_log().insert("status", "200");
+        let mut output = Vec::new();
+        transform.process(event, &mut output).unwrap();
+
+        let counter = output[0].as_metric();
+        assert_eq!(counter.name, "requests.web1");
+        assert_eq!(counter.kind, MetricKind::Incremental);
+        assert_eq!(counter.value, MetricValue::Counter { value: 2.0 });
+        assert_eq!(counter.tags.as_ref().unwrap()["status"], "200".to_owned());
+
+        let gauge = output[1].as_metric();
+        assert_eq!(gauge.kind, MetricKind::Absolute);
+        assert_eq!(gauge.value, MetricValue::Gauge { value: 1.0 });
+    }
+
+    #[test]
+    fn lua_vector_module_state_outlives_transform() {
+        let config = r#"
+            hooks.process = """function (event, emit)
+                local state = require("vector").state
+                state.count = (state.count or 0) + 1
+                state.last = nil
+                event.log.count = state.count
+                emit(event)
+            end
+            """
+            "#;
+        let build = || {
+            Lua::new(
+                &toml::from_str(config).unwrap(),
+                State::for_transform(Some("lua_vector_module_state_outlives_transform")),
+            )
+            .unwrap()
+        };
+
+        let mut transform = build();
+        transform.transform(Event::from("one")).unwrap();
+
+        // Reloads build the new transform before dropping the previous one.
+        let event = build().transform(Event::from("two")).unwrap();
+        assert_eq!(event.as_log()[&"count".into()], Value::Integer(2));
+        drop(transform);
+
+        let event = from_config(config)
+            .unwrap()
+            .transform(Event::from("three"))
+            .unwrap();
+        assert_eq!(event.as_log()[&"count".into()], Value::Integer(1));
+    }
 }
@@ -0,0 +1,271 @@
+//! The `vector` module available to Lua code with `require("vector")`,
+//! exposing helpers implemented in Rust and the persistent state of the
+//! transform.
+
+use crate::{
+    event::{
+        metric::{Metric, MetricKind, MetricValue},
+        Event, Value,
+    },
+    template::Template,
+    types::Conversion,
+};
+use chrono::Utc;
+use grok::{Grok, Pattern};
+use lru::LruCache;
+use rlua::prelude::*;
+use serde_json::Value as JsonValue;
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+    sync::{Arc, Mutex, Weak},
+};
+
+/// How many compiled grok patterns each transform keeps.
+const GROK_CACHE_SIZE: usize = 100;
+
+type Values = Mutex<BTreeMap<String, Value>>;
+
+lazy_static::lazy_static! {
+    static ref STATES: Mutex<HashMap<String, Weak<Values>>> = Mutex::new(HashMap::new());
+}
+
+/// Key/value state of a Lua transform, available as `vector.state`.
+///
+/// The state of a named transform is shared by all instances built for that
+/// name. A transform rebuilt on a config reload is built while the previous
+/// instance is still running, so it takes over the state, which is dropped
+/// once the transform is removed. Values are copied in and out, so tables read
+/// from the state have to be assigned back after modifying them.
+#[derive(Clone, Default)]
+pub struct State(Arc<Values>);
+
+impl State {
+    /// The state of the transform named `name`, or a new private state for
+    /// transforms without a name.
+    pub fn for_transform(name: Option<&str>) -> Self {
+        let name = match name {
+            Some(name) => name,
+            None => return State::default(),
+        };
+
+        let mut states = STATES.lock().expect("Lua state lock poisoned");
+        // Drop the states of transforms that were removed.
+        states.retain(|_, state| state.strong_count() > 0);
+        let state = states
+            .get(name)
+            .and_then(Weak::upgrade)
+            .map(State)
+            .unwrap_or_default();
+        states.insert(name.to_owned(), Arc::downgrade(&state.0));
+        state
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<String, Value>> {
+        self.0.lock().expect("Lua state lock poisoned")
+    }
+}
+
+impl LuaUserData for State {
+    fn add_methods<'lua, M: LuaUserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_meta_method(LuaMetaMethod::Index, |_, this, key: String| {
+            Ok(this.lock().get(&key).cloned())
+        });
+        methods.add_meta_method(
+            LuaMetaMethod::NewIndex,
+            |_, this, (key, value): (String, Option<Value>)| {
+                match value {
+                    Some(value) => this.lock().insert(key, value),
+                    None => this.lock().remove(&key),
+                };
+                Ok(())
+            },
+        );
+    }
+}
+
+/// Recently used grok patterns, as compiling is far more expensive than
+/// matching.
+struct GrokCache {
+    grok: Grok,
+    patterns: LruCache<String, Pattern>,
+}
+
+/// Registers the `vector` module, so `require("vector")` returns it without
+/// searching the package path.
+pub fn register(ctx: LuaContext<'_>, state: State) -> LuaResult<()> {
+    let vector = ctx.create_table()?;
+
+    // Parsing failures are returned as `nil, error` instead of raising errors,
+    // following the convention of the Lua standard library.
+    vector.set(
+        "parse_json",
+        ctx.create_function(|_, text: String| {
+            Ok(match serde_json::from_str::<JsonValue>(&text) {
+                Ok(value) => (Some(Value::from(value)), None),
+                Err(error) => (None, Some(error.to_string())),
+            })
+        })?,
+    )?;
+
+    vector.set(
+        "parse_logfmt",
+        ctx.create_function(|_, text: String| {
+            Ok(logfmt::parse(&text)
+                .into_iter()
+                .filter_map(|logfmt::Pair { key, val }| val.map(|val| (key, Value::from(val))))
+                .collect::<BTreeMap<_, _>>())
+        })?,
+    )?;
+
+    let cache = Mutex::new(GrokCache {
+        grok: Grok::with_patterns(),
+        patterns: LruCache::new(GROK_CACHE_SIZE),
+    });
+    vector.set(
+        "parse_grok",
+        ctx.create_function(move |_, (pattern, text): (String, String)| {
+            let mut cache = cache.lock().expect("grok cache lock poisoned");
+            let cache = &mut *cache;
+            if !cache.patterns.contains(&pattern) {
+                match cache.grok.compile(&pattern, true) {
+                    Ok(compiled) => cache.patterns.put(pattern.clone(), compiled),
+                    Err(error) => return Ok((None, Some(error.to_string()))),
+                };
+            }
+            let compiled = cache
+                .patterns
+                .get(&pattern)
+                .expect("pattern was compiled above");
+
+            Ok(match compiled.match_against(&text) {
+                Some(matches) => (
+                    Some(
+                        matches
+                            .iter()
+                            .map(|(name, value)| (name.to_owned(), Value::from(value)))
+                            .collect::<BTreeMap<_, _>>(),
+                    ),
+                    None,
+                ),
+                None => (None, Some("Pattern does not match".to_owned())),
+            })
+        })?,
+    )?;
+
+    vector.set(
+        "parse_timestamp",
+        ctx.create_function(|_, (text, format): (String, Option<String>)| {
+            let conversion = match format {
+                Some(format) => Conversion::from_str(&format!("timestamp|{}", format)),
+                None => Ok(Conversion::Timestamp),
+            };
+            Ok(
+                match conversion.map(|conversion| conversion.convert(Value::from(text))) {
+                    Ok(Ok(value)) => (Some(value), None),
+                    Ok(Err(error)) => (None, Some(error.to_string())),
+                    Err(error) => (None, Some(error.to_string())),
+                },
+            )
+        })?,
+    )?;
+
+    vector.set(
+        "render_template",
+        ctx.create_function(|_, (template, event): (String, Event)| {
+            Ok(
+                match Template::from(template.as_str()).render_string(&event) {
+                    Ok(rendered) => (Some(rendered), None),
+                    Err(missing) => (
+                        None,
+                        Some(format!(
+                            "Missing fields: {}",
+                            missing
+                                .iter()
+                                .map(|field| &field[..])
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        )),
+                    ),
+                },
+            )
+        })?,
+    )?;
+
+    vector.set(
+        "counter",
+        ctx.create_function(
+            |_, (name, value, tags): (String, f64, Option<BTreeMap<String, String>>)| {
+                Ok(new_metric(
+                    name,
+                    tags,
+                    MetricKind::Incremental,
+                    MetricValue::Counter { value },
+                ))
+            },
+        )?,
+    )?;
+
+    vector.set(
+        "gauge",
+        ctx.create_function(
+            |_, (name, value, tags): (String, f64, Option<BTreeMap<String, String>>)| {
+                Ok(new_metric(
+                    name,
+                    tags,
+                    MetricKind::Absolute,
+                    MetricValue::Gauge { value },
+                ))
+            },
+        )?,
+    )?;
+
+    vector.set("state", state)?;
+
+    let loaded = ctx
+        .globals()
+        .get::<_, LuaTable<'_>>("package")?
+        .get::<_, LuaTable<'_>>("loaded")?;
+    loaded.set("vector", vector)
+}
+
+fn new_metric(
+    name: String,
+    tags: Option<BTreeMap<String, String>>,
+    kind: MetricKind,
+    value: MetricValue,
+) -> Event {
+    Event::Metric(Metric {
+        name,
+        timestamp: Some(Utc::now()),
+        tags,
+        kind,
+        value,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{State, STATES};
+    use crate::event::Value;
+
+    #[test]
+    fn lua_state_dropped_with_transform() {
+        let name = "lua_state_dropped_with_transform";
+        let state = State::for_transform(Some(name));
+        state.lock().insert("count".into(), Value::Integer(1));
+
+        // Instances built while another one is alive share its state.
+        let rebuilt = State::for_transform(Some(name));
+        assert_eq!(rebuilt.lock().get("count"), Some(&Value::Integer(1)));
+        drop(state);
+        drop(rebuilt);
+
+        let state = State::for_transform(Some(name));
+        assert!(state.lock().is_empty());
+        drop(state);
+
+        State::for_transform(Some("lua_state_dropped_with_transform_other"));
+        assert!(!STATES.lock().unwrap().contains_key(name));
+    }
+}
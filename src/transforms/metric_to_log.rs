@@ -0,0 +1,294 @@
+use super::Transform;
+use crate::{
+    event::{self, Event, Value},
+    topology::config::{DataType, TransformConfig, TransformContext, TransformDescription},
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use string_cache::DefaultAtom as Atom;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields, default)]
+pub struct MetricToLogConfig {
+    pub host_tag: Option<String>,
+    pub host_key: Option<Atom>,
+    pub timestamp_key: Option<Atom>,
+}
+
+pub struct MetricToLog {
+    host_tag: String,
+    host_key: Atom,
+    timestamp_key: Atom,
+}
+
+inventory::submit! {
+    TransformDescription::new::<MetricToLogConfig>("metric_to_log")
+}
+
+#[typetag::serde(name = "metric_to_log")]
+impl TransformConfig for MetricToLogConfig {
+    fn build(&self, _cx: TransformContext) -> crate::Result<Box<dyn Transform>> {
+        Ok(Box::new(MetricToLog::new(self)))
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Metric
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn transform_type(&self) -> &'static str {
+        "metric_to_log"
+    }
+}
+
+impl MetricToLog {
+    pub fn new(config: &MetricToLogConfig) -> Self {
+        Self {
+            host_tag: config.host_tag.clone().unwrap_or_else(|| "host".into()),
+            host_key: config
+                .host_key
+                .clone()
+                .unwrap_or_else(|| event::log_schema().host_key().clone()),
+            timestamp_key: config
+                .timestamp_key
+                .clone()
+                .unwrap_or_else(|| event::log_schema().timestamp_key().clone()),
+        }
+    }
+}
+
+impl Transform for MetricToLog {
+    fn transform(&mut self, event: Event) -> Option<Event> {
+        let metric = event.into_metric();
+        let timestamp = metric.timestamp.unwrap_or_else(Utc::now);
+        let host = metric
+            .tags
+            .as_ref()
+            .and_then(|tags| tags.get(&self.host_tag))
+            .cloned();
+
+        // The layout follows the serialized form of the metric: `name`,
+        // `kind`, `tags` and a single field named after the value type.
+        let fields = match serde_json::to_value(&metric) {
+            Ok(JsonValue::Object(fields)) => fields,
+            Ok(_) => unreachable!("metrics serialize to objects"),
+            Err(error) => {
+                error!(message = "Error serializing metric.", %error, rate_limit_secs = 30);
+                return None;
+            }
+        };
+
+        let mut event = Event::new_empty_log();
+        let log = event.as_mut_log();
+        for (key, value) in fields {
+            match (key.as_str(), value) {
+                ("timestamp", _) | (_, JsonValue::Null) => {}
+                (_, value) => log.insert_flat(key, Value::from(value)),
+            }
+        }
+        log.insert(self.timestamp_key.clone(), timestamp);
+        if let Some(host) = host {
+            log.insert(self.host_key.clone(), host);
+        }
+
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::{
+        metric::{Metric, MetricKind, MetricValue},
+        LogEvent,
+    };
+    use chrono::{offset::TimeZone, DateTime};
+    use std::collections::BTreeMap;
+
+    fn ts() -> DateTime<Utc> {
+        Utc.ymd(2018, 11, 14).and_hms_nano(8, 9, 10, 11)
+    }
+
+    fn tags() -> BTreeMap<String, String> {
+        vec![
+            ("host".to_owned(), "localhost".to_owned()),
+            ("some_tag".to_owned(), "some_value".to_owned()),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn transform_metric(metric: Metric) -> LogEvent {
+        let mut transformer = MetricToLog::new(&MetricToLogConfig::default());
+        transformer
+            .transform(Event::Metric(metric))
+            .unwrap()
+            .into_log()
+    }
+
+    fn fields(log: &LogEvent) -> BTreeMap<String, Value> {
+        log.all_fields()
+            .map(|(key, value)| (key, value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn transform_counter() {
+        let log = transform_metric(Metric {
+            name: "counter".into(),
+            timestamp: Some(ts()),
+            tags: Some(tags()),
+            kind: MetricKind::Absolute,
+            value: MetricValue::Counter { value: 1.0 },
+        });
+
+        let expected = vec![
+            ("counter.value".to_owned(), Value::from(1.0)),
+            ("host".to_owned(), Value::from("localhost")),
+            ("kind".to_owned(), Value::from("absolute")),
+            ("name".to_owned(), Value::from("counter")),
+            ("tags.host".to_owned(), Value::from("localhost")),
+            ("tags.some_tag".to_owned(), Value::from("some_value")),
+            ("timestamp".to_owned(), Value::from(ts())),
+        ]
+        .into_iter()
+        .collect::<BTreeMap<_, _>>();
+        assert_eq!(fields(&log), expected);
+    }
+
+    #[test]
+    fn transform_gauge_without_tags_or_timestamp() {
+        let log = transform_metric(Metric {
+            name: "gauge".into(),
+            timestamp: None,
+            tags: None,
+            kind: MetricKind::Absolute,
+            value: MetricValue::Gauge { value: 1.5 },
+        });
+
+        assert_eq!(log.get(&"gauge.value".into()), Some(&Value::from(1.5)));
+        assert!(log.get(&"tags".into()).is_none());
+        assert!(log.get(&"host".into()).is_none());
+        assert!(log
+            .get(&"timestamp".into())
+            .and_then(Value::as_timestamp)
+            .is_some());
+    }
+
+    #[test]
+    fn transform_set() {
+        let log = transform_metric(Metric {
+            name: "set".into(),
+            timestamp: Some(ts()),
+            tags: None,
+            kind: MetricKind::Incremental,
+            value: MetricValue::Set {
+                values: vec!["one".into(), "two".into()].into_iter().collect(),
+            },
+        });
+
+        assert_eq!(log.get(&"kind".into()), Some(&Value::from("incremental")));
+        assert_eq!(
+            log.get(&"set.values".into()),
+            Some(&Value::from(vec![Value::from("one"), Value::from("two")]))
+        );
+    }
+
+    #[test]
+    fn transform_distribution() {
+        let log = transform_metric(Metric {
+            name: "distribution".into(),
+            timestamp: Some(ts()),
+            tags: None,
+            kind: MetricKind::Incremental,
+            value: MetricValue::Distribution {
+                values: vec![1.0, 2.0],
+                sample_rates: vec![10, 20],
+            },
+        });
+
+        assert_eq!(
+            log.get(&"distribution.values".into()),
+            Some(&Value::from(vec![Value::from(1.0), Value::from(2.0)]))
+        );
+        assert_eq!(
+            log.get(&"distribution.sample_rates".into()),
+            Some(&Value::from(vec![Value::from(10), Value::from(20)]))
+        );
+    }
+
+    #[test]
+    fn transform_histogram_and_summary() {
+        let log = transform_metric(Metric {
+            name: "histogram".into(),
+            timestamp: Some(ts()),
+            tags: None,
+            kind: MetricKind::Absolute,
+            value: MetricValue::AggregatedHistogram {
+                buckets: vec![1.0, 2.0],
+                counts: vec![10, 20],
+                count: 30,
+                sum: 50.0,
+            },
+        });
+        assert_eq!(
+            log.get(&"aggregated_histogram.count".into()),
+            Some(&Value::from(30))
+        );
+        assert_eq!(
+            log.get(&"aggregated_histogram.sum".into()),
+            Some(&Value::from(50.0))
+        );
+
+        let log = transform_metric(Metric {
+            name: "summary".into(),
+            timestamp: Some(ts()),
+            tags: None,
+            kind: MetricKind::Absolute,
+            value: MetricValue::AggregatedSummary {
+                quantiles: vec![0.5, 0.9],
+                values: vec![10.0, 20.0],
+                count: 30,
+                sum: 50.0,
+            },
+        });
+        assert_eq!(
+            log.get(&"aggregated_summary.quantiles".into()),
+            Some(&Value::from(vec![Value::from(0.5), Value::from(0.9)]))
+        );
+    }
+
+    #[test]
+    fn custom_field_mapping() {
+        let config: MetricToLogConfig = toml::from_str(
+            r#"
+            host_tag = "instance"
+            host_key = "hostname"
+            timestamp_key = "ts"
+            "#,
+        )
+        .unwrap();
+        let mut transformer = MetricToLog::new(&config);
+
+        let mut tags = tags();
+        tags.insert("instance".into(), "10.0.0.1".into());
+        let log = transformer
+            .transform(Event::Metric(Metric {
+                name: "counter".into(),
+                timestamp: Some(ts()),
+                tags: Some(tags),
+                kind: MetricKind::Incremental,
+                value: MetricValue::Counter { value: 1.0 },
+            }))
+            .unwrap()
+            .into_log();
+
+        assert_eq!(log.get(&"hostname".into()), Some(&Value::from("10.0.0.1")));
+        assert_eq!(log.get(&"ts".into()), Some(&Value::from(ts())));
+        assert!(log.get(&"timestamp".into()).is_none());
+    }
+}
@@ -7,6 +7,8 @@ mod util;
 pub mod add_fields;
 #[cfg(feature = "transforms-add_tags")]
 pub mod add_tags;
+#[cfg(feature = "transforms-aggregate")]
+pub mod aggregate;
 #[cfg(feature = "transforms-ansi_stripper")]
 pub mod ansi_stripper;
 #[cfg(feature = "transforms-aws_ec2_metadata")]
@@ -17,16 +19,22 @@ pub mod coercer;
 pub mod concat;
 #[cfg(feature = "transforms-dedupe")]
 pub mod dedupe;
+#[cfg(feature = "transforms-enrich")]
+pub mod enrich;
 #[cfg(feature = "transforms-field_filter")]
 pub mod field_filter;
 #[cfg(feature = "transforms-filter")]
 pub mod filter;
+#[cfg(feature = "transforms-format_parser")]
+pub mod format_parser;
 #[cfg(feature = "transforms-geoip")]
 pub mod geoip;
 #[cfg(feature = "transforms-grok_parser")]
 pub mod grok_parser;
 #[cfg(feature = "transforms-json_parser")]
 pub mod json_parser;
+#[cfg(feature = "transforms-key_value_parser")]
+pub mod key_value_parser;
 #[cfg(feature = "transforms-log_to_metric")]
 pub mod log_to_metric;
 #[cfg(feature = "transforms-logfmt_parser")]
@@ -35,6 +43,12 @@ pub mod logfmt_parser;
 pub mod lua;
 #[cfg(feature = "transforms-merge")]
 pub mod merge;
+#[cfg(feature = "transforms-metric_to_log")]
+pub mod metric_to_log;
+#[cfg(feature = "transforms-redact")]
+pub mod redact;
+#[cfg(feature = "transforms-reduce")]
+pub mod reduce;
 #[cfg(feature = "transforms-regex_parser")]
 pub mod regex_parser;
 #[cfg(feature = "transforms-remove_fields")]
@@ -43,6 +57,8 @@ pub mod remove_fields;
 pub mod remove_tags;
 #[cfg(feature = "transforms-rename_fields")]
 pub mod rename_fields;
+#[cfg(feature = "transforms-route")]
+pub mod route;
 #[cfg(feature = "transforms-sampler")]
 pub mod sampler;
 #[cfg(feature = "transforms-split")]
@@ -51,6 +67,10 @@ pub mod split;
 pub mod swimlanes;
 #[cfg(feature = "transforms-tag_cardinality_limit")]
 pub mod tag_cardinality_limit;
+#[cfg(feature = "transforms-throttle")]
+pub mod throttle;
+#[cfg(feature = "transforms-timestamp_parser")]
+pub mod timestamp_parser;
 #[cfg(feature = "transforms-tokenizer")]
 pub mod tokenizer;
 
@@ -65,6 +85,15 @@ pub trait Transform: Send {
         }
     }
 
+    /// Transforms with named outputs tag each event with the index of the
+    /// output it is routed to, see `TransformConfig::named_outputs`. Any
+    /// other transform has a single output at index zero.
+    fn transform_routed(&mut self, output: &mut Vec<(usize, Event)>, event: Event) {
+        let mut transformed = Vec::with_capacity(1);
+        self.transform_into(&mut transformed, event);
+        output.extend(transformed.into_iter().map(|event| (0, event)));
+    }
+
     fn transform_stream(
         self: Box<Self>,
         input_rx: Box<dyn Stream<Item = Event, Error = ()> + Send>,
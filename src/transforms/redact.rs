@@ -0,0 +1,559 @@
+use super::Transform;
+use crate::{
+    event::{Event, Value},
+    internal_events::{RedactEventProcessed, RedactionApplied},
+    topology::config::{DataType, TransformConfig, TransformContext, TransformDescription},
+};
+use lazy_static::lazy_static;
+use openssl::sha::Sha256;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+use std::fmt::Write;
+use std::net::Ipv6Addr;
+use std::ops::Range;
+use string_cache::DefaultAtom as Atom;
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RedactConfig {
+    #[serde(default)]
+    pub fields: Vec<Atom>,
+    #[serde(default)]
+    pub detectors: Vec<DetectorName>,
+    #[serde(default)]
+    pub patterns: Vec<PatternConfig>,
+    #[serde(default)]
+    pub strategy: Strategy,
+    #[serde(default = "default_mask")]
+    pub mask: String,
+    pub salt: Option<String>,
+    #[serde(default = "default_partial_keep")]
+    pub partial_keep: usize,
+}
+
+fn default_mask() -> String {
+    "[REDACTED]".into()
+}
+
+fn default_partial_keep() -> usize {
+    4
+}
+
+/// The built-in detectors.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DetectorName {
+    Email,
+    CreditCard,
+    Ipv4,
+    Ipv6,
+    UsSsn,
+    BearerToken,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PatternConfig {
+    pub name: String,
+    pub pattern: String,
+}
+
+/// How matches are replaced.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Strategy {
+    /// Replace with `mask`.
+    Mask,
+    /// Replace with the hex encoded SHA-256 hash of `salt` and the match.
+    Hash,
+    /// Replace all but the last `partial_keep` characters with `*`.
+    Partial,
+}
+
+impl Default for Strategy {
+    fn default() -> Self {
+        Strategy::Mask
+    }
+}
+
+lazy_static! {
+    static ref EMAIL: Regex =
+        Regex::new(r"(?i)\b[a-z0-9._%+-]+@[a-z0-9-]+(?:\.[a-z0-9-]+)*\.[a-z]{2,}\b").unwrap();
+    static ref CREDIT_CARD: Regex = Regex::new(r"\b(?:\d[ -]?){12,18}\d\b").unwrap();
+    static ref IPV4: Regex = Regex::new(
+        r"\b(?:(?:25[0-5]|2[0-4]\d|1\d\d|[1-9]?\d)\.){3}(?:25[0-5]|2[0-4]\d|1\d\d|[1-9]?\d)\b"
+    )
+    .unwrap();
+    /// Candidates only, which are checked by parsing them.
+    static ref IPV6: Regex =
+        Regex::new(r"(?i)[0-9a-f:.]*:[0-9a-f.]*:[0-9a-f:.]*").unwrap();
+    static ref US_SSN: Regex = Regex::new(r"\b\d{3}-\d{2}-\d{4}\b").unwrap();
+    static ref BEARER_TOKEN: Regex =
+        Regex::new(r"(?i)\bbearer\s+(?P<value>[a-z0-9\-._~+/]+=*)").unwrap();
+}
+
+impl DetectorName {
+    fn as_str(self) -> &'static str {
+        match self {
+            DetectorName::Email => "email",
+            DetectorName::CreditCard => "credit_card",
+            DetectorName::Ipv4 => "ipv4",
+            DetectorName::Ipv6 => "ipv6",
+            DetectorName::UsSsn => "us_ssn",
+            DetectorName::BearerToken => "bearer_token",
+        }
+    }
+
+    fn regex(self) -> &'static Regex {
+        match self {
+            DetectorName::Email => &EMAIL,
+            DetectorName::CreditCard => &CREDIT_CARD,
+            DetectorName::Ipv4 => &IPV4,
+            DetectorName::Ipv6 => &IPV6,
+            DetectorName::UsSsn => &US_SSN,
+            DetectorName::BearerToken => &BEARER_TOKEN,
+        }
+    }
+
+    /// Checks matches the regex alone can not tell apart from other values.
+    fn validate(self) -> Option<Validator> {
+        match self {
+            DetectorName::CreditCard => Some(card_numbers),
+            DetectorName::Ipv6 => Some(ipv6_address),
+            DetectorName::UsSsn => Some(us_ssn),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the ranges of a regex match that hold actual values.
+type Validator = fn(&str) -> Vec<Range<usize>>;
+
+fn whole_if(s: &str, valid: bool) -> Vec<Range<usize>> {
+    if valid {
+        vec![0..s.len()]
+    } else {
+        Vec::new()
+    }
+}
+
+/// Finds the numbers passing the Luhn check within a run of digit groups,
+/// which may also hold other numbers such as expiry dates. Numbers start
+/// and end at group boundaries, preferring the longest one at each start.
+fn card_numbers(s: &str) -> Vec<Range<usize>> {
+    let bytes = s.as_bytes();
+    let digits = (0..bytes.len())
+        .filter(|&index| bytes[index].is_ascii_digit())
+        .collect::<Vec<_>>();
+    let starts_group = |index: usize| index == 0 || !bytes[index - 1].is_ascii_digit();
+    let ends_group = |index: usize| bytes.get(index + 1).map_or(true, |b| !b.is_ascii_digit());
+
+    let mut numbers = Vec::new();
+    let mut first = 0;
+    'digits: while first + 13 <= digits.len() {
+        if starts_group(digits[first]) {
+            let longest = 19.min(digits.len() - first);
+            for length in (13..=longest).rev() {
+                let last = digits[first + length - 1];
+                let range = digits[first]..last + 1;
+                if ends_group(last) && luhn_valid(&s[range.clone()]) {
+                    numbers.push(range);
+                    first += length;
+                    continue 'digits;
+                }
+            }
+        }
+        first += 1;
+    }
+    numbers
+}
+
+/// Checks the Luhn checksum of card numbers, ignoring separators.
+fn luhn_valid(s: &str) -> bool {
+    let digits = s.chars().filter_map(|c| c.to_digit(10)).collect::<Vec<_>>();
+    if digits.len() < 13 || digits.len() > 19 {
+        return false;
+    }
+    let sum = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(index, &digit)| match (index % 2, digit * 2) {
+            (0, _) => digit,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum::<u32>();
+    sum % 10 == 0
+}
+
+fn ipv6_address(s: &str) -> Vec<Range<usize>> {
+    whole_if(s, s.parse::<Ipv6Addr>().is_ok())
+}
+
+/// Leaves out numbers the Social Security Administration never issues.
+fn us_ssn(s: &str) -> Vec<Range<usize>> {
+    let mut parts = s.split('-');
+    let valid = match (parts.next(), parts.next(), parts.next()) {
+        (Some(area), Some(group), Some(serial)) => {
+            area != "000"
+                && area != "666"
+                && !area.starts_with('9')
+                && group != "00"
+                && serial != "0000"
+        }
+        _ => false,
+    };
+    whole_if(s, valid)
+}
+
+struct Detector {
+    name: String,
+    regex: Regex,
+    validate: Option<Validator>,
+}
+
+inventory::submit! {
+    TransformDescription::new_without_default::<RedactConfig>("redact")
+}
+
+#[typetag::serde(name = "redact")]
+impl TransformConfig for RedactConfig {
+    fn build(&self, _cx: TransformContext) -> crate::Result<Box<dyn Transform>> {
+        Ok(Box::new(Redact::new(self)?))
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn transform_type(&self) -> &'static str {
+        "redact"
+    }
+}
+
+pub struct Redact {
+    fields: Vec<Atom>,
+    detectors: Vec<Detector>,
+    strategy: Strategy,
+    mask: String,
+    salt: String,
+    partial_keep: usize,
+}
+
+impl Redact {
+    pub fn new(config: &RedactConfig) -> crate::Result<Self> {
+        if config.detectors.is_empty() && config.patterns.is_empty() {
+            return Err("must specify at least one of `detectors` or `patterns`".into());
+        }
+        let salt = match (config.strategy, &config.salt) {
+            (Strategy::Hash, None) => {
+                return Err("`salt` must be specified for the `hash` strategy".into())
+            }
+            (_, salt) => salt.clone().unwrap_or_default(),
+        };
+
+        let mut detectors = config
+            .detectors
+            .iter()
+            .map(|name| Detector {
+                name: name.as_str().to_owned(),
+                regex: name.regex().clone(),
+                validate: name.validate(),
+            })
+            .collect::<Vec<_>>();
+        for pattern in &config.patterns {
+            detectors.push(Detector {
+                name: pattern.name.clone(),
+                regex: Regex::new(&pattern.pattern).context(super::InvalidRegex)?,
+                validate: None,
+            });
+        }
+
+        Ok(Self {
+            fields: config.fields.clone(),
+            detectors,
+            strategy: config.strategy,
+            mask: config.mask.clone(),
+            salt,
+            partial_keep: config.partial_keep,
+        })
+    }
+
+    fn replacement(&self, value: &str) -> String {
+        match self.strategy {
+            Strategy::Mask => self.mask.clone(),
+            Strategy::Hash => {
+                let mut hasher = Sha256::new();
+                hasher.update(self.salt.as_bytes());
+                hasher.update(value.as_bytes());
+                hasher
+                    .finish()
+                    .iter()
+                    .fold(String::with_capacity(64), |mut hex, byte| {
+                        let _ = write!(hex, "{:02x}", byte);
+                        hex
+                    })
+            }
+            Strategy::Partial => {
+                let length = value.chars().count();
+                if length <= self.partial_keep {
+                    "*".repeat(length)
+                } else {
+                    let masked = length - self.partial_keep;
+                    "*".repeat(masked) + &value.chars().skip(masked).collect::<String>()
+                }
+            }
+        }
+    }
+
+    /// Replaces the matches of a detector, or their `value` group where the
+    /// pattern has one, returning the number of replacements.
+    fn redact_str(&self, detector: &Detector, text: &str) -> (String, usize) {
+        let mut count = 0;
+        let redacted = detector.regex.replace_all(text, |captures: &Captures| {
+            let whole = captures.get(0).unwrap();
+            let value = captures.name("value").unwrap_or(whole);
+            // Trailing dots end sentences more often than addresses.
+            let matched = value.as_str().trim_end_matches('.');
+            let ranges = match detector.validate {
+                _ if matched.is_empty() => Vec::new(),
+                Some(validate) => validate(matched),
+                None => vec![0..matched.len()],
+            };
+            count += ranges.len();
+
+            let offset = value.start() - whole.start();
+            let whole = whole.as_str();
+            let mut redacted = String::with_capacity(whole.len());
+            let mut end = 0;
+            for range in ranges {
+                redacted.push_str(&whole[end..offset + range.start]);
+                redacted.push_str(&self.replacement(&matched[range.clone()]));
+                end = offset + range.end;
+            }
+            redacted.push_str(&whole[end..]);
+            redacted
+        });
+        (redacted.into_owned(), count)
+    }
+
+    fn redact_value(&self, value: &mut Value, counts: &mut [usize]) {
+        match value {
+            Value::Bytes(bytes) => {
+                let mut text = String::from_utf8_lossy(bytes).into_owned();
+                let mut changed = false;
+                for (detector, total) in self.detectors.iter().zip(counts.iter_mut()) {
+                    let (redacted, count) = self.redact_str(detector, &text);
+                    if count > 0 {
+                        text = redacted;
+                        *total += count;
+                        changed = true;
+                    }
+                }
+                if changed {
+                    *bytes = text.into();
+                }
+            }
+            Value::Map(map) => map
+                .values_mut()
+                .for_each(|value| self.redact_value(value, counts)),
+            Value::Array(array) => array
+                .iter_mut()
+                .for_each(|value| self.redact_value(value, counts)),
+            _ => {}
+        }
+    }
+}
+
+impl Transform for Redact {
+    fn transform(&mut self, mut event: Event) -> Option<Event> {
+        emit!(RedactEventProcessed);
+
+        let log = event.as_mut_log();
+        let fields = if self.fields.is_empty() {
+            log.all_fields()
+                .filter(|(_, value)| matches!(value, Value::Bytes(_)))
+                .map(|(path, _)| Atom::from(path))
+                .collect()
+        } else {
+            self.fields.clone()
+        };
+
+        let mut counts = vec![0; self.detectors.len()];
+        for field in &fields {
+            if let Some(value) = log.get_mut(field) {
+                self.redact_value(value, &mut counts);
+            }
+        }
+
+        for (detector, count) in self.detectors.iter().zip(counts) {
+            if count > 0 {
+                emit!(RedactionApplied {
+                    detector: &detector.name,
+                    count
+                });
+            }
+        }
+
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{card_numbers, luhn_valid, RedactConfig};
+    use crate::{
+        event::{LogEvent, Value},
+        topology::config::{TransformConfig, TransformContext},
+        Event,
+    };
+
+    fn do_transform(event: Event, config: &str) -> LogEvent {
+        let rt = crate::runtime::Runtime::single_threaded().unwrap();
+        let mut redact = toml::from_str::<RedactConfig>(config)
+            .unwrap()
+            .build(TransformContext::new_test(rt.executor()))
+            .unwrap();
+
+        redact.transform(event).unwrap().into_log()
+    }
+
+    #[test]
+    fn redact_builtin_detectors() {
+        let log = do_transform(
+            Event::from(
+                "user jane.doe@example.com paid with 4111 1111 1111 1111 (not 4111 1111 1111 1112) \
+                 from 192.168.0.1 and fe80::1ff:fe23:4567:890a at 12:30:45, \
+                 ssn 123-45-6789 (not 000-12-3456), Authorization: Bearer abc.DEF-123=",
+            ),
+            r#"detectors = ["email", "credit_card", "ipv4", "ipv6", "us_ssn", "bearer_token"]"#,
+        );
+
+        assert_eq!(
+            log[&"message".into()],
+            "user [REDACTED] paid with [REDACTED] (not 4111 1111 1111 1112) \
+             from [REDACTED] and [REDACTED] at 12:30:45, \
+             ssn [REDACTED] (not 000-12-3456), Authorization: Bearer [REDACTED]"
+                .into()
+        );
+    }
+
+    #[test]
+    fn redact_all_string_fields_by_default() {
+        let mut event = Event::from("contact admin@example.com");
+        event.as_mut_log().insert("user.email", "jane@example.org");
+        event.as_mut_log().insert("user.id", 42);
+        event
+            .as_mut_log()
+            .insert("cc", vec![Value::from("bob@example.net")]);
+
+        let log = do_transform(event, r#"detectors = ["email"]"#);
+        assert_eq!(log[&"message".into()], "contact [REDACTED]".into());
+        assert_eq!(log[&"user.email".into()], "[REDACTED]".into());
+        assert_eq!(log[&"user.id".into()], Value::Integer(42));
+        assert_eq!(log[&"cc[0]".into()], "[REDACTED]".into());
+    }
+
+    #[test]
+    fn redact_selected_fields_with_custom_patterns() {
+        let mut event = Event::from("key-0123456789abcdef in message");
+        event
+            .as_mut_log()
+            .insert("request.header", "token=key-fedcba9876543210");
+
+        let log = do_transform(
+            event,
+            r#"
+                fields = ["request"]
+                mask = "***"
+                [[patterns]]
+                name = "api_key"
+                pattern = 'key-(?P<value>[0-9a-f]{16})'
+            "#,
+        );
+        assert_eq!(
+            log[&"message".into()],
+            "key-0123456789abcdef in message".into()
+        );
+        assert_eq!(log[&"request.header".into()], "token=key-***".into());
+    }
+
+    #[test]
+    fn redact_hash_and_partial() {
+        let log = do_transform(
+            Event::from("from 10.1.2.3"),
+            r#"
+                detectors = ["ipv4"]
+                strategy = "hash"
+                salt = "pepper"
+            "#,
+        );
+        // echo -n pepper10.1.2.3 | sha256sum
+        assert_eq!(
+            log[&"message".into()],
+            "from 127ef7719aeda56833aac0a13a2f29608f189c6d1c546335db595877c1dd33ba".into()
+        );
+
+        let log = do_transform(
+            Event::from("card 4111-1111-1111-1111"),
+            r#"
+                detectors = ["credit_card"]
+                strategy = "partial"
+            "#,
+        );
+        assert_eq!(log[&"message".into()], "card ***************1111".into());
+    }
+
+    #[test]
+    fn redact_requires_salt_for_hash() {
+        let rt = crate::runtime::Runtime::single_threaded().unwrap();
+        let config = toml::from_str::<RedactConfig>(
+            r#"
+                detectors = ["email"]
+                strategy = "hash"
+            "#,
+        )
+        .unwrap();
+        assert!(config
+            .build(TransformContext::new_test(rt.executor()))
+            .is_err());
+    }
+
+    #[test]
+    fn redact_card_numbers_next_to_other_digits() {
+        let log = do_transform(
+            Event::from(
+                "card 4111 1111 1111 1111 12/25, order 12 5500000000000004 and \
+                 4111-1111-1111-1111 2024",
+            ),
+            r#"detectors = ["credit_card"]"#,
+        );
+
+        assert_eq!(
+            log[&"message".into()],
+            "card [REDACTED] 12/25, order 12 [REDACTED] and [REDACTED] 2024".into()
+        );
+    }
+
+    #[test]
+    fn card_numbers_within_digit_runs() {
+        let text = "4111 1111 1111 1111 12";
+        assert_eq!(card_numbers(text), vec![0..19]);
+        assert_eq!(card_numbers("12 4111111111111111"), vec![3..19]);
+        assert!(card_numbers("4111 1111 1111 1112 12").is_empty());
+    }
+
+    #[test]
+    fn luhn() {
+        assert!(luhn_valid("4111111111111111"));
+        assert!(luhn_valid("5500 0000 0000 0004"));
+        assert!(!luhn_valid("4111111111111112"));
+        assert!(!luhn_valid("0000"));
+    }
+}
@@ -0,0 +1,531 @@
+use super::Transform;
+use crate::{
+    conditions::{AnyCondition, Condition},
+    event::{LogEvent, Value},
+    expiring_hash_map::ExpiringHashMap,
+    topology::config::{DataType, TransformConfig, TransformContext, TransformDescription},
+    Event,
+};
+use futures::{
+    compat::{Compat, Stream01CompatExt},
+    stream::{self, StreamExt},
+};
+use futures01::Stream as Stream01;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use string_cache::DefaultAtom as Atom;
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ReduceConfig {
+    #[serde(default = "default_expire_after_ms")]
+    pub expire_after_ms: u64,
+    #[serde(default)]
+    pub identifier_fields: Vec<Atom>,
+    #[serde(default)]
+    pub merge_strategies: IndexMap<String, MergeStrategy>,
+    pub ends_when: Option<AnyCondition>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    Concat,
+    Array,
+    Sum,
+    Max,
+    Min,
+    First,
+    Last,
+    Discard,
+}
+
+fn default_expire_after_ms() -> u64 {
+    30000
+}
+
+inventory::submit! {
+    TransformDescription::new_without_default::<ReduceConfig>("reduce")
+}
+
+#[typetag::serde(name = "reduce")]
+impl TransformConfig for ReduceConfig {
+    fn build(&self, _cx: TransformContext) -> crate::Result<Box<dyn Transform>> {
+        Ok(Box::new(Reduce::new(self)?))
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn transform_type(&self) -> &'static str {
+        "reduce"
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Number {
+    Integer(i64),
+    Float(f64),
+}
+
+impl Number {
+    fn from_value(value: &Value) -> Option<Self> {
+        match value {
+            Value::Integer(i) => Some(Number::Integer(*i)),
+            Value::Float(f) => Some(Number::Float(*f)),
+            _ => None,
+        }
+    }
+
+    fn as_f64(self) -> f64 {
+        match self {
+            Number::Integer(i) => i as f64,
+            Number::Float(f) => f,
+        }
+    }
+
+    fn add(self, other: Self) -> Self {
+        match (self, other) {
+            (Number::Integer(a), Number::Integer(b)) => Number::Integer(a.wrapping_add(b)),
+            (a, b) => Number::Float(a.as_f64() + b.as_f64()),
+        }
+    }
+}
+
+impl From<Number> for Value {
+    fn from(number: Number) -> Self {
+        match number {
+            Number::Integer(i) => Value::Integer(i),
+            Number::Float(f) => Value::Float(f),
+        }
+    }
+}
+
+/// The merged state of a single field across the events of a group.
+#[derive(Debug)]
+enum FieldMerger {
+    Concat(String),
+    Array(Vec<Value>),
+    Sum(Option<Number>),
+    Max(Option<Number>),
+    Min(Option<Number>),
+    First(Value),
+    Last(Value),
+    Discard,
+    /// The default for timestamps, keeps both the first and the last one.
+    Timestamps {
+        first: Value,
+        last: Value,
+    },
+}
+
+impl FieldMerger {
+    fn new(strategy: Option<MergeStrategy>, value: Value) -> Self {
+        match strategy {
+            Some(MergeStrategy::Concat) => FieldMerger::Concat(value.to_string_lossy()),
+            Some(MergeStrategy::Array) => FieldMerger::Array(vec![value]),
+            Some(MergeStrategy::Sum) => FieldMerger::Sum(Number::from_value(&value)),
+            Some(MergeStrategy::Max) => FieldMerger::Max(Number::from_value(&value)),
+            Some(MergeStrategy::Min) => FieldMerger::Min(Number::from_value(&value)),
+            Some(MergeStrategy::First) => FieldMerger::First(value),
+            Some(MergeStrategy::Last) => FieldMerger::Last(value),
+            Some(MergeStrategy::Discard) => FieldMerger::Discard,
+            None => match value {
+                Value::Integer(_) | Value::Float(_) => FieldMerger::Sum(Number::from_value(&value)),
+                Value::Timestamp(_) => FieldMerger::Timestamps {
+                    first: value.clone(),
+                    last: value,
+                },
+                value => FieldMerger::First(value),
+            },
+        }
+    }
+
+    fn add(&mut self, value: Value) {
+        match self {
+            FieldMerger::Concat(s) => {
+                s.push(' ');
+                s.push_str(&value.to_string_lossy());
+            }
+            FieldMerger::Array(values) => values.push(value),
+            FieldMerger::Sum(acc) => {
+                if let Some(number) = Number::from_value(&value) {
+                    *acc = Some(acc.map_or(number, |acc| acc.add(number)));
+                }
+            }
+            FieldMerger::Max(acc) => {
+                if let Some(number) = Number::from_value(&value) {
+                    if acc.map_or(true, |acc| number.as_f64() > acc.as_f64()) {
+                        *acc = Some(number);
+                    }
+                }
+            }
+            FieldMerger::Min(acc) => {
+                if let Some(number) = Number::from_value(&value) {
+                    if acc.map_or(true, |acc| number.as_f64() < acc.as_f64()) {
+                        *acc = Some(number);
+                    }
+                }
+            }
+            FieldMerger::First(_) | FieldMerger::Discard => {}
+            FieldMerger::Last(last) | FieldMerger::Timestamps { last, .. } => *last = value,
+        }
+    }
+
+    fn insert_into(self, key: String, log: &mut LogEvent) {
+        match self {
+            FieldMerger::Concat(s) => log.insert_flat(key, s),
+            FieldMerger::Array(values) => log.insert_flat(key, values),
+            FieldMerger::Sum(acc) | FieldMerger::Max(acc) | FieldMerger::Min(acc) => {
+                if let Some(number) = acc {
+                    log.insert_flat(key, number);
+                }
+            }
+            FieldMerger::First(value) | FieldMerger::Last(value) => log.insert_flat(key, value),
+            FieldMerger::Discard => {}
+            FieldMerger::Timestamps { first, last } => {
+                log.insert_flat(format!("{}_end", key), last);
+                log.insert_flat(key, first);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ReduceState {
+    fields: HashMap<String, FieldMerger>,
+}
+
+impl ReduceState {
+    fn add_event(&mut self, log: LogEvent, strategies: &IndexMap<String, MergeStrategy>) {
+        for (key, value) in log {
+            match self.fields.get_mut(&key) {
+                Some(merger) => merger.add(value),
+                None => {
+                    let merger = FieldMerger::new(strategies.get(&key).copied(), value);
+                    self.fields.insert(key, merger);
+                }
+            }
+        }
+    }
+
+    fn flush(self) -> Event {
+        let mut event = Event::new_empty_log();
+        let log = event.as_mut_log();
+        for (key, merger) in self.fields {
+            merger.insert_into(key, log);
+        }
+        event
+    }
+}
+
+pub struct Reduce {
+    expire_after: Duration,
+    identifier_fields: Vec<Atom>,
+    merge_strategies: IndexMap<String, MergeStrategy>,
+    ends_when: Option<Box<dyn Condition>>,
+    groups: ExpiringHashMap<Vec<Option<String>>, ReduceState>,
+}
+
+impl Reduce {
+    pub fn new(config: &ReduceConfig) -> crate::Result<Self> {
+        let ends_when = match &config.ends_when {
+            Some(condition) => Some(condition.build()?),
+            None => None,
+        };
+
+        Ok(Self {
+            expire_after: Duration::from_millis(config.expire_after_ms),
+            identifier_fields: config.identifier_fields.clone(),
+            merge_strategies: config.merge_strategies.clone(),
+            ends_when,
+            groups: ExpiringHashMap::new(),
+        })
+    }
+
+    fn flush_all_into(&mut self, output: &mut Vec<Event>) {
+        output.extend(
+            self.groups
+                .drain()
+                .into_iter()
+                .map(|(state, _)| state.flush()),
+        );
+    }
+}
+
+impl Transform for Reduce {
+    fn transform(&mut self, event: Event) -> Option<Event> {
+        let mut output = Vec::new();
+        self.transform_into(&mut output, event);
+        output.pop()
+    }
+
+    fn transform_into(&mut self, output: &mut Vec<Event>, event: Event) {
+        let ends_here = self
+            .ends_when
+            .as_ref()
+            .map_or(false, |condition| condition.check(&event));
+
+        let log = event.into_log();
+        let key = self
+            .identifier_fields
+            .iter()
+            .map(|field| log.get(field).map(Value::to_string_lossy))
+            .collect::<Vec<_>>();
+        let deadline = Instant::now() + self.expire_after;
+
+        if ends_here {
+            let mut state = self
+                .groups
+                .remove(&key)
+                .map(|(state, _)| state)
+                .unwrap_or_default();
+            state.add_event(log, &self.merge_strategies);
+            output.push(state.flush());
+        } else if let Some(state) = self.groups.reset_at(&key, deadline) {
+            state.add_event(log, &self.merge_strategies);
+        } else {
+            let mut state = ReduceState::default();
+            state.add_event(log, &self.merge_strategies);
+            self.groups.insert_at(key, state, deadline);
+        }
+    }
+
+    fn transform_stream(
+        self: Box<Self>,
+        input_rx: Box<dyn Stream01<Item = Event, Error = ()> + Send>,
+    ) -> Box<dyn Stream01<Item = Event, Error = ()> + Send>
+    where
+        Self: 'static,
+    {
+        let input = input_rx.compat();
+        let output = stream::unfold(
+            (self, input, false),
+            |(mut me, mut input, done)| async move {
+                if done {
+                    return None;
+                }
+
+                let mut output = Vec::new();
+                let done = tokio::select! {
+                    expired = me.groups.next_expired(), if !me.groups.is_empty() => {
+                        match expired {
+                            // We do not poll the map when it's empty, so we
+                            // should never reach this branch.
+                            None => unreachable!(),
+                            Some(Ok((state, _))) => output.push(state.flush()),
+                            Some(Err(error)) => error!(
+                                message = "An error occured while expiring a group.",
+                                %error,
+                            ),
+                        }
+                        false
+                    }
+                    event = input.next() => match event {
+                        Some(Ok(event)) => {
+                            me.transform_into(&mut output, event);
+                            false
+                        }
+                        // The input is exhausted, flush all pending groups.
+                        _ => {
+                            me.flush_all_into(&mut output);
+                            true
+                        }
+                    }
+                };
+
+                Some((stream::iter(output), (me, input, done)))
+            },
+        )
+        .flatten()
+        .map(Ok::<_, ()>);
+
+        Box::new(Compat::new(Box::pin(output)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures01::sync::mpsc;
+
+    fn parse_config(s: &str) -> ReduceConfig {
+        toml::from_str(s).unwrap()
+    }
+
+    fn log_event(fields: Vec<(&str, Value)>) -> Event {
+        let mut event = Event::new_empty_log();
+        for (key, value) in fields {
+            event.as_mut_log().insert_flat(key, value);
+        }
+        event
+    }
+
+    async fn collect(reduce: Reduce, events: Vec<Event>) -> Vec<Event> {
+        let input = futures01::stream::iter_ok(events);
+        Box::new(reduce)
+            .transform_stream(Box::new(input))
+            .compat()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .await
+    }
+
+    #[tokio::test]
+    async fn reduce_until_ends_when() {
+        let reduce = Reduce::new(&parse_config(
+            r#"
+identifier_fields = [ "request_id" ]
+
+[ends_when]
+  "test_end.exists" = true
+"#,
+        ))
+        .unwrap();
+
+        let output = collect(
+            reduce,
+            vec![
+                log_event(vec![
+                    ("message", "test message 1".into()),
+                    ("counter", 1.into()),
+                    ("request_id", "1".into()),
+                ]),
+                log_event(vec![
+                    ("message", "test message 2".into()),
+                    ("counter", 2.into()),
+                    ("request_id", "2".into()),
+                ]),
+                log_event(vec![
+                    ("message", "test message 3".into()),
+                    ("counter", 3.into()),
+                    ("request_id", "1".into()),
+                    ("test_end", "yep".into()),
+                ]),
+                log_event(vec![
+                    ("message", "test message 4".into()),
+                    ("counter", 4.into()),
+                    ("request_id", "2".into()),
+                    ("test_end", "yep".into()),
+                ]),
+            ],
+        )
+        .await;
+
+        assert_eq!(output.len(), 2);
+        let first = output[0].as_log();
+        assert_eq!(first[&"message".into()], "test message 1".into());
+        assert_eq!(first[&"counter".into()], 4.into());
+        assert_eq!(first[&"request_id".into()], "1".into());
+        let second = output[1].as_log();
+        assert_eq!(second[&"message".into()], "test message 2".into());
+        assert_eq!(second[&"counter".into()], 6.into());
+        assert_eq!(second[&"request_id".into()], "2".into());
+    }
+
+    #[tokio::test]
+    async fn reduce_with_merge_strategies() {
+        let reduce = Reduce::new(&parse_config(
+            r#"
+[merge_strategies]
+  message = "concat"
+  codes = "array"
+  max_bytes = "max"
+  min_bytes = "min"
+  status = "last"
+  host = "discard"
+  counter = "first"
+"#,
+        ))
+        .unwrap();
+
+        let ts = chrono::Utc::now();
+        let events = (1..=3)
+            .map(|i| {
+                log_event(vec![
+                    ("message", format!("line {}", i).into()),
+                    ("codes", i.into()),
+                    ("max_bytes", (i * 10).into()),
+                    ("min_bytes", (i as f64 * 1.5).into()),
+                    ("status", format!("status {}", i).into()),
+                    ("host", "localhost".into()),
+                    ("counter", i.into()),
+                    ("timestamp", (ts + chrono::Duration::seconds(i)).into()),
+                ])
+            })
+            .collect();
+
+        let output = collect(reduce, events).await;
+
+        assert_eq!(output.len(), 1);
+        let log = output[0].as_log();
+        assert_eq!(log[&"message".into()], "line 1 line 2 line 3".into());
+        assert_eq!(
+            log[&"codes".into()],
+            Value::from(vec![Value::from(1), Value::from(2), Value::from(3)])
+        );
+        assert_eq!(log[&"max_bytes".into()], 30.into());
+        assert_eq!(log[&"min_bytes".into()], 1.5.into());
+        assert_eq!(log[&"status".into()], "status 3".into());
+        assert!(log.get(&"host".into()).is_none());
+        assert_eq!(log[&"counter".into()], 1.into());
+        assert_eq!(
+            log[&"timestamp".into()],
+            (ts + chrono::Duration::seconds(1)).into()
+        );
+        assert_eq!(
+            log[&"timestamp_end".into()],
+            (ts + chrono::Duration::seconds(3)).into()
+        );
+    }
+
+    #[tokio::test]
+    async fn reduce_flushes_expired_groups() {
+        let reduce = Reduce::new(&parse_config(
+            r#"
+expire_after_ms = 100
+identifier_fields = [ "request_id" ]
+"#,
+        ))
+        .unwrap();
+
+        let (tx, rx) = mpsc::unbounded();
+        let mut output = Box::new(reduce).transform_stream(Box::new(rx)).compat();
+
+        tx.unbounded_send(log_event(vec![
+            ("message", "test message 1".into()),
+            ("request_id", "1".into()),
+        ]))
+        .unwrap();
+        tx.unbounded_send(log_event(vec![
+            ("message", "test message 2".into()),
+            ("request_id", "1".into()),
+        ]))
+        .unwrap();
+
+        // The input stays open, so the group can only be flushed by expiring.
+        let event = output.next().await.unwrap().unwrap();
+        assert_eq!(event.as_log()[&"message".into()], "test message 1".into());
+
+        drop(tx);
+        assert!(output.next().await.is_none());
+    }
+
+    #[test]
+    fn reduce_invalid_ends_when() {
+        let config = parse_config(
+            r#"
+[ends_when]
+  "message.nope" = "foo"
+"#,
+        );
+        assert!(Reduce::new(&config).is_err());
+    }
+}
@@ -0,0 +1,184 @@
+use super::Transform;
+use crate::{
+    conditions::{AnyCondition, Condition},
+    event::Event,
+    internal_events::{RouteEventProcessed, RouteEventRouted},
+    topology::config::{DataType, TransformConfig, TransformContext, TransformDescription},
+};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+/// The output receiving events that match none of the lanes.
+pub const UNMATCHED_OUTPUT: &str = "_unmatched";
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct RouteConfig {
+    lanes: IndexMap<String, AnyCondition>,
+    #[serde(default)]
+    first_match: bool,
+}
+
+inventory::submit! {
+    TransformDescription::new_without_default::<RouteConfig>("route")
+}
+
+#[typetag::serde(name = "route")]
+impl TransformConfig for RouteConfig {
+    fn build(&self, _cx: TransformContext) -> crate::Result<Box<dyn Transform>> {
+        if self.lanes.is_empty() {
+            return Err("must specify at least one lane".into());
+        }
+
+        let mut lanes = Vec::with_capacity(self.lanes.len());
+        for (name, condition) in &self.lanes {
+            if name.is_empty() || name.contains('.') {
+                return Err(format!(
+                    "invalid lane name {:?}, lane names must be non-empty and not contain dots",
+                    name
+                )
+                .into());
+            }
+            if name == UNMATCHED_OUTPUT {
+                return Err(format!("lane name {:?} is reserved", name).into());
+            }
+            lanes.push((name.clone(), condition.build()?));
+        }
+
+        Ok(Box::new(Route::new(lanes, self.first_match)))
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Any
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Any
+    }
+
+    fn transform_type(&self) -> &'static str {
+        "route"
+    }
+
+    fn named_outputs(&self) -> Vec<String> {
+        self.lanes
+            .keys()
+            .cloned()
+            .chain(std::iter::once(UNMATCHED_OUTPUT.to_owned()))
+            .collect()
+    }
+}
+
+pub struct Route {
+    lanes: Vec<(String, Box<dyn Condition>)>,
+    first_match: bool,
+}
+
+impl Route {
+    pub fn new(lanes: Vec<(String, Box<dyn Condition>)>, first_match: bool) -> Self {
+        Self { lanes, first_match }
+    }
+}
+
+impl Transform for Route {
+    /// Without named outputs the transform only forwards events matching any
+    /// of its lanes.
+    fn transform(&mut self, event: Event) -> Option<Event> {
+        if self
+            .lanes
+            .iter()
+            .any(|(_, condition)| condition.check(&event))
+        {
+            Some(event)
+        } else {
+            None
+        }
+    }
+
+    fn transform_routed(&mut self, output: &mut Vec<(usize, Event)>, event: Event) {
+        emit!(RouteEventProcessed);
+
+        let mut matched = false;
+        for (index, (name, condition)) in self.lanes.iter().enumerate() {
+            if condition.check(&event) {
+                emit!(RouteEventRouted { lane: name });
+                output.push((index, event.clone()));
+                matched = true;
+                if self.first_match {
+                    break;
+                }
+            }
+        }
+
+        if !matched {
+            emit!(RouteEventRouted {
+                lane: UNMATCHED_OUTPUT
+            });
+            output.push((self.lanes.len(), event));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::Runtime;
+
+    fn route(config: &str) -> (Vec<String>, Box<dyn Transform>) {
+        let rt = Runtime::single_threaded().unwrap();
+        let config: RouteConfig = toml::from_str(config).unwrap();
+        let transform = config
+            .build(TransformContext::new_test(rt.executor()))
+            .unwrap();
+        (config.named_outputs(), transform)
+    }
+
+    fn routed(transform: &mut Box<dyn Transform>, level: &str) -> Vec<usize> {
+        let mut event = Event::from("message");
+        event.as_mut_log().insert("level", level);
+        let mut output = Vec::new();
+        transform.transform_routed(&mut output, event);
+        output.into_iter().map(|(index, _)| index).collect()
+    }
+
+    const LANES: &str = r#"
+        [lanes.errors]
+          "level.eq" = "error"
+        [lanes.problems]
+          "level.neq" = "info"
+    "#;
+
+    #[test]
+    fn route_to_all_matching_lanes() {
+        let (outputs, mut transform) = route(LANES);
+        assert_eq!(outputs, vec!["errors", "problems", "_unmatched"]);
+
+        assert_eq!(routed(&mut transform, "error"), vec![0, 1]);
+        assert_eq!(routed(&mut transform, "warn"), vec![1]);
+        assert_eq!(routed(&mut transform, "info"), vec![2]);
+    }
+
+    #[test]
+    fn route_to_first_matching_lane() {
+        let (_, mut transform) = route(&format!("first_match = true\n{}", LANES));
+
+        assert_eq!(routed(&mut transform, "error"), vec![0]);
+        assert_eq!(routed(&mut transform, "warn"), vec![1]);
+        assert_eq!(routed(&mut transform, "info"), vec![2]);
+    }
+
+    #[test]
+    fn route_rejects_invalid_lanes() {
+        let rt = Runtime::single_threaded().unwrap();
+        for config in &[
+            "lanes = {}",
+            "[lanes._unmatched]\n\"level.eq\" = \"error\"",
+            "[lanes.\"a.b\"]\n\"level.eq\" = \"error\"",
+        ] {
+            let config: RouteConfig = toml::from_str(config).unwrap();
+            assert!(config
+                .build(TransformContext::new_test(rt.executor()))
+                .is_err());
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use super::Transform;
+use crate::{
+    conditions::{AnyCondition, Condition},
+    internal_events::{ThrottleEventDiscarded, ThrottleEventProcessed},
+    template::Template,
+    topology::config::{DataType, TransformConfig, TransformContext, TransformDescription},
+    Event,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ThrottleConfig {
+    pub threshold: u64,
+    #[serde(default = "default_window_secs")]
+    pub window_secs: u64,
+    pub key_field: Option<Template>,
+    pub exclude: Option<AnyCondition>,
+}
+
+fn default_window_secs() -> u64 {
+    1
+}
+
+inventory::submit! {
+    TransformDescription::new_without_default::<ThrottleConfig>("throttle")
+}
+
+#[typetag::serde(name = "throttle")]
+impl TransformConfig for ThrottleConfig {
+    fn build(&self, _cx: TransformContext) -> crate::Result<Box<dyn Transform>> {
+        Ok(Box::new(Throttle::new(self)?))
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn transform_type(&self) -> &'static str {
+        "throttle"
+    }
+}
+
+#[derive(Debug)]
+struct WindowCounter {
+    started_at: Instant,
+    count: u64,
+}
+
+pub struct Throttle {
+    threshold: u64,
+    window: Duration,
+    key_field: Option<Template>,
+    exclude: Option<Box<dyn Condition>>,
+    counters: HashMap<String, WindowCounter>,
+    last_cleanup: Instant,
+}
+
+impl Throttle {
+    pub fn new(config: &ThrottleConfig) -> crate::Result<Self> {
+        if config.threshold == 0 {
+            return Err("threshold must be greater than zero".into());
+        }
+        if config.window_secs == 0 {
+            return Err("window_secs must be greater than zero".into());
+        }
+
+        let exclude = match &config.exclude {
+            Some(condition) => Some(condition.build()?),
+            None => None,
+        };
+
+        Ok(Self {
+            threshold: config.threshold,
+            window: Duration::from_secs(config.window_secs),
+            key_field: config.key_field.clone(),
+            exclude,
+            counters: HashMap::new(),
+            last_cleanup: Instant::now(),
+        })
+    }
+
+    fn key(&self, event: &Event) -> String {
+        match &self.key_field {
+            // Events missing the fields of the template share the same key.
+            Some(template) => template.render_string(event).unwrap_or_default(),
+            None => String::new(),
+        }
+    }
+
+    /// Count an event for the key, returning whether it fits in the
+    /// threshold of the current window of that key.
+    fn check_at(&mut self, key: &str, now: Instant) -> bool {
+        if now.duration_since(self.last_cleanup) >= self.window {
+            let window = self.window;
+            self.counters
+                .retain(|_, counter| now.duration_since(counter.started_at) < window);
+            self.last_cleanup = now;
+        }
+
+        match self.counters.get_mut(key) {
+            Some(counter) if now.duration_since(counter.started_at) < self.window => {
+                counter.count += 1;
+                counter.count <= self.threshold
+            }
+            Some(counter) => {
+                counter.started_at = now;
+                counter.count = 1;
+                true
+            }
+            None => {
+                self.counters.insert(
+                    key.to_owned(),
+                    WindowCounter {
+                        started_at: now,
+                        count: 1,
+                    },
+                );
+                true
+            }
+        }
+    }
+
+    fn transform_at(&mut self, event: Event, now: Instant) -> Option<Event> {
+        emit!(ThrottleEventProcessed);
+
+        if let Some(exclude) = &self.exclude {
+            if exclude.check(&event) {
+                return Some(event);
+            }
+        }
+
+        let key = self.key(&event);
+        if self.check_at(&key, now) {
+            Some(event)
+        } else {
+            emit!(ThrottleEventDiscarded { key: &key });
+            None
+        }
+    }
+}
+
+impl Transform for Throttle {
+    fn transform(&mut self, event: Event) -> Option<Event> {
+        self.transform_at(event, Instant::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn throttle(config: &str) -> Throttle {
+        Throttle::new(&toml::from_str(config).unwrap()).unwrap()
+    }
+
+    fn event(tenant: &str, level: &str) -> Event {
+        let mut event = Event::from("message");
+        event.as_mut_log().insert("tenant", tenant);
+        event.as_mut_log().insert("level", level);
+        event
+    }
+
+    #[test]
+    fn throttle_events_over_threshold() {
+        let mut throttle = throttle(
+            r#"
+            threshold = 2
+            window_secs = 5
+            "#,
+        );
+        let start = Instant::now();
+
+        assert!(throttle.transform_at(event("a", "info"), start).is_some());
+        assert!(throttle.transform_at(event("a", "info"), start).is_some());
+        assert!(throttle.transform_at(event("b", "info"), start).is_none());
+
+        let later = start + Duration::from_secs(4);
+        assert!(throttle.transform_at(event("a", "info"), later).is_none());
+
+        // A new window starts once the previous one has elapsed.
+        let next_window = start + Duration::from_secs(5);
+        assert!(throttle
+            .transform_at(event("a", "info"), next_window)
+            .is_some());
+        assert!(throttle
+            .transform_at(event("a", "info"), next_window)
+            .is_some());
+        assert!(throttle
+            .transform_at(event("a", "info"), next_window)
+            .is_none());
+    }
+
+    #[test]
+    fn throttle_per_key() {
+        let mut throttle = throttle(
+            r#"
+            threshold = 1
+            key_field = "{{ tenant }}"
+            "#,
+        );
+        let now = Instant::now();
+
+        assert!(throttle.transform_at(event("a", "info"), now).is_some());
+        assert!(throttle.transform_at(event("a", "info"), now).is_none());
+        assert!(throttle.transform_at(event("b", "info"), now).is_some());
+        assert!(throttle.transform_at(event("b", "info"), now).is_none());
+
+        // Events missing the key fields share a key.
+        let mut missing = Event::from("message");
+        assert!(throttle.transform_at(missing.clone(), now).is_some());
+        missing.as_mut_log().insert("other", "field");
+        assert!(throttle.transform_at(missing, now).is_none());
+    }
+
+    #[test]
+    fn throttle_excluded_events_are_not_counted() {
+        let mut throttle = throttle(
+            r#"
+            threshold = 1
+            exclude."level.eq" = "error"
+            "#,
+        );
+        let now = Instant::now();
+
+        assert!(throttle.transform_at(event("a", "error"), now).is_some());
+        assert!(throttle.transform_at(event("a", "info"), now).is_some());
+        assert!(throttle.transform_at(event("a", "info"), now).is_none());
+        assert!(throttle.transform_at(event("a", "error"), now).is_some());
+    }
+
+    #[test]
+    fn throttle_forgets_expired_keys() {
+        let mut throttle = throttle(
+            r#"
+            threshold = 1
+            window_secs = 1
+            key_field = "{{ tenant }}"
+            "#,
+        );
+        let start = Instant::now();
+
+        for tenant in &["a", "b", "c"] {
+            throttle.transform_at(event(tenant, "info"), start);
+        }
+        assert_eq!(throttle.counters.len(), 3);
+
+        throttle.transform_at(event("d", "info"), start + Duration::from_secs(2));
+        assert_eq!(throttle.counters.len(), 1);
+    }
+
+    #[test]
+    fn throttle_invalid_config() {
+        let config: ThrottleConfig = toml::from_str("threshold = 0").unwrap();
+        assert!(Throttle::new(&config).is_err());
+        let config: ThrottleConfig = toml::from_str("threshold = 1\nwindow_secs = 0").unwrap();
+        assert!(Throttle::new(&config).is_err());
+    }
+}
@@ -0,0 +1,477 @@
+use super::Transform;
+use crate::{
+    event::{self, Event, Value},
+    internal_events::{
+        TimestampParserEventProcessed, TimestampParserFailedParse, TimestampParserMissingField,
+    },
+    topology::config::{DataType, TransformConfig, TransformContext, TransformDescription},
+};
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use string_cache::DefaultAtom as Atom;
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct TimestampParserConfig {
+    pub field: Option<Atom>,
+    pub formats: Vec<String>,
+    pub timezone: Option<String>,
+    pub target_field: Option<Atom>,
+    #[serde(default)]
+    pub set_timestamp: bool,
+    #[serde(default)]
+    pub drop_failed: bool,
+}
+
+inventory::submit! {
+    TransformDescription::new_without_default::<TimestampParserConfig>("timestamp_parser")
+}
+
+#[typetag::serde(name = "timestamp_parser")]
+impl TransformConfig for TimestampParserConfig {
+    fn build(&self, _cx: TransformContext) -> crate::Result<Box<dyn Transform>> {
+        Ok(Box::new(TimestampParser::new(self)?))
+    }
+
+    fn input_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn output_type(&self) -> DataType {
+        DataType::Log
+    }
+
+    fn transform_type(&self) -> &'static str {
+        "timestamp_parser"
+    }
+}
+
+/// The time zone of timestamps that do not specify one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DefaultTimeZone {
+    Local,
+    Named(Tz),
+}
+
+impl DefaultTimeZone {
+    fn parse(name: &str) -> crate::Result<Self> {
+        if name == "local" {
+            Ok(DefaultTimeZone::Local)
+        } else {
+            name.parse::<Tz>()
+                .map(DefaultTimeZone::Named)
+                .map_err(|error| format!("invalid timezone {:?}: {}", name, error).into())
+        }
+    }
+
+    fn from_local(self, naive: &NaiveDateTime) -> Option<DateTime<Utc>> {
+        // Ambiguous times, such as those repeated when daylight saving time
+        // ends, resolve to the earliest one.
+        match self {
+            DefaultTimeZone::Local => Local
+                .from_local_datetime(naive)
+                .earliest()
+                .map(|ts| ts.with_timezone(&Utc)),
+            DefaultTimeZone::Named(tz) => tz
+                .from_local_datetime(naive)
+                .earliest()
+                .map(|ts| ts.with_timezone(&Utc)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Format {
+    Rfc3339,
+    UnixSeconds,
+    UnixMillis,
+    UnixNanos,
+    /// The `Mmm dd hh:mm:ss` timestamps of BSD syslog, which omit the year.
+    Syslog,
+    Strftime {
+        format: String,
+        has_zone: bool,
+    },
+}
+
+impl Format {
+    fn new(format: &str) -> Self {
+        match format {
+            "rfc3339" => Format::Rfc3339,
+            "unix" => Format::UnixSeconds,
+            "unix_ms" => Format::UnixMillis,
+            "unix_ns" => Format::UnixNanos,
+            "syslog" => Format::Syslog,
+            _ => Format::Strftime {
+                format: format.into(),
+                has_zone: format_has_zone(format),
+            },
+        }
+    }
+
+    fn parse(&self, s: &str, timezone: DefaultTimeZone) -> Option<DateTime<Utc>> {
+        match self {
+            Format::Rfc3339 => DateTime::parse_from_rfc3339(s)
+                .ok()
+                .map(|ts| ts.with_timezone(&Utc)),
+            Format::UnixSeconds => match s.parse::<i64>() {
+                Ok(seconds) => Utc.timestamp_opt(seconds, 0).single(),
+                Err(_) => {
+                    // Flooring keeps the fraction positive for times before
+                    // the epoch, so `-1.5` is half a second after `-2`.
+                    let seconds = s.parse::<f64>().ok()?;
+                    let whole = seconds.floor();
+                    let nanos = ((seconds - whole) * 1e9).round();
+                    // Rounding may carry over to the next second.
+                    let (whole, nanos) = if nanos >= 1e9 {
+                        (whole + 1.0, 0.0)
+                    } else {
+                        (whole, nanos)
+                    };
+                    // Casting out of range floats, or NaN, isn't defined.
+                    if !(i64::MIN as f64..i64::MAX as f64).contains(&whole) {
+                        return None;
+                    }
+                    Utc.timestamp_opt(whole as i64, nanos as u32).single()
+                }
+            },
+            Format::UnixMillis => {
+                let millis = s.parse::<i64>().ok()?;
+                Utc.timestamp_opt(
+                    millis.div_euclid(1_000),
+                    (millis.rem_euclid(1_000) * 1_000_000) as u32,
+                )
+                .single()
+            }
+            Format::UnixNanos => {
+                let nanos = s.parse::<i64>().ok()?;
+                Utc.timestamp_opt(
+                    nanos.div_euclid(1_000_000_000),
+                    nanos.rem_euclid(1_000_000_000) as u32,
+                )
+                .single()
+            }
+            Format::Syslog => {
+                let now = Utc::now();
+                let parse = |year: i32| {
+                    NaiveDateTime::parse_from_str(
+                        &format!("{} {}", year, s.trim()),
+                        "%Y %b %e %H:%M:%S",
+                    )
+                    .ok()
+                };
+                let mut naive = parse(now.year())?;
+                // Messages from December received in January are from the
+                // previous year.
+                if now.month() == 1 && naive.month() == 12 {
+                    naive = parse(now.year() - 1)?;
+                }
+                timezone.from_local(&naive)
+            }
+            Format::Strftime {
+                format,
+                has_zone: true,
+            } => DateTime::parse_from_str(s, format)
+                .ok()
+                .map(|ts| ts.with_timezone(&Utc)),
+            Format::Strftime {
+                format,
+                has_zone: false,
+            } => {
+                let naive = NaiveDateTime::parse_from_str(s, format).ok().or_else(|| {
+                    NaiveDate::parse_from_str(s, format)
+                        .ok()
+                        .map(|date| date.and_hms(0, 0, 0))
+                })?;
+                timezone.from_local(&naive)
+            }
+        }
+    }
+}
+
+fn format_has_zone(format: &str) -> bool {
+    ["%z", "%:z", "%#z", "%+"]
+        .iter()
+        .any(|zone| format.contains(zone))
+}
+
+pub struct TimestampParser {
+    field: Atom,
+    target_field: Atom,
+    formats: Vec<Format>,
+    timezone: DefaultTimeZone,
+    set_timestamp: bool,
+    drop_failed: bool,
+}
+
+impl TimestampParser {
+    pub fn new(config: &TimestampParserConfig) -> crate::Result<Self> {
+        if config.formats.is_empty() {
+            return Err("must specify at least one format".into());
+        }
+        // Time zone names are ambiguous, so chrono can't parse them.
+        if let Some(format) = config.formats.iter().find(|format| format.contains("%Z")) {
+            return Err(format!(
+                "format {:?} uses `%Z`, which can't be parsed; use `%z` or set `timezone` instead",
+                format
+            )
+            .into());
+        }
+
+        let timezone = match &config.timezone {
+            Some(name) => DefaultTimeZone::parse(name)?,
+            None => DefaultTimeZone::Local,
+        };
+        let field = config
+            .field
+            .clone()
+            .unwrap_or_else(|| event::log_schema().timestamp_key().clone());
+        let target_field = config.target_field.clone().unwrap_or_else(|| field.clone());
+
+        Ok(Self {
+            field,
+            target_field,
+            formats: config.formats.iter().map(|f| Format::new(f)).collect(),
+            timezone,
+            set_timestamp: config.set_timestamp,
+            drop_failed: config.drop_failed,
+        })
+    }
+
+    /// Parses a value with the first of the formats matching it.
+    fn parse(&self, value: &Value) -> Option<DateTime<Utc>> {
+        let s = match value {
+            Value::Timestamp(timestamp) => return Some(*timestamp),
+            Value::Bytes(_) => value.to_string_lossy(),
+            Value::Integer(i) => i.to_string(),
+            Value::Float(f) => f.to_string(),
+            _ => return None,
+        };
+
+        self.formats
+            .iter()
+            .find_map(|format| format.parse(&s, self.timezone))
+    }
+}
+
+impl Transform for TimestampParser {
+    fn transform(&mut self, mut event: Event) -> Option<Event> {
+        emit!(TimestampParserEventProcessed);
+
+        let log = event.as_mut_log();
+        let parsed = match log.get(&self.field) {
+            Some(value) => match self.parse(value) {
+                Some(timestamp) => Some(timestamp),
+                None => {
+                    emit!(TimestampParserFailedParse {
+                        field: &self.field,
+                        value: &value.to_string_lossy(),
+                    });
+                    None
+                }
+            },
+            None => {
+                emit!(TimestampParserMissingField { field: &self.field });
+                None
+            }
+        };
+
+        match parsed {
+            Some(timestamp) => {
+                log.insert(self.target_field.clone(), timestamp);
+                if self.set_timestamp {
+                    log.insert(event::log_schema().timestamp_key().clone(), timestamp);
+                }
+                Some(event)
+            }
+            None if self.drop_failed => None,
+            None => Some(event),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parser(config: &str) -> TimestampParser {
+        TimestampParser::new(&toml::from_str(config).unwrap()).unwrap()
+    }
+
+    fn parse(parser: &mut TimestampParser, value: impl Into<Value>) -> Option<Value> {
+        let mut event = Event::new_empty_log();
+        event.as_mut_log().insert("time", value);
+        parser
+            .transform(event)
+            .and_then(|event| event.as_log().get(&"time".into()).cloned())
+    }
+
+    fn timestamp(s: &str) -> Value {
+        Value::Timestamp(DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc))
+    }
+
+    #[test]
+    fn timestamp_parser_default_timezone() {
+        let mut parser = parser(
+            r#"
+            field = "time"
+            formats = ["%Y-%m-%d %H:%M:%S", "%d/%b/%Y:%H:%M:%S %z"]
+            timezone = "America/New_York"
+            "#,
+        );
+
+        // Timestamps without a zone are in the configured time zone.
+        assert_eq!(
+            parse(&mut parser, "2020-01-15 12:00:00"),
+            Some(timestamp("2020-01-15T17:00:00Z"))
+        );
+        assert_eq!(
+            parse(&mut parser, "2020-07-15 12:00:00"),
+            Some(timestamp("2020-07-15T16:00:00Z"))
+        );
+        // Those with a zone keep it.
+        assert_eq!(
+            parse(&mut parser, "15/Jan/2020:12:00:00 +0100"),
+            Some(timestamp("2020-01-15T11:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn timestamp_parser_ordered_formats() {
+        let mut parser = parser(
+            r#"
+            field = "time"
+            formats = ["rfc3339", "unix_ms", "%Y-%m-%d"]
+            timezone = "UTC"
+            "#,
+        );
+
+        assert_eq!(
+            parse(&mut parser, "2020-01-15T12:00:00.5-02:00"),
+            Some(timestamp("2020-01-15T14:00:00.5Z"))
+        );
+        assert_eq!(
+            parse(&mut parser, "1579089600500"),
+            Some(timestamp("2020-01-15T12:00:00.5Z"))
+        );
+        assert_eq!(
+            parse(&mut parser, 1579089600500i64),
+            Some(timestamp("2020-01-15T12:00:00.5Z"))
+        );
+        assert_eq!(
+            parse(&mut parser, "2020-01-15"),
+            Some(timestamp("2020-01-15T00:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn timestamp_parser_unix() {
+        let mut seconds = parser("field = \"time\"\nformats = [\"unix\"]");
+        assert_eq!(
+            parse(&mut seconds, "1579089600"),
+            Some(timestamp("2020-01-15T12:00:00Z"))
+        );
+        assert_eq!(
+            parse(&mut seconds, 1579089600.25),
+            Some(timestamp("2020-01-15T12:00:00.25Z"))
+        );
+        assert_eq!(
+            parse(&mut seconds, "-1.5"),
+            Some(timestamp("1969-12-31T23:59:58.5Z"))
+        );
+        assert_eq!(
+            parse(&mut seconds, -0.25),
+            Some(timestamp("1969-12-31T23:59:59.75Z"))
+        );
+        assert_eq!(
+            parse(&mut seconds, "-0.0000000001"),
+            Some(timestamp("1970-01-01T00:00:00Z"))
+        );
+        // Values that aren't timestamps are left as they are.
+        for value in &["1e30", "-1e30", "NaN", "inf"] {
+            assert_eq!(parse(&mut seconds, *value), Some(Value::from(*value)));
+        }
+
+        let mut nanos = parser("field = \"time\"\nformats = [\"unix_ns\"]");
+        assert_eq!(
+            parse(&mut nanos, "1579089600000000001"),
+            Some(timestamp("2020-01-15T12:00:00.000000001Z"))
+        );
+    }
+
+    #[test]
+    fn timestamp_parser_syslog() {
+        let mut parser = parser(
+            r#"
+            field = "time"
+            formats = ["syslog"]
+            timezone = "Europe/Berlin"
+            "#,
+        );
+
+        let parsed = match parse(&mut parser, "Jul  4 12:30:00") {
+            Some(Value::Timestamp(ts)) => ts,
+            other => panic!("unexpected value {:?}", other),
+        };
+        let year = parsed.year();
+        assert!(year == Utc::now().year() || year == Utc::now().year() - 1);
+        assert_eq!(
+            parsed,
+            Tz::Europe__Berlin
+                .ymd(year, 7, 4)
+                .and_hms(12, 30, 0)
+                .with_timezone(&Utc)
+        );
+    }
+
+    #[test]
+    fn timestamp_parser_failures() {
+        let mut keep = parser("field = \"time\"\nformats = [\"rfc3339\"]");
+        assert_eq!(parse(&mut keep, "yesterday"), Some("yesterday".into()));
+        assert!(keep.transform(Event::new_empty_log()).is_some());
+
+        let mut drop = parser("field = \"time\"\nformats = [\"rfc3339\"]\ndrop_failed = true");
+        assert_eq!(parse(&mut drop, "yesterday"), None);
+        assert!(drop.transform(Event::new_empty_log()).is_none());
+    }
+
+    #[test]
+    fn timestamp_parser_target_and_timestamp_fields() {
+        let mut parser = parser(
+            r#"
+            field = "time"
+            target_field = "parsed"
+            formats = ["rfc3339"]
+            set_timestamp = true
+            "#,
+        );
+
+        let mut event = Event::new_empty_log();
+        event.as_mut_log().insert("time", "2020-01-15T12:00:00Z");
+        let event = parser.transform(event).unwrap();
+
+        let expected = timestamp("2020-01-15T12:00:00Z");
+        assert_eq!(
+            event.as_log().get(&"time".into()),
+            Some(&"2020-01-15T12:00:00Z".into())
+        );
+        assert_eq!(event.as_log().get(&"parsed".into()), Some(&expected));
+        assert_eq!(
+            event.as_log().get(&event::log_schema().timestamp_key()),
+            Some(&expected)
+        );
+    }
+
+    #[test]
+    fn timestamp_parser_invalid_config() {
+        let config: TimestampParserConfig = toml::from_str("formats = []").unwrap();
+        assert!(TimestampParser::new(&config).is_err());
+        let config: TimestampParserConfig =
+            toml::from_str("formats = [\"rfc3339\"]\ntimezone = \"Mars/Olympus_Mons\"").unwrap();
+        assert!(TimestampParser::new(&config).is_err());
+        let config: TimestampParserConfig =
+            toml::from_str("formats = [\"rfc3339\", \"%Y-%m-%d %H:%M:%S %Z\"]").unwrap();
+        assert!(TimestampParser::new(&config).is_err());
+    }
+}
@@ -1,9 +1,13 @@
 use crate::{
     config_paths, event,
-    topology::{config::Config, unit_test::UnitTest},
+    topology::{
+        config::Config,
+        unit_test::{SnapshotMode, UnitTest},
+    },
 };
 use colored::*;
-use std::{fs::File, path::PathBuf};
+use serde::Serialize;
+use std::{fs::File, path::PathBuf, time::Instant};
 use structopt::StructOpt;
 
 #[derive(StructOpt, Debug)]
@@ -12,6 +16,159 @@ pub struct Opts {
     /// Any number of Vector config files to test. If none are specified the
     /// default config path `/etc/vector/vector.toml` will be targeted.
     paths: Vec<PathBuf>,
+
+    /// Record the outputs of tests with a snapshot instead of comparing them
+    /// with the recorded ones.
+    #[structopt(long)]
+    update_snapshots: bool,
+
+    /// Write a machine readable report of the test results to the file given
+    /// with `--output`.
+    #[structopt(long, requires = "output", possible_values = &["junit", "json"])]
+    reporter: Option<Reporter>,
+
+    /// The file to write the report to.
+    #[structopt(long, requires = "reporter")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Reporter {
+    Junit,
+    Json,
+}
+
+impl std::str::FromStr for Reporter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "junit" => Ok(Reporter::Junit),
+            "json" => Ok(Reporter::Json),
+            s => Err(format!(
+                "{} is not a valid option, expected `junit` or `json`",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Default)]
+struct Report {
+    suites: Vec<SuiteReport>,
+}
+
+/// The results of the tests of one config file.
+#[derive(Serialize, Debug)]
+struct SuiteReport {
+    path: String,
+    /// Errors preventing the tests of the file from being built.
+    errors: Vec<String>,
+    tests: Vec<TestReport>,
+}
+
+#[derive(Serialize, Debug)]
+struct TestReport {
+    name: String,
+    duration_secs: f64,
+    passed: bool,
+    failures: Vec<String>,
+    inspections: Vec<String>,
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters other than whitespace are not allowed in XML.
+            c if c.is_control() && !matches!(c, '\n' | '\r' | '\t') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl Report {
+    fn to_junit(&self) -> String {
+        let tests = self.suites.iter().map(|s| s.tests.len()).sum::<usize>();
+        let failures = self.suites.iter().map(SuiteReport::failures).sum::<usize>();
+        let errors = self.suites.iter().filter(|s| !s.errors.is_empty()).count();
+        let time = self.suites.iter().fold(0.0, |time, s| time + s.time());
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuites name=\"vector\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.6}\">\n",
+            tests, failures, errors, time
+        ));
+        for suite in &self.suites {
+            let path = escape_xml(&suite.path);
+            xml.push_str(&format!(
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.6}\">\n",
+                path,
+                suite.tests.len(),
+                suite.failures(),
+                if suite.errors.is_empty() { 0 } else { 1 },
+                suite.time()
+            ));
+            if !suite.errors.is_empty() {
+                xml.push_str(&format!(
+                    "    <testcase name=\"build\" classname=\"{}\" time=\"0\">\n      <error message=\"failed to build tests\">{}</error>\n    </testcase>\n",
+                    path,
+                    escape_xml(&suite.errors.join("\n"))
+                ));
+            }
+            for test in &suite.tests {
+                xml.push_str(&format!(
+                    "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.6}\">\n",
+                    escape_xml(&test.name),
+                    path,
+                    test.duration_secs
+                ));
+                if !test.passed {
+                    xml.push_str(&format!(
+                        "      <failure message=\"{} checks failed\">{}</failure>\n",
+                        test.failures.len(),
+                        escape_xml(&test.failures.join("\n\n"))
+                    ));
+                }
+                if !test.inspections.is_empty() {
+                    xml.push_str(&format!(
+                        "      <system-out>{}</system-out>\n",
+                        escape_xml(&test.inspections.join("\n\n"))
+                    ));
+                }
+                xml.push_str("    </testcase>\n");
+            }
+            xml.push_str("  </testsuite>\n");
+        }
+        xml.push_str("</testsuites>\n");
+        xml
+    }
+
+    fn write(&self, reporter: Reporter, path: &PathBuf) -> std::io::Result<()> {
+        let contents = match reporter {
+            Reporter::Junit => self.to_junit(),
+            Reporter::Json => serde_json::to_string_pretty(self)?,
+        };
+        std::fs::write(path, contents)
+    }
+}
+
+impl SuiteReport {
+    fn failures(&self) -> usize {
+        self.tests.iter().filter(|t| !t.passed).count()
+    }
+
+    fn time(&self) -> f64 {
+        self.tests
+            .iter()
+            .fold(0.0, |time, t| time + t.duration_secs)
+    }
 }
 
 fn build_tests(i: usize, path: &PathBuf) -> Result<Vec<UnitTest>, Vec<String>> {
@@ -45,29 +202,57 @@ fn build_tests(i: usize, path: &PathBuf) -> Result<Vec<UnitTest>, Vec<String>> {
             .expect("Couldn't set schema");
     }
 
-    crate::topology::unit_test::build_unit_tests(&mut config)
+    crate::topology::unit_test::build_unit_tests_for_file(&mut config, path)
+}
+
+fn write_report(opts: &Opts, report: &Report) {
+    if let (Some(reporter), Some(output)) = (opts.reporter, &opts.output) {
+        if let Err(error) = report.write(reporter, output) {
+            error!("Failed to write test report to {:?}: {}", output, error);
+        }
+    }
 }
 
 pub fn cmd(opts: &Opts) -> exitcode::ExitCode {
     let mut failed_files: Vec<(String, Vec<(String, Vec<String>)>)> = Vec::new();
     let mut inspected_files: Vec<(String, Vec<(String, Vec<String>)>)> = Vec::new();
 
+    let snapshot_mode = if opts.update_snapshots {
+        SnapshotMode::Update
+    } else {
+        SnapshotMode::Compare
+    };
+
     let paths = config_paths::expand(opts.paths.clone()).unwrap_or_else(|| {
         std::process::exit(exitcode::CONFIG);
     });
 
+    let mut report = Report::default();
     for (i, p) in paths.iter().enumerate() {
         let path_str = p.to_str().unwrap_or("");
         if i > 0 {
             println!();
         }
         println!("Running {} tests", path_str);
+        let mut suite = SuiteReport {
+            path: path_str.to_owned(),
+            errors: Vec::new(),
+            tests: Vec::new(),
+        };
         match build_tests(i, p) {
             Ok(mut tests) => {
                 let mut aggregated_test_errors = Vec::new();
                 let mut aggregated_test_inspections = Vec::new();
                 tests.iter_mut().for_each(|t| {
-                    let (test_inspections, test_errors) = t.run();
+                    let start = Instant::now();
+                    let (test_inspections, test_errors) = t.run_with(snapshot_mode);
+                    suite.tests.push(TestReport {
+                        name: t.name.clone(),
+                        duration_secs: start.elapsed().as_secs_f64(),
+                        passed: test_errors.is_empty(),
+                        failures: test_errors.clone(),
+                        inspections: test_inspections.clone(),
+                    });
                     if !test_inspections.is_empty() {
                         aggregated_test_inspections.push((t.name.clone(), test_inspections));
                     }
@@ -87,14 +272,20 @@ pub fn cmd(opts: &Opts) -> exitcode::ExitCode {
                 if tests.is_empty() {
                     println!("{}", "no tests found".yellow());
                 }
+                report.suites.push(suite);
             }
             Err(errs) => {
                 error!("Failed to execute {} tests:\n{}", path_str, errs.join("\n"));
+                suite.errors = errs;
+                report.suites.push(suite);
+                write_report(opts, &report);
                 return exitcode::CONFIG;
             }
         }
     }
 
+    write_report(opts, &report);
+
     if !inspected_files.is_empty() {
         println!("\ninspections:");
         for (path, inspections) in inspected_files {
@@ -124,3 +315,63 @@ pub fn cmd(opts: &Opts) -> exitcode::ExitCode {
         exitcode::OK
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn junit_report() {
+        let report = Report {
+            suites: vec![
+                SuiteReport {
+                    path: "vector.toml".to_owned(),
+                    errors: Vec::new(),
+                    tests: vec![
+                        TestReport {
+                            name: "passing".to_owned(),
+                            duration_secs: 0.5,
+                            passed: true,
+                            failures: Vec::new(),
+                            inspections: Vec::new(),
+                        },
+                        TestReport {
+                            name: "failing <test>".to_owned(),
+                            duration_secs: 0.25,
+                            passed: false,
+                            failures: vec![
+                                "condition[0]: predicates failed: [ a.eq: \"b\" ]".to_owned()
+                            ],
+                            inspections: Vec::new(),
+                        },
+                    ],
+                },
+                SuiteReport {
+                    path: "broken.toml".to_owned(),
+                    errors: vec!["must specify at least one input.".to_owned()],
+                    tests: Vec::new(),
+                },
+            ],
+        };
+
+        assert_eq!(
+            report.to_junit(),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="vector" tests="2" failures="1" errors="1" time="0.750000">
+  <testsuite name="vector.toml" tests="2" failures="1" errors="0" time="0.750000">
+    <testcase name="passing" classname="vector.toml" time="0.500000">
+    </testcase>
+    <testcase name="failing &lt;test&gt;" classname="vector.toml" time="0.250000">
+      <failure message="1 checks failed">condition[0]: predicates failed: [ a.eq: &quot;b&quot; ]</failure>
+    </testcase>
+  </testsuite>
+  <testsuite name="broken.toml" tests="0" failures="0" errors="1" time="0.000000">
+    <testcase name="build" classname="broken.toml" time="0">
+      <error message="failed to build tests">must specify at least one input.</error>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+    }
+}
@@ -0,0 +1,144 @@
+#!/usr/bin/env python3
+"""Writes the small GeoIP test databases used by the `geoip` transform tests.
+
+Their records are modelled on MaxMind's test databases at
+https://github.com/maxmind/MaxMind-DB/tree/main/test-data, following the
+MaxMind DB format described at https://maxmind.github.io/MaxMind-DB/.
+Run from this directory with `python3 generate-geoip.py`.
+"""
+
+import ipaddress
+import struct
+
+DATABASES = {
+    "GeoLite2-ASN-Test.mmdb": [
+        ("1.128.0.0/11", {
+            "autonomous_system_number": 1221,
+            "autonomous_system_organization": "Telstra Pty Ltd",
+        }),
+        ("12.81.92.0/22", {
+            "autonomous_system_number": 7018,
+            "autonomous_system_organization": "AT&T Services",
+        }),
+    ],
+    "GeoIP2-ISP-Test.mmdb": [
+        ("1.128.0.0/11", {
+            "autonomous_system_number": 1221,
+            "autonomous_system_organization": "Telstra Pty Ltd",
+            "isp": "Telstra Internet",
+            "organization": "Telstra Internet",
+        }),
+    ],
+    "GeoIP2-Connection-Type-Test.mmdb": [
+        ("1.0.0.0/24", {"connection_type": "Dialup"}),
+        ("1.0.1.0/24", {"connection_type": "Cable/DSL"}),
+        ("80.214.0.0/20", {"connection_type": "Cellular"}),
+    ],
+    "GeoIP2-Anonymous-IP-Test.mmdb": [
+        ("1.2.0.0/16", {"is_anonymous": True, "is_anonymous_vpn": True}),
+        ("81.2.69.0/24", {
+            "is_anonymous": True,
+            "is_anonymous_vpn": True,
+            "is_hosting_provider": True,
+            "is_public_proxy": True,
+            "is_tor_exit_node": True,
+        }),
+    ],
+}
+
+
+def control(type_, size):
+    if size < 29:
+        size_bytes = b""
+    elif size < 285:
+        size_bytes, size = bytes([size - 29]), 29
+    elif size < 65821:
+        size_bytes, size = struct.pack(">H", size - 285), 30
+    else:
+        size_bytes, size = struct.pack(">I", size - 65821)[1:], 31
+    if type_ <= 7:
+        return bytes([(type_ << 5) | size]) + size_bytes
+    return bytes([size, type_ - 7]) + size_bytes
+
+
+def encode(value):
+    if isinstance(value, bool):
+        return control(14, int(value))
+    if isinstance(value, int):
+        payload = value.to_bytes(8, "big").lstrip(b"\0")
+        return control(6 if value < 2 ** 32 else 9, len(payload)) + payload
+    if isinstance(value, str):
+        payload = value.encode()
+        return control(2, len(payload)) + payload
+    if isinstance(value, list):
+        return control(11, len(value)) + b"".join(encode(item) for item in value)
+    if isinstance(value, dict):
+        return control(7, len(value)) + b"".join(
+            encode(key) + encode(item) for key, item in value.items()
+        )
+    raise TypeError(value)
+
+
+def uint16(value):
+    return control(5, 2) + struct.pack(">H", value)
+
+
+def uint64(value):
+    return control(9, 8) + struct.pack(">Q", value)
+
+
+def build(database_type, records):
+    data = b""
+    # Nodes are [left, right] pairs holding a node index, a data offset as
+    # ("data", offset), or None for no data.
+    nodes = [[None, None]]
+    for network, record in records:
+        offset = len(data)
+        data += encode(record)
+
+        network = ipaddress.ip_network(network)
+        bits = int(network.network_address)
+        # IPv4 networks live below ::/96 in an IPv6 tree.
+        prefix = network.prefixlen + (0 if network.version == 6 else 96)
+        node = 0
+        for depth in range(prefix):
+            bit = (bits >> (127 - depth)) & 1
+            if depth == prefix - 1:
+                nodes[node][bit] = ("data", offset)
+            else:
+                if nodes[node][bit] is None:
+                    nodes.append([None, None])
+                    nodes[node][bit] = len(nodes) - 1
+                node = nodes[node][bit]
+
+    node_count = len(nodes)
+
+    def record(value):
+        if value is None:
+            return node_count
+        if isinstance(value, tuple):
+            return node_count + 16 + value[1]
+        return value
+
+    tree = b"".join(
+        struct.pack(">I", record(left))[1:] + struct.pack(">I", record(right))[1:]
+        for left, right in nodes
+    )
+    metadata = (
+        control(7, 9)
+        + encode("binary_format_major_version") + uint16(2)
+        + encode("binary_format_minor_version") + uint16(0)
+        + encode("build_epoch") + uint64(1590000000)
+        + encode("database_type") + encode(database_type)
+        + encode("description") + encode({"en": database_type + " test database"})
+        + encode("ip_version") + uint16(6)
+        + encode("languages") + encode(["en"])
+        + encode("node_count") + encode(node_count)
+        + encode("record_size") + uint16(24)
+    )
+    return tree + b"\0" * 16 + data + b"\xab\xcd\xefMaxMind.com" + metadata
+
+
+for name, records in DATABASES.items():
+    with open(name, "wb") as file:
+        file.write(build(name[: -len("-Test.mmdb")], records))
@@ -0,0 +1,47 @@
+#!/usr/bin/env python3
+"""Compresses a journal field with systemd's own `compress_blob_lz4` and
+`compress_blob_zstd`, producing the payloads `systemd-journald` writes into
+compressed data objects.
+
+Needs the `libsystemd-shared` of systemd 252, the version which wrote
+`system.journal`. Run from this directory with `python3 compress-message.py`.
+"""
+
+import ctypes
+
+LIBSYSTEMD_SHARED = "/usr/lib/x86_64-linux-gnu/systemd/libsystemd-shared-252.so"
+
+FIELD = ("MESSAGE=" + " ".join(
+    "unit vector-%d.service entered failed state, restarting in %dms" % (i, i * 100)
+    for i in range(8)
+)).encode()
+
+OUTPUTS = {
+    "compress_blob_lz4": "message.lz4",
+    "compress_blob_zstd": "message.zst",
+}
+
+
+def main():
+    systemd = ctypes.CDLL(LIBSYSTEMD_SHARED)
+    with open("message", "wb") as f:
+        f.write(FIELD)
+    for function, path in OUTPUTS.items():
+        compress = getattr(systemd, function)
+        compress.argtypes = [
+            ctypes.c_void_p,
+            ctypes.c_uint64,
+            ctypes.c_void_p,
+            ctypes.c_size_t,
+            ctypes.POINTER(ctypes.c_size_t),
+        ]
+        dst = ctypes.create_string_buffer(len(FIELD))
+        size = ctypes.c_size_t()
+        if compress(FIELD, len(FIELD), dst, len(FIELD), ctypes.byref(size)) < 0:
+            raise RuntimeError("%s failed" % function)
+        with open(path, "wb") as f:
+            f.write(dst.raw[:size.value])
+
+
+if __name__ == "__main__":
+    main()
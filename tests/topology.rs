@@ -432,6 +432,93 @@ fn topology_swap_transform() {
     assert_eq!(vec!["this replaced"], res1v2);
 }
 
+#[cfg(feature = "transforms-route")]
+fn route(lanes: &[&str]) -> vector::transforms::route::RouteConfig {
+    let lanes = lanes
+        .iter()
+        .map(|lane| format!("[lanes.{}]\n\"level.eq\" = \"{}\"\n", lane, lane))
+        .collect::<String>();
+    toml::from_str(&lanes).unwrap()
+}
+
+#[cfg(feature = "transforms-route")]
+fn with_level(level: &str) -> Event {
+    let mut event = Event::from(level);
+    event.as_mut_log().insert("level", level);
+    event
+}
+
+#[cfg(feature = "transforms-route")]
+#[test]
+fn topology_route_to_sinks() {
+    let mut rt = runtime();
+    let (in1, source1) = source();
+    let (out_error, sink_error) = sink(10);
+    let (out_unmatched, sink_unmatched) = sink(10);
+
+    let mut config = Config::empty();
+    config.add_source("in1", source1);
+    config.add_transform("route", &["in1"], route(&["error", "warn"]));
+    config.add_sink("out_error", &["route.error"], sink_error);
+    config.add_sink("out_unmatched", &["route._unmatched"], sink_unmatched);
+
+    let (topology, _crash) = topology::start(config, &mut rt, false).unwrap();
+
+    let in1 = in1.send(with_level("error")).wait().unwrap();
+    let in1 = in1.send(with_level("warn")).wait().unwrap();
+    in1.send(with_level("info")).wait().unwrap();
+
+    rt.block_on(topology.stop()).unwrap();
+
+    let res_error = out_error.map(into_message).collect().wait().unwrap();
+    let res_unmatched = out_unmatched.map(into_message).collect().wait().unwrap();
+
+    shutdown_on_idle(rt);
+    assert_eq!(vec!["error"], res_error);
+    assert_eq!(vec!["info"], res_unmatched);
+}
+
+#[cfg(feature = "transforms-route")]
+#[test]
+fn topology_route_remove_lane() {
+    let mut rt = runtime();
+    let (in1, source1) = source();
+    let (out_error, sink_error) = sink(10);
+    let (out_warn, sink_warn) = sink(10);
+
+    let mut config = Config::empty();
+    config.add_source("in1", source1);
+    config.add_transform("route", &["in1"], route(&["error", "warn"]));
+    config.add_sink("out_error", &["route.error"], sink_error);
+    config.add_sink("out_warn", &["route.warn"], sink_warn);
+
+    let (mut topology, _crash) = topology::start(config, &mut rt, false).unwrap();
+
+    let mut config = Config::empty();
+    config.add_source("in1", source().1);
+    config.add_transform("route", &["in1"], route(&["error"]));
+    config.add_sink("out_error", &["route.error"], sink(10).1);
+
+    assert!(topology
+        .reload_config_and_respawn(config, &mut rt, false)
+        .unwrap());
+
+    let h_out_error = oneshot::spawn(out_error.map(into_message).collect(), &rt.executor());
+    let h_out_warn = oneshot::spawn(out_warn.map(into_message).collect(), &rt.executor());
+    let h_in1 = oneshot::spawn(
+        in1.send(with_level("error"))
+            .and_then(|in1| in1.send(with_level("warn"))),
+        &rt.executor(),
+    );
+    rt.block_on(h_in1).unwrap();
+    rt.block_on(topology.stop()).unwrap();
+    let res_error = rt.block_on(h_out_error).unwrap();
+    let res_warn = rt.block_on(h_out_warn).unwrap();
+    shutdown_on_idle(rt);
+    assert_eq!(vec!["error"], res_error);
+    assert_eq!(Vec::<String>::new(), res_warn);
+}
+
 #[ignore] // TODO: issue #2186
 #[test]
 fn topology_swap_transform_is_atomic() {